@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // the vendored protoc is used unless PROTOC points to another one
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    // only the server is generated, red-monkey is never a client of the fault service
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_protos(&["src/grpc/fault_service.proto"], &["src/grpc"])?;
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+-- The timestamps are stored as RFC 3339 strings, as TIMESTAMPTZ truncates them to microseconds
+CREATE TABLE IF NOT EXISTS faults (
+    name TEXT PRIMARY KEY NOT NULL,
+    description TEXT,
+    fault_type TEXT NOT NULL,
+    duration BIGINT,
+    error_msg TEXT,
+    command TEXT NOT NULL,
+    last_modified TEXT,
+    expires_at TEXT,
+    activate_at TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    one_shot BOOLEAN NOT NULL DEFAULT FALSE,
+    max_hits BIGINT,
+    jitter_ms BIGINT,
+    adaptive_delay_step_ms BIGINT,
+    max_adaptive_duration_ms BIGINT,
+    burst_count BIGINT,
+    burst_interval_ms BIGINT,
+    truncate_after_bytes BIGINT,
+    bytes_per_ms BIGINT,
+    key_pattern TEXT,
+    tags JSONB NOT NULL DEFAULT '[]',
+    fault_group TEXT,
+    client_ip_filter JSONB,
+    redis_database BIGINT,
+    proxy_port BIGINT,
+    priority BIGINT NOT NULL DEFAULT 100,
+    probability DOUBLE PRECISION,
+    sub_faults JSONB,
+    mutation_pattern TEXT,
+    mutation_replacement TEXT,
+    arg_matchers JSONB,
+    hit_count BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS faults_command_idx ON faults (command);
@@ -0,0 +1,100 @@
+use crate::store::fault_store::Fault;
+use chrono::{DateTime, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// AuditAction is the change made to a fault by a fault config server request.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// AuditEntry is a single line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub fault_name: String,
+    /// The identity of the client that made the change, `anonymous` without authentication
+    pub actor: String,
+    /// The fault after it is created or updated, or before it is deleted
+    pub fault: Fault,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, actor: &str, fault: &Fault) -> Self {
+        AuditEntry {
+            timestamp: Utc::now(),
+            action,
+            fault_name: fault.name.clone(),
+            actor: actor.to_string(),
+            fault: fault.clone(),
+        }
+    }
+}
+
+/// A trait providing the method for pluggable audit trails of the fault changes
+pub trait AuditLogger {
+    /// Records the given entry in the audit trail
+    fn log(&self, entry: &AuditEntry) -> Result<(), anyhow::Error>;
+}
+
+/// AuditLog is the audit logger shared by the fault config server handlers.
+pub type AuditLog = Arc<dyn AuditLogger + Send + Sync>;
+
+/// FileAuditLogger appends the audit entries to a file as JSON lines.
+pub struct FileAuditLogger {
+    file: Mutex<File>,
+}
+
+impl FileAuditLogger {
+    /// Opens the audit log file at the given path in the append mode, creating it if it doesn't
+    /// exist.
+    pub fn new(path: &str) -> Result<Self, std::io::Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(FileAuditLogger {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn new_log(path: &str) -> Result<AuditLog, std::io::Error> {
+        Ok(Arc::new(FileAuditLogger::new(path)?))
+    }
+}
+
+impl AuditLogger for FileAuditLogger {
+    fn log(&self, entry: &AuditEntry) -> Result<(), anyhow::Error> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        // a single write per entry, such that the concurrent entries are not interleaved
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow::anyhow!("audit log file lock is poisoned"))?;
+        file.write_all(&line)?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// NopAuditLogger discards the audit entries. It is used when no audit log is configured.
+pub struct NopAuditLogger;
+
+impl NopAuditLogger {
+    pub fn new_log() -> AuditLog {
+        Arc::new(NopAuditLogger)
+    }
+}
+
+impl AuditLogger for NopAuditLogger {
+    fn log(&self, _entry: &AuditEntry) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
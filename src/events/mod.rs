@@ -0,0 +1,52 @@
+pub mod nats_publisher;
+
+use crate::store::fault_store::Fault;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// The number of the fault events buffered for a subscriber, beyond which a subscriber that lags
+/// behind misses the oldest events.
+pub const FAULT_EVENTS_CAPACITY: usize = 1024;
+
+/// FaultEvent is published to the live event streams of the fault config server for every
+/// injected fault.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FaultEvent {
+    pub ts: DateTime<Utc>,
+    pub fault_name: String,
+    /// The command of the injected fault
+    pub command: String,
+    /// The type of the injected fault, e.g. `Delay`
+    pub action: String,
+}
+
+impl FaultEvent {
+    /// Returns the event of the given fault being injected now.
+    pub fn new(fault: &Fault) -> Self {
+        FaultEvent {
+            ts: Utc::now(),
+            fault_name: fault.name.clone(),
+            command: fault.command.clone(),
+            action: format!("{:?}", fault.fault_type),
+        }
+    }
+}
+
+/// FaultEventPublisher publishes the fault events to a message bus, such that they can be
+/// consumed by the downstream audit or analytics systems.
+#[async_trait]
+pub trait FaultEventPublisher: Send + Sync {
+    /// Publishes the event of an executed fault.
+    async fn publish_fault_event(&self, event: &FaultEvent) -> Result<(), anyhow::Error>;
+}
+
+/// FaultEventSender publishes the fault events to all the subscribed event streams.
+pub type FaultEventSender = broadcast::Sender<FaultEvent>;
+
+/// Returns the sender of the fault events, which has no subscribers until an event stream
+/// subscribes to it.
+pub fn fault_event_sender() -> FaultEventSender {
+    broadcast::channel(FAULT_EVENTS_CAPACITY).0
+}
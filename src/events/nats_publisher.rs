@@ -0,0 +1,67 @@
+use crate::events::{FaultEvent, FaultEventPublisher};
+use async_trait::async_trait;
+
+/// NatsPublisher publishes the fault events as JSON messages to the NATS subject of the fault,
+/// i.e. `<subject_prefix>.<fault_name>`, e.g. `red-monkey.faults.delay_get`.
+pub struct NatsPublisher {
+    client: async_nats::Client,
+    subject_prefix: String,
+}
+
+impl NatsPublisher {
+    /// Connects to the NATS server at the given URL, e.g. `nats://localhost:4222`.
+    pub async fn connect(
+        nats_url: &str,
+        subject_prefix: &str,
+    ) -> Result<Self, async_nats::ConnectError> {
+        Ok(NatsPublisher {
+            client: async_nats::connect(nats_url).await?,
+            subject_prefix: subject_prefix.to_string(),
+        })
+    }
+
+    fn subject(&self, event: &FaultEvent) -> String {
+        format!(
+            "{}.{}",
+            self.subject_prefix,
+            subject_token(&event.fault_name)
+        )
+    }
+}
+
+#[async_trait]
+impl FaultEventPublisher for NatsPublisher {
+    async fn publish_fault_event(&self, event: &FaultEvent) -> Result<(), anyhow::Error> {
+        let payload = serde_json::to_vec(event)?;
+        self.client
+            .publish(self.subject(event), payload.into())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the given value as a token of a NATS subject, replacing the token separator `.`, the
+/// wildcards `*` and `>` and the whitespaces with `_`.
+fn subject_token(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_token() {
+        assert_eq!(subject_token("delay_get"), "delay_get");
+        assert_eq!(subject_token("delay 10.5ms"), "delay_10_5ms");
+        assert_eq!(subject_token("cache.*.>"), "cache____");
+    }
+}
@@ -0,0 +1,114 @@
+pub mod replay;
+
+use chrono::{DateTime, Utc};
+use std::io;
+use std::sync::Arc;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// ExperimentEvent is a single line of the experiment log, recorded for every injected fault.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentEvent {
+    pub ts: DateTime<Utc>,
+    pub fault_name: String,
+    /// The command of the injected fault
+    pub command: String,
+    /// The IP address of the client, None for the clients of a Unix domain socket
+    pub client_ip: Option<String>,
+    /// The type of the injected fault, e.g. `Delay`
+    pub action: String,
+}
+
+/// ExperimentLog appends the fault injection events of a chaos experiment to a file as JSON lines,
+/// such that the faults that fired during a test run can be reproduced.
+#[derive(Clone)]
+pub struct ExperimentLog {
+    path: String,
+    file: Arc<Mutex<File>>,
+}
+
+impl ExperimentLog {
+    /// Opens the experiment log file at the given path in the append mode, creating it if it
+    /// doesn't exist.
+    pub async fn open(path: &str) -> Result<Self, io::Error> {
+        Ok(ExperimentLog {
+            path: path.to_string(),
+            file: Arc::new(Mutex::new(open_append(path).await?)),
+        })
+    }
+
+    /// Appends the event to the experiment log.
+    pub async fn record(&self, event: &ExperimentEvent) -> Result<(), anyhow::Error> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        // a single write per event, such that the concurrent events are not interleaved
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    /// Moves the events recorded so far to a file named after the current time, e.g.
+    /// `experiment.jsonl.20240102T030405.123456Z`, and keeps recording to a new empty file.
+    ///
+    /// Returns the path of the file the recorded events are moved to.
+    pub async fn rotate(&self) -> Result<String, io::Error> {
+        let mut file = self.file.lock().await;
+        file.flush().await?;
+
+        let rotated_path = format!("{}.{}", self.path, Utc::now().format("%Y%m%dT%H%M%S%.6fZ"));
+        fs::rename(&self.path, &rotated_path).await?;
+        *file = open_append(&self.path).await?;
+
+        Ok(rotated_path)
+    }
+}
+
+async fn open_append(path: &str) -> Result<File, io::Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_experiment_log_rotate() {
+        let path = std::env::temp_dir().join(format!(
+            "red-monkey-experiment-{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        let path = path.to_str().unwrap();
+        let experiment_log = ExperimentLog::open(path).await.unwrap();
+
+        let event = ExperimentEvent {
+            ts: Utc::now(),
+            fault_name: "get_error".to_string(),
+            command: "GET".to_string(),
+            client_ip: Some("127.0.0.1".to_string()),
+            action: "Error".to_string(),
+        };
+        experiment_log.record(&event).await.unwrap();
+
+        let rotated_path = experiment_log.rotate().await.unwrap();
+        assert!(rotated_path.starts_with(path));
+        let rotated_events: Vec<ExperimentEvent> = std::fs::read_to_string(&rotated_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rotated_events, vec![event.clone()]);
+
+        // the events after the rotation are recorded to the new file
+        assert!(std::fs::read_to_string(path).unwrap().is_empty());
+        experiment_log.record(&event).await.unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 1);
+    }
+}
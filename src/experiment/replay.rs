@@ -0,0 +1,405 @@
+use crate::experiment::ExperimentEvent;
+use crate::metrics::Metrics;
+use crate::proxy::faulter::{ClientIdentity, Context, Faulter};
+use crate::store::fault_store::{FaultVariants, DB};
+use crate::store::mem_store::MemStore;
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// ReplayedEvent is the outcome of replaying a recorded fault injection event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayedEvent {
+    /// The time the fault was originally injected at
+    pub ts: DateTime<Utc>,
+    pub fault_name: String,
+    /// Whether the fault is injected again
+    pub fired: bool,
+    /// The action the proxy would take after the fault, e.g. `Exit`
+    pub action: Option<String>,
+    /// The bytes written to the mock client by the fault, e.g. the error reply
+    pub reply: Option<String>,
+    /// The reason the fault is not injected again
+    pub error: Option<String>,
+}
+
+/// The maximum size in bytes of an experiment log that is replayed.
+pub const MAX_EXPERIMENT_LOG_BYTES: u64 = 64 * 1024 * 1024;
+
+/// ReplayEngine re-injects the faults recorded in an experiment log, see
+/// [crate::experiment::ExperimentLog], such that a chaos scenario can be reproduced without a live
+/// Redis server.
+#[derive(Clone)]
+pub struct ReplayEngine {
+    fault_store: DB,
+    /// The directory of the experiment logs that can be replayed, None if the experiment log is
+    /// not configured
+    experiment_log_dir: Option<PathBuf>,
+    max_log_bytes: u64,
+}
+
+impl ReplayEngine {
+    /// The recorded faults are looked up by their name in the given store. No experiment log can
+    /// be replayed until [ReplayEngine::with_experiment_log_path] is set.
+    pub fn new(fault_store: DB) -> Self {
+        ReplayEngine {
+            fault_store,
+            experiment_log_dir: None,
+            max_log_bytes: MAX_EXPERIMENT_LOG_BYTES,
+        }
+    }
+
+    /// Restricts the replays to the files of the directory of the given experiment log, i.e. the
+    /// experiment log and the files it is rotated to, see [crate::experiment::ExperimentLog].
+    pub fn with_experiment_log_path(mut self, experiment_log_path: &str) -> Self {
+        let experiment_log_dir = Path::new(experiment_log_path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        self.experiment_log_dir = Some(experiment_log_dir.to_path_buf());
+        self
+    }
+
+    /// Replays the events of the experiment log at the given path in the order of their `ts`. The
+    /// time between two events is divided by `speed_factor`, e.g. `2.0` replays the experiment at
+    /// double speed.
+    ///
+    /// Only the files of the experiment log directory, once the symbolic links and the `..` of the
+    /// path are resolved, of at most [MAX_EXPERIMENT_LOG_BYTES] are replayed, such that a caller
+    /// can't read the other files of the host.
+    ///
+    /// The faults are executed by a [Faulter] against a mock client that captures the reply of
+    /// the fault. The faulter works on a snapshot of the fault store, such that the replay doesn't
+    /// count the hits of the live faults nor disable the one-shot ones. The faults that no longer
+    /// exist, and the timeout faults which would hold the mock client forever, are not injected.
+    pub async fn replay(
+        &self,
+        path: &str,
+        speed_factor: f64,
+    ) -> Result<Vec<ReplayedEvent>, ReplayErrors> {
+        if !speed_factor.is_finite() || speed_factor <= 0.0 {
+            return Err(ReplayErrors::InvalidSpeedFactorError(speed_factor));
+        }
+
+        let path = self.resolve_path(path).await?;
+        let mut events = read_events(&path, self.max_log_bytes).await?;
+        events.sort_by_key(|event| event.ts);
+        info!("replaying {} event(s) of {}", events.len(), path.display());
+
+        let (snapshot, faulter) = self.snapshot_faulter().await?;
+        let mut replayed_events = Vec::with_capacity(events.len());
+        let mut previous_ts: Option<DateTime<Utc>> = None;
+
+        for event in events {
+            if let Some(previous_ts) = previous_ts {
+                let delay = (event.ts - previous_ts).to_std().unwrap_or_default();
+                tokio::time::sleep(delay.div_f64(speed_factor)).await;
+            }
+            previous_ts = Some(event.ts);
+
+            replayed_events.push(replay_event(&snapshot, &faulter, event).await);
+        }
+
+        Ok(replayed_events)
+    }
+
+    /// Returns the canonical path of the experiment log, if it is a file of the experiment log
+    /// directory. A missing file is rejected like a file of another directory, such that the
+    /// error doesn't tell which files exist on the host.
+    async fn resolve_path(&self, path: &str) -> Result<PathBuf, ReplayErrors> {
+        let experiment_log_dir = self
+            .experiment_log_dir
+            .as_ref()
+            .ok_or(ReplayErrors::NotConfiguredError)?;
+        let experiment_log_dir = tokio::fs::canonicalize(experiment_log_dir).await?;
+
+        let canonical_path = match tokio::fs::canonicalize(path).await {
+            Ok(canonical_path) => canonical_path,
+            Err(_) => return Err(ReplayErrors::PathNotAllowedError(path.to_string())),
+        };
+        let is_file = tokio::fs::metadata(&canonical_path)
+            .await
+            .map(|metadata| metadata.is_file())
+            .unwrap_or(false);
+        if !is_file || !canonical_path.starts_with(&experiment_log_dir) {
+            return Err(ReplayErrors::PathNotAllowedError(path.to_string()));
+        }
+
+        Ok(canonical_path)
+    }
+
+    /// Returns a copy of the fault store and a faulter of the copy, with its own metrics.
+    async fn snapshot_faulter(&self) -> Result<(DB, Faulter), ReplayErrors> {
+        let snapshot = MemStore::new_db();
+        let faults = self
+            .fault_store
+            .get_all_faults()
+            .await
+            .map_err(|err| ReplayErrors::FaultStoreError(err.message))?;
+        for fault in faults {
+            snapshot
+                .store(&fault.name, &fault)
+                .await
+                .map_err(|err| ReplayErrors::FaultStoreError(err.message))?;
+        }
+
+        let faulter = Faulter::new(snapshot.clone(), Arc::new(Metrics::new()?));
+
+        Ok((snapshot, faulter))
+    }
+}
+
+/// Reads the events of the experiment log, one JSON object per line, failing once more than
+/// `max_bytes` are read.
+async fn read_events(path: &Path, max_bytes: u64) -> Result<Vec<ExperimentEvent>, ReplayErrors> {
+    let file = tokio::fs::File::open(path).await?;
+    // a byte more than the limit is read to tell a file of exactly the limit from a larger one
+    let mut reader = BufReader::new(file.take(max_bytes + 1));
+    let mut read_bytes = 0;
+    let mut events = Vec::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        let line_bytes = reader.read_line(&mut line).await?;
+        if line_bytes == 0 {
+            break;
+        }
+        read_bytes += line_bytes as u64;
+        if read_bytes > max_bytes {
+            return Err(ReplayErrors::TooLargeError(max_bytes));
+        }
+
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(&line)
+            .map_err(|err| ReplayErrors::InvalidEventError(line_number, err.to_string()))?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Injects the fault of the recorded event again.
+async fn replay_event(
+    fault_store: &DB,
+    faulter: &Faulter,
+    event: ExperimentEvent,
+) -> ReplayedEvent {
+    let mut replayed_event = ReplayedEvent {
+        ts: event.ts,
+        fault_name: event.fault_name.clone(),
+        fired: false,
+        action: None,
+        reply: None,
+        error: None,
+    };
+
+    let fault = match fault_store.get_by_fault_name(&event.fault_name).await {
+        Ok(fault) => fault,
+        Err(err) => {
+            replayed_event.error = Some(format!("fault not found: {}", err.message));
+            return replayed_event;
+        }
+    };
+    if fault.fault_type == FaultVariants::Timeout {
+        replayed_event.error = Some("the timeout fault is not replayed".to_string());
+        return replayed_event;
+    }
+
+    let mut mock_client = Vec::new();
+    let mut ctx = Context {
+        request_id: Uuid::new_v4(),
+        client_identity: event
+            .client_ip
+            .and_then(|client_ip| client_ip.parse::<IpAddr>().ok())
+            .map_or(ClientIdentity::Unknown, |client_ip| {
+                ClientIdentity::Socket(SocketAddr::new(client_ip, 0))
+            }),
+        client_write_stream: &mut mock_client,
+        client_socket: None,
+    };
+
+    match faulter.execute_fault(&mut ctx, Some(fault)).await {
+        Ok(action) => {
+            replayed_event.fired = true;
+            replayed_event.action = Some(format!("{:?}", action));
+        }
+        Err(err) => replayed_event.error = Some(err.to_string()),
+    }
+    if !mock_client.is_empty() {
+        replayed_event.reply = Some(String::from_utf8_lossy(&mock_client).to_string());
+    }
+    info!(
+        "replayed fault {}: fired: {}",
+        replayed_event.fault_name, replayed_event.fired
+    );
+
+    replayed_event
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayErrors {
+    #[error("Error as the speed factor {0} is not a positive number")]
+    InvalidSpeedFactorError(f64),
+    #[error("Error as the experiment log is not configured, set EXPERIMENT_LOG_PATH")]
+    NotConfiguredError,
+    #[error("Error as {0} is not a file of the experiment log directory")]
+    PathNotAllowedError(String),
+    #[error("Error as the experiment log is larger than {0} bytes")]
+    TooLargeError(u64),
+    #[error("Error reading the experiment log: {0}")]
+    ReadError(#[from] std::io::Error),
+    #[error("Error parsing the event at line {0} of the experiment log: {1}")]
+    InvalidEventError(usize, String),
+    #[error("Error reading the faults: {0}")]
+    FaultStoreError(String),
+    #[error("Error registering the metrics of the replay: {0}")]
+    MetricsError(#[from] prometheus::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fault_store::Fault;
+    use std::time::Duration;
+
+    const EXPERIMENT_LOG_PATH: &str = "testdata/experiment/experiment.jsonl";
+
+    fn fault(name: &str, fault_type: FaultVariants) -> Fault {
+        Fault {
+            duration: Some(10),
+            error_msg: Some("ERR replayed for {client_ip}".to_string()),
+            one_shot: true,
+            ..Fault::fixture(name, fault_type, "GET")
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay() {
+        let fault_store = MemStore::new_db();
+        for fault in [
+            fault("get_delay", FaultVariants::Delay),
+            fault("get_error", FaultVariants::Error),
+            fault("get_drop", FaultVariants::DropConn),
+        ] {
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+        let engine =
+            ReplayEngine::new(fault_store.clone()).with_experiment_log_path(EXPERIMENT_LOG_PATH);
+
+        // the events of the fixture are 2 seconds apart, but not recorded in order
+        let start = tokio::time::Instant::now();
+        let replayed_events = engine
+            .replay("testdata/experiment/replay.jsonl", 2.0)
+            .await
+            .unwrap();
+        assert_eq!(
+            start.elapsed(),
+            Duration::from_secs(2) + Duration::from_millis(10)
+        );
+
+        let fault_names: Vec<&str> = replayed_events
+            .iter()
+            .map(|event| event.fault_name.as_str())
+            .collect();
+        assert_eq!(fault_names, vec!["get_delay", "get_error", "get_drop"]);
+        assert!(replayed_events.iter().all(|event| event.fired));
+        assert_eq!(
+            replayed_events[1].reply.as_deref(),
+            Some("-ERR replayed for 10.0.0.1\r\n")
+        );
+        assert_eq!(replayed_events[2].action.as_deref(), Some("Exit"));
+
+        // the one-shot faults of the live store are left untouched
+        let faults = fault_store.get_all_faults().await.unwrap();
+        assert!(faults.iter().all(|fault| fault.enabled));
+    }
+
+    #[tokio::test]
+    async fn test_replay_errors() {
+        let engine =
+            ReplayEngine::new(MemStore::new_db()).with_experiment_log_path(EXPERIMENT_LOG_PATH);
+
+        for speed_factor in [0.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                engine
+                    .replay("testdata/experiment/replay.jsonl", speed_factor)
+                    .await,
+                Err(ReplayErrors::InvalidSpeedFactorError(_))
+            ));
+        }
+        assert!(matches!(
+            engine
+                .replay("testdata/experiment/unknown.jsonl", 1.0)
+                .await,
+            Err(ReplayErrors::PathNotAllowedError(_))
+        ));
+
+        // the faults that no longer exist are not injected
+        let replayed_events = engine
+            .replay("testdata/experiment/replay.jsonl", 1000.0)
+            .await
+            .unwrap();
+        assert_eq!(replayed_events.len(), 3);
+        assert!(replayed_events.iter().all(|event| !event.fired));
+    }
+
+    #[tokio::test]
+    async fn test_replay_outside_experiment_log_dir() {
+        let engine = ReplayEngine::new(MemStore::new_db());
+        assert!(matches!(
+            engine.replay("testdata/experiment/replay.jsonl", 1.0).await,
+            Err(ReplayErrors::NotConfiguredError)
+        ));
+
+        let engine = engine.with_experiment_log_path(EXPERIMENT_LOG_PATH);
+        for path in [
+            "Cargo.toml",
+            "testdata/experiment/../../Cargo.toml",
+            "/dev/zero",
+            "testdata/experiment",
+        ] {
+            assert!(
+                matches!(
+                    engine.replay(path, 1.0).await,
+                    Err(ReplayErrors::PathNotAllowedError(_))
+                ),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_too_large() {
+        let mut engine =
+            ReplayEngine::new(MemStore::new_db()).with_experiment_log_path(EXPERIMENT_LOG_PATH);
+        let size = std::fs::metadata("testdata/experiment/replay.jsonl")
+            .unwrap()
+            .len();
+
+        engine.max_log_bytes = size - 1;
+        assert!(matches!(
+            engine
+                .replay("testdata/experiment/replay.jsonl", 1000.0)
+                .await,
+            Err(ReplayErrors::TooLargeError(_))
+        ));
+
+        engine.max_log_bytes = size;
+        assert!(engine
+            .replay("testdata/experiment/replay.jsonl", 1000.0)
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,510 @@
+use crate::config::Secret;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use futures::future::{ready, Ready};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tracing::debug;
+
+/// The header that carries the API key of the fault config server requests
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// AuthConfig holds the credentials the fault config server requests are authenticated with.
+///
+/// The API keys are preferred when both the API keys and the JWT config are set. Without any
+/// credentials, all the requests are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// The API key of the [Role::Admin] role
+    pub api_key: Option<Secret>,
+    /// The API keys and their roles, along with `api_key`
+    pub api_keys: HashMap<Secret, Role>,
+    pub jwt: Option<JwtConfig>,
+}
+
+/// Role tells what the client of a fault config server request is allowed to do.
+///
+/// The [authenticate] middleware stores the role in the request extensions. The JWT Bearer tokens
+/// and the requests without configured credentials have the [Role::Admin] role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Role {
+    /// Can read and change the faults
+    #[serde(rename = "admin")]
+    Admin,
+    /// Can only read the faults, i.e. the `GET` requests
+    #[serde(rename = "read-only")]
+    ReadOnly,
+}
+
+impl Role {
+    /// Returns whether the role is allowed to send a request with the given method. The `GET`,
+    /// `HEAD` and `OPTIONS` requests are allowed for every role, the others only for
+    /// [Role::Admin].
+    pub fn allows(&self, method: &Method) -> bool {
+        *self == Role::Admin || [Method::GET, Method::HEAD, Method::OPTIONS].contains(method)
+    }
+}
+
+impl FromRequest for Role {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Role>()
+            .copied()
+            .unwrap_or(Role::Admin)))
+    }
+}
+
+/// JwtConfig holds what the JWT Bearer tokens are validated with.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// The HS256 secret the tokens are signed with
+    pub secret: Secret,
+    /// The expected `iss` claim of the tokens
+    pub issuer: String,
+}
+
+/// Claims holds the JWT claims that are validated. The `exp` claim is validated by
+/// `jsonwebtoken`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub iss: String,
+    pub exp: u64,
+    /// The optional subject of the token, which identifies the client in the audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+}
+
+/// Actor is the identity of the client of a fault config server request, i.e. `api_key` for the
+/// API key, the `sub` claim (or the `iss` claim, without a subject) of the JWT, or `anonymous`
+/// when no credentials are configured.
+///
+/// The [authenticate] middleware stores the actor in the request extensions. Handlers extract it
+/// like any other argument, falling back to `anonymous` when the middleware is not in use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actor(pub String);
+
+impl Actor {
+    pub fn anonymous() -> Self {
+        Actor("anonymous".to_string())
+    }
+}
+
+impl FromRequest for Actor {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Actor>()
+            .cloned()
+            .unwrap_or_else(Actor::anonymous)))
+    }
+}
+
+impl AuthConfig {
+    /// Returns the actor of the request and its role when the request headers carry the
+    /// configured credentials, None otherwise.
+    pub(crate) fn authorize(&self, headers: &HeaderMap) -> Option<(Actor, Role)> {
+        if self.api_key.is_some() || !self.api_keys.is_empty() {
+            let key = headers.get(API_KEY_HEADER)?;
+            return self
+                .api_key
+                .iter()
+                .map(|api_key| (api_key, Role::Admin))
+                .chain(self.api_keys.iter().map(|(api_key, role)| (api_key, *role)))
+                .find(|(api_key, _)| constant_time_eq(key.as_bytes(), api_key.0.as_bytes()))
+                .map(|(_, role)| (Actor("api_key".to_string()), role));
+        }
+
+        match &self.jwt {
+            Some(jwt) => headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .and_then(|token| jwt.validate(token))
+                .map(|claims| (Actor(claims.sub.unwrap_or(claims.iss)), Role::Admin)),
+            None => Some((Actor::anonymous(), Role::Admin)),
+        }
+    }
+}
+
+impl JwtConfig {
+    /// Returns the claims of the token if the token is signed with the secret, not expired and
+    /// issued by the expected issuer.
+    fn validate(&self, token: &str) -> Option<Claims> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss"]);
+
+        match decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret.0.as_bytes()),
+            &validation,
+        ) {
+            Ok(token_data) => Some(token_data.claims),
+            Err(err) => {
+                debug!("Invalid JWT: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/// Compares the given bytes in a time that doesn't depend on the position of the first mismatch,
+/// such that the API key can't be guessed byte by byte from the response time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// authenticate is the middleware that rejects the requests without the credentials of the
+/// [AuthConfig] in the app data with HTTP Unauthorized 401, and the requests that the [Role] of
+/// the credentials doesn't allow with HTTP Forbidden 403. The [Actor] and the [Role] of an
+/// authorized request are stored in the request extensions.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let authorization = match req.app_data::<web::Data<AuthConfig>>() {
+        Some(auth_config) => auth_config.authorize(req.headers()),
+        None => Some((Actor::anonymous(), Role::Admin)),
+    };
+
+    let (actor, role) = match authorization {
+        Some(authorization) => authorization,
+        None => {
+            debug!("Unauthorized request to {}", req.path());
+            let response =
+                HttpResponse::Unauthorized().json(serde_json::json!({"error": "unauthorized"}));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    };
+    if !role.allows(req.method()) {
+        debug!(
+            "Forbidden {} request to {} with the {:?} role",
+            req.method(),
+            req.path(),
+            role
+        );
+        let response = HttpResponse::Forbidden().json(serde_json::json!({"error": "forbidden"}));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+    req.extensions_mut().insert(actor);
+    req.extensions_mut().insert(role);
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, App};
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn actor(actor: Actor) -> HttpResponse {
+        HttpResponse::Ok().body(actor.0)
+    }
+
+    #[tokio::test]
+    async fn test_api_key_auth() {
+        let auth_config = AuthConfig {
+            api_key: Some(Secret("s3cr3t".to_string())),
+            api_keys: HashMap::new(),
+            jwt: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(authenticate))
+                .route("/faults", web::get().to(ok))
+                .app_data(web::Data::new(auth_config)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/faults")
+            .insert_header((API_KEY_HEADER, "s3cr3t"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for api_key in [Some("wrong"), Some("s3cr3t "), None] {
+            let mut req = test::TestRequest::get().uri("/faults");
+            if let Some(api_key) = api_key {
+                req = req.insert_header((API_KEY_HEADER, api_key));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+            let body: serde_json::Value =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            assert_eq!(body, serde_json::json!({"error": "unauthorized"}));
+        }
+    }
+
+    async fn role(role: Role) -> HttpResponse {
+        HttpResponse::Ok().body(format!("{:?}", role))
+    }
+
+    #[tokio::test]
+    async fn test_api_key_roles() {
+        let auth_config = AuthConfig {
+            api_key: None,
+            api_keys: HashMap::from([
+                (Secret("s3cr3t".to_string()), Role::Admin),
+                (Secret("g3t0nly".to_string()), Role::ReadOnly),
+            ]),
+            jwt: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(authenticate))
+                .route("/faults", web::get().to(role))
+                .route("/faults", web::post().to(role))
+                .route("/faults", web::put().to(role))
+                .route("/faults", web::patch().to(role))
+                .route("/faults", web::delete().to(role))
+                .app_data(web::Data::new(auth_config)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/faults")
+            .insert_header((API_KEY_HEADER, "g3t0nly"))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "ReadOnly");
+
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri("/faults")
+                .insert_header((API_KEY_HEADER, "g3t0nly"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", method);
+
+            let body: serde_json::Value =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            assert_eq!(body, serde_json::json!({"error": "forbidden"}));
+
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri("/faults")
+                .insert_header((API_KEY_HEADER, "s3cr3t"))
+                .to_request();
+            assert_eq!(test::call_and_read_body(&app, req).await, "Admin");
+        }
+
+        // an unknown key is unauthorized rather than forbidden
+        let req = test::TestRequest::post()
+            .uri("/faults")
+            .insert_header((API_KEY_HEADER, "wrong"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    fn token(secret: &str, issuer: &str, exp: u64) -> String {
+        token_with_subject(secret, issuer, exp, None)
+    }
+
+    fn token_with_subject(secret: &str, issuer: &str, exp: u64, sub: Option<&str>) -> String {
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(Algorithm::HS256),
+            &Claims {
+                iss: issuer.to_string(),
+                exp,
+                sub: sub.map(|sub| sub.to_string()),
+            },
+            &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth() {
+        let auth_config = AuthConfig {
+            api_key: None,
+            api_keys: HashMap::new(),
+            jwt: Some(JwtConfig {
+                secret: Secret("jwt-s3cr3t".to_string()),
+                issuer: "red-monkey".to_string(),
+            }),
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(authenticate))
+                .route("/faults", web::get().to(ok))
+                .app_data(web::Data::new(auth_config)),
+        )
+        .await;
+
+        let now = jsonwebtoken::get_current_timestamp();
+        let cases = [
+            (token("jwt-s3cr3t", "red-monkey", now + 600), StatusCode::OK),
+            (
+                token("jwt-s3cr3t", "red-monkey", now - 3600),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                token("wrong-secret", "red-monkey", now + 600),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                token("jwt-s3cr3t", "someone-else", now + 600),
+                StatusCode::UNAUTHORIZED,
+            ),
+            ("not-a-jwt".to_string(), StatusCode::UNAUTHORIZED),
+        ];
+
+        for (token, status) in cases {
+            let req = test::TestRequest::get()
+                .uri("/faults")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status);
+        }
+
+        let req = test::TestRequest::get().uri("/faults").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_preferred_over_jwt() {
+        let auth_config = AuthConfig {
+            api_key: Some(Secret("s3cr3t".to_string())),
+            api_keys: HashMap::new(),
+            jwt: Some(JwtConfig {
+                secret: Secret("jwt-s3cr3t".to_string()),
+                issuer: "red-monkey".to_string(),
+            }),
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(authenticate))
+                .route("/faults", web::get().to(ok))
+                .app_data(web::Data::new(auth_config)),
+        )
+        .await;
+
+        let token = token(
+            "jwt-s3cr3t",
+            "red-monkey",
+            jsonwebtoken::get_current_timestamp() + 600,
+        );
+        let req = test::TestRequest::get()
+            .uri("/faults")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/faults")
+            .insert_header((API_KEY_HEADER, "s3cr3t"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_no_api_key_configured() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(authenticate))
+                .route("/faults", web::get().to(ok))
+                .app_data(web::Data::new(AuthConfig::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/faults").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_actor() {
+        let jwt = JwtConfig {
+            secret: Secret("jwt-s3cr3t".to_string()),
+            issuer: "red-monkey".to_string(),
+        };
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+        let cases = [
+            (
+                AuthConfig {
+                    api_key: Some(Secret("s3cr3t".to_string())),
+                    api_keys: HashMap::new(),
+                    jwt: None,
+                },
+                (API_KEY_HEADER, "s3cr3t".to_string()),
+                "api_key",
+            ),
+            (
+                AuthConfig {
+                    api_key: None,
+                    api_keys: HashMap::new(),
+                    jwt: Some(jwt.clone()),
+                },
+                (
+                    header::AUTHORIZATION.as_str(),
+                    format!(
+                        "Bearer {}",
+                        token_with_subject("jwt-s3cr3t", "red-monkey", exp, Some("alice"))
+                    ),
+                ),
+                "alice",
+            ),
+            (
+                AuthConfig {
+                    api_key: None,
+                    api_keys: HashMap::new(),
+                    jwt: Some(jwt),
+                },
+                (
+                    header::AUTHORIZATION.as_str(),
+                    format!("Bearer {}", token("jwt-s3cr3t", "red-monkey", exp)),
+                ),
+                "red-monkey",
+            ),
+            (
+                AuthConfig::default(),
+                (API_KEY_HEADER, "ignored".to_string()),
+                "anonymous",
+            ),
+        ];
+
+        for (auth_config, credentials, expected_actor) in cases {
+            let app = test::init_service(
+                App::new()
+                    .wrap(from_fn(authenticate))
+                    .route("/actor", web::get().to(actor))
+                    .app_data(web::Data::new(auth_config)),
+            )
+            .await;
+
+            let req = test::TestRequest::get()
+                .uri("/actor")
+                .insert_header(credentials)
+                .to_request();
+            let body = test::call_and_read_body(&app, req).await;
+            assert_eq!(body, expected_actor);
+        }
+    }
+}
@@ -1,10 +1,35 @@
-use crate::store::fault_store::{Fault, DB};
-use chrono::Utc;
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::events::{FaultEvent, FaultEventSender};
+use crate::experiment::replay::{ReplayEngine, ReplayErrors, ReplayedEvent};
+use crate::experiment::ExperimentLog;
+use crate::fault_config_server::auth::Actor;
+use crate::fault_config_server::version::API_VERSION;
+use crate::metrics::Metrics;
+use crate::reload::LogLevelHandle;
+use crate::store::fault_store::{
+    diff_faults, ArgMatcher, BatchStoreError, Fault, FaultDiff, FaultPlaybook, FaultSearchQuery,
+    FaultSelectionStrategy, FaultStats, FaultVariants, MatchType, PlaybookDB, StoreError, SubFault,
+    DB, WILDCARD_COMMAND,
+};
+use actix_multipart::{Multipart, MultipartError};
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
 use std::string::ToString;
-use tracing::{debug, error, info};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
+use utoipa::{IntoParams, ToSchema};
 
 use actix_web::{
-    http::{header::ContentType, StatusCode},
+    http::{
+        header::{self, ContentType},
+        StatusCode,
+    },
     HttpResponseBuilder, ResponseError,
 };
 use actix_web::{web, HttpRequest, HttpResponse};
@@ -14,43 +39,70 @@ use actix_web::{web, HttpRequest, HttpResponse};
 /// 1. When the fault is successfully stored in the fault store, HTTP Created 201 is retuned.
 /// 2. For invalid POST body payload, HTTP Bad request 400 is returned.
 /// 3. When the fault that is posted conflicts with the current state of the fault store, HTTP
-///    Conflict 409 is returned.
-/// 4. If the fault type is not one of [`delay`, `error`, `drop`] value, HTTP Bad request would be returned.
+///    Conflict 409 is returned, see [check_conflicts].
+/// 4. If the fault type is not one of the [FaultVariants], e.g. `Delay`, `Error` or `DropConn`
+///    (also accepted as `drop_conn`), HTTP Bad request 400 is returned.
 /// 5. When the fault fails to be stored in the fault store, HTTP Internal Server Error 500 is returned.
-#[tracing::instrument(skip(fault_store))]
+/// 6. If the fault fails the validation (see [validate_fault]), HTTP Bad request 400 is returned.
+///
+/// With `?dry_run=true`, the fault is validated and previewed like in POST /fault/validate (see
+/// [preview_fault]) without being stored. With `?proxy_port=<port>`, the fault is scoped to the
+/// given proxy port, see [ProxyPortParams].
+#[utoipa::path(
+    post,
+    path = "/fault",
+    tag = "faults",
+    params(DryRunParams, ProxyPortParams),
+    request_body = Fault,
+    responses(
+        (status = 200, description = "The preview of the fault that is not stored with ?dry_run=true", body = FaultPreview),
+        (status = 201, description = "The fault is created"),
+        (status = 400, description = "The fault is invalid", body = ServerErrorResponse),
+        (status = 409, description = "The fault conflicts with an existing fault", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(request, fault_store, audit_log))]
 pub async fn store_fault(
+    request: HttpRequest,
     fault: web::Json<Fault>,
+    params: web::Query<DryRunParams>,
+    scope: web::Query<ProxyPortParams>,
+    actor: Actor,
     fault_store: web::Data<DB>,
-) -> Result<HttpResponseBuilder, ServerErrorResponse> {
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let mut fault = fault.into_inner();
+    scope.apply(&mut fault)?;
+
+    if params.dry_run == Some(true) {
+        info!("Dry run of creating fault: {:?}", fault.name);
+        let preview = fault_preview(&request, fault, &fault_store).await?;
+        return Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(preview));
+    }
+
     info!("Create fault: fault name: {:?}", fault.name);
-    let mut fault = fault.clone();
     fault.last_modified = Some(Utc::now());
 
+    validate_fault(&fault)?;
+
     let faults = fault_store
-        .read()
-        .await
         .get_all_faults()
+        .await
         .map_err(|err| ServerErrorResponse {
             status_code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             message: err.message,
         })?;
 
-    for f in faults {
-        if f.command == fault.command {
-            return Err(ServerErrorResponse::new(
-                StatusCode::CONFLICT,
-                format!(
-                    "There already exists a fault for the same {} command",
-                    fault.command
-                ),
-            ));
-        }
-    }
+    check_conflicts(&fault, &faults, selection_strategy(&request))?;
 
-    match fault_store.write().await.store(&fault.name, &fault) {
+    match fault_store.store(&fault.name, &fault).await {
         Ok(_) => {
             info!("Fault {} created in the store", fault.name);
-            Ok(HttpResponse::Created())
+            audit(&audit_log, AuditAction::Created, &actor, &fault);
+            Ok(HttpResponse::Created().finish())
         }
 
         Err(err) => {
@@ -63,342 +115,5900 @@ pub async fn store_fault(
     }
 }
 
-/// get_fault is the handler of GET /fault/<fault_name> endpoint.
+/// DryRunParams holds the optional `dry_run` query param of the POST /fault endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DryRunParams {
+    /// Validates and previews the fault without storing it
+    pub dry_run: Option<bool>,
+}
+
+/// ProxyPortParams holds the optional `proxy_port` query param that scopes the fault operations
+/// to the faults of a proxy port, see `PROXY_PORTS`.
 ///
-/// 1. On successful fetch, returns the fault configuration of the given fault <fault_name> with
-///    HTTP status OK.
-/// 2. If the given fault name is not available in the fault store, HTTP Bad request 400 is
+/// - POST /fault and POST /faults scope the created faults to the port. A fault that is scoped to
+///   another port in its body is rejected with HTTP Bad request 400.
+/// - GET /faults and DELETE /faults only list and delete the faults scoped to the port.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProxyPortParams {
+    /// Scopes the operation to the faults of the given proxy port
+    pub proxy_port: Option<u16>,
+}
+
+impl ProxyPortParams {
+    /// Scopes the fault to the proxy port of the params, if any.
+    fn apply(&self, fault: &mut Fault) -> Result<(), ServerErrorResponse> {
+        let proxy_port = match self.proxy_port {
+            Some(proxy_port) => proxy_port,
+            None => return Ok(()),
+        };
+
+        if fault
+            .proxy_port
+            .is_some_and(|fault_proxy_port| fault_proxy_port != proxy_port)
+        {
+            return Err(ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "The proxy_port of fault {} doesn't match the proxy_port query param {}",
+                    fault.name, proxy_port
+                ),
+            ));
+        }
+        fault.proxy_port = Some(proxy_port);
+
+        Ok(())
+    }
+
+    /// Returns true if the fault is scoped to the proxy port of the params, or any fault when the
+    /// params have no proxy port.
+    fn matches(&self, fault: &Fault) -> bool {
+        self.proxy_port
+            .is_none_or(|proxy_port| fault.proxy_port == Some(proxy_port))
+    }
+}
+
+/// FaultPreview is the response body of the POST /fault/validate endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FaultPreview {
+    /// The fault as it would be stored
+    pub fault: Fault,
+    /// The names of the existing faults that the fault conflicts with (see [check_conflicts]).
+    /// The fault can't be stored until they are deleted.
+    pub conflicting_faults: Vec<String>,
+}
+
+/// preview_fault is the handler of POST /fault/validate endpoint. It is the dry run of POST
+/// /fault, i.e. the fault is validated in the same way but the fault store is left untouched.
+///
+/// 1. When the fault is valid, its preview is returned with HTTP status OK. The preview lists the
+///    existing faults that the fault conflicts with, if any.
+/// 2. For invalid POST body payload, or if the fault fails the validation (see
+///    [validate_fault]), HTTP Bad request 400 is returned.
+/// 3. When the faults fail to be fetched from the fault store, HTTP Internal Server Error 500 is
 ///    returned.
-#[tracing::instrument(skip(fault_store, request))]
-pub async fn get_fault(
+#[utoipa::path(
+    post,
+    path = "/fault/validate",
+    tag = "faults",
+    request_body = Fault,
+    responses(
+        (status = 200, description = "The preview of the valid fault", body = FaultPreview),
+        (status = 400, description = "The fault is invalid", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(request, fault_store))]
+pub async fn preview_fault(
     request: HttpRequest,
+    fault: web::Json<Fault>,
     fault_store: web::Data<DB>,
 ) -> Result<HttpResponse, ServerErrorResponse> {
-    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
-        ServerErrorResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Error fetching fault name from the request path".to_string(),
-        )
-    })?;
-    info!("Fetch fault by name: {:?}", fault_name);
+    info!("Validate fault: {:?}", fault.name);
+    let preview = fault_preview(&request, fault.into_inner(), &fault_store).await?;
 
-    match fault_store.read().await.get_by_fault_name(fault_name) {
-        Ok(fault) => {
-            info!("Fault {} fetched from the store", fault_name);
-            Ok(HttpResponse::Ok()
-                .content_type(ContentType::json())
-                .json(fault))
-        }
-        Err(err) => {
-            error!("Error fetching fault {}: {}", fault_name, err);
-            Err(ServerErrorResponse::new(
-                StatusCode::BAD_REQUEST,
-                err.to_string(),
-            ))
-        }
-    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(preview))
 }
 
-/// get_all_faults is the handler of GET /faults endpoint.
+/// Validates the fault like POST /fault and returns its preview, without storing it.
+async fn fault_preview(
+    request: &HttpRequest,
+    mut fault: Fault,
+    fault_store: &DB,
+) -> Result<FaultPreview, ServerErrorResponse> {
+    fault.last_modified = Some(Utc::now());
+    validate_fault(&fault)?;
+
+    let faults = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?;
+    let conflicting_faults = faults
+        .iter()
+        .filter(|f| {
+            check_conflicts(&fault, std::slice::from_ref(f), selection_strategy(request)).is_err()
+        })
+        .map(|f| f.name.clone())
+        .collect();
+
+    Ok(FaultPreview {
+        fault,
+        conflicting_faults,
+    })
+}
+
+/// BatchParams holds the optional `atomic` query param of the POST /faults endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BatchParams {
+    /// Rejects the whole batch on the first invalid fault
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// BatchItemResult is the result of storing a single fault of the POST /faults endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchItemResult {
+    pub name: String,
+    pub status_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// store_faults is the handler of POST /faults endpoint that stores a batch of faults.
 ///
-/// 1. On success fetch, returns all the fault configurations with HTTP status 200.
-/// 2. If unable to fetch the fault configurations from the fault store, HTTP Internal Server Error
-///    is returned.
-#[tracing::instrument(skip(fault_store))]
-pub async fn get_all_faults(
+/// Each fault is validated like in POST /fault (see [validate_fault] and [check_conflicts]). The
+/// faults are checked for conflicts against the fault store and the preceding faults of the
+/// batch.
+///
+/// 1. By default, the valid faults are stored and HTTP Multi-Status 207 is returned with the
+///    result of each fault, i.e. 201 when the fault is stored, or the 400 / 409 error of the
+///    fault.
+/// 2. With `?atomic=true`, the whole batch is rejected on the first invalid fault with its 400 /
+///    409 error, and no fault is stored. Otherwise, all the faults are stored together with
+///    [FaultStore::store_batch_atomic], which rolls the batch back when a fault fails to be
+///    stored, and HTTP Multi-Status 207 is returned.
+/// 3. When the faults fail to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+#[utoipa::path(
+    post,
+    path = "/faults",
+    tag = "faults",
+    params(BatchParams, ProxyPortParams),
+    request_body = Vec<Fault>,
+    responses(
+        (status = 207, description = "The result of each fault of the batch", body = Vec<BatchItemResult>),
+        (status = 400, description = "A fault of the atomic batch is invalid", body = ServerErrorResponse),
+        (status = 409, description = "A fault of the atomic batch conflicts with another fault", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(request, fault_store, faults, audit_log))]
+pub async fn store_faults(
+    request: HttpRequest,
+    faults: web::Json<Vec<Fault>>,
+    params: web::Query<BatchParams>,
+    scope: web::Query<ProxyPortParams>,
+    actor: Actor,
     fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
 ) -> Result<HttpResponse, ServerErrorResponse> {
-    info!("Fetch all faults");
-    let faults = fault_store.read().await.get_all_faults();
+    info!("Create {} faults, atomic: {}", faults.len(), params.atomic);
 
-    match faults {
-        Ok(mut faults) => {
-            faults.sort_by(|a, b| {
-                b.last_modified
-                    .unwrap()
-                    .timestamp()
-                    .cmp(&a.last_modified.unwrap().timestamp())
-            });
+    let mut accepted_faults = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?;
+    let num_existing_faults = accepted_faults.len();
 
-            Ok(HttpResponse::Ok()
-                .content_type(ContentType::json())
-                .json(faults))
+    let results = check_batch(
+        faults.into_inner(),
+        &mut accepted_faults,
+        &scope,
+        selection_strategy(&request),
+        params.atomic,
+    )?;
+
+    let new_faults = &accepted_faults[num_existing_faults..];
+    if params.atomic {
+        if let Err(err) = fault_store.store_batch_atomic(new_faults).await {
+            error!(
+                "Error storing the atomic batch of faults in the store: {}",
+                err
+            );
+            let status_code = match err {
+                BatchStoreError::InvalidBatchError(_) => StatusCode::BAD_REQUEST,
+                BatchStoreError::StoreError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return Err(ServerErrorResponse::new(status_code, err.to_string()));
         }
-        Err(err) => {
-            error!("Error fetching all faults: {}", err);
+    } else if let Err(err) = fault_store.store_batch(new_faults).await {
+        error!("Error storing the batch of faults in the store: {}", err);
+        return Err(ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            err.message,
+        ));
+    }
+    info!(
+        "{} faults of the batch created in the store",
+        new_faults.len()
+    );
+    for fault in new_faults {
+        audit(&audit_log, AuditAction::Created, &actor, fault);
+    }
+
+    Ok(HttpResponse::MultiStatus()
+        .content_type(ContentType::json())
+        .json(results))
+}
+
+/// check_batch validates each fault of the batch like in POST /fault (see [validate_fault] and
+/// [check_conflicts]) against the given accepted faults and the preceding faults of the batch,
+/// and appends the valid faults to the accepted faults. It returns the result of each fault, i.e.
+/// 201 when the fault is valid, or the 400 / 409 error of the fault. With `atomic`, the error of
+/// the first invalid fault is returned instead.
+fn check_batch(
+    faults: Vec<Fault>,
+    accepted_faults: &mut Vec<Fault>,
+    scope: &ProxyPortParams,
+    strategy: FaultSelectionStrategy,
+    atomic: bool,
+) -> Result<Vec<BatchItemResult>, ServerErrorResponse> {
+    let num_existing_faults = accepted_faults.len();
+
+    let mut results = Vec::new();
+    for mut fault in faults {
+        fault.last_modified = Some(Utc::now());
+
+        let is_duplicate_name = accepted_faults[num_existing_faults..]
+            .iter()
+            .any(|f| f.name == fault.name);
+        let validation = if is_duplicate_name {
             Err(ServerErrorResponse::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                err.message,
+                StatusCode::CONFLICT,
+                format!("Fault {} is repeated in the batch", fault.name),
             ))
+        } else {
+            scope
+                .apply(&mut fault)
+                .and_then(|_| validate_fault(&fault))
+                .and_then(|_| check_conflicts(&fault, accepted_faults, strategy))
+        };
+
+        match validation {
+            Ok(_) => {
+                results.push(BatchItemResult {
+                    name: fault.name.clone(),
+                    status_code: StatusCode::CREATED.as_u16(),
+                    message: None,
+                });
+                accepted_faults.push(fault);
+            }
+            Err(err) if atomic => {
+                error!("Rejecting the batch as fault {} is invalid", fault.name);
+                return Err(ServerErrorResponse::new(
+                    StatusCode::from_u16(err.status_code)
+                        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                    format!("Fault {}: {}", fault.name, err.message),
+                ));
+            }
+            Err(err) => results.push(BatchItemResult {
+                name: fault.name,
+                status_code: err.status_code,
+                message: Some(err.message),
+            }),
         }
     }
+
+    Ok(results)
 }
 
-/// delete_fault is the handler of DELETE /fault/<fault_name> endpoint.
+/// The default maximum size of the fault file of the POST /faults/import endpoint, 1 MiB
+pub const DEFAULT_IMPORT_MAX_BYTES: usize = 1024 * 1024;
+
+/// The multipart form field that holds the fault file of the POST /faults/import endpoint
+const IMPORT_FILE_FIELD: &str = "file";
+
+/// ImportLimit is the maximum size in bytes of the fault file of the POST /faults/import
+/// endpoint, [DEFAULT_IMPORT_MAX_BYTES] when it is not in the app data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportLimit(pub usize);
+
+/// ImportParams holds the optional `merge` query param of the POST /faults/import endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// Keeps the faults of the store, otherwise they are all replaced by the imported faults
+    #[serde(default = "default_merge")]
+    pub merge: bool,
+}
+
+fn default_merge() -> bool {
+    true
+}
+
+/// ImportForm is the multipart form of the POST /faults/import endpoint.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ImportForm {
+    /// The JSON file of the faults, i.e. a JSON array of faults like the body of POST /faults
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// import_faults is the handler of POST /faults/import endpoint that stores the faults of a JSON
+/// file, e.g. a fault library kept in version control.
 ///
-/// 1. DELETE /fault/<fault_name> endpoint is idempotent.
-/// 2. On successful delete, HTTP No Content 204 status is returned.
-/// 3. On failing to delete the given fault <fault_name>, HTTP Internal Server Error 500 is returned.
-#[tracing::instrument(skip(fault_store, request))]
-pub async fn delete_fault(
+/// The file is the `file` field of the `multipart/form-data` body. Its faults are validated and
+/// stored like the batch of POST /faults, see [store_faults].
+///
+/// 1. The valid faults are stored and HTTP Multi-Status 207 is returned with the result of each
+///    fault, i.e. 201 when the fault is stored, or the 400 / 409 error of the fault.
+/// 2. With `?merge=false`, the file replaces the faults of the store: the faults of the file are
+///    stored all together with [FaultStore::store_batch_atomic], and only then the faults of the
+///    store that are not in the file are deleted. When the faults of the file fail to be stored,
+///    the faults of the store are left as they were.
+/// 3. When the file is missing or is not a JSON array of faults, HTTP Bad request 400 is returned.
+/// 4. When the file is larger than the [ImportLimit], HTTP Payload Too Large 413 is returned.
+/// 5. When the faults fail to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+#[utoipa::path(
+    post,
+    path = "/faults/import",
+    tag = "faults",
+    params(ImportParams),
+    request_body(content = ImportForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 207, description = "The result of each fault of the file", body = Vec<BatchItemResult>),
+        (status = 400, description = "The file is missing or invalid", body = ServerErrorResponse),
+        (status = 413, description = "The file is too large", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(request, payload, fault_store, audit_log))]
+pub async fn import_faults(
     request: HttpRequest,
+    payload: Multipart,
+    params: web::Query<ImportParams>,
+    actor: Actor,
     fault_store: web::Data<DB>,
-) -> Result<HttpResponseBuilder, ServerErrorResponse> {
-    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let max_bytes = request
+        .app_data::<web::Data<ImportLimit>>()
+        .map_or(DEFAULT_IMPORT_MAX_BYTES, |limit| limit.0);
+    let file = read_import_file(payload, max_bytes).await?;
+    let faults: Vec<Fault> = serde_json::from_slice(&file).map_err(|err| {
         ServerErrorResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Error fetching fault name from the request path".to_string(),
+            StatusCode::BAD_REQUEST,
+            format!("Invalid fault file: {}", err),
         )
     })?;
-    info!("Delete fault: {}", fault_name);
+    info!("Import {} faults, merge: {}", faults.len(), params.merge);
 
-    match fault_store.write().await.delete_fault(fault_name) {
-        Ok(_) => {
-            debug!("Deleted fault: {:?}", fault_name);
-            Ok(HttpResponse::NoContent())
-        }
-        Err(err) => {
-            error!("Error deleting fault {}: {}", fault_name, err);
-            Err(ServerErrorResponse::new(
+    let existing_faults = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?;
+    // without merging, the faults of the file only conflict with each other
+    let mut accepted_faults = if params.merge {
+        existing_faults.clone()
+    } else {
+        Vec::new()
+    };
+    let num_kept_faults = accepted_faults.len();
+
+    let results = check_batch(
+        faults,
+        &mut accepted_faults,
+        &ProxyPortParams::default(),
+        selection_strategy(&request),
+        false,
+    )?;
+
+    let new_faults = &accepted_faults[num_kept_faults..];
+    if params.merge {
+        if let Err(err) = fault_store.store_batch(new_faults).await {
+            error!("Error storing the imported faults in the store: {}", err);
+            return Err(ServerErrorResponse::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 err.message,
-            ))
+            ));
+        }
+    } else if let Err(err) = fault_store.store_batch_atomic(new_faults).await {
+        error!("Error storing the imported faults in the store: {}", err);
+        let status_code = match err {
+            BatchStoreError::InvalidBatchError(_) => StatusCode::BAD_REQUEST,
+            BatchStoreError::StoreError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        return Err(ServerErrorResponse::new(status_code, err.to_string()));
+    }
+    info!("{} imported faults created in the store", new_faults.len());
+    for fault in new_faults {
+        audit(&audit_log, AuditAction::Created, &actor, fault);
+    }
+
+    if !params.merge {
+        // the faults of the store are only deleted once the faults of the file are stored
+        let replaced_faults = existing_faults.iter().filter(|fault| {
+            !new_faults
+                .iter()
+                .any(|new_fault| new_fault.name == fault.name)
+        });
+        for fault in replaced_faults {
+            fault_store.delete_fault(&fault.name).await.map_err(|err| {
+                error!("Error deleting the faults replaced by the import: {}", err);
+                ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+            })?;
+            audit(&audit_log, AuditAction::Deleted, &actor, fault);
         }
     }
+
+    Ok(HttpResponse::MultiStatus()
+        .content_type(ContentType::json())
+        .json(results))
 }
 
-/// delete_all_faults is the handler for DELETE /faults.
+/// The number of serialized faults that GET /faults/export buffers ahead of the client
+const EXPORT_CHANNEL_CAPACITY: usize = 16;
+
+/// ExportParams holds the optional `group` query param of the GET /faults/export endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    /// Exports only the faults of the given fault group
+    pub group: Option<String>,
+}
+
+/// export_faults is the handler of GET /faults/export endpoint that returns the faults as a JSON
+/// file, which can be loaded back with POST /faults/import.
 ///
-/// DELETE /faults endpoint is idempotent.
-/// On successful delete, it returns 204 No Content HTTP status.
-/// On failing to delete all faults, returns HTTP Internal Server Error 500 status.
+/// The faults that are not expired are returned as a JSON array sorted by name, with the
+/// `Content-Disposition: attachment; filename="faults-<timestamp>.json"` header. With
+/// `?group=<group>`, only the faults of the fault group are exported. The body is streamed one
+/// fault at a time rather than serialized as a whole.
+/// On failing to fetch the faults, returns HTTP Internal Server Error 500 status.
+#[utoipa::path(
+    get,
+    path = "/faults/export",
+    tag = "faults",
+    params(ExportParams),
+    responses(
+        (status = 200, description = "The JSON file of the faults", body = [Fault]),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
 #[tracing::instrument(skip(fault_store))]
-pub async fn delete_all_faults(
+pub async fn export_faults(
+    params: web::Query<ExportParams>,
     fault_store: web::Data<DB>,
-) -> Result<HttpResponseBuilder, ServerErrorResponse> {
-    debug!("Delete all faults");
-
-    let fault_store = fault_store.write().await;
-    let faults = fault_store.get_all_faults().map_err(|err| {
-        error!("Error fetching all faults: {}", err);
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let faults = match &params.group {
+        Some(group) => fault_store.get_faults_by_group(group).await,
+        None => fault_store.get_all_faults().await,
+    };
+    let mut faults = faults.map_err(|err| {
+        error!("Error fetching the faults to export: {}", err);
         ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
     })?;
+    faults.retain(|fault| !fault.is_expired());
+    faults.sort_by(|a, b| a.name.cmp(&b.name));
+    info!("Export {} faults", faults.len());
 
-    for fault in faults {
-        match fault_store.delete_fault(fault.name.as_str()) {
-            Ok(_) => {
-                info!("Deleted fault: {}", fault.name);
+    let (mut tx, rx) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        let mut separator = "[";
+        for fault in faults {
+            let mut chunk = separator.as_bytes().to_vec();
+            separator = ",";
+            let chunk = serde_json::to_writer(&mut chunk, &fault).map(|_| web::Bytes::from(chunk));
+            if tx.send(chunk).await.is_err() {
+                debug!("The client of the fault export went away");
+                return;
             }
-            Err(err) => {
-                error!("Error deleting fault {}: {}", fault.name, err);
+        }
+
+        let end = if separator == "[" { "[]" } else { "]" };
+        let _ = tx.send(Ok(web::Bytes::from_static(end.as_bytes()))).await;
+    });
+
+    let filename = format!("faults-{}.json", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header(header::ContentDisposition::attachment(filename))
+        .streaming(rx))
+}
+
+/// Reads the fault file of the `file` field of the multipart form, failing with HTTP Payload Too
+/// Large 413 as soon as the file exceeds the given size.
+async fn read_import_file(
+    mut payload: Multipart,
+    max_bytes: usize,
+) -> Result<Vec<u8>, ServerErrorResponse> {
+    let invalid_form = |err: MultipartError| {
+        ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid multipart form: {}", err),
+        )
+    };
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(invalid_form)?;
+        if field.name() != IMPORT_FILE_FIELD {
+            continue;
+        }
+
+        let mut file = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(invalid_form)?;
+            if file.len() + chunk.len() > max_bytes {
                 return Err(ServerErrorResponse::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    err.message,
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("The fault file is larger than {} bytes", max_bytes),
                 ));
             }
+            file.extend_from_slice(&chunk);
         }
+
+        return Ok(file);
     }
 
-    debug!("Deleted all faults from the store");
-    Ok(HttpResponse::NoContent())
+    Err(ServerErrorResponse::new(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "The {} field of the fault file is missing",
+            IMPORT_FILE_FIELD
+        ),
+    ))
 }
 
-#[derive(serde::Serialize)]
-pub struct ServerErrorResponse {
-    status_code: u16,
-    message: String,
+/// Returns the fault selection strategy in the app data, [FaultSelectionStrategy::Priority] when
+/// it is not set.
+fn selection_strategy(request: &HttpRequest) -> FaultSelectionStrategy {
+    request
+        .app_data::<web::Data<FaultSelectionStrategy>>()
+        .map_or_else(FaultSelectionStrategy::default, |strategy| {
+            *strategy.get_ref()
+        })
 }
 
-impl ServerErrorResponse {
-    fn new(status_code: StatusCode, message: String) -> Self {
-        Self {
-            status_code: status_code.as_u16(),
-            message,
+/// check_conflicts returns HTTP Conflict 409 when the fault conflicts with any of the given
+/// faults. A fault always conflicts with a fault of the same name, which it would otherwise
+/// replace. With [FaultSelectionStrategy::Priority], at most one fault per command
+/// (case-insensitive) and one wildcard (`*`) fault is allowed at a time. With
+/// [FaultSelectionStrategy::Weighted], the faults of the same command don't conflict, as the fault
+/// applied to a request is sampled among the faults of its command. The faults scoped to different
+/// proxy ports don't conflict either, as they are never applied to the same request.
+pub(crate) fn check_conflicts(
+    fault: &Fault,
+    faults: &[Fault],
+    strategy: FaultSelectionStrategy,
+) -> Result<(), ServerErrorResponse> {
+    for f in faults {
+        if f.name == fault.name {
+            return Err(ServerErrorResponse::new(
+                StatusCode::CONFLICT,
+                format!("There already exists a fault named {}", fault.name),
+            ));
+        }
+
+        if strategy == FaultSelectionStrategy::Weighted {
+            continue;
+        }
+
+        if f.proxy_port
+            .zip(fault.proxy_port)
+            .is_some_and(|(a, b)| a != b)
+        {
+            continue;
+        }
+
+        if f.command == WILDCARD_COMMAND && fault.command == WILDCARD_COMMAND {
+            return Err(ServerErrorResponse::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "There already exists a wildcard fault {}; only one is allowed at a time",
+                    f.name
+                ),
+            ));
+        }
+
+        if f.command.eq_ignore_ascii_case(&fault.command) {
+            return Err(ServerErrorResponse::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "There already exists a fault for the same {} command",
+                    fault.command
+                ),
+            ));
         }
     }
+
+    Ok(())
 }
 
-impl std::fmt::Debug for ServerErrorResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "error: {}", self.message)
+/// validate_fault checks the field constraints of a fault before it is stored.
+///
+/// 1. `expires_at`, when set, must not be in the past.
+/// 2. `max_hits`, when set, must be greater than zero.
+/// 3. `jitter_ms`, `adaptive_delay_step_ms` and `max_adaptive_duration_ms` are only accepted for
+///    the `delay` fault.
+/// 4. `burst_count` and `burst_interval_ms` are only accepted for the `error` fault, where
+///    `burst_count` must be greater than zero.
+/// 5. `truncate_after_bytes` must be greater than zero for the `partial response` fault.
+/// 6. `bytes_per_ms` must be at least one for the `slow drain` fault.
+/// 7. `key_pattern`, when set, must be a valid glob pattern.
+/// 8. `sub_faults` is only accepted for the `composite` fault, where it must be a non-empty list
+///    of `delay`, `error` and `drop` faults that doesn't end with a `delay`.
+/// 9. `mutation_pattern` must be a valid regular expression for the `mutate response` fault.
+/// 10. The value of an `arg_matchers` entry of the `Regex` match type must be a valid regular
+///     expression.
+/// 11. `probability`, when set, must be in `(0, 1]`.
+///
+/// A fault that is activated at or after it expires is never applied. It is accepted, but a
+/// warning is logged.
+pub(crate) fn validate_fault(fault: &Fault) -> Result<(), ServerErrorResponse> {
+    if fault.is_expired() {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!("Fault {} has already expired", fault.name),
+        ));
     }
-}
 
-impl std::fmt::Display for ServerErrorResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "error: {}", self.message)
+    if let (Some(activate_at), Some(expires_at)) = (fault.activate_at, fault.expires_at) {
+        if activate_at >= expires_at {
+            warn!(
+                "Fault {} is activated at {} which is not before it expires at {}; it will never \
+                 be applied",
+                fault.name, activate_at, expires_at
+            );
+        }
     }
-}
 
-impl ResponseError for ServerErrorResponse {
-    fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+    if fault.max_hits == Some(0) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "max_hits must be greater than zero".to_string(),
+        ));
     }
 
-    fn error_response(&self) -> HttpResponse {
-        HttpResponseBuilder::new(StatusCode::from_u16(self.status_code).unwrap())
-            .content_type(ContentType::json())
-            .json(self)
+    if fault.jitter_ms.is_some() && fault.fault_type != FaultVariants::Delay {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "jitter_ms is only supported for the delay fault".to_string(),
+        ));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::store::fault_store::FaultVariants;
-    use actix_web::{http::StatusCode, test, web, web::Data, App};
+    if (fault.adaptive_delay_step_ms.is_some() || fault.max_adaptive_duration_ms.is_some())
+        && fault.fault_type != FaultVariants::Delay
+    {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "adaptive_delay_step_ms and max_adaptive_duration_ms are only supported for the delay \
+             fault"
+                .to_string(),
+        ));
+    }
 
-    #[tokio::test]
-    async fn test_store_fault() {
-        let fault_store = crate::store::mem_store::MemStore::new_db();
-        let mut app = test::init_service(
-            App::new()
-                .route("/fault", web::post().to(store_fault))
-                .app_data(Data::new(fault_store.clone())),
-        )
-        .await;
+    if (fault.burst_count.is_some() || fault.burst_interval_ms.is_some())
+        && fault.fault_type != FaultVariants::Error
+    {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "burst_count and burst_interval_ms are only supported for the error fault".to_string(),
+        ));
+    }
+
+    if fault.burst_count == Some(0) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "burst_count must be greater than zero".to_string(),
+        ));
+    }
+
+    if fault.fault_type == FaultVariants::PartialResponse
+        && !matches!(fault.truncate_after_bytes, Some(n) if n > 0)
+    {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "truncate_after_bytes must be greater than zero for the partial response fault"
+                .to_string(),
+        ));
+    }
+
+    if fault.fault_type == FaultVariants::SlowDrain
+        && !matches!(fault.bytes_per_ms, Some(n) if n >= 1)
+    {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "bytes_per_ms must be at least 1 for the slow drain fault".to_string(),
+        ));
+    }
+
+    // the glob is compiled once, the fault being stored with its compiled key pattern
+    if let (Some(key_pattern), Some(Err(err))) = (&fault.key_pattern, fault.key_glob()) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid key_pattern {}: {}", key_pattern, err),
+        ));
+    }
+
+    if fault.fault_type == FaultVariants::MutateResponse {
+        let mutation_pattern = fault.mutation_pattern.as_deref().ok_or_else(|| {
+            ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "mutation_pattern must be set for the mutate response fault".to_string(),
+            )
+        })?;
+
+        regex::bytes::Regex::new(mutation_pattern).map_err(|err| {
+            ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid mutation_pattern {}: {}", mutation_pattern, err),
+            )
+        })?;
+    }
+
+    for arg_matcher in fault.arg_matchers.iter().flatten() {
+        // the regex is compiled once, the fault being stored with its compiled matchers
+        if arg_matcher.match_type == MatchType::Regex {
+            arg_matcher.regex().as_ref().map_err(|err| {
+                ServerErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid arg matcher regex {}: {}", arg_matcher.value, err),
+                )
+            })?;
+        }
+    }
+
+    if let Some(probability) = fault.probability {
+        if !(probability > 0.0 && probability <= 1.0) {
+            return Err(ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("probability {} must be in (0, 1]", probability),
+            ));
+        }
+    }
+
+    validate_sub_faults(fault)
+}
+
+/// validate_sub_faults checks the `sub_faults` of a fault, see [validate_fault].
+fn validate_sub_faults(fault: &Fault) -> Result<(), ServerErrorResponse> {
+    let bad_request = |message: &str| {
+        Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            message.to_string(),
+        ))
+    };
+
+    let sub_faults = match (&fault.fault_type, &fault.sub_faults) {
+        (FaultVariants::Composite, Some(sub_faults)) => sub_faults,
+        (FaultVariants::Composite, None) => {
+            return bad_request("sub_faults must be set for the composite fault")
+        }
+        (_, Some(_)) => return bad_request("sub_faults is only supported for the composite fault"),
+        (_, None) => return Ok(()),
+    };
+
+    if sub_faults.is_empty() {
+        return bad_request("sub_faults must not be empty for the composite fault");
+    }
+
+    if sub_faults.iter().any(|sub_fault| {
+        !matches!(
+            sub_fault.fault_type,
+            FaultVariants::Delay | FaultVariants::Error | FaultVariants::DropConn
+        )
+    }) {
+        return bad_request("sub_faults only supports the delay, error and drop faults");
+    }
+
+    if sub_faults.last().map(|sub_fault| &sub_fault.fault_type) == Some(&FaultVariants::Delay) {
+        return bad_request("The last of the sub_faults must not be a delay fault");
+    }
+
+    Ok(())
+}
+
+/// get_fault is the handler of GET /fault/<fault_name> endpoint.
+///
+/// 1. On successful fetch, returns the fault configuration of the given fault <fault_name> with
+///    HTTP status OK.
+/// 2. If the given fault name is not available in the fault store, HTTP Bad request 400 is
+///    returned.
+///
+/// The `ETag` header of the response is the ETag of the fault (see [Fault::etag]), which can be
+/// sent in the `If-Match` header of the PUT, PATCH and DELETE requests of the fault.
+#[utoipa::path(
+    get,
+    path = "/fault/{fault_name}",
+    tag = "faults",
+    params(("fault_name" = String, Path, description = "The name of the fault")),
+    responses(
+        (status = 200, description = "The fault", body = Fault,
+            headers(("ETag" = String, description = "The ETag of the fault"))),
+        (status = 400, description = "The fault is not found", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_fault(
+    request: HttpRequest,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Fetch fault by name: {:?}", fault_name);
+
+    match fault_store.get_by_fault_name(fault_name).await {
+        Ok(fault) => {
+            info!("Fault {} fetched from the store", fault_name);
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .insert_header(etag_header(&fault))
+                .json(fault))
+        }
+        Err(err) => {
+            error!("Error fetching fault {}: {}", fault_name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                err.to_string(),
+            ))
+        }
+    }
+}
+
+/// Returns the `ETag` header of the fault, i.e. the quoted [Fault::etag].
+fn etag_header(fault: &Fault) -> (header::HeaderName, String) {
+    (header::ETAG, format!("\"{}\"", fault.etag()))
+}
+
+/// Checks the `If-Match` header of the request, if any, against the current ETag of the fault, such
+/// that a fault is not updated or deleted based on a stale read of it.
+///
+/// The header holds a comma-separated list of quoted ETags, or `*` for any ETag. When none of them
+/// matches the current ETag, or the fault doesn't exist, HTTP Precondition Failed 412 is returned.
+fn check_if_match(
+    request: &HttpRequest,
+    current_etag: Option<&str>,
+) -> Result<(), ServerErrorResponse> {
+    let if_match = match request.headers().get(header::IF_MATCH) {
+        Some(if_match) => if_match.to_str().unwrap_or_default(),
+        None => return Ok(()),
+    };
+
+    let is_match = current_etag.is_some_and(|current_etag| {
+        if_match
+            .split(',')
+            .map(|etag| etag.trim().trim_matches('"'))
+            .any(|etag| etag == "*" || etag == current_etag)
+    });
+
+    if !is_match {
+        return Err(ServerErrorResponse::new(
+            StatusCode::PRECONDITION_FAILED,
+            format!(
+                "The fault doesn't match the If-Match header {}, it was modified or deleted",
+                if_match
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// FaultHitStats is the response body of the GET /fault/<fault_name>/stats endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FaultHitStats {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: FaultStats,
+}
+
+/// get_fault_stats is the handler of GET /fault/<fault_name>/stats endpoint.
+///
+/// 1. On successful fetch, returns the injection statistics of the fault <fault_name>, i.e. the
+///    number of times it has been injected, its first and last hit times and the total delay it
+///    injected, with HTTP status OK.
+/// 2. If the given fault name is not available in the fault store, HTTP Not Found 404 is
+///    returned.
+#[utoipa::path(
+    get,
+    path = "/fault/{fault_name}/stats",
+    tag = "faults",
+    params(("fault_name" = String, Path, description = "The name of the fault")),
+    responses(
+        (status = 200, description = "The injection statistics of the fault", body = FaultHitStats),
+        (status = 404, description = "The fault is not found", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_fault_stats(
+    request: HttpRequest,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Fetch fault stats: {}", fault_name);
+
+    match fault_store.get_fault_stats(fault_name).await {
+        Ok(stats) => Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(FaultHitStats {
+                name: fault_name.to_string(),
+                stats,
+            })),
+        Err(err) => {
+            error!("Error fetching stats of fault {}: {}", fault_name, err);
+            Err(ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))
+        }
+    }
+}
+
+/// get_all_fault_stats is the handler of GET /faults/stats endpoint.
+///
+/// 1. On successful fetch, returns the injection statistics of all the faults by fault name with
+///    HTTP status OK.
+/// 2. If unable to fetch the statistics from the fault store, HTTP Internal Server Error is
+///    returned.
+#[utoipa::path(
+    get,
+    path = "/faults/stats",
+    tag = "faults",
+    responses(
+        (status = 200, description = "The injection statistics of all the faults by fault name", body = HashMap<String, FaultStats>),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store))]
+pub async fn get_all_fault_stats(
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    info!("Fetch all fault stats");
+
+    match fault_store.get_all_fault_stats().await {
+        Ok(stats) => Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(stats)),
+        Err(err) => {
+            error!("Error fetching fault stats: {}", err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// The header of the GET /report response that holds the time the report is generated at
+pub const REPORT_GENERATED_AT_HEADER: &str = "X-Report-Generated-At";
+
+/// The number of the most injected faults and commands listed in the [Report]
+pub const REPORT_TOP_COUNT: usize = 5;
+
+/// ReportFormat is the format of the GET /report response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Text,
+}
+
+/// ReportParams holds the optional `format` query param of the GET /report endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportParams {
+    /// The format of the report, `json` by default
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// Report is the response body of the GET /report endpoint, which summarizes the configured
+/// faults and their injections.
+#[derive(Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Report {
+    /// The number of the configured faults, including the disabled and the expired ones
+    pub total_faults: usize,
+    /// The number of the configured faults by fault type, e.g. `{"Delay": 2}`
+    pub faults_by_type: BTreeMap<String, usize>,
+    /// The number of the injections of the last hour, to the minute
+    pub hits_last_hour: u64,
+    /// The number of the injections of the last day, to the minute
+    pub hits_last_day: u64,
+    /// The most injected faults, the most injected first
+    pub top_faults: Vec<ReportHits>,
+    /// The commands with the most injections, the most injected first
+    pub top_commands: Vec<ReportHits>,
+    /// The average injected delay in milliseconds of the delay faults that were injected, by
+    /// fault name
+    pub average_delay_ms: BTreeMap<String, f64>,
+}
+
+/// ReportHits is the number of injections of a fault or a command in the [Report].
+#[derive(Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportHits {
+    pub name: String,
+    pub hit_count: u64,
+}
+
+impl Report {
+    /// Summarizes the given faults and their injection statistics by fault name at the given
+    /// time. The hits of the last hour and day are only counted by the in-memory store.
+    pub fn new(faults: &[Fault], stats: &HashMap<String, FaultStats>, now: DateTime<Utc>) -> Self {
+        let mut faults_by_type = BTreeMap::new();
+        let mut command_hits: HashMap<String, u64> = HashMap::new();
+        let mut fault_hits = Vec::new();
+        let mut average_delay_ms = BTreeMap::new();
+        let mut hits_last_hour = 0;
+        let mut hits_last_day = 0;
+
+        for fault in faults {
+            *faults_by_type
+                .entry(format!("{:?}", fault.fault_type))
+                .or_insert(0) += 1;
+
+            let stats = match stats.get(&fault.name) {
+                Some(stats) if stats.hit_count > 0 => stats,
+                _ => continue,
+            };
+            hits_last_hour += stats.hits_since(now - chrono::Duration::hours(1));
+            hits_last_day += stats.hits_since(now - chrono::Duration::days(1));
+            *command_hits
+                .entry(fault.command.to_uppercase())
+                .or_insert(0) += stats.hit_count;
+            fault_hits.push(ReportHits {
+                name: fault.name.clone(),
+                hit_count: stats.hit_count,
+            });
+            if fault.fault_type == FaultVariants::Delay {
+                average_delay_ms.insert(
+                    fault.name.clone(),
+                    stats.total_delay_ms as f64 / stats.hit_count as f64,
+                );
+            }
+        }
+
+        let command_hits = command_hits
+            .into_iter()
+            .map(|(name, hit_count)| ReportHits { name, hit_count })
+            .collect();
+
+        Report {
+            total_faults: faults.len(),
+            faults_by_type,
+            hits_last_hour,
+            hits_last_day,
+            top_faults: top_hits(fault_hits),
+            top_commands: top_hits(command_hits),
+            average_delay_ms,
+        }
+    }
+}
+
+/// Returns the [REPORT_TOP_COUNT] most injected of the given hits, the most injected first and
+/// then by name.
+fn top_hits(mut hits: Vec<ReportHits>) -> Vec<ReportHits> {
+    hits.sort_by(|a, b| {
+        b.hit_count
+            .cmp(&a.hit_count)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    hits.truncate(REPORT_TOP_COUNT);
+    hits
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Total faults: {}", self.total_faults)?;
+        writeln!(f, "Faults by type:")?;
+        for (fault_type, count) in &self.faults_by_type {
+            writeln!(f, "  {}: {}", fault_type, count)?;
+        }
+        writeln!(f, "Injections in the last hour: {}", self.hits_last_hour)?;
+        writeln!(f, "Injections in the last day: {}", self.hits_last_day)?;
+        writeln!(f, "Top faults:")?;
+        for (rank, hits) in self.top_faults.iter().enumerate() {
+            writeln!(f, "  {}. {}: {}", rank + 1, hits.name, hits.hit_count)?;
+        }
+        writeln!(f, "Top commands:")?;
+        for (rank, hits) in self.top_commands.iter().enumerate() {
+            writeln!(f, "  {}. {}: {}", rank + 1, hits.name, hits.hit_count)?;
+        }
+        writeln!(f, "Average delay per fault:")?;
+        for (fault_name, delay_ms) in &self.average_delay_ms {
+            writeln!(f, "  {}: {:.1} ms", fault_name, delay_ms)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// get_report is the handler of GET /report endpoint.
+///
+/// 1. On successful fetch, returns the [Report] of the faults and their injection statistics with
+///    HTTP status OK, as JSON or, with `?format=text`, as plain text. The time the report is
+///    generated at is returned in the `X-Report-Generated-At` header.
+/// 2. If the format is neither `json` nor `text`, HTTP Bad request 400 is returned.
+/// 3. If unable to fetch the faults or the statistics from the fault store, HTTP Internal Server
+///    Error is returned.
+#[utoipa::path(
+    get,
+    path = "/report",
+    tag = "faults",
+    params(ReportParams),
+    responses(
+        (status = 200, description = "The report of the faults", body = Report,
+            headers(("X-Report-Generated-At" = String, description = "The RFC 3339 time the report is generated at"))),
+        (status = 400, description = "The format is unknown"),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store))]
+pub async fn get_report(
+    params: web::Query<ReportParams>,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    info!("Generate the fault report");
+    let store_error = |err: StoreError| {
+        error!("Error fetching the faults of the report: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    };
+
+    let faults = fault_store.get_all_faults().await.map_err(store_error)?;
+    let stats = fault_store
+        .get_all_fault_stats()
+        .await
+        .map_err(store_error)?;
+    let generated_at = Utc::now();
+    let report = Report::new(&faults, &stats, generated_at);
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header((REPORT_GENERATED_AT_HEADER, generated_at.to_rfc3339()));
+    Ok(match params.format {
+        ReportFormat::Json => response.json(report),
+        ReportFormat::Text => response
+            .content_type(ContentType::plaintext())
+            .body(report.to_string()),
+    })
+}
+
+/// PatchFault holds the fields of a fault that can be partially updated by the
+/// PATCH /fault/<fault_name> endpoint. Only the fields that are present in the request body are
+/// updated. The optional fields of the fault are nullable, `null` clearing them, e.g.
+/// `{"expires_at": null}` makes the fault never expire.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PatchFault {
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub description: Option<Option<String>>,
+    pub fault_type: Option<FaultVariants>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub duration: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub error_msg: Option<Option<String>>,
+    pub command: Option<String>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub activate_at: Option<Option<DateTime<Utc>>>,
+    pub enabled: Option<bool>,
+    pub one_shot: Option<bool>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub max_hits: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub jitter_ms: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub adaptive_delay_step_ms: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub max_adaptive_duration_ms: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub burst_count: Option<Option<u32>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub burst_interval_ms: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub truncate_after_bytes: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub bytes_per_ms: Option<Option<u64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub key_pattern: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub group: Option<Option<String>>,
+    #[schema(value_type = Option<Vec<String>>, nullable)]
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub client_ip_filter: Option<Option<Vec<IpAddr>>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub database: Option<Option<u8>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub proxy_port: Option<Option<u16>>,
+    pub priority: Option<u32>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub probability: Option<Option<f64>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub sub_faults: Option<Option<Vec<SubFault>>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub mutation_pattern: Option<Option<String>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub mutation_replacement: Option<Option<String>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub arg_matchers: Option<Option<Vec<ArgMatcher>>>,
+}
+
+impl PatchFault {
+    /// Applies the fields that are set in the patch on the given fault.
+    fn apply(&self, fault: &mut Fault) {
+        if let Some(description) = &self.description {
+            fault.description = description.clone();
+        }
+        if let Some(fault_type) = &self.fault_type {
+            fault.fault_type = fault_type.clone();
+        }
+        if let Some(duration) = self.duration {
+            fault.duration = duration;
+        }
+        if let Some(error_msg) = &self.error_msg {
+            fault.error_msg = error_msg.clone();
+        }
+        if let Some(command) = &self.command {
+            fault.command = command.clone();
+        }
+        if let Some(expires_at) = self.expires_at {
+            fault.expires_at = expires_at;
+        }
+        if let Some(activate_at) = self.activate_at {
+            fault.activate_at = activate_at;
+        }
+        if let Some(enabled) = self.enabled {
+            fault.enabled = enabled;
+        }
+        if let Some(one_shot) = self.one_shot {
+            fault.one_shot = one_shot;
+        }
+        if let Some(max_hits) = self.max_hits {
+            fault.max_hits = max_hits;
+        }
+        if let Some(jitter_ms) = self.jitter_ms {
+            fault.jitter_ms = jitter_ms;
+        }
+        if let Some(adaptive_delay_step_ms) = self.adaptive_delay_step_ms {
+            fault.adaptive_delay_step_ms = adaptive_delay_step_ms;
+        }
+        if let Some(max_adaptive_duration_ms) = self.max_adaptive_duration_ms {
+            fault.max_adaptive_duration_ms = max_adaptive_duration_ms;
+        }
+        if let Some(burst_count) = self.burst_count {
+            fault.burst_count = burst_count;
+        }
+        if let Some(burst_interval_ms) = self.burst_interval_ms {
+            fault.burst_interval_ms = burst_interval_ms;
+        }
+        if let Some(truncate_after_bytes) = self.truncate_after_bytes {
+            fault.truncate_after_bytes = truncate_after_bytes;
+        }
+        if let Some(bytes_per_ms) = self.bytes_per_ms {
+            fault.bytes_per_ms = bytes_per_ms;
+        }
+        if let Some(key_pattern) = &self.key_pattern {
+            fault.set_key_pattern(key_pattern.clone());
+        }
+        if let Some(tags) = &self.tags {
+            fault.tags = tags.clone();
+        }
+        if let Some(group) = &self.group {
+            fault.group = group.clone();
+        }
+        if let Some(client_ip_filter) = &self.client_ip_filter {
+            fault.client_ip_filter = client_ip_filter.clone();
+        }
+        if let Some(database) = self.database {
+            fault.database = database;
+        }
+        if let Some(proxy_port) = self.proxy_port {
+            fault.proxy_port = proxy_port;
+        }
+        if let Some(priority) = self.priority {
+            fault.priority = priority;
+        }
+        if let Some(probability) = self.probability {
+            fault.probability = probability;
+        }
+        if let Some(sub_faults) = &self.sub_faults {
+            fault.sub_faults = sub_faults.clone();
+        }
+        if let Some(mutation_pattern) = &self.mutation_pattern {
+            fault.mutation_pattern = mutation_pattern.clone();
+        }
+        if let Some(mutation_replacement) = &self.mutation_replacement {
+            fault.mutation_replacement = mutation_replacement.clone();
+        }
+        if let Some(arg_matchers) = &self.arg_matchers {
+            fault.arg_matchers = arg_matchers.clone();
+        }
+    }
+}
+
+/// patch_fault is the handler of PATCH /fault/<fault_name> endpoint.
+///
+/// 1. Only the fields present in the request body are updated, e.g. `{"enabled": false}`
+///    suspends the fault without deleting it.
+/// 2. On successful update, the updated fault is returned with HTTP status OK.
+/// 3. If the given fault name is not available in the fault store, HTTP Not Found 404 is
+///    returned.
+/// 4. If the patched fault fails the validation (see [validate_fault]), e.g. a `fault_type`
+///    patched without the field it is executed with, HTTP Bad request 400 is returned.
+/// 5. If the patched fault conflicts with any other fault (see [check_conflicts]), e.g. its
+///    `command` is patched to the command of another fault, HTTP Conflict 409 is returned.
+/// 6. If the `If-Match` header doesn't match the ETag of the fault, HTTP Precondition Failed 412
+///    is returned, see [check_if_match].
+/// 7. When the fault fails to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+#[utoipa::path(
+    patch,
+    path = "/fault/{fault_name}",
+    tag = "faults",
+    params(
+        ("fault_name" = String, Path, description = "The name of the fault"),
+        ("If-Match" = Option<String>, Header, description = "The expected ETag of the fault"),
+    ),
+    request_body = PatchFault,
+    responses(
+        (status = 200, description = "The updated fault", body = Fault,
+            headers(("ETag" = String, description = "The ETag of the updated fault"))),
+        (status = 400, description = "The patched fault is invalid", body = ServerErrorResponse),
+        (status = 404, description = "The fault is not found", body = ServerErrorResponse),
+        (status = 409, description = "The patched fault conflicts with another fault", body = ServerErrorResponse),
+        (status = 412, description = "The fault doesn't match the If-Match header", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn patch_fault(
+    request: HttpRequest,
+    patch: web::Json<PatchFault>,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Patch fault: {}", fault_name);
+
+    let mut fault = fault_store
+        .get_by_fault_name(fault_name)
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))?;
+    check_if_match(
+        &request,
+        fault_store.get_fault_etag(fault_name).await.as_deref(),
+    )?;
+
+    patch.apply(&mut fault);
+    fault.last_modified = Some(Utc::now());
+
+    validate_fault(&fault)?;
+
+    let other_faults: Vec<Fault> = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?
+        .into_iter()
+        .filter(|f| f.name != fault.name)
+        .collect();
+    check_conflicts(&fault, &other_faults, selection_strategy(&request))?;
+
+    match fault_store.store(&fault.name, &fault).await {
+        Ok(_) => {
+            info!("Fault {} patched in the store", fault.name);
+            audit(&audit_log, AuditAction::Updated, &actor, &fault);
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .insert_header(etag_header(&fault))
+                .json(fault))
+        }
+        Err(err) => {
+            error!("Error patching fault {} in the store: {}", fault.name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// replace_fault is the handler of PUT /fault/<fault_name> endpoint.
+///
+/// Unlike POST /fault, the fault must already exist and it is replaced as a whole.
+///
+/// 1. On successful replacement, the updated fault is returned with HTTP status OK.
+/// 2. If the given fault name is not available in the fault store, HTTP Not Found 404 is
+///    returned.
+/// 3. If the `name` in the request body doesn't match the fault name in the path, or the fault
+///    fails the validation (see [validate_fault]), HTTP Bad request 400 is returned.
+/// 4. If the fault conflicts with any other fault (see [check_conflicts]), HTTP Conflict 409 is
+///    returned.
+/// 5. If the `If-Match` header doesn't match the ETag of the fault, HTTP Precondition Failed 412
+///    is returned, see [check_if_match].
+/// 6. When the fault fails to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+#[utoipa::path(
+    put,
+    path = "/fault/{fault_name}",
+    tag = "faults",
+    params(
+        ("fault_name" = String, Path, description = "The name of the fault"),
+        ("If-Match" = Option<String>, Header, description = "The expected ETag of the fault"),
+    ),
+    request_body = Fault,
+    responses(
+        (status = 200, description = "The replaced fault", body = Fault,
+            headers(("ETag" = String, description = "The ETag of the replaced fault"))),
+        (status = 400, description = "The fault is invalid or its name doesn't match the path", body = ServerErrorResponse),
+        (status = 404, description = "The fault is not found", body = ServerErrorResponse),
+        (status = 409, description = "The fault conflicts with another fault", body = ServerErrorResponse),
+        (status = 412, description = "The fault doesn't match the If-Match header", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn replace_fault(
+    request: HttpRequest,
+    fault: web::Json<Fault>,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Replace fault: {}", fault_name);
+
+    let mut fault = fault.into_inner();
+    if fault.name != fault_name {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Fault name {} in the body doesn't match the fault name {} in the path",
+                fault.name, fault_name
+            ),
+        ));
+    }
+    fault.last_modified = Some(Utc::now());
+
+    validate_fault(&fault)?;
+
+    fault_store
+        .get_by_fault_name(fault_name)
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))?;
+    check_if_match(
+        &request,
+        fault_store.get_fault_etag(fault_name).await.as_deref(),
+    )?;
+
+    let other_faults: Vec<Fault> = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?
+        .into_iter()
+        .filter(|f| f.name != fault.name)
+        .collect();
+    check_conflicts(&fault, &other_faults, selection_strategy(&request))?;
+
+    match fault_store.store(&fault.name, &fault).await {
+        Ok(_) => {
+            info!("Fault {} replaced in the store", fault.name);
+            audit(&audit_log, AuditAction::Updated, &actor, &fault);
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .insert_header(etag_header(&fault))
+                .json(fault))
+        }
+        Err(err) => {
+            error!("Error replacing fault {} in the store: {}", fault.name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// get_fault_history is the handler of GET /fault/<fault_name>/history endpoint.
+///
+/// 1. On successful fetch, returns the previous versions of the fault <fault_name>, the most
+///    recent first, with HTTP status OK. The index of a version in the list is the version that
+///    POST /fault/<fault_name>/rollback/<version> restores.
+/// 2. If the given fault name is not available in the fault store, or the fault store doesn't keep
+///    the history of the faults, HTTP Not Found 404 is returned.
+#[utoipa::path(
+    get,
+    path = "/fault/{fault_name}/history",
+    tag = "faults",
+    params(("fault_name" = String, Path, description = "The name of the fault")),
+    responses(
+        (status = 200, description = "The previous versions of the fault, the most recent first", body = Vec<Fault>),
+        (status = 404, description = "The fault or its history is not found", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_fault_history(
+    request: HttpRequest,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Fetch fault history: {}", fault_name);
+
+    match fault_store.get_fault_history(fault_name).await {
+        Ok(versions) => Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(versions)),
+        Err(err) => {
+            error!("Error fetching history of fault {}: {}", fault_name, err);
+            Err(ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))
+        }
+    }
+}
+
+/// DiffParams holds the `version` query param of the GET /fault/<fault_name>/diff endpoint.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiffParams {
+    /// The version of the history of the fault to compare the current fault with, 0 for the
+    /// version before the latest change
+    pub version: usize,
+}
+
+/// get_fault_diff is the handler of GET /fault/<fault_name>/diff?version=<version> endpoint.
+///
+/// 1. On success, returns the fields that changed from the given version of the history of the
+///    fault (see [get_fault_history]) to the current fault with HTTP status OK, see
+///    [diff_faults].
+/// 2. If the `version` query param is missing or not a number, HTTP Bad request 400 is returned.
+/// 3. If the fault or its version is not found, HTTP Not Found 404 is returned.
+#[utoipa::path(
+    get,
+    path = "/fault/{fault_name}/diff",
+    tag = "faults",
+    params(
+        ("fault_name" = String, Path, description = "The name of the fault"),
+        DiffParams,
+    ),
+    responses(
+        (status = 200, description = "The fields that changed since the version", body = FaultDiff),
+        (status = 400, description = "The version is missing or not a number", body = ServerErrorResponse),
+        (status = 404, description = "The fault or its version is not found", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_fault_diff(
+    request: HttpRequest,
+    params: web::Query<DiffParams>,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Diff fault {} with version {}", fault_name, params.version);
+
+    let fault = fault_store
+        .get_by_fault_name(fault_name)
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))?;
+    let version = fault_store
+        .get_fault_history(fault_name)
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))?
+        .into_iter()
+        .nth(params.version)
+        .ok_or_else(|| {
+            ServerErrorResponse::new(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Version {} of fault {} not found",
+                    params.version, fault_name
+                ),
+            )
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(diff_faults(&version, &fault)))
+}
+
+/// rollback_fault is the handler of POST /fault/<fault_name>/rollback/<version> endpoint.
+///
+/// The given version of the history of the fault (see [get_fault_history]) is restored as is, and
+/// the replaced configuration becomes the latest version of the history.
+///
+/// 1. On successful rollback, the restored fault is returned with HTTP status OK.
+/// 2. If the version is not a number, HTTP Bad request 400 is returned.
+/// 3. If the fault or its version is not found, HTTP Not Found 404 is returned.
+/// 4. If the restored fault conflicts with any other fault (see [check_conflicts]), HTTP Conflict
+///    409 is returned.
+/// 5. When the fault fails to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+#[utoipa::path(
+    post,
+    path = "/fault/{fault_name}/rollback/{version}",
+    tag = "faults",
+    params(
+        ("fault_name" = String, Path, description = "The name of the fault"),
+        ("version" = usize, Path, description = "The version of the fault to restore, 0 for the version before the latest change"),
+    ),
+    responses(
+        (status = 200, description = "The restored fault", body = Fault,
+            headers(("ETag" = String, description = "The ETag of the restored fault"))),
+        (status = 400, description = "The version is not a number", body = ServerErrorResponse),
+        (status = 404, description = "The fault or its version is not found", body = ServerErrorResponse),
+        (status = 409, description = "The restored fault conflicts with another fault", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn rollback_fault(
+    request: HttpRequest,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    let version: usize = request
+        .match_info()
+        .get("version")
+        .unwrap_or_default()
+        .parse()
+        .map_err(|_| {
+            ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "The version must be a non-negative number".to_string(),
+            )
+        })?;
+    info!("Rollback fault {} to version {}", fault_name, version);
+
+    let fault = fault_store
+        .get_fault_history(fault_name)
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::NOT_FOUND, err.message))?
+        .into_iter()
+        .nth(version)
+        .ok_or_else(|| {
+            ServerErrorResponse::new(
+                StatusCode::NOT_FOUND,
+                format!("Version {} of fault {} not found", version, fault_name),
+            )
+        })?;
+
+    let other_faults: Vec<Fault> = fault_store
+        .get_all_faults()
+        .await
+        .map_err(|err| ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message))?
+        .into_iter()
+        .filter(|f| f.name != fault.name)
+        .collect();
+    check_conflicts(&fault, &other_faults, selection_strategy(&request))?;
+
+    match fault_store.rollback_fault(fault_name, version).await {
+        Ok(_) => {
+            info!("Fault {} rolled back to version {}", fault_name, version);
+            audit(&audit_log, AuditAction::Updated, &actor, &fault);
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .insert_header(etag_header(&fault))
+                .json(fault))
+        }
+        Err(err) => {
+            error!("Error rolling back fault {}: {}", fault_name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// The number of faults per page of GET /faults when `per_page` is not given
+const DEFAULT_PER_PAGE: usize = 50;
+
+/// The maximum number of faults per page of GET /faults
+const MAX_PER_PAGE: usize = 200;
+
+/// PaginationParams holds the optional `page` and `per_page` query params of the GET /faults
+/// endpoint. Pages are numbered from 1.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PaginationParams {
+    /// The page number, starting from 1
+    #[serde(default = "default_page")]
+    #[param(default = 1, minimum = 1)]
+    pub page: usize,
+    /// The number of faults per page, capped at 200
+    #[serde(default = "default_per_page")]
+    #[param(default = 50, minimum = 1, maximum = 200)]
+    pub per_page: usize,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    DEFAULT_PER_PAGE
+}
+
+/// FaultFilter holds the optional `command`, `fault_type` and `tag` query params of the
+/// GET /faults endpoint. `command` and `fault_type` are matched case-insensitively. The `tag` param
+/// can be repeated and a fault matches when it has all the given tags.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FaultFilter {
+    /// Lists only the faults of the given Redis command
+    pub command: Option<String>,
+    /// Lists only the faults of the given fault type, e.g. `delay`
+    pub fault_type: Option<String>,
+    /// The repeated `tag` params can't be deserialized by `web::Query`, hence they are parsed
+    /// from the query string with [query_tags].
+    #[serde(skip)]
+    pub tags: Vec<String>,
+}
+
+/// Returns the values of the repeated `tag` query param, e.g. `?tag=production&tag=auth-service`.
+fn query_tags(request: &HttpRequest) -> Vec<String> {
+    url::form_urlencoded::parse(request.query_string().as_bytes())
+        .filter(|(key, _)| key == "tag")
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+impl FaultFilter {
+    /// Returns true if the fault matches all the given filters.
+    fn matches(&self, fault: &Fault) -> bool {
+        let is_command_match = self
+            .command
+            .as_ref()
+            .is_none_or(|command| command.eq_ignore_ascii_case(&fault.command));
+        let is_fault_type_match = self.fault_type.as_ref().is_none_or(|fault_type| {
+            fault_type.eq_ignore_ascii_case(&format!("{:?}", fault.fault_type))
+        });
+
+        is_command_match && is_fault_type_match && fault.has_tags(&self.tags)
+    }
+}
+
+/// FaultsPage is the response body of the GET /faults endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FaultsPage {
+    pub faults: Vec<Fault>,
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+/// get_all_faults is the handler of GET /faults endpoint.
+///
+/// 1. On success fetch, returns a page of the fault configurations, most recently modified first,
+///    with HTTP status 200. The page is selected by the `page` (default 1) and `per_page`
+///    (default 50, capped at 200) query params. The faults can be filtered by the `command` and
+///    `fault_type` query params and the repeated `tag` query param (see [FaultFilter]), and the
+///    `proxy_port` query param (see [ProxyPortParams]) before they are paginated.
+/// 2. If `page` or `per_page` is zero, HTTP Bad request 400 is returned.
+/// 3. If unable to fetch the fault configurations from the fault store, HTTP Internal Server Error
+///    is returned.
+#[utoipa::path(
+    get,
+    path = "/faults",
+    tag = "faults",
+    params(
+        PaginationParams,
+        FaultFilter,
+        ProxyPortParams,
+        ("tag" = Option<Vec<String>>, Query, description = "Lists only the faults that have all the given tags"),
+    ),
+    responses(
+        (status = 200, description = "A page of the faults, most recently modified first", body = FaultsPage),
+        (status = 400, description = "page or per_page is zero", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_all_faults(
+    request: HttpRequest,
+    pagination: web::Query<PaginationParams>,
+    filter: web::Query<FaultFilter>,
+    scope: web::Query<ProxyPortParams>,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    info!("Fetch all faults");
+    let mut filter = filter.into_inner();
+    filter.tags = query_tags(&request);
+
+    if pagination.page == 0 || pagination.per_page == 0 {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "page and per_page must be greater than 0".to_string(),
+        ));
+    }
+    let page = pagination.page;
+    let per_page = pagination.per_page.min(MAX_PER_PAGE);
+
+    let faults = fault_store.get_all_faults().await;
+
+    match faults {
+        Ok(mut faults) => {
+            faults.retain(|fault| filter.matches(fault) && scope.matches(fault));
+            faults.sort_by_key(|fault| std::cmp::Reverse(fault.last_modified));
+
+            let total = faults.len();
+            let faults = faults
+                .into_iter()
+                .skip((page - 1).saturating_mul(per_page))
+                .take(per_page)
+                .collect();
+
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .json(FaultsPage {
+                    faults,
+                    total,
+                    page,
+                    per_page,
+                }))
+        }
+        Err(err) => {
+            error!("Error fetching all faults: {}", err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// delete_fault is the handler of DELETE /fault/<fault_name> endpoint.
+///
+/// 1. DELETE /fault/<fault_name> endpoint is idempotent.
+/// 2. On successful delete, HTTP No Content 204 status is returned.
+/// 3. If the `If-Match` header doesn't match the ETag of the fault, HTTP Precondition Failed 412
+///    is returned, see [check_if_match].
+/// 4. On failing to delete the given fault <fault_name>, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    delete,
+    path = "/fault/{fault_name}",
+    tag = "faults",
+    params(
+        ("fault_name" = String, Path, description = "The name of the fault"),
+        ("If-Match" = Option<String>, Header, description = "The expected ETag of the fault"),
+    ),
+    responses(
+        (status = 204, description = "The fault is deleted"),
+        (status = 412, description = "The fault doesn't match the If-Match header", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn delete_fault(
+    request: HttpRequest,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponseBuilder, ServerErrorResponse> {
+    let fault_name = request.match_info().get("fault_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault name from the request path".to_string(),
+        )
+    })?;
+    info!("Delete fault: {}", fault_name);
+
+    // the deleted fault is recorded in the audit log, unless it doesn't exist
+    let fault = fault_store.get_by_fault_name(fault_name).await.ok();
+    check_if_match(
+        &request,
+        fault_store.get_fault_etag(fault_name).await.as_deref(),
+    )?;
+
+    match fault_store.delete_fault(fault_name).await {
+        Ok(_) => {
+            debug!("Deleted fault: {:?}", fault_name);
+            if let Some(fault) = fault {
+                audit(&audit_log, AuditAction::Deleted, &actor, &fault);
+            }
+            Ok(HttpResponse::NoContent())
+        }
+        Err(err) => {
+            error!("Error deleting fault {}: {}", fault_name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// delete_all_faults is the handler for DELETE /faults.
+///
+/// DELETE /faults endpoint is idempotent. When the repeated `tag` query param is given, e.g.
+/// `?tag=production`, only the faults that have all the given tags are deleted. With
+/// `?proxy_port=<port>`, only the faults scoped to the given proxy port are deleted.
+/// On successful delete, it returns 204 No Content HTTP status.
+/// On failing to delete all faults, returns HTTP Internal Server Error 500 status.
+#[utoipa::path(
+    delete,
+    path = "/faults",
+    tag = "faults",
+    params(
+        ("tag" = Option<Vec<String>>, Query, description = "Deletes only the faults that have all the given tags"),
+        ProxyPortParams,
+    ),
+    responses(
+        (status = 204, description = "The faults are deleted"),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn delete_all_faults(
+    request: HttpRequest,
+    scope: web::Query<ProxyPortParams>,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponseBuilder, ServerErrorResponse> {
+    let tags = query_tags(&request);
+    debug!("Delete all faults with tags {:?}", tags);
+
+    let faults = fault_store.get_all_faults().await.map_err(|err| {
+        error!("Error fetching all faults: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    })?;
+
+    // without a filter, the store deletes all the faults at once
+    if tags.is_empty() && scope.proxy_port.is_none() {
+        let deleted = fault_store.clear_all().await.map_err(|err| {
+            error!("Error deleting all faults: {}", err);
+            ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+        })?;
+        for fault in &faults {
+            audit(&audit_log, AuditAction::Deleted, &actor, fault);
+        }
+
+        debug!("Deleted all {} faults from the store", deleted);
+        return Ok(HttpResponse::NoContent());
+    }
+
+    for fault in faults
+        .into_iter()
+        .filter(|fault| fault.has_tags(&tags) && scope.matches(fault))
+    {
+        match fault_store.delete_fault(fault.name.as_str()).await {
+            Ok(_) => {
+                info!("Deleted fault: {}", fault.name);
+                audit(&audit_log, AuditAction::Deleted, &actor, &fault);
+            }
+            Err(err) => {
+                error!("Error deleting fault {}: {}", fault.name, err);
+                return Err(ServerErrorResponse::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    err.message,
+                ));
+            }
+        }
+    }
+
+    debug!("Deleted all faults from the store");
+    Ok(HttpResponse::NoContent())
+}
+
+/// store_playbook is the handler of POST /playbook endpoint.
+///
+/// 1. When the playbook is successfully stored in the playbook store, HTTP Created 201 is
+///    returned. The playbook starts from its first step with the next request.
+/// 2. If the playbook fails the validation (see [validate_playbook]), HTTP Bad request 400 is
+///    returned.
+/// 3. When a playbook with the same name already exists, HTTP Conflict 409 is returned.
+/// 4. When the playbook fails to be stored, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    post,
+    path = "/playbook",
+    tag = "playbooks",
+    request_body = FaultPlaybook,
+    responses(
+        (status = 201, description = "The playbook is created"),
+        (status = 400, description = "The playbook is invalid", body = ServerErrorResponse),
+        (status = 409, description = "The playbook already exists", body = ServerErrorResponse),
+        (status = 500, description = "The playbook store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, playbook_store))]
+pub async fn store_playbook(
+    playbook: web::Json<FaultPlaybook>,
+    fault_store: web::Data<DB>,
+    playbook_store: web::Data<PlaybookDB>,
+) -> Result<HttpResponseBuilder, ServerErrorResponse> {
+    info!("Create playbook: playbook name: {:?}", playbook.name);
+
+    validate_playbook(&playbook, &fault_store).await?;
+
+    let store_error = |err: StoreError| {
+        error!("Error storing playbook {}: {}", playbook.name, err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    };
+
+    let playbooks = playbook_store.get_all_playbooks().map_err(store_error)?;
+    if playbooks.iter().any(|p| p.name == playbook.name) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::CONFLICT,
+            format!("There already exists a playbook {}", playbook.name),
+        ));
+    }
+
+    playbook_store
+        .store_playbook(&playbook)
+        .map_err(store_error)?;
+    info!("Playbook {} created in the store", playbook.name);
+    Ok(HttpResponse::Created())
+}
+
+/// validate_playbook checks the playbook before it is stored.
+///
+/// 1. The playbook must have at least one step.
+/// 2. `repeat_count` of every step must be greater than zero.
+/// 3. The `fault_name` of every step, when set, must refer to a fault in the fault store.
+async fn validate_playbook(
+    playbook: &FaultPlaybook,
+    fault_store: &DB,
+) -> Result<(), ServerErrorResponse> {
+    if playbook.steps.is_empty() {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!("Playbook {} has no steps", playbook.name),
+        ));
+    }
+
+    if playbook.steps.iter().any(|step| step.repeat_count == 0) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "repeat_count must be greater than zero".to_string(),
+        ));
+    }
+
+    for fault_name in playbook
+        .steps
+        .iter()
+        .filter_map(|step| step.fault_name.as_ref())
+    {
+        if fault_store.get_by_fault_name(fault_name).await.is_err() {
+            return Err(ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("Fault {} of the playbook is not found", fault_name),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// delete_playbook is the handler of DELETE /playbook/<playbook_name> endpoint.
+///
+/// 1. DELETE /playbook/<playbook_name> endpoint is idempotent.
+/// 2. On successful delete, HTTP No Content 204 status is returned.
+/// 3. On failing to delete the given playbook, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    delete,
+    path = "/playbook/{playbook_name}",
+    tag = "playbooks",
+    params(("playbook_name" = String, Path, description = "The name of the playbook")),
+    responses(
+        (status = 204, description = "The playbook is deleted"),
+        (status = 500, description = "The playbook store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(playbook_store, request))]
+pub async fn delete_playbook(
+    request: HttpRequest,
+    playbook_store: web::Data<PlaybookDB>,
+) -> Result<HttpResponseBuilder, ServerErrorResponse> {
+    let playbook_name = request.match_info().get("playbook_name").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching playbook name from the request path".to_string(),
+        )
+    })?;
+    info!("Delete playbook: {}", playbook_name);
+
+    match playbook_store.delete_playbook(playbook_name) {
+        Ok(_) => {
+            debug!("Deleted playbook: {:?}", playbook_name);
+            Ok(HttpResponse::NoContent())
+        }
+        Err(err) => {
+            error!("Error deleting playbook {}: {}", playbook_name, err);
+            Err(ServerErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                err.message,
+            ))
+        }
+    }
+}
+
+/// search_faults is the handler of GET /fault/search endpoint.
+///
+/// It returns the faults that match the `q` free-text search and all the `command`,
+/// `fault_type` and `enabled` filters of the query string (see [FaultSearchQuery]), the most
+/// relevant first, with HTTP status OK.
+/// If the `fault_type` is not one of the [FaultVariants], HTTP Bad request 400 is returned.
+/// On failing to fetch the faults, returns HTTP Internal Server Error 500 status.
+#[utoipa::path(
+    get,
+    path = "/fault/search",
+    tag = "faults",
+    params(FaultSearchQuery),
+    responses(
+        (status = 200, description = "The matching faults, the most relevant first", body = [Fault]),
+        (status = 400, description = "The fault type is unknown", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store))]
+pub async fn search_faults(
+    query: web::Query<FaultSearchQuery>,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    debug!("Search faults");
+
+    if let Some(fault_type) = &query.fault_type {
+        if fault_type.parse::<FaultVariants>().is_err() {
+            return Err(ServerErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown fault type {}", fault_type),
+            ));
+        }
+    }
+
+    let faults = fault_store.search(&query).await.map_err(|err| {
+        error!("Error searching the faults: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(faults))
+}
+
+/// get_fault_group is the handler of GET /fault-group/<group> endpoint.
+///
+/// It returns the faults that belong to the given fault group with HTTP status OK. The list is
+/// empty when no fault belongs to the group.
+/// On failing to fetch the faults, returns HTTP Internal Server Error 500 status.
+#[utoipa::path(
+    get,
+    path = "/fault-group/{group}",
+    tag = "fault groups",
+    params(("group" = String, Path, description = "The name of the fault group")),
+    responses(
+        (status = 200, description = "The faults of the group", body = [Fault]),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request))]
+pub async fn get_fault_group(
+    request: HttpRequest,
+    fault_store: web::Data<DB>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let group = fault_group_from_path(&request)?;
+    debug!("Get fault group: {}", group);
+
+    let faults = fault_store
+        .get_faults_by_group(group)
+        .await
+        .map_err(|err| {
+            error!("Error fetching the faults of group {}: {}", group, err);
+            ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(faults))
+}
+
+/// enable_fault_group is the handler of POST /fault-group/<group>/enable endpoint.
+///
+/// See [set_fault_group_enabled].
+#[utoipa::path(
+    post,
+    path = "/fault-group/{group}/enable",
+    tag = "fault groups",
+    params(("group" = String, Path, description = "The name of the fault group")),
+    responses(
+        (status = 200, description = "The enabled faults of the group", body = [Fault]),
+        (status = 404, description = "No fault belongs to the group", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn enable_fault_group(
+    request: HttpRequest,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    set_fault_group_enabled(&request, &actor, &fault_store, &audit_log, true).await
+}
+
+/// disable_fault_group is the handler of POST /fault-group/<group>/disable endpoint.
+///
+/// See [set_fault_group_enabled].
+#[utoipa::path(
+    post,
+    path = "/fault-group/{group}/disable",
+    tag = "fault groups",
+    params(("group" = String, Path, description = "The name of the fault group")),
+    responses(
+        (status = 200, description = "The disabled faults of the group", body = [Fault]),
+        (status = 404, description = "No fault belongs to the group", body = ServerErrorResponse),
+        (status = 500, description = "The fault store failed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, request, audit_log))]
+pub async fn disable_fault_group(
+    request: HttpRequest,
+    actor: Actor,
+    fault_store: web::Data<DB>,
+    audit_log: web::Data<AuditLog>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    set_fault_group_enabled(&request, &actor, &fault_store, &audit_log, false).await
+}
+
+/// Sets the `enabled` flag of all the faults that belong to the fault group in the request path.
+///
+/// 1. On success, the updated faults are returned with HTTP status OK.
+/// 2. If no fault belongs to the group, HTTP Not Found 404 is returned.
+/// 3. When the faults fail to be stored in the fault store, HTTP Internal Server Error 500 is
+///    returned.
+async fn set_fault_group_enabled(
+    request: &HttpRequest,
+    actor: &Actor,
+    fault_store: &DB,
+    audit_log: &AuditLog,
+    enabled: bool,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let group = fault_group_from_path(request)?;
+    info!("Set fault group {} enabled: {}", group, enabled);
+
+    let mut faults = fault_store
+        .get_faults_by_group(group)
+        .await
+        .map_err(|err| {
+            error!("Error fetching the faults of group {}: {}", group, err);
+            ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+        })?;
+
+    if faults.is_empty() {
+        return Err(ServerErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            format!("No fault belongs to the group {}", group),
+        ));
+    }
+
+    let now = Utc::now();
+    for fault in faults.iter_mut() {
+        fault.enabled = enabled;
+        fault.last_modified = Some(now);
+    }
+
+    fault_store.store_batch(&faults).await.map_err(|err| {
+        error!("Error storing the faults of group {}: {}", group, err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    })?;
+
+    info!(
+        "Set {} faults of group {} enabled: {}",
+        faults.len(),
+        group,
+        enabled
+    );
+    for fault in &faults {
+        audit(audit_log, AuditAction::Updated, actor, fault);
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(faults))
+}
+
+/// Records the change of the fault in the audit log. A failure is only logged, as the change is
+/// already applied to the fault store.
+pub(crate) fn audit(audit_log: &AuditLog, action: AuditAction, actor: &Actor, fault: &Fault) {
+    if let Err(err) = audit_log.log(&AuditEntry::new(action, &actor.0, fault)) {
+        error!(
+            "Error writing the audit log of fault {}: {:?}",
+            fault.name, err
+        );
+    }
+}
+
+/// Fetches the fault group name from the request path
+fn fault_group_from_path(request: &HttpRequest) -> Result<&str, ServerErrorResponse> {
+    request.match_info().get("group").ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error fetching fault group from the request path".to_string(),
+        )
+    })
+}
+
+/// ApiVersion is the response body of the GET /v1/version endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiVersion {
+    /// The version of the fault config API
+    pub version: String,
+    /// The version of red-monkey
+    pub server_version: String,
+}
+
+/// get_version is the handler for GET /v1/version.
+///
+/// Returns the version of the fault config API and of red-monkey.
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "version",
+    responses((status = 200, description = "The versions", body = ApiVersion))
+)]
+pub async fn get_version() -> HttpResponse {
+    HttpResponse::Ok().json(ApiVersion {
+        version: API_VERSION.to_string(),
+        server_version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
+
+/// ProbeStatus is the response body of the health and readiness probes.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProbeStatus {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// health is the handler for GET /health.
+///
+/// It always returns HTTP OK 200 to confirm that the process is alive.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "probes",
+    security(()),
+    responses((status = 200, description = "The process is alive", body = ProbeStatus))
+)]
+pub async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(ProbeStatus {
+        status: "ok".to_string(),
+        reason: None,
+    })
+}
+
+/// ready is the handler for GET /ready.
+///
+/// Returns HTTP OK 200 when the fault store can be queried and HTTP Service Unavailable 503 with
+/// the reason otherwise.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "probes",
+    security(()),
+    responses(
+        (status = 200, description = "The fault store can be queried", body = ProbeStatus),
+        (status = 503, description = "The fault store is unavailable", body = ProbeStatus),
+    )
+)]
+pub async fn ready(fault_store: web::Data<DB>) -> HttpResponse {
+    match fault_store.get_all_faults().await {
+        Ok(_) => HttpResponse::Ok().json(ProbeStatus {
+            status: "ready".to_string(),
+            reason: None,
+        }),
+        Err(err) => {
+            error!("Readiness check failed: {}", err);
+            HttpResponse::ServiceUnavailable().json(ProbeStatus {
+                status: "unavailable".to_string(),
+                reason: Some(err.message),
+            })
+        }
+    }
+}
+
+/// get_metrics is the handler for GET /metrics.
+///
+/// Returns the Prometheus metrics of red-monkey in the text exposition format. The number of
+/// active faults is refreshed from the fault store on every scrape. The metrics are never
+/// compressed, as the compression costs more than it saves on the frequent scrapes.
+/// On failing to gather the metrics, returns HTTP Internal Server Error 500 status.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses(
+        (status = 200, description = "The metrics in the Prometheus text exposition format", body = String, content_type = "text/plain"),
+        (status = 500, description = "The metrics can't be gathered", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(fault_store, metrics))]
+pub async fn get_metrics(
+    fault_store: web::Data<DB>,
+    metrics: web::Data<Arc<Metrics>>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    debug!("Fetch metrics");
+
+    let faults = fault_store.get_all_faults().await.map_err(|err| {
+        error!("Error fetching all faults: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.message)
+    })?;
+    let active_faults = faults.iter().filter(|fault| fault.is_active()).count();
+    metrics.active_faults.set(active_faults as i64);
+
+    let (content_type, body) = metrics.encode().map_err(|err| {
+        error!("Error encoding metrics: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(header::ContentEncoding::Identity)
+        .body(body))
+}
+
+/// The log levels accepted by the POST /admin/log-level endpoint
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+/// LogLevel is the request and response body of the POST /admin/log-level endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LogLevel {
+    /// One of `trace`, `debug`, `info`, `warn`, `error`
+    pub level: String,
+}
+
+/// set_log_level is the handler for POST /admin/log-level.
+///
+/// 1. Changes the log level of red-monkey at runtime and returns the new level with HTTP OK 200.
+/// 2. If the level is not one of `trace`, `debug`, `info`, `warn`, `error`, HTTP Bad request 400
+///    is returned.
+/// 3. On failing to change the log level, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    post,
+    path = "/admin/log-level",
+    tag = "admin",
+    request_body = LogLevel,
+    responses(
+        (status = 200, description = "The log level is changed", body = LogLevel),
+        (status = 400, description = "The log level is invalid", body = ServerErrorResponse),
+        (status = 500, description = "The log level can't be changed", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(log_level_handle))]
+pub async fn set_log_level(
+    log_level: web::Json<LogLevel>,
+    log_level_handle: web::Data<LogLevelHandle>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let level = log_level.level.to_lowercase();
+    if !LOG_LEVELS.contains(&level.as_str()) {
+        return Err(ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid log level {}, expected one of {:?}",
+                log_level.level, LOG_LEVELS
+            ),
+        ));
+    }
+
+    log_level_handle
+        .reload(EnvFilter::new(&level))
+        .map_err(|err| {
+            error!("Error changing the log level: {}", err);
+            ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+    info!("Log level changed to {}", level);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(LogLevel { level }))
+}
+
+/// Experiment is the response body of the POST /experiment/start and POST /experiment/stop
+/// endpoints.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Experiment {
+    /// The path of the file the events recorded so far are moved to
+    pub rotated_log_path: String,
+}
+
+/// start_experiment is the handler for POST /experiment/start.
+///
+/// 1. Moves the fault injection events recorded so far to a timestamped file, such that the
+///    experiment log only holds the events of the experiment being started, and returns the path
+///    of that file with HTTP OK 200.
+/// 2. If no experiment log is configured, HTTP Bad request 400 is returned.
+/// 3. On failing to rotate the experiment log, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    post,
+    path = "/experiment/start",
+    tag = "experiments",
+    responses(
+        (status = 200, description = "The experiment log is rotated", body = Experiment),
+        (status = 400, description = "The experiment log is not configured", body = ServerErrorResponse),
+        (status = 500, description = "The experiment log can't be rotated", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(experiment_log))]
+pub async fn start_experiment(
+    experiment_log: web::Data<Option<ExperimentLog>>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let experiment = rotate_experiment_log(&experiment_log).await?;
+    info!(
+        "Experiment started, previous events moved to {}",
+        experiment.rotated_log_path
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(experiment))
+}
+
+/// stop_experiment is the handler for POST /experiment/stop.
+///
+/// 1. Moves the fault injection events of the experiment to a timestamped file and returns the
+///    path of that file with HTTP OK 200.
+/// 2. If no experiment log is configured, HTTP Bad request 400 is returned.
+/// 3. On failing to rotate the experiment log, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    post,
+    path = "/experiment/stop",
+    tag = "experiments",
+    responses(
+        (status = 200, description = "The events of the experiment are moved to the rotated file", body = Experiment),
+        (status = 400, description = "The experiment log is not configured", body = ServerErrorResponse),
+        (status = 500, description = "The experiment log can't be rotated", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(experiment_log))]
+pub async fn stop_experiment(
+    experiment_log: web::Data<Option<ExperimentLog>>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let experiment = rotate_experiment_log(&experiment_log).await?;
+    info!(
+        "Experiment stopped, events recorded to {}",
+        experiment.rotated_log_path
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(experiment))
+}
+
+fn default_speed_factor() -> f64 {
+    1.0
+}
+
+/// Replay is the request body of the POST /experiment/replay endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Replay {
+    /// The path of the recorded experiment log on the red-monkey host, in the directory of the
+    /// `EXPERIMENT_LOG_PATH`
+    pub path: String,
+    /// The speed of the replay, `1.0` replays the events in real time and `2.0` at double speed
+    #[serde(default = "default_speed_factor")]
+    pub speed_factor: f64,
+}
+
+/// replay_experiment is the handler for POST /experiment/replay.
+///
+/// 1. Re-injects the faults recorded in the given experiment log in order, with the recorded time
+///    between them scaled by the `speed_factor`, and returns the outcome of every event with HTTP
+///    OK 200 once the replay completes. See [ReplayEngine::replay].
+/// 2. If the speed factor is not positive, the experiment log is not configured, the path is not a
+///    file of the experiment log directory, or the experiment log is too large or can't be read or
+///    parsed, HTTP Bad request 400 is returned.
+/// 3. On failing to read the faults, HTTP Internal Server Error 500 is returned.
+#[utoipa::path(
+    post,
+    path = "/experiment/replay",
+    tag = "experiments",
+    request_body = Replay,
+    responses(
+        (status = 200, description = "The experiment is replayed", body = [ReplayedEvent]),
+        (status = 400, description = "The speed factor or the experiment log is invalid", body = ServerErrorResponse),
+        (status = 500, description = "The faults can't be read", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(replay_engine))]
+pub async fn replay_experiment(
+    replay: web::Json<Replay>,
+    replay_engine: web::Data<ReplayEngine>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let replayed_events = replay_engine
+        .replay(&replay.path, replay.speed_factor)
+        .await
+        .map_err(|err| {
+            error!(
+                "Error replaying the experiment log {}: {}",
+                replay.path, err
+            );
+            let status_code = match err {
+                ReplayErrors::FaultStoreError(_) | ReplayErrors::MetricsError(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+                _ => StatusCode::BAD_REQUEST,
+            };
+            ServerErrorResponse::new(status_code, err.to_string())
+        })?;
+    info!(
+        "Replayed {} event(s) of the experiment log {}",
+        replayed_events.len(),
+        replay.path
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(replayed_events))
+}
+
+async fn rotate_experiment_log(
+    experiment_log: &Option<ExperimentLog>,
+) -> Result<Experiment, ServerErrorResponse> {
+    let experiment_log = experiment_log.as_ref().ok_or_else(|| {
+        ServerErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "The experiment log is not configured, set EXPERIMENT_LOG_PATH".to_string(),
+        )
+    })?;
+
+    let rotated_log_path = experiment_log.rotate().await.map_err(|err| {
+        error!("Error rotating the experiment log: {}", err);
+        ServerErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    })?;
+
+    Ok(Experiment { rotated_log_path })
+}
+
+/// fault_events is the handler of GET /fault-events endpoint that streams the faults injected by
+/// the proxy as Server-Sent Events, one `data: <FaultEvent JSON>` message per fault.
+///
+/// Only the faults injected after the client connects are streamed. A client that lags more than
+/// [crate::events::FAULT_EVENTS_CAPACITY] events behind misses the oldest ones. The stream ends
+/// when the client disconnects.
+#[utoipa::path(
+    get,
+    path = "/fault-events",
+    tag = "faults",
+    responses(
+        (status = 200, description = "The stream of the injected faults", body = FaultEvent, content_type = "text/event-stream"),
+    )
+)]
+#[tracing::instrument(skip(fault_event_sender))]
+pub async fn fault_events(fault_event_sender: web::Data<FaultEventSender>) -> HttpResponse {
+    let receiver = fault_event_sender.subscribe();
+    info!("Fault event stream subscribed");
+
+    let events = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((sse_message(&event), receiver)),
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("The fault event stream missed {} events", missed);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        // the events are not compressed, as the compression buffers them
+        .insert_header(header::ContentEncoding::Identity)
+        .streaming(events)
+}
+
+/// Frames the fault event as a Server-Sent Events message.
+fn sse_message(event: &FaultEvent) -> Result<web::Bytes, serde_json::Error> {
+    let mut message = b"data: ".to_vec();
+    serde_json::to_writer(&mut message, event)?;
+    message.extend_from_slice(b"\n\n");
+    Ok(web::Bytes::from(message))
+}
+
+/// The interval of the pings that keep the fault event WebSocket connections alive.
+pub const WS_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// ws_fault_events is the handler of GET /ws/fault-events endpoint that streams the faults
+/// injected by the proxy over a WebSocket, one text message of the [FaultEvent] JSON per fault,
+/// like GET /fault-events does with Server-Sent Events.
+///
+/// The messages of the client are ignored, except for the close frame. The connection is pinged
+/// every [WS_PING_INTERVAL], and closed when the client doesn't answer two pings in a row.
+/// If the connection is not a WebSocket handshake, HTTP Bad request 400 is returned.
+#[utoipa::path(
+    get,
+    path = "/ws/fault-events",
+    tag = "faults",
+    responses(
+        (status = 101, description = "The connection is upgraded to a WebSocket streaming the injected faults", body = FaultEvent),
+        (status = 400, description = "The request is not a WebSocket handshake", body = ServerErrorResponse),
+    )
+)]
+#[tracing::instrument(skip(request, body, fault_event_sender))]
+pub async fn ws_fault_events(
+    request: HttpRequest,
+    body: web::Payload,
+    fault_event_sender: web::Data<FaultEventSender>,
+) -> Result<HttpResponse, ServerErrorResponse> {
+    let (response, mut session, mut messages) = actix_ws::handle(&request, body)
+        .map_err(|err| ServerErrorResponse::new(StatusCode::BAD_REQUEST, err.to_string()))?;
+    // subscribed before the handshake completes, such that no event is missed once it does
+    let mut receiver = fault_event_sender.subscribe();
+    info!("Fault event WebSocket subscribed");
+
+    actix_web::rt::spawn(async move {
+        let mut pings = tokio::time::interval_at(
+            tokio::time::Instant::now() + WS_PING_INTERVAL,
+            WS_PING_INTERVAL,
+        );
+        let mut last_heard = Instant::now();
+
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => {
+                        let text = match serde_json::to_string(&event) {
+                            Ok(text) => text,
+                            Err(err) => {
+                                error!("Error serializing the fault event: {}", err);
+                                continue;
+                            }
+                        };
+                        if session.text(text).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("The fault event WebSocket missed {} events", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = pings.tick() => {
+                    if last_heard.elapsed() > 2 * WS_PING_INTERVAL {
+                        debug!("The fault event WebSocket client stopped answering the pings");
+                        break;
+                    }
+                    if session.ping(b"").await.is_err() {
+                        break;
+                    }
+                }
+                message = messages.recv() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        last_heard = Instant::now();
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => last_heard = Instant::now(),
+                },
+            }
+        }
+
+        // the session is closed already when the client went away
+        let _ = session.close(None).await;
+        info!("Fault event WebSocket closed");
+    });
+
+    Ok(response)
+}
+
+/// ServerErrorResponse is the error response body of the fault config server endpoints.
+#[derive(serde::Serialize, ToSchema)]
+pub struct ServerErrorResponse {
+    status_code: u16,
+    message: String,
+}
+
+impl ServerErrorResponse {
+    fn new(status_code: StatusCode, message: String) -> Self {
+        Self {
+            status_code: status_code.as_u16(),
+            message,
+        }
+    }
+
+    /// Returns the HTTP status of the error response.
+    pub(crate) fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Debug for ServerErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error: {}", self.message)
+    }
+}
+
+impl std::fmt::Display for ServerErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error: {}", self.message)
+    }
+}
+
+impl ResponseError for ServerErrorResponse {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponseBuilder::new(StatusCode::from_u16(self.status_code).unwrap())
+            .content_type(ContentType::json())
+            .json(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::NopAuditLogger;
+    use crate::fault_config_server::server::api_routes;
+    use crate::fault_config_server::version::api_prefix;
+    use crate::store::fault_store::{FaultRequest, FaultStore, FieldDiff, PlaybookStep};
+    use actix_web::{http::StatusCode, test, web, web::Data, App};
+
+    #[tokio::test]
+    async fn test_store_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_store_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let existing_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store
+            .store(&existing_fault.name, &existing_fault)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut set_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        set_fault.name = "set_err".to_string();
+        set_fault.command = "SET".to_string();
+        let mut conflicting_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        conflicting_fault.name = "get_err".to_string();
+        let mut invalid_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        invalid_fault.name = "del_err".to_string();
+        invalid_fault.command = "DEL".to_string();
+        invalid_fault.max_hits = Some(0);
+        let mut batch_conflicting_fault = set_fault.clone();
+        batch_conflicting_fault.name = "set_err_2".to_string();
+
+        let batch = vec![
+            set_fault.clone(),
+            conflicting_fault,
+            invalid_fault,
+            batch_conflicting_fault,
+        ];
+
+        let req = test::TestRequest::post()
+            .uri("/v1/faults")
+            .set_json(&batch)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        let results: Vec<BatchItemResult> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        let statuses: Vec<(&str, u16)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.status_code))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("set_err", 201),
+                ("get_err", 409),
+                ("del_err", 400),
+                ("set_err_2", 409)
+            ]
+        );
+
+        assert_eq!(fault_store.get_all_faults().await.unwrap().len(), 2);
+        assert!(fault_store.get_by_fault_name(&set_fault.name).await.is_ok());
+    }
+
+    /// Builds the multipart form of POST /faults/import with the given field, returning its
+    /// content type and body
+    fn import_form(field: &str, file: &[u8]) -> (String, Vec<u8>) {
+        let boundary = "red-monkey-boundary";
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"faults.json\"\r\n\
+             Content-Type: application/json\r\n\r\n",
+            boundary, field
+        )
+        .into_bytes();
+        body.extend_from_slice(file);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        (format!("multipart/form-data; boundary={}", boundary), body)
+    }
+
+    #[tokio::test]
+    async fn test_import_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let existing_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store
+            .store(&existing_fault.name, &existing_fault)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut set_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        set_fault.name = "set_err".to_string();
+        set_fault.command = "SET".to_string();
+        let mut conflicting_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        conflicting_fault.name = "get_err".to_string();
+        let file = serde_json::to_vec(&vec![set_fault.clone(), conflicting_fault]).unwrap();
+
+        // the faults of the file are merged with the faults of the store by default
+        let (content_type, body) = import_form("file", &file);
+        let req = test::TestRequest::post()
+            .uri("/v1/faults/import")
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        let results: Vec<BatchItemResult> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        let statuses: Vec<(&str, u16)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.status_code))
+            .collect();
+        assert_eq!(statuses, vec![("set_err", 201), ("get_err", 409)]);
+        assert_eq!(fault_store.get_all_faults().await.unwrap().len(), 2);
+
+        // the faults of the file replace the faults of the store without merging
+        let (content_type, body) = import_form("file", &file);
+        let req = test::TestRequest::post()
+            .uri("/v1/faults/import?merge=false")
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        let results: Vec<BatchItemResult> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert!(results.iter().all(|r| r.status_code == 201));
+        let mut fault_names: Vec<String> = fault_store
+            .get_all_faults()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|fault| fault.name)
+            .collect();
+        fault_names.sort();
+        assert_eq!(fault_names, vec!["get_err", "set_err"]);
+        assert!(fault_store
+            .get_by_fault_name(&existing_fault.name)
+            .await
+            .is_err());
+    }
+
+    /// A fault store that fails to store the fault of the given name, and otherwise delegates to
+    /// the inner store.
+    #[derive(Clone)]
+    struct FailingStore {
+        inner: DB,
+        failing_fault: String,
+    }
+
+    #[async_trait::async_trait]
+    impl FaultStore for FailingStore {
+        async fn store(&self, key: &str, fault: &Fault) -> Result<bool, StoreError> {
+            if key == self.failing_fault {
+                return Err(StoreError::new("store is unavailable"));
+            }
+            self.inner.store(key, fault).await
+        }
+
+        async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+            self.inner.get_by_fault_name(fault_name).await
+        }
+
+        async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+            self.inner.get_all_faults().await
+        }
+
+        async fn get_by_redis_cmd(
+            &self,
+            request: &FaultRequest<'_>,
+            strategy: FaultSelectionStrategy,
+        ) -> Option<Fault> {
+            self.inner.get_by_redis_cmd(request, strategy).await
+        }
+
+        async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+            self.inner.delete_fault(fault_name).await
+        }
+
+        async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+            self.inner.disable_fault(fault_name).await
+        }
+
+        async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+            self.inner.claim_fault(fault_name).await
+        }
+
+        async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+            self.inner.record_fault_hit(fault_name).await
+        }
+
+        async fn reserve_hit(
+            &self,
+            fault_name: &str,
+            max_hits: u64,
+            delay_ms: Option<u64>,
+        ) -> Result<Option<u64>, StoreError> {
+            self.inner.reserve_hit(fault_name, max_hits, delay_ms).await
+        }
+
+        async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+            self.inner.get_fault_hit_count(fault_name).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_faults_store_failure() {
+        let inner = crate::store::mem_store::MemStore::new_db();
+        let existing_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        inner
+            .store(&existing_fault.name, &existing_fault)
+            .await
+            .unwrap();
+        let fault_store: DB = Arc::new(FailingStore {
+            inner: inner.clone(),
+            failing_fault: "set_err".to_string(),
+        });
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut del_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        del_fault.name = "del_err".to_string();
+        del_fault.command = "DEL".to_string();
+        let mut set_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        set_fault.name = "set_err".to_string();
+        set_fault.command = "SET".to_string();
+        let file = serde_json::to_vec(&vec![del_fault, set_fault]).unwrap();
+
+        // the faults of the store are kept when the faults of the file fail to be stored
+        let (content_type, body) = import_form("file", &file);
+        let req = test::TestRequest::post()
+            .uri("/v1/faults/import?merge=false")
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let faults = inner.get_all_faults().await.unwrap();
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].name, existing_fault.name);
+    }
+
+    #[tokio::test]
+    async fn test_import_invalid_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let existing_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store
+            .store(&existing_fault.name, &existing_fault)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log()))
+                .app_data(Data::new(ImportLimit(64))),
+        )
+        .await;
+
+        let large_file = serde_json::to_vec(&vec![Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        }])
+        .unwrap();
+        let cases: [(&str, &[u8], StatusCode); 3] = [
+            ("file", &large_file, StatusCode::PAYLOAD_TOO_LARGE),
+            ("file", b"{\"name\": \"get_err\"}", StatusCode::BAD_REQUEST),
+            ("faults", b"[]", StatusCode::BAD_REQUEST),
+        ];
+        for (field, file, status) in cases {
+            let (content_type, body) = import_form(field, file);
+            let req = test::TestRequest::post()
+                .uri("/v1/faults/import?merge=false")
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .set_payload(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status, "{}", field);
+        }
+
+        // the store is left untouched by the rejected files
+        assert_eq!(
+            fault_store.get_all_faults().await.unwrap(),
+            vec![existing_fault]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let mut faults = Vec::new();
+        for (name, command, group) in [
+            ("get_err", "GET", Some("production")),
+            ("set_err", "SET", Some("staging")),
+            ("del_err", "DEL", None),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.command = command.to_string();
+            fault.group = group.map(str::to_string);
+            fault_store.store(&fault.name, &fault).await.unwrap();
+            faults.push(fault);
+        }
+        faults.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults/export?group=production")
+            .to_request();
+        let exported: Vec<Fault> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(exported, vec![faults[1].clone()]);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults/export")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let content_disposition = resp
+            .headers()
+            .get(header::CONTENT_DISPOSITION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(content_disposition.starts_with("attachment; filename=\"faults-"));
+        assert!(content_disposition.ends_with(".json\""));
+        let file = test::read_body(resp).await;
+        assert_eq!(serde_json::from_slice::<Vec<Fault>>(&file).unwrap(), faults);
+
+        // the exported file restores the faults once the store is cleared
+        fault_store.clear_all().await.unwrap();
+        let (content_type, body) = import_form("file", &file);
+        let req = test::TestRequest::post()
+            .uri("/v1/faults/import")
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        // the import stamps the faults with their modification time
+        let mut imported_faults = fault_store.get_all_faults().await.unwrap();
+        imported_faults.sort_by(|a, b| a.name.cmp(&b.name));
+        for fault in &mut imported_faults {
+            assert!(fault.last_modified.is_some());
+            fault.last_modified = None;
+        }
+        assert_eq!(imported_faults, faults);
+
+        // an empty store is exported as an empty array
+        fault_store.clear_all().await.unwrap();
+        let req = test::TestRequest::get()
+            .uri("/v1/faults/export")
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "[]");
+    }
+
+    #[tokio::test]
+    async fn test_store_faults_atomic() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut set_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        set_fault.name = "set_err".to_string();
+        set_fault.command = "SET".to_string();
+        let mut invalid_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        invalid_fault.max_hits = Some(0);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/faults?atomic=true")
+            .set_json(vec![set_fault.clone(), invalid_fault])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(fault_store.get_all_faults().await.unwrap().is_empty());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/faults?atomic=true")
+            .set_json(vec![
+                set_fault,
+                Fault {
+                    description: Some("GET custom error".to_string()),
+                    error_msg: Some("KEY not found".to_string()),
+                    ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+                },
+            ])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        let results: Vec<BatchItemResult> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert!(results.iter().all(|r| r.status_code == 201));
+        assert_eq!(fault_store.get_all_faults().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_store_drop_conn_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = serde_json::to_value(Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        })
+        .unwrap();
+        fault["fault_type"] = serde_json::json!("drop_conn");
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let stored_fault = fault_store
+            .get_by_fault_name("get_custom_err")
+            .await
+            .unwrap();
+        assert_eq!(stored_fault.fault_type, FaultVariants::DropConn);
+    }
+
+    #[tokio::test]
+    async fn test_store_expired_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_store_scheduled_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        // a fault that expires before it is activated is only warned about
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.activate_at = Some(Utc::now() + chrono::Duration::hours(2));
+        fault.expires_at = Some(Utc::now() + chrono::Duration::hours(1));
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(
+            fault_store
+                .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preview_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        for uri in ["/v1/fault/validate", "/v1/fault?dry_run=true"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(Fault {
+                    description: Some("GET custom error".to_string()),
+                    error_msg: Some("KEY not found".to_string()),
+                    ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+                })
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let preview: FaultPreview =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            assert_eq!(preview.fault.name, "get_custom_err");
+            assert!(preview.conflicting_faults.is_empty());
+        }
+
+        // the previewed fault is not stored
+        assert!(fault_store.get_all_faults().await.unwrap().is_empty());
+        let req = test::TestRequest::get().uri("/v1/faults").to_request();
+        let page: FaultsPage =
+            serde_json::from_slice(&test::read_body(test::call_service(&app, req).await).await)
+                .unwrap();
+        assert_eq!(page.total, 0);
+
+        // the preview lists the conflicting faults
+        let existing_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store
+            .store(&existing_fault.name, &existing_fault)
+            .await
+            .unwrap();
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.name = "another GET fault".to_string();
+        let req = test::TestRequest::post()
+            .uri("/v1/fault/validate")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let preview: FaultPreview = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(
+            preview.conflicting_faults,
+            vec![existing_fault.name.clone()]
+        );
+
+        // the invalid faults are rejected
+        fault.max_hits = Some(0);
+        for uri in ["/v1/fault/validate", "/v1/fault?dry_run=true"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(fault_store.get_all_faults().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_store_zero_max_hits_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.max_hits = Some(0);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_store_jitter_on_non_delay_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.jitter_ms = Some(10);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_store_burst_error_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut delay_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        delay_fault.fault_type = FaultVariants::Delay;
+        delay_fault.duration = Some(10);
+        delay_fault.burst_count = Some(3);
+        let mut zero_burst_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        zero_burst_fault.burst_count = Some(0);
+
+        for fault in [delay_fault, zero_burst_fault] {
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.burst_count = Some(3);
+        fault.burst_interval_ms = Some(10);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_store_partial_response_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.fault_type = FaultVariants::PartialResponse;
+        fault.truncate_after_bytes = Some(0);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        fault.truncate_after_bytes = Some(4);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_store_slow_drain_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.fault_type = FaultVariants::SlowDrain;
+        fault.bytes_per_ms = Some(0);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        fault.bytes_per_ms = Some(1);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_store_mutate_response_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.fault_type = FaultVariants::MutateResponse;
+        fault.mutation_replacement = Some("+MOCKED".to_string());
+
+        for (mutation_pattern, expected_status) in [
+            (None, StatusCode::BAD_REQUEST),
+            (Some("+OK"), StatusCode::BAD_REQUEST),
+            (Some(r"\+OK"), StatusCode::CREATED),
+        ] {
+            fault.mutation_pattern = mutation_pattern.map(str::to_string);
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), expected_status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_invalid_key_pattern() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.key_pattern = Some("user:[".to_string());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_store_composite_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let delay = SubFault {
+            fault_type: FaultVariants::Delay,
+            duration: Some(500),
+            error_msg: None,
+        };
+        let error = SubFault {
+            fault_type: FaultVariants::Error,
+            duration: None,
+            error_msg: Some("SET FAILED".to_string()),
+        };
+        let timeout = SubFault {
+            fault_type: FaultVariants::Timeout,
+            duration: None,
+            error_msg: None,
+        };
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.fault_type = FaultVariants::Composite;
+        for sub_faults in [
+            None,
+            Some(vec![]),
+            Some(vec![error.clone(), delay.clone()]),
+            Some(vec![delay.clone(), timeout]),
+        ] {
+            fault.sub_faults = sub_faults;
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let mut error_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        error_fault.sub_faults = Some(vec![error.clone()]);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&error_fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        fault.sub_faults = Some(vec![delay, error]);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_store() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            })
+            .to_request();
+        let mut resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            })
+            .to_request();
+        resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_wildcard_store() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.command = "*".to_string();
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        fault.name = "another_wildcard".to_string();
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/v1/faults").to_request();
+        let resp = test::call_service(&app, req).await;
+        let result = test::read_body(resp).await;
+        let faults_page: FaultsPage = serde_json::from_slice(&result).unwrap();
+
+        assert_eq!(faults_page.faults.len(), 1);
+        assert_eq!(
+            faults_page.faults[0],
+            Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            }
+        );
+        assert_eq!(faults_page.total, 1);
+        assert_eq!(faults_page.page, 1);
+        assert_eq!(faults_page.per_page, 50);
+    }
+
+    #[tokio::test]
+    async fn test_replace_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut replacement = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        replacement.fault_type = FaultVariants::Delay;
+        replacement.error_msg = None;
+        replacement.duration = Some(100);
+        replacement.description = None;
+
+        let req = test::TestRequest::put()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(&replacement)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let replaced: Fault = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert!(replaced.last_modified.is_some());
+        replacement.last_modified = replaced.last_modified;
+        assert_eq!(replaced, replacement);
+        assert_eq!(
+            fault_store.get_by_fault_name(&fault.name).await.unwrap(),
+            replacement
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_fault_not_found() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        let req = test::TestRequest::put()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_replace_fault_name_mismatch() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let mut renamed = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        renamed.name = "renamed".to_string();
+        let req = test::TestRequest::put()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(&renamed)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_faults_filter() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let mut faults = vec![
+            Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            3
+        ];
+        faults[1].name = "set_delay".to_string();
+        faults[1].fault_type = FaultVariants::Delay;
+        faults[1].command = "SET".to_string();
+        faults[2].name = "del_err".to_string();
+        faults[2].command = "DEL".to_string();
+        for fault in &faults {
+            fault_store.store(&fault.name, fault).await.unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let cases = [
+            ("/v1/faults?command=set", vec!["set_delay"]),
+            (
+                "/v1/faults?fault_type=error",
+                vec!["del_err", "get_custom_err"],
+            ),
+            ("/v1/faults?command=Del&fault_type=ERROR", vec!["del_err"]),
+            ("/v1/faults?command=get&fault_type=delay", vec![]),
+            ("/v1/faults?command=ping", vec![]),
+        ];
+
+        for (uri, expected_names) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let faults_page: FaultsPage =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            let mut names: Vec<&str> = faults_page.faults.iter().map(|f| f.name.as_str()).collect();
+            names.sort_unstable();
+            assert_eq!(names, expected_names, "{}", uri);
+            assert_eq!(faults_page.total, expected_names.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let mut faults = vec![
+            Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            3
+        ];
+        faults[0].tags = vec!["cache".to_string()];
+        faults[1].name = "set_delay".to_string();
+        faults[1].description = Some("slow cache writes".to_string());
+        faults[1].fault_type = FaultVariants::Delay;
+        faults[1].command = "SET".to_string();
+        faults[1].enabled = false;
+        faults[2].name = "cache_miss".to_string();
+        faults[2].description = Some("cache miss on get".to_string());
+        faults[2].tags = vec!["cache".to_string()];
+        faults.push(Fault::fixture("drop_conn", FaultVariants::DropConn, "PING"));
+        for fault in &faults {
+            fault_store.store(&fault.name, fault).await.unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let cases = [
+            // cache_miss matches by name, description and tag, get_custom_err by tag only
+            (
+                "/v1/fault/search?q=CACHE",
+                vec!["cache_miss", "get_custom_err", "set_delay"],
+            ),
+            ("/v1/fault/search?q=set", vec!["set_delay"]),
+            (
+                "/v1/fault/search?q=cache&command=get",
+                vec!["cache_miss", "get_custom_err"],
+            ),
+            (
+                "/v1/fault/search?command=SET&fault_type=delay&enabled=false",
+                vec!["set_delay"],
+            ),
+            ("/v1/fault/search?fault_type=delay&enabled=true", vec![]),
+            ("/v1/fault/search?fault_type=drop_conn", vec!["drop_conn"]),
+            ("/v1/fault/search?fault_type=DropConn", vec!["drop_conn"]),
+            ("/v1/fault/search?q=unknown", vec![]),
+        ];
+
+        for (uri, expected_names) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let faults: Vec<Fault> = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            let names: Vec<&str> = faults.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(names, expected_names, "{}", uri);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/v1/fault/search?fault_type=slow")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_faults_pagination() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        for i in 0..5 {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = format!("fault_{}", i);
+            fault.command = format!("CMD{}", i);
+            fault.last_modified = Some(Utc::now() + chrono::Duration::seconds(i));
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults?page=2&per_page=2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let faults_page: FaultsPage = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+
+        let names: Vec<&str> = faults_page.faults.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["fault_2", "fault_1"]);
+        assert_eq!(faults_page.total, 5);
+        assert_eq!(faults_page.page, 2);
+        assert_eq!(faults_page.per_page, 2);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults?page=4&per_page=2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let faults_page: FaultsPage = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert!(faults_page.faults.is_empty());
+        assert_eq!(faults_page.total, 5);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults?per_page=1000")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let faults_page: FaultsPage = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(faults_page.faults.len(), 5);
+        assert_eq!(faults_page.per_page, 200);
+
+        for uri in [
+            "/v1/faults?page=0",
+            "/v1/faults?per_page=0",
+            "/v1/faults?page=abc",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let result = test::read_body(resp).await;
+
+        let fault: Fault = serde_json::from_slice(&result).unwrap();
+        assert_eq!(
+            fault,
+            Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_patch_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::patch()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(serde_json::json!({"enabled": false}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let patched: Fault = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert!(!patched.enabled);
+        assert_eq!(patched.error_msg, fault.error_msg);
+        assert_eq!(patched.command, fault.command);
+
+        let stored = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+        assert!(!stored.enabled);
+        assert_eq!(
+            fault_store
+                .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                .await,
+            None
+        );
+
+        // the patched fault is validated as a whole
+        for patch in [
+            serde_json::json!({"max_hits": 0}),
+            serde_json::json!({"fault_type": "PartialResponse"}),
+        ] {
+            let req = test::TestRequest::patch()
+                .uri(format!("/v1/fault/{}", fault.name).as_str())
+                .set_json(patch)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+        let stored = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+        assert_eq!(stored.max_hits, fault.max_hits);
+        assert_eq!(stored.fault_type, fault.fault_type);
+
+        // a null clears the optional field, while an absent field is left as is
+        let req = test::TestRequest::patch()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(serde_json::json!({
+                "expires_at": "2099-01-01T00:00:00Z",
+                "key_pattern": "user:*",
+                "client_ip_filter": ["10.0.0.1"],
+                "max_hits": 5,
+                "probability": 0.5,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::patch()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .set_json(serde_json::json!({
+                "expires_at": null,
+                "key_pattern": null,
+                "client_ip_filter": null,
+                "max_hits": null,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let stored = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+        assert_eq!(stored.expires_at, None);
+        assert_eq!(stored.key_pattern, None);
+        assert_eq!(stored.client_ip_filter, None);
+        assert_eq!(stored.max_hits, None);
+        assert_eq!(stored.probability, Some(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_patch_fault_conflict() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let get_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store
+            .store(&get_fault.name, &get_fault)
+            .await
+            .unwrap();
+        let mut set_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        set_fault.name = "set_err".to_string();
+        set_fault.command = "SET".to_string();
+        fault_store
+            .store(&set_fault.name, &set_fault)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        // patching the command to the command of another fault creates a conflict
+        let req = test::TestRequest::patch()
+            .uri("/v1/fault/set_err")
+            .set_json(serde_json::json!({"command": get_fault.command}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let stored = fault_store.get_by_fault_name("set_err").await.unwrap();
+        assert_eq!(stored.command, "SET");
+
+        // the patched fault doesn't conflict with its own previous version
+        let req = test::TestRequest::patch()
+            .uri("/v1/fault/set_err")
+            .set_json(serde_json::json!({"command": "SET", "duration": 20}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_fault_etag() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+        let uri = format!("/v1/fault/{}", fault.name);
+
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
+        assert_eq!(etag, format!("\"{}\"", fault.etag()));
+        let etag = etag.to_string();
+
+        // another operator updates the fault in the meantime
+        let req = test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, etag.as_str()))
+            .set_json(serde_json::json!({"enabled": false}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let patched_etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_ne!(patched_etag.to_str().unwrap(), etag);
+
+        // the updates based on the stale ETag are rejected
+        let req = test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, etag.as_str()))
+            .set_json(serde_json::json!({"enabled": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, etag.as_str()))
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, etag.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert!(
+            !fault_store
+                .get_by_fault_name(&fault.name)
+                .await
+                .unwrap()
+                .enabled
+        );
+
+        // the current ETag, or any of the listed ETags, matches
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header((
+                header::IF_MATCH,
+                format!("{}, {}", etag, patched_etag.to_str().unwrap()),
+            ))
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, "*"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        // the deleted fault matches no ETag
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, "*"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_patch_unknown_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::patch()
+            .uri("/v1/fault/unknown")
+            .set_json(serde_json::json!({"enabled": false}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// A fault store that fails every operation, used to test the readiness probe.
+    #[derive(Clone)]
+    struct UnavailableStore;
+
+    #[async_trait::async_trait]
+    impl FaultStore for UnavailableStore {
+        async fn store(&self, _key: &str, _fault: &Fault) -> Result<bool, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn get_by_fault_name(&self, _fault_name: &str) -> Result<Fault, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn get_by_redis_cmd(
+            &self,
+            _request: &FaultRequest<'_>,
+            _strategy: FaultSelectionStrategy,
+        ) -> Option<Fault> {
+            None
+        }
+
+        async fn delete_fault(&self, _fault_name: &str) -> Result<bool, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn disable_fault(&self, _fault_name: &str) -> Result<bool, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn claim_fault(&self, _fault_name: &str) -> Result<bool, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn record_fault_hit(&self, _fault_name: &str) -> Result<u64, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn reserve_hit(
+            &self,
+            _fault_name: &str,
+            _max_hits: u64,
+            _delay_ms: Option<u64>,
+        ) -> Result<Option<u64>, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+
+        async fn get_fault_hit_count(&self, _fault_name: &str) -> Result<u64, StoreError> {
+            Err(StoreError::new("store is unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let app = test::init_service(App::new().route("/health", web::get().to(health))).await;
+
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let status: ProbeStatus = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(status.status, "ok");
+        assert!(status.reason.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ready() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .route("/ready", web::get().to(ready))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let status: ProbeStatus = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(status.status, "ready");
+
+        let fault_store: DB = Arc::new(UnavailableStore);
+        let app = test::init_service(
+            App::new()
+                .route("/ready", web::get().to(ready))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let status: ProbeStatus = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(status.status, "unavailable");
+        assert_eq!(status.reason.unwrap(), "store is unavailable");
+    }
+
+    #[tokio::test]
+    async fn test_get_metrics() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let metrics = Arc::new(Metrics::new().unwrap());
+        metrics.proxy_requests_total.inc_by(3);
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(metrics)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/v1/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("red_monkey_proxy_requests_total 3"));
+        assert!(body.contains("red_monkey_active_faults 1"));
+    }
+
+    #[tokio::test]
+    async fn test_get_fault_stats() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        for _ in 0..2 {
+            fault_store.record_fault_hit(&fault.name).await.unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(format!("/v1/fault/{}/stats", fault.name).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let stats: FaultHitStats = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(stats.name, fault.name);
+        assert_eq!(stats.stats.hit_count, 2);
+        assert!(stats.stats.first_hit_at.is_some());
+
+        let req = test::TestRequest::get()
+            .uri("/v1/fault/unknown/stats")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_fault_stats() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        let mut delay_fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        delay_fault.name = "delay_get".to_string();
+        for fault in [&fault, &delay_fault] {
+            fault_store.store(&fault.name, fault).await.unwrap();
+        }
+        fault_store
+            .record_hit(&delay_fault.name, Some(150))
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults/stats")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let stats: std::collections::HashMap<String, FaultStats> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[&fault.name], FaultStats::default());
+        assert_eq!(stats[&delay_fault.name].hit_count, 1);
+        assert_eq!(stats[&delay_fault.name].total_delay_ms, 150);
+    }
+
+    fn get_report_faults() -> Vec<Fault> {
+        let mut faults = Vec::new();
+        for (name, fault_type, command) in [
+            ("get_err", FaultVariants::Error, "GET"),
+            ("get_delay", FaultVariants::Delay, "GET"),
+            ("set_delay", FaultVariants::Delay, "set"),
+            ("del_drop", FaultVariants::DropConn, "DEL"),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.fault_type = fault_type;
+            fault.command = command.to_string();
+            faults.push(fault);
+        }
+        faults
+    }
+
+    #[tokio::test]
+    async fn test_report_new() {
+        let now = Utc::now();
+        let faults = get_report_faults();
+        let mut stats = HashMap::new();
+        // The hits of each fault by the minutes ago they happened at, the oldest first
+        for (name, hits) in [
+            ("get_err", vec![(60 * 20, None), (120, None), (1, None)]),
+            ("get_delay", vec![(90, Some(300)), (5, Some(100))]),
+            ("set_delay", vec![(60 * 48, Some(50))]),
+        ] {
+            let mut fault_stats = FaultStats::default();
+            for (hit_count, (minutes_ago, delay_ms)) in hits.into_iter().enumerate() {
+                fault_stats.record_hit(
+                    hit_count as u64 + 1,
+                    now - chrono::Duration::minutes(minutes_ago),
+                    delay_ms,
+                );
+            }
+            stats.insert(name.to_string(), fault_stats);
+        }
+        stats.insert("del_drop".to_string(), FaultStats::default());
+
+        let report = Report::new(&faults, &stats, now);
+        assert_eq!(
+            report,
+            Report {
+                total_faults: 4,
+                faults_by_type: BTreeMap::from([
+                    ("Delay".to_string(), 2),
+                    ("DropConn".to_string(), 1),
+                    ("Error".to_string(), 1),
+                ]),
+                hits_last_hour: 2,
+                hits_last_day: 5,
+                top_faults: vec![
+                    ReportHits {
+                        name: "get_err".to_string(),
+                        hit_count: 3
+                    },
+                    ReportHits {
+                        name: "get_delay".to_string(),
+                        hit_count: 2
+                    },
+                    ReportHits {
+                        name: "set_delay".to_string(),
+                        hit_count: 1
+                    },
+                ],
+                top_commands: vec![
+                    ReportHits {
+                        name: "GET".to_string(),
+                        hit_count: 5
+                    },
+                    ReportHits {
+                        name: "SET".to_string(),
+                        hit_count: 1
+                    },
+                ],
+                average_delay_ms: BTreeMap::from([
+                    ("get_delay".to_string(), 200.0),
+                    ("set_delay".to_string(), 50.0),
+                ]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_report_top_hits() {
+        let hits = (0..7)
+            .map(|i| ReportHits {
+                name: format!("fault_{}", i),
+                hit_count: i % 3,
+            })
+            .collect();
+        let names: Vec<String> = top_hits(hits).into_iter().map(|hits| hits.name).collect();
+        assert_eq!(
+            names,
+            vec!["fault_2", "fault_5", "fault_1", "fault_4", "fault_0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_report() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        for fault in get_report_faults() {
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+        for (name, delay_ms) in [
+            ("get_err", None),
+            ("get_err", None),
+            ("get_delay", Some(100)),
+            ("get_delay", Some(200)),
+            ("set_delay", Some(30)),
+        ] {
+            fault_store.record_hit(name, delay_ms).await.unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/v1/report").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let generated_at = resp
+            .headers()
+            .get(REPORT_GENERATED_AT_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(DateTime::parse_from_rfc3339(generated_at).is_ok());
+
+        let report: Report = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(report.total_faults, 4);
+        assert_eq!(report.faults_by_type["Delay"], 2);
+        assert_eq!(report.hits_last_hour, 5);
+        assert_eq!(report.hits_last_day, 5);
+        assert_eq!(report.top_faults[0].name, "get_delay");
+        assert_eq!(report.top_faults[1].name, "get_err");
+        assert_eq!(report.top_commands[0].name, "GET");
+        assert_eq!(report.top_commands[0].hit_count, 4);
+        assert_eq!(report.average_delay_ms["get_delay"], 150.0);
+        assert_eq!(report.average_delay_ms["set_delay"], 30.0);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/report?format=text")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key(REPORT_GENERATED_AT_HEADER));
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.starts_with("Total faults: 4\n"));
+        assert!(body.contains("Injections in the last hour: 5\n"));
+        assert!(body.contains("  1. GET: 4\n"));
+        assert!(body.contains("  get_delay: 150.0 ms\n"));
+
+        let req = test::TestRequest::get()
+            .uri("/v1/report?format=xml")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_fault() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri(format!("/v1/fault/{}", fault.name).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let audit_log_path =
+            std::env::temp_dir().join(format!("red-monkey-audit-{}.log", uuid::Uuid::new_v4()));
+        let audit_log_path = audit_log_path.to_str().unwrap().to_string();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(crate::store::mem_store::MemStore::new_db()))
+                .app_data(Data::new(
+                    crate::audit::FileAuditLogger::new_log(&audit_log_path).unwrap(),
+                )),
+        )
+        .await;
 
         let req = test::TestRequest::post()
-            .uri("/fault")
-            .set_json(get_mock_fault())
+            .uri("/v1/fault")
+            .set_json(&fault)
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), StatusCode::CREATED);
+
+        // deleting a fault that doesn't exist is not audited
+        for fault_name in [fault.name.as_str(), "unknown"] {
+            let req = test::TestRequest::delete()
+                .uri(format!("/v1/fault/{}", fault_name).as_str())
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        }
+
+        let audit_log = std::fs::read_to_string(&audit_log_path).unwrap();
+        let entries: Vec<AuditEntry> = audit_log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+
+        for (entry, action) in entries
+            .iter()
+            .zip([AuditAction::Created, AuditAction::Deleted])
+        {
+            assert_eq!(entry.action, action);
+            assert_eq!(entry.fault_name, fault.name);
+            assert_eq!(entry.actor, "anonymous");
+            assert_eq!(entry.fault.name, fault.name);
+            assert_eq!(entry.fault.error_msg, fault.error_msg);
+        }
+        let line: serde_json::Value =
+            serde_json::from_str(audit_log.lines().next().unwrap()).unwrap();
+        assert_eq!(line["action"], "created");
+
+        std::fs::remove_file(&audit_log_path).unwrap();
     }
 
     #[tokio::test]
-    async fn test_conflict_store() {
+    async fn test_store_playbook() {
         let fault_store = crate::store::mem_store::MemStore::new_db();
-        let mut app = test::init_service(
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let playbook_store = crate::store::mem_store::MemPlaybookStore::new_db();
+
+        let app = test::init_service(
             App::new()
-                .route("/fault", web::post().to(store_fault))
-                .app_data(Data::new(fault_store.clone())),
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store))
+                .app_data(Data::new(playbook_store.clone())),
         )
         .await;
 
-        let mut req = test::TestRequest::post()
-            .uri("/fault")
-            .set_json(get_mock_fault())
+        let mut playbook = FaultPlaybook {
+            name: "flaky get".to_string(),
+            steps: vec![
+                PlaybookStep {
+                    fault_name: None,
+                    repeat_count: 3,
+                },
+                PlaybookStep {
+                    fault_name: Some("unknown".to_string()),
+                    repeat_count: 2,
+                },
+            ],
+            loop_playbook: true,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/v1/playbook")
+            .set_json(&playbook)
             .to_request();
-        let mut resp = test::call_service(&mut app, req).await;
-        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-        req = test::TestRequest::post()
-            .uri("/fault")
-            .set_json(get_mock_fault())
+        playbook.steps[1].fault_name = Some(fault.name);
+        playbook.steps[1].repeat_count = 0;
+        let req = test::TestRequest::post()
+            .uri("/v1/playbook")
+            .set_json(&playbook)
             .to_request();
-        resp = test::call_service(&mut app, req).await;
-        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        playbook.steps[1].repeat_count = 2;
+        for expected_status in [StatusCode::CREATED, StatusCode::CONFLICT] {
+            let req = test::TestRequest::post()
+                .uri("/v1/playbook")
+                .set_json(&playbook)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), expected_status);
+        }
+
+        assert_eq!(playbook_store.get_all_playbooks().unwrap(), vec![playbook]);
     }
 
     #[tokio::test]
-    async fn test_get_all_faults() {
-        let fault_store = crate::store::mem_store::MemStore::new_db();
-        let fault = get_mock_fault();
-        fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
+    async fn test_delete_playbook() {
+        let playbook_store = crate::store::mem_store::MemPlaybookStore::new_db();
+        playbook_store
+            .store_playbook(&FaultPlaybook {
+                name: "flaky_get".to_string(),
+                steps: vec![PlaybookStep {
+                    fault_name: None,
+                    repeat_count: 1,
+                }],
+                loop_playbook: false,
+            })
             .unwrap();
 
-        let mut app = test::init_service(
+        let app = test::init_service(
             App::new()
-                .route("/faults", web::get().to(get_all_faults))
-                .app_data(Data::new(fault_store)),
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(playbook_store.clone())),
         )
         .await;
 
-        let req = test::TestRequest::get().uri("/faults").to_request();
-        let resp = test::call_service(&mut app, req).await;
-        let result = test::read_body(resp).await;
-        let faults: Vec<Fault> = serde_json::from_slice(&result).unwrap();
-
-        assert_eq!(faults.len(), 1);
-        assert_eq!(faults[0], get_mock_fault());
+        for _ in 0..2 {
+            let req = test::TestRequest::delete()
+                .uri("/v1/playbook/flaky_get")
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        }
+        assert!(playbook_store.get_all_playbooks().unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_get_fault() {
+    async fn test_fault_group() {
         let fault_store = crate::store::mem_store::MemStore::new_db();
-        let fault = get_mock_fault();
-        fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
-            .unwrap();
+        for (name, command, group) in [
+            ("get_error", "GET", Some("cache")),
+            ("set_error", "SET", Some("cache")),
+            ("del_error", "DEL", None),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.command = command.to_string();
+            fault.group = group.map(|group| group.to_string());
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
 
-        let mut app = test::init_service(
+        let app = test::init_service(
             App::new()
-                .route("/fault/{fault_name}", web::get().to(get_fault))
-                .app_data(Data::new(fault_store)),
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
         )
         .await;
 
         let req = test::TestRequest::get()
-            .uri(format!("/fault/{}", fault.name).as_str())
+            .uri("/v1/fault-group/cache")
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
-        let result = test::read_body(resp).await;
+        let faults: Vec<Fault> = test::call_and_read_body_json(&app, req).await;
+        let mut names: Vec<&str> = faults.iter().map(|f| f.name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["get_error", "set_error"]);
 
-        let fault: Fault = serde_json::from_slice(&result).unwrap();
-        assert_eq!(fault, get_mock_fault());
+        // disabling the group suppresses the injection of all its faults
+        let req = test::TestRequest::post()
+            .uri("/v1/fault-group/cache/disable")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        for command in ["GET", "SET"] {
+            assert!(fault_store
+                .get_by_redis_cmd(
+                    &FaultRequest::from(command),
+                    FaultSelectionStrategy::Priority
+                )
+                .await
+                .is_none());
+        }
+        assert!(fault_store
+            .get_by_redis_cmd(&FaultRequest::from("DEL"), FaultSelectionStrategy::Priority)
+            .await
+            .is_some());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault-group/cache/enable")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        for command in ["GET", "SET"] {
+            assert!(fault_store
+                .get_by_redis_cmd(
+                    &FaultRequest::from(command),
+                    FaultSelectionStrategy::Priority
+                )
+                .await
+                .is_some());
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/v1/fault-group/unknown/disable")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn test_delete_fault() {
+    async fn test_delete_all_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        let req = test::TestRequest::delete().uri("/v1/faults").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(fault_store.get_all_faults().await.unwrap().is_empty());
+    }
+
+    /// Stores faults on distinct commands with the given tags and returns the fault store
+    async fn get_tagged_fault_store(tags: &[&[&str]]) -> DB {
         let fault_store = crate::store::mem_store::MemStore::new_db();
-        let fault = get_mock_fault();
+        for (i, fault_tags) in tags.iter().enumerate() {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = format!("fault_{}", i);
+            fault.command = format!("CMD{}", i);
+            fault.tags = fault_tags.iter().map(|tag| tag.to_string()).collect();
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+
         fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
-            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_all_faults_by_tags() {
+        let fault_store = get_tagged_fault_store(&[
+            &["production", "auth-service"],
+            &["production"],
+            &["auth-service", "staging"],
+            &[],
+        ])
+        .await;
 
-        let mut app = test::init_service(
+        let app = test::init_service(
             App::new()
-                .route("/fault/{fault_name}", web::delete().to(delete_fault))
+                .service(web::scope(&api_prefix()).configure(api_routes))
                 .app_data(Data::new(fault_store)),
         )
         .await;
 
+        let cases = [
+            ("/v1/faults?tag=production", vec!["fault_0", "fault_1"]),
+            ("/v1/faults?tag=auth-service", vec!["fault_0", "fault_2"]),
+            (
+                "/v1/faults?tag=production&tag=auth-service",
+                vec!["fault_0"],
+            ),
+            ("/v1/faults?tag=production&tag=staging", vec![]),
+            (
+                "/v1/faults",
+                vec!["fault_0", "fault_1", "fault_2", "fault_3"],
+            ),
+        ];
+
+        for (uri, expected_names) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            let faults_page: FaultsPage =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            let mut names: Vec<&str> = faults_page.faults.iter().map(|f| f.name.as_str()).collect();
+            names.sort_unstable();
+            assert_eq!(names, expected_names, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_faults_by_tag() {
+        let fault_store = get_tagged_fault_store(&[
+            &["production", "auth-service"],
+            &["production"],
+            &["staging"],
+        ])
+        .await;
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
         let req = test::TestRequest::delete()
-            .uri(format!("/fault/{}", fault.name).as_str())
+            .uri("/v1/faults?tag=production")
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let faults = fault_store.get_all_faults().await.unwrap();
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].name, "fault_2");
+    }
+
+    /// LogBuffer collects the formatted log lines of a test subscriber.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl LogBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+        }
     }
 
     #[tokio::test]
-    async fn test_delete_all_faults() {
+    async fn test_set_log_level() {
+        use crate::proxy::connection_pool::ConnectionPool;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let logs = LogBuffer::default();
+        let (log_filter, log_level_handle) =
+            tracing_subscriber::reload::Layer::new(EnvFilter::new("info"));
+        let log_writer = logs.clone();
+        let subscriber = tracing_subscriber::Registry::default()
+            .with(log_filter)
+            .with(tracing_subscriber::fmt::layer().with_writer(move || log_writer.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(log_level_handle)),
+        )
+        .await;
+
+        // the proxy logs the pool checkout at the debug level
+        let pool = ConnectionPool::new(1);
+        pool.get("127.0.0.1:1").await;
+        assert!(!logs.contents().contains("taken from the pool"));
+
+        let req = test::TestRequest::post()
+            .uri("/v1/admin/log-level")
+            .set_json(serde_json::json!({"level": "verbose"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/admin/log-level")
+            .set_json(serde_json::json!({"level": "debug"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let log_level: LogLevel = test::read_body_json(resp).await;
+        assert_eq!(log_level.level, "debug");
+
+        pool.get("127.0.0.1:1").await;
+        assert!(logs.contents().contains("taken from the pool"));
+    }
+
+    #[tokio::test]
+    async fn test_experiment_start_stop() {
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(None::<ExperimentLog>)),
+        )
+        .await;
+        for uri in ["/v1/experiment/start", "/v1/experiment/stop"] {
+            let req = test::TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let experiment_log_path = std::env::temp_dir().join(format!(
+            "red-monkey-experiment-{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        let experiment_log_path = experiment_log_path.to_str().unwrap();
+        let experiment_log = ExperimentLog::open(experiment_log_path).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(Some(experiment_log))),
+        )
+        .await;
+
+        let mut rotated_log_paths = vec![];
+        for uri in ["/v1/experiment/start", "/v1/experiment/stop"] {
+            let req = test::TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let experiment: Experiment =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            assert!(std::path::Path::new(&experiment.rotated_log_path).exists());
+            rotated_log_paths.push(experiment.rotated_log_path);
+        }
+        assert_ne!(rotated_log_paths[0], rotated_log_paths[1]);
+        assert!(std::path::Path::new(experiment_log_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_replay_experiment() {
         let fault_store = crate::store::mem_store::MemStore::new_db();
-        let fault = get_mock_fault();
-        fault_store
-            .write()
+        for (name, fault_type) in [
+            ("get_delay", FaultVariants::Delay),
+            ("get_error", FaultVariants::Error),
+            ("get_drop", FaultVariants::DropConn),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.fault_type = fault_type;
+            fault.duration = Some(1);
+            fault.error_msg = Some("ERR replayed".to_string());
+            fault_store.store(name, &fault).await.unwrap();
+        }
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(
+                    ReplayEngine::new(fault_store)
+                        .with_experiment_log_path("testdata/experiment/experiment.jsonl"),
+                )),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/experiment/replay")
+            .set_json(serde_json::json!({
+                "path": "testdata/experiment/replay.jsonl",
+                "speed_factor": 1000.0,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let replayed_events: Vec<ReplayedEvent> =
+            serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        let fault_names: Vec<&str> = replayed_events
+            .iter()
+            .filter(|event| event.fired)
+            .map(|event| event.fault_name.as_str())
+            .collect();
+        assert_eq!(fault_names, vec!["get_delay", "get_error", "get_drop"]);
+
+        for body in [
+            serde_json::json!({"path": "testdata/experiment/replay.jsonl", "speed_factor": 0.0}),
+            serde_json::json!({"path": "testdata/experiment/unknown.jsonl"}),
+            serde_json::json!({"path": "/dev/zero"}),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/v1/experiment/replay")
+                .set_json(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fault_events() {
+        use crate::proxy::faulter::{ClientIdentity, Context, Faulter};
+        use actix_web::body::MessageBody;
+
+        let fault_event_sender = crate::events::fault_event_sender();
+        let faulter = Faulter::new(
+            crate::store::mem_store::MemStore::new_db(),
+            Arc::new(Metrics::new().unwrap()),
+        )
+        .with_fault_events(fault_event_sender.clone());
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_event_sender)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/fault-events")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let mut body = resp.into_body();
+
+        // the faults injected by the proxy once the stream is subscribed arrive in order
+        let mut faults = Vec::new();
+        for (name, fault_type) in [
+            ("get_delay", FaultVariants::Delay),
+            ("get_error", FaultVariants::Error),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.command = "GET".to_string();
+            fault.fault_type = fault_type;
+            fault.duration = Some(1);
+            fault.error_msg = Some("ERR injected".to_string());
+
+            let mut write_stream = tokio::io::sink();
+            let mut ctx = Context {
+                request_id: uuid::Uuid::new_v4(),
+                client_identity: ClientIdentity::Unknown,
+                client_write_stream: &mut write_stream,
+                client_socket: None,
+            };
+            faulter
+                .execute_fault(&mut ctx, Some(fault.clone()))
+                .await
+                .unwrap();
+            faults.push(fault);
+        }
+
+        for fault in faults {
+            let message =
+                futures::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx))
+                    .await
+                    .unwrap()
+                    .unwrap();
+            let message = std::str::from_utf8(&message).unwrap();
+            let event = message
+                .strip_prefix("data: ")
+                .and_then(|message| message.strip_suffix("\n\n"))
+                .unwrap();
+            let event: FaultEvent = serde_json::from_str(event).unwrap();
+            assert_eq!(event.fault_name, fault.name);
+            assert_eq!(event.command, "GET");
+            assert_eq!(event.action, format!("{:?}", fault.fault_type));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ws_fault_events() {
+        use actix_web::HttpServer;
+        use tokio_tungstenite::tungstenite;
+
+        let fault_event_sender = crate::events::fault_event_sender();
+        let server_fault_event_sender = fault_event_sender.clone();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(move || {
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(server_fault_event_sender.clone()))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let server_handle = server.handle();
+        tokio::spawn(server);
+
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/v1/ws/fault-events", server_addr))
+                .await
+                .unwrap();
+        assert_eq!(fault_event_sender.receiver_count(), 1);
+
+        // the messages of the client are ignored
+        ws.send(tungstenite::Message::text("hello")).await.unwrap();
+
+        let event = FaultEvent {
+            ts: Utc::now(),
+            fault_name: "get_delay".to_string(),
+            command: "GET".to_string(),
+            action: "Delay".to_string(),
+        };
+        fault_event_sender.send(event.clone()).unwrap();
+        let message = ws.next().await.unwrap().unwrap();
+        let received: FaultEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(received, event);
+
+        // the subscriber is dropped once the client disconnects
+        ws.close(None).await.unwrap();
+        drop(ws);
+        for _ in 0..100 {
+            if fault_event_sender.receiver_count() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(fault_event_sender.receiver_count(), 0);
+
+        server_handle.stop(true).await;
+    }
+
+    #[tokio::test]
+    async fn test_store_multiple_faults_per_command() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log()))
+                .app_data(Data::new(FaultSelectionStrategy::Weighted)),
+        )
+        .await;
+
+        for (name, fault_type, probability) in [
+            ("GET delay", FaultVariants::Delay, 0.3),
+            ("GET error", FaultVariants::Error, 0.1),
+        ] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = name.to_string();
+            fault.fault_type = fault_type;
+            fault.probability = Some(probability);
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+        }
+        assert_eq!(fault_store.get_all_faults().await.unwrap().len(), 2);
+
+        for probability in [0.0, 1.5, -0.1] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = "invalid probability".to_string();
+            fault.probability = Some(probability);
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_fault_name_conflict() {
+        for strategy in [
+            FaultSelectionStrategy::Priority,
+            FaultSelectionStrategy::Weighted,
+        ] {
+            let fault_store = crate::store::mem_store::MemStore::new_db();
+            let app = test::init_service(
+                App::new()
+                    .service(web::scope(&api_prefix()).configure(api_routes))
+                    .app_data(Data::new(fault_store.clone()))
+                    .app_data(Data::new(NopAuditLogger::new_log()))
+                    .app_data(Data::new(strategy)),
+            )
+            .await;
+
+            let fault = Fault {
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            // the same name on another command would replace the stored fault
+            let same_name_fault = Fault {
+                duration: Some(10),
+                ..Fault::fixture("get_custom_err", FaultVariants::Delay, "SET")
+            };
+            let req = test::TestRequest::post()
+                .uri("/v1/fault")
+                .set_json(&same_name_fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT, "{:?}", strategy);
+
+            let req = test::TestRequest::post()
+                .uri("/v1/faults")
+                .set_json(vec![&same_name_fault])
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+            let results: Vec<BatchItemResult> =
+                serde_json::from_slice(&test::read_body(resp).await).unwrap();
+            assert_eq!(results[0].status_code, 409, "{:?}", strategy);
+
+            let stored_fault = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+            assert_eq!(stored_fault.command, "GET");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_proxy_port_scoped_faults() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
+        )
+        .await;
+
+        // the faults of the same command don't conflict on different proxy ports
+        for proxy_port in [6351, 6352] {
+            let mut fault = Fault {
+                description: Some("GET custom error".to_string()),
+                error_msg: Some("KEY not found".to_string()),
+                ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+            };
+            fault.name = format!("GET error {}", proxy_port);
+            let req = test::TestRequest::post()
+                .uri(&format!("/v1/fault?proxy_port={}", proxy_port))
+                .set_json(&fault)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+        }
+        let fault = fault_store
+            .get_by_fault_name("GET error 6351")
             .await
-            .store(&fault.name, &fault)
             .unwrap();
+        assert_eq!(fault.proxy_port, Some(6351));
+
+        let mut fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.name = "GET error 6351 again".to_string();
+        let req = test::TestRequest::post()
+            .uri("/v1/fault?proxy_port=6351")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        fault.proxy_port = Some(6352);
+        let req = test::TestRequest::post()
+            .uri("/v1/fault?proxy_port=6351")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults?proxy_port=6351")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let faults_page: FaultsPage = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(faults_page.total, 1);
+        assert_eq!(faults_page.faults[0].name, "GET error 6351");
 
-        let mut app = test::init_service(
+        let req = test::TestRequest::delete()
+            .uri("/v1/faults?proxy_port=6351")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let faults = fault_store.get_all_faults().await.unwrap();
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].name, "GET error 6352");
+    }
+
+    #[actix_web::test]
+    async fn test_fault_history_rollback() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
             App::new()
-                .route("/faults", web::delete().to(delete_all_faults))
-                .app_data(Data::new(fault_store)),
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(NopAuditLogger::new_log())),
         )
         .await;
 
-        let req = test::TestRequest::delete().uri("/faults").to_request();
-        let resp = test::call_service(&mut app, req).await;
-        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let fault = Fault {
+            description: Some("GET custom error".to_string()),
+            error_msg: Some("KEY not found".to_string()),
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        let req = test::TestRequest::post()
+            .uri("/v1/fault")
+            .set_json(&fault)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let original = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+
+        for error_msg in ["ERR first change", "ERR second change"] {
+            let req = test::TestRequest::patch()
+                .uri("/v1/fault/get_custom_err")
+                .set_json(serde_json::json!({ "error_msg": error_msg, "one_shot": true }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/v1/fault/get_custom_err/history")
+            .to_request();
+        let history: Vec<Fault> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].error_msg.as_deref(), Some("ERR first change"));
+        assert_eq!(history[1], original);
+
+        // the rollback restores the exact previous configuration
+        let req = test::TestRequest::post()
+            .uri("/v1/fault/get_custom_err/rollback/1")
+            .to_request();
+        let restored: Fault = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(restored, original);
+        assert_eq!(
+            fault_store.get_by_fault_name(&fault.name).await.unwrap(),
+            original
+        );
+        let history = fault_store.get_fault_history(&fault.name).await.unwrap();
+        assert_eq!(history[0].error_msg.as_deref(), Some("ERR second change"));
+
+        for (uri, status_code) in [
+            (
+                "/v1/fault/get_custom_err/rollback/10",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                "/v1/fault/get_custom_err/rollback/first",
+                StatusCode::BAD_REQUEST,
+            ),
+            ("/v1/fault/unknown/rollback/0", StatusCode::NOT_FOUND),
+        ] {
+            let req = test::TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status_code, "{}", uri);
+        }
+        let req = test::TestRequest::get()
+            .uri("/v1/fault/unknown/history")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    fn get_mock_fault() -> Fault {
-        Fault {
-            name: "get_custom_err".to_string(),
+    #[actix_web::test]
+    async fn test_get_fault_diff() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(&api_prefix()).configure(api_routes))
+                .app_data(Data::new(fault_store.clone())),
+        )
+        .await;
+
+        let mut fault = Fault {
             description: Some("GET custom error".to_string()),
-            fault_type: FaultVariants::Error,
             error_msg: Some("KEY not found".to_string()),
-            duration: None,
-            command: "GET".to_string(),
-            last_modified: None,
+            ..Fault::fixture("get_custom_err", FaultVariants::Error, "GET")
+        };
+        fault.fault_type = FaultVariants::Delay;
+        fault.duration = Some(100);
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        fault.duration = Some(200);
+        fault.error_msg = None;
+        fault.key_pattern = Some("user:*".to_string());
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/v1/fault/get_custom_err/diff?version=0")
+            .to_request();
+        let diff: FaultDiff = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            diff.changed,
+            vec![FieldDiff {
+                field: "duration".to_string(),
+                old: Some(serde_json::json!(100)),
+                new: Some(serde_json::json!(200)),
+            }]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![FieldDiff {
+                field: "error_msg".to_string(),
+                old: Some(serde_json::json!("KEY not found")),
+                new: None,
+            }]
+        );
+        assert_eq!(
+            diff.added,
+            vec![FieldDiff {
+                field: "key_pattern".to_string(),
+                old: None,
+                new: Some(serde_json::json!("user:*")),
+            }]
+        );
+
+        for (uri, status_code) in [
+            (
+                "/v1/fault/get_custom_err/diff?version=1",
+                StatusCode::NOT_FOUND,
+            ),
+            ("/v1/fault/get_custom_err/diff", StatusCode::BAD_REQUEST),
+            ("/v1/fault/unknown/diff?version=0", StatusCode::NOT_FOUND),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status_code, "{}", uri);
         }
     }
 }
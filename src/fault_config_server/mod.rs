@@ -1,2 +1,6 @@
+pub mod auth;
 pub mod handler;
+pub mod openapi;
+pub mod request_id;
 pub mod server;
+pub mod version;
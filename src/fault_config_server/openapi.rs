@@ -0,0 +1,190 @@
+use crate::events::FaultEvent;
+use crate::experiment::replay::ReplayedEvent;
+use crate::fault_config_server::auth::API_KEY_HEADER;
+use crate::fault_config_server::handler::*;
+use crate::fault_config_server::version::api_prefix;
+use crate::store::fault_store::{
+    ArgMatcher, Fault, FaultDiff, FaultPlaybook, FaultStats, FaultVariants, FieldDiff, MatchType,
+    PlaybookStep, SubFault,
+};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// ApiDoc is the OpenAPI specification of the fault config server, generated from the handlers.
+/// It is served at GET /openapi.json and rendered by the Swagger UI at GET /docs. The paths of
+/// the API are prefixed with the API version by [VersionAddon].
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "red-monkey fault config server"),
+    paths(
+        store_fault,
+        preview_fault,
+        store_faults,
+        import_faults,
+        export_faults,
+        fault_events,
+        ws_fault_events,
+        get_fault,
+        search_faults,
+        get_fault_stats,
+        get_all_fault_stats,
+        get_report,
+        get_fault_history,
+        get_fault_diff,
+        rollback_fault,
+        patch_fault,
+        replace_fault,
+        get_all_faults,
+        delete_fault,
+        delete_all_faults,
+        store_playbook,
+        delete_playbook,
+        get_fault_group,
+        enable_fault_group,
+        disable_fault_group,
+        health,
+        ready,
+        get_metrics,
+        set_log_level,
+        start_experiment,
+        stop_experiment,
+        replay_experiment,
+        get_version,
+    ),
+    components(schemas(
+        Fault,
+        FaultVariants,
+        SubFault,
+        ArgMatcher,
+        MatchType,
+        PatchFault,
+        FaultPlaybook,
+        PlaybookStep,
+        BatchItemResult,
+        ImportForm,
+        FaultEvent,
+        FaultPreview,
+        FaultHitStats,
+        FaultStats,
+        Report,
+        ReportHits,
+        ReportFormat,
+        FaultDiff,
+        FieldDiff,
+        FaultsPage,
+        ProbeStatus,
+        LogLevel,
+        Experiment,
+        Replay,
+        ReplayedEvent,
+        ServerErrorResponse,
+        ApiVersion,
+    )),
+    modifiers(&SecurityAddon, &VersionAddon),
+    security(("api_key" = []), ("jwt" = [])),
+)]
+pub struct ApiDoc;
+
+/// SecurityAddon documents the API key and the JWT bearer token authentication schemes. Either one
+/// is only enforced when it is configured.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+        components.add_security_scheme(
+            "jwt",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// VersionAddon prefixes the paths of the API with the API version, e.g. `/v1/faults`, leaving
+/// out the probes that are not versioned.
+struct VersionAddon;
+
+impl Modify for VersionAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let paths = std::mem::take(&mut openapi.paths.paths);
+        openapi.paths.paths = paths
+            .into_iter()
+            .map(|(path, item)| match path.as_str() {
+                "/health" | "/ready" => (path, item),
+                _ => (format!("{}{}", api_prefix(), path), item),
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_doc() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        for (path, method) in [
+            ("/v1/fault", "post"),
+            ("/v1/faults", "post"),
+            ("/v1/faults", "get"),
+            ("/v1/faults", "delete"),
+            ("/v1/faults/import", "post"),
+            ("/v1/faults/export", "get"),
+            ("/v1/fault-events", "get"),
+            ("/v1/ws/fault-events", "get"),
+            ("/v1/fault/{fault_name}", "get"),
+            ("/v1/fault/{fault_name}", "put"),
+            ("/v1/fault/{fault_name}", "patch"),
+            ("/v1/fault/{fault_name}", "delete"),
+            ("/v1/fault/{fault_name}/stats", "get"),
+            ("/v1/faults/stats", "get"),
+            ("/v1/report", "get"),
+            ("/v1/fault/{fault_name}/history", "get"),
+            ("/v1/fault/{fault_name}/diff", "get"),
+            ("/v1/fault/{fault_name}/rollback/{version}", "post"),
+            ("/v1/fault/search", "get"),
+            ("/v1/version", "get"),
+            ("/health", "get"),
+        ] {
+            assert!(
+                spec["paths"][path][method].is_object(),
+                "{} {} is not documented",
+                method,
+                path
+            );
+        }
+
+        let params: Vec<&str> = spec["paths"]["/v1/faults"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                "page",
+                "per_page",
+                "command",
+                "fault_type",
+                "proxy_port",
+                "tag"
+            ]
+        );
+
+        let schemas = &spec["components"]["schemas"];
+        assert!(schemas["Fault"]["properties"]["fault_type"].is_object());
+        assert!(schemas["ServerErrorResponse"]["properties"]["message"].is_object());
+        assert!(spec["components"]["securitySchemes"]["api_key"].is_object());
+    }
+}
@@ -0,0 +1,154 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ready, Ready};
+use std::convert::Infallible;
+use std::fmt;
+use tracing::{field, Span};
+use tracing_actix_web::{root_span, DefaultRootSpanBuilder, RootSpanBuilder};
+use uuid::Uuid;
+
+/// The header that carries the identifier of a fault config server request, such that the logs
+/// of the client and of the server can be correlated
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// RequestId is the identifier of a fault config server request, i.e. the `X-Request-Id` header
+/// of the request, or a generated UUID when the header is not set.
+///
+/// The [propagate_request_id] middleware stores the request id in the request extensions.
+/// Handlers extract it like any other argument, falling back to a generated UUID when the
+/// middleware is not in use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn generate() -> Self {
+        RequestId(Uuid::new_v4().to_string())
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::generate)))
+    }
+}
+
+/// propagate_request_id is the middleware that stores the [RequestId] of the request in the
+/// request extensions and echoes it in the `X-Request-Id` header of the response. An empty or
+/// non-ASCII `X-Request-Id` header is replaced by a generated UUID.
+pub async fn propagate_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| RequestId(value.to_string()))
+        .unwrap_or_else(RequestId::generate);
+    req.extensions_mut().insert(request_id.clone());
+
+    let mut res = next.call(req).await?;
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        res.headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+
+    Ok(res)
+}
+
+/// RequestIdRootSpanBuilder builds the root span of the request logging like
+/// [DefaultRootSpanBuilder], with the [RequestId] of the request as the `request_id` field, such
+/// that all the spans of the request carry the id of the `X-Request-Id` header.
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let span = root_span!(request);
+        if let Some(request_id) = request.extensions().get::<RequestId>() {
+            span.record("request_id", field::display(request_id));
+        }
+        span
+    }
+
+    fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn request_id(request_id: RequestId) -> HttpResponse {
+        HttpResponse::Ok().body(request_id.0)
+    }
+
+    #[tokio::test]
+    async fn test_propagate_request_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(propagate_request_id))
+                .route("/faults", web::get().to(request_id)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/faults")
+            .insert_header((REQUEST_ID_HEADER, "client-request-42"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "client-request-42"
+        );
+        assert_eq!(test::read_body(resp).await, "client-request-42");
+
+        for header in [None, Some("")] {
+            let mut req = test::TestRequest::get().uri("/faults");
+            if let Some(header) = header {
+                req = req.insert_header((REQUEST_ID_HEADER, header));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            let generated_id = resp
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert!(Uuid::parse_str(&generated_id).is_ok());
+            assert_eq!(test::read_body(resp).await, generated_id);
+        }
+
+        // the unmatched routes get the header too
+        let req = test::TestRequest::get()
+            .uri("/unknown")
+            .insert_header((REQUEST_ID_HEADER, "client-request-43"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "client-request-43"
+        );
+    }
+}
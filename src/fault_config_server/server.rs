@@ -1,25 +1,156 @@
+use crate::audit::AuditLog;
+use crate::events::FaultEventSender;
+use crate::experiment::replay::ReplayEngine;
+use crate::experiment::ExperimentLog;
+use crate::fault_config_server::auth::{authenticate, AuthConfig};
 use crate::fault_config_server::handler::*;
-use crate::store::fault_store::DB;
+use crate::fault_config_server::openapi::ApiDoc;
+use crate::fault_config_server::request_id::{propagate_request_id, RequestIdRootSpanBuilder};
+use crate::fault_config_server::version::{api_prefix, route_version};
+use crate::metrics::Metrics;
+use crate::reload::LogLevelHandle;
+use crate::store::fault_store::{FaultSelectionStrategy, PlaybookDB, DB};
+use actix_cors::Cors;
+use actix_web::http::header::{self, HeaderName};
+use actix_web::middleware::{from_fn, Compress, Condition};
 use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
-use std::net::TcpListener;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
 use tracing::info;
 use tracing_actix_web::TracingLogger;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
-pub async fn run(port: u16, fault_store: DB) -> Result<(), anyhow::Error> {
-    let server_listener_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+/// Builds the CORS middleware that lets the browser-based tooling served from the given origins
+/// manage the faults. The authentication, conditional update and request id headers are allowed,
+/// and the ETag and the request id of the responses are exposed to the tooling.
+fn cors(allowed_origins: &[String]) -> Cors {
+    allowed_origins.iter().fold(
+        Cors::default()
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+            .allowed_headers(vec![
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                header::IF_MATCH,
+                HeaderName::from_static("x-api-key"),
+                HeaderName::from_static("x-request-id"),
+            ])
+            .expose_headers(vec![header::ETAG, HeaderName::from_static("x-request-id")]),
+        |cors, origin| cors.allowed_origin(origin),
+    )
+}
+
+/// Registers the routes of the fault config API, which are served under the API version prefix,
+/// see [api_prefix].
+pub(crate) fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/version", web::get().to(get_version))
+        .route("/fault", web::post().to(store_fault))
+        .route("/fault/validate", web::post().to(preview_fault))
+        // registered before GET /fault/{fault_name}, which would match it otherwise
+        .route("/fault/search", web::get().to(search_faults))
+        .route("/fault/{fault_name}", web::get().to(get_fault))
+        .route("/fault/{fault_name}", web::put().to(replace_fault))
+        .route("/fault/{fault_name}", web::patch().to(patch_fault))
+        .route("/fault/{fault_name}/stats", web::get().to(get_fault_stats))
+        .route("/faults/stats", web::get().to(get_all_fault_stats))
+        .route("/report", web::get().to(get_report))
+        .route(
+            "/fault/{fault_name}/history",
+            web::get().to(get_fault_history),
+        )
+        .route("/fault/{fault_name}/diff", web::get().to(get_fault_diff))
+        .route(
+            "/fault/{fault_name}/rollback/{version}",
+            web::post().to(rollback_fault),
+        )
+        .route("/faults", web::post().to(store_faults))
+        .route("/faults/import", web::post().to(import_faults))
+        .route("/faults/export", web::get().to(export_faults))
+        .route("/fault-events", web::get().to(fault_events))
+        .route("/ws/fault-events", web::get().to(ws_fault_events))
+        .route("/faults", web::get().to(get_all_faults))
+        .route("/fault/{fault_name}", web::delete().to(delete_fault))
+        .route("/faults", web::delete().to(delete_all_faults))
+        .route("/playbook", web::post().to(store_playbook))
+        .route(
+            "/playbook/{playbook_name}",
+            web::delete().to(delete_playbook),
+        )
+        .route("/fault-group/{group}", web::get().to(get_fault_group))
+        .route(
+            "/fault-group/{group}/enable",
+            web::post().to(enable_fault_group),
+        )
+        .route(
+            "/fault-group/{group}/disable",
+            web::post().to(disable_fault_group),
+        )
+        .route("/metrics", web::get().to(get_metrics))
+        .route("/admin/log-level", web::post().to(set_log_level))
+        .route("/experiment/start", web::post().to(start_experiment))
+        .route("/experiment/stop", web::post().to(stop_experiment))
+        .route("/experiment/replay", web::post().to(replay_experiment));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    server_listener_addr: SocketAddr,
+    fault_store: DB,
+    playbook_store: PlaybookDB,
+    audit_log: AuditLog,
+    selection_strategy: FaultSelectionStrategy,
+    experiment_log: Option<ExperimentLog>,
+    replay_engine: ReplayEngine,
+    metrics: Arc<Metrics>,
+    cors_allowed_origins: Vec<String>,
+    auth_config: AuthConfig,
+    log_level_handle: LogLevelHandle,
+    import_limit: ImportLimit,
+    fault_event_sender: FaultEventSender,
+) -> Result<(), anyhow::Error> {
     let listener = TcpListener::bind(server_listener_addr)?;
 
+    let api_doc = ApiDoc::openapi();
+
     let server = HttpServer::new(move || {
         App::new()
-            .wrap(TracingLogger::default())
-            .route("/fault", web::post().to(store_fault))
-            .route("/fault/{fault_name}", web::get().to(get_fault))
-            .route("/faults", web::get().to(get_all_faults))
-            .route("/fault/{fault_name}", web::delete().to(delete_fault))
-            .route("/faults", web::delete().to(delete_all_faults))
+            // The unversioned API routes are mapped to the routes of the API version before the
+            // request is routed
+            .wrap(from_fn(route_version))
+            // CORS is only enforced when the allowed origins are configured
+            .wrap(Condition::new(
+                !cors_allowed_origins.is_empty(),
+                cors(&cors_allowed_origins),
+            ))
+            // Every response, including the probes and the redirects, echoes the request id
+            .wrap(from_fn(propagate_request_id))
+            // The probes are polled frequently, hence they are kept out of the request logging.
+            // They are not authenticated either, such that the orchestrator can reach them.
+            .route("/health", web::get().to(health))
+            .route("/ready", web::get().to(ready))
+            // The API docs are public, the fault endpoints they describe are still authenticated
+            .service(web::redirect("/docs", "/docs/"))
+            .service(SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", api_doc.clone()))
+            .service(
+                web::scope(&api_prefix())
+                    // The responses are compressed as negotiated by the Accept-Encoding header
+                    .wrap(Compress::default())
+                    .wrap(from_fn(authenticate))
+                    .wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+                    .configure(api_routes),
+            )
             .app_data(Data::new(fault_store.clone()))
+            .app_data(Data::new(playbook_store.clone()))
+            .app_data(Data::new(audit_log.clone()))
+            .app_data(Data::new(selection_strategy))
+            .app_data(Data::new(experiment_log.clone()))
+            .app_data(Data::new(replay_engine.clone()))
+            .app_data(Data::new(metrics.clone()))
+            .app_data(Data::new(auth_config.clone()))
+            .app_data(Data::new(log_level_handle.clone()))
+            .app_data(Data::new(import_limit))
+            .app_data(Data::new(fault_event_sender.clone()))
     })
     .shutdown_timeout(2)
     .listen(listener)?
@@ -34,13 +165,184 @@ pub async fn run(port: u16, fault_store: DB) -> Result<(), anyhow::Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fault_config_server::handler::store_fault;
+    use actix_web::{http::StatusCode, test};
 
     #[tokio::test(flavor = "multi_thread")]
     async fn test_server_initialization() {
         let fault_store = crate::store::mem_store::MemStore::new_db();
+        let replay_engine = ReplayEngine::new(fault_store.clone());
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let (_, log_level_handle) =
+            tracing_subscriber::reload::Layer::new(tracing_subscriber::EnvFilter::new("info"));
 
         tokio::spawn(async move {
-            run(9999, fault_store).await.unwrap();
+            run(
+                "0.0.0.0:9999".parse().unwrap(),
+                fault_store,
+                crate::store::mem_store::MemPlaybookStore::new_db(),
+                crate::audit::NopAuditLogger::new_log(),
+                FaultSelectionStrategy::Priority,
+                None,
+                replay_engine,
+                metrics,
+                vec![],
+                AuthConfig::default(),
+                log_level_handle,
+                ImportLimit(DEFAULT_IMPORT_MAX_BYTES),
+                crate::events::fault_event_sender(),
+            )
+            .await
+            .unwrap();
         });
     }
+
+    #[tokio::test]
+    async fn test_compression() {
+        let fault_store = crate::store::mem_store::MemStore::new_db();
+        let app = test::init_service(
+            App::new()
+                .route("/health", web::get().to(health))
+                .service(
+                    web::scope(&api_prefix())
+                        .wrap(Compress::default())
+                        .configure(api_routes),
+                )
+                .app_data(Data::new(fault_store.clone()))
+                .app_data(Data::new(Arc::new(Metrics::new().unwrap()))),
+        )
+        .await;
+
+        let fault: crate::store::fault_store::Fault = serde_json::from_value(serde_json::json!({
+            "name": "get_err",
+            "fault_type": "Error",
+            "error_msg": "KEY not found",
+            "command": "GET"
+        }))
+        .unwrap();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/v1/faults")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+
+        let mut body = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(&test::read_body(resp).await[..]),
+            &mut body,
+        )
+        .unwrap();
+        let page: FaultsPage = serde_json::from_str(&body).unwrap();
+        assert_eq!(page.faults, vec![fault]);
+
+        // the probes and the metrics are small and frequently polled, hence never compressed
+        for uri in ["/health", "/v1/metrics"] {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .is_none_or(|encoding| encoding == "identity"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let allowed_origins = vec![
+            "http://localhost:3000".to_string(),
+            "https://chaos.example.com".to_string(),
+        ];
+        let app = test::init_service(
+            App::new()
+                .wrap(cors(&allowed_origins))
+                .route("/fault", web::post().to(store_fault)),
+        )
+        .await;
+
+        for origin in &allowed_origins {
+            let req = test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/fault")
+                .insert_header((header::ORIGIN, origin.as_str()))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                    .unwrap(),
+                origin.as_str()
+            );
+        }
+
+        // the authenticated, conditional and traced requests are allowed
+        let req = test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/fault")
+            .insert_header((header::ORIGIN, allowed_origins[0].as_str()))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+            .insert_header((
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "content-type, x-api-key, authorization, if-match, x-request-id",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let allowed_headers = resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_ascii_lowercase();
+        for allowed_header in ["x-api-key", "authorization", "if-match", "x-request-id"] {
+            assert!(
+                allowed_headers.contains(allowed_header),
+                "{}",
+                allowed_header
+            );
+        }
+
+        // the ETag and the request id of the responses are readable by the tooling
+        let req = test::TestRequest::post()
+            .uri("/fault")
+            .insert_header((header::ORIGIN, allowed_origins[0].as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let exposed_headers = resp
+            .headers()
+            .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_ascii_lowercase();
+        assert!(exposed_headers.contains("etag"));
+        assert!(exposed_headers.contains("x-request-id"));
+
+        let req = test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/fault")
+            .insert_header((header::ORIGIN, "http://evil.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
 }
@@ -0,0 +1,165 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Uri};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use tracing::debug;
+
+/// The version of the fault config API, which prefixes the API routes, e.g. `/v1/faults`
+pub const API_VERSION: &str = "1";
+
+/// The header that selects the API version of a request to an unversioned route, e.g.
+/// `Accept-Version: 1` for `/faults`
+pub const ACCEPT_VERSION_HEADER: &str = "Accept-Version";
+
+/// The routes that are not versioned, as they are not part of the fault config API
+const UNVERSIONED_ROUTES: [&str; 4] = ["/health", "/ready", "/openapi.json", "/docs"];
+
+/// Returns the path prefix of the routes of the API version, e.g. `/v1`.
+pub fn api_prefix() -> String {
+    format!("/v{}", API_VERSION)
+}
+
+/// Returns whether the path is a route of the API that lacks the version prefix.
+fn is_unversioned_api_path(path: &str) -> bool {
+    let is_route_of = |route: &str| {
+        path.strip_prefix(route)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+
+    !is_route_of(&api_prefix()) && !UNVERSIONED_ROUTES.iter().any(|route| is_route_of(route))
+}
+
+/// route_version is the middleware that maps the requests to the unversioned API routes, e.g.
+/// `/faults`, to the routes of the API version, e.g. `/v1/faults`.
+///
+/// 1. With the `Accept-Version` header of the API version, the request is served by the versioned
+///    route as is.
+/// 2. With the `Accept-Version` header of any other version, HTTP Bad Request 400 is returned.
+/// 3. Without the `Accept-Version` header, the client is redirected to the versioned route with
+///    HTTP Permanent Redirect 308, which keeps the method and the body of the request.
+pub async fn route_version(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if !is_unversioned_api_path(req.path()) {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    let versioned_uri = match req.uri().query() {
+        Some(query) => format!("{}{}?{}", api_prefix(), req.path(), query),
+        None => format!("{}{}", api_prefix(), req.path()),
+    };
+
+    let accept_version = req.headers().get(ACCEPT_VERSION_HEADER).cloned();
+    match accept_version {
+        Some(version) if version == API_VERSION => {
+            let uri: Uri = versioned_uri
+                .parse()
+                .map_err(actix_web::error::ErrorBadRequest)?;
+            req.match_info_mut().get_mut().update(&uri);
+            req.head_mut().uri = uri;
+
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        Some(version) => {
+            debug!("Unsupported API version {:?} of {}", version, req.path());
+            let response = HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("unsupported API version, expected {}", API_VERSION)
+            }));
+            Ok(req.into_response(response).map_into_right_body())
+        }
+        None => {
+            let response = HttpResponse::PermanentRedirect()
+                .insert_header((header::LOCATION, versioned_uri))
+                .finish();
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    async fn path(req: actix_web::HttpRequest) -> HttpResponse {
+        HttpResponse::Ok().body(req.uri().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_route_version() {
+        for path in [
+            "/faults",
+            "/fault/get_err",
+            "/v2/faults",
+            "/v10/faults",
+            "/healthz",
+        ] {
+            assert!(is_unversioned_api_path(path), "{}", path);
+        }
+        for path in [
+            "/v1/faults",
+            "/v1",
+            "/health",
+            "/ready",
+            "/docs/",
+            "/openapi.json",
+        ] {
+            assert!(!is_unversioned_api_path(path), "{}", path);
+        }
+
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(route_version))
+                .route("/health", web::get().to(path))
+                .service(web::scope(&api_prefix()).route("/faults", web::post().to(path))),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/faults?atomic=true")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/v1/faults?atomic=true"
+        );
+
+        let req = test::TestRequest::post()
+            .uri("/faults?atomic=true")
+            .insert_header((ACCEPT_VERSION_HEADER, "1"))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "/v1/faults?atomic=true");
+
+        let req = test::TestRequest::post()
+            .uri("/faults")
+            .insert_header((ACCEPT_VERSION_HEADER, "2"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        for uri in ["/v1/faults", "/health"] {
+            let method = if uri == "/health" {
+                actix_web::http::Method::GET
+            } else {
+                actix_web::http::Method::POST
+            };
+            let req = test::TestRequest::default()
+                .method(method)
+                .uri(uri)
+                .to_request();
+            let body = test::call_and_read_body(&app, req).await;
+            assert_eq!(body, uri);
+        }
+    }
+}
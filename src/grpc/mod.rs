@@ -0,0 +1,747 @@
+use crate::audit::{AuditAction, AuditLog};
+use crate::fault_config_server::auth::{Actor, AuthConfig};
+use crate::fault_config_server::handler::{
+    audit, check_conflicts, validate_fault, ServerErrorResponse,
+};
+use crate::store::fault_store::{
+    default_enabled, default_priority, ArgMatcher, Fault, FaultSelectionStrategy, FaultVariants,
+    MatchType, SubFault, DB,
+};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use chrono::{DateTime, Utc};
+use std::convert::{TryFrom, TryInto};
+use std::net::{IpAddr, SocketAddr};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+pub mod proto {
+    tonic::include_proto!("redmonkey.v1");
+}
+
+use proto::fault_service_server::{FaultService, FaultServiceServer};
+
+/// FaultGrpcService implements the gRPC fault configuration API, an alternative to the REST API
+/// of the fault config server for the gRPC-first clients. The RPCs of `fault_service.proto` mirror
+/// the REST endpoints and work on the same fault store.
+///
+/// The requests are authenticated like the REST requests, with the `x-api-key` or the
+/// `authorization` metadata. StoreFault and DeleteFault require the
+/// [crate::fault_config_server::auth::Role::Admin] role.
+#[derive(Clone)]
+pub struct FaultGrpcService {
+    fault_store: DB,
+    audit_log: AuditLog,
+    selection_strategy: FaultSelectionStrategy,
+    auth_config: AuthConfig,
+}
+
+impl FaultGrpcService {
+    pub fn new(
+        fault_store: DB,
+        audit_log: AuditLog,
+        selection_strategy: FaultSelectionStrategy,
+        auth_config: AuthConfig,
+    ) -> Self {
+        FaultGrpcService {
+            fault_store,
+            audit_log,
+            selection_strategy,
+            auth_config,
+        }
+    }
+
+    /// Returns the actor of the request if its credentials allow the REST method the RPC mirrors.
+    fn authorize<T>(&self, request: &Request<T>, method: Method) -> Result<Actor, Status> {
+        let (actor, role) = self
+            .auth_config
+            .authorize(&http_headers(request.metadata()))
+            .ok_or_else(|| Status::unauthenticated("unauthorized"))?;
+        if !role.allows(&method) {
+            debug!("Forbidden gRPC request with the {:?} role", role);
+            return Err(Status::permission_denied("forbidden"));
+        }
+
+        Ok(actor)
+    }
+}
+
+#[tonic::async_trait]
+impl FaultService for FaultGrpcService {
+    #[tracing::instrument(name = "gRPC StoreFault", skip(self, request))]
+    async fn store_fault(
+        &self,
+        request: Request<proto::StoreFaultRequest>,
+    ) -> Result<Response<proto::StoreFaultResponse>, Status> {
+        let actor = self.authorize(&request, Method::POST)?;
+        let mut fault: Fault = request
+            .into_inner()
+            .fault
+            .ok_or_else(|| Status::invalid_argument("The fault is missing"))?
+            .try_into()?;
+        info!("Create fault: fault name: {:?}", fault.name);
+        fault.last_modified = Some(Utc::now());
+
+        validate_fault(&fault).map_err(status)?;
+        let faults = self.fault_store.get_all_faults().await.map_err(|err| {
+            error!("Error fetching all faults: {}", err);
+            Status::internal(err.message)
+        })?;
+        check_conflicts(&fault, &faults, self.selection_strategy).map_err(status)?;
+
+        self.fault_store
+            .store(&fault.name, &fault)
+            .await
+            .map_err(|err| {
+                error!("Error storing fault {} in the store: {}", fault.name, err);
+                Status::internal(err.message)
+            })?;
+        info!("Fault {} created in the store", fault.name);
+        audit(&self.audit_log, AuditAction::Created, &actor, &fault);
+
+        Ok(Response::new(proto::StoreFaultResponse {}))
+    }
+
+    #[tracing::instrument(name = "gRPC GetFault", skip(self, request))]
+    async fn get_fault(
+        &self,
+        request: Request<proto::GetFaultRequest>,
+    ) -> Result<Response<proto::Fault>, Status> {
+        self.authorize(&request, Method::GET)?;
+        let fault_name = request.into_inner().name;
+        info!("Fetch fault by name: {:?}", fault_name);
+
+        match self.fault_store.get_by_fault_name(&fault_name).await {
+            Ok(fault) => Ok(Response::new(fault.into())),
+            Err(err) => {
+                error!("Error fetching fault {}: {}", fault_name, err);
+                Err(Status::not_found(err.message))
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "gRPC DeleteFault", skip(self, request))]
+    async fn delete_fault(
+        &self,
+        request: Request<proto::DeleteFaultRequest>,
+    ) -> Result<Response<proto::DeleteFaultResponse>, Status> {
+        let actor = self.authorize(&request, Method::DELETE)?;
+        let fault_name = request.into_inner().name;
+        info!("Delete fault: {}", fault_name);
+
+        // the deleted fault is recorded in the audit log, unless it doesn't exist
+        let fault = self.fault_store.get_by_fault_name(&fault_name).await.ok();
+        self.fault_store
+            .delete_fault(&fault_name)
+            .await
+            .map_err(|err| {
+                error!("Error deleting fault {}: {}", fault_name, err);
+                Status::internal(err.message)
+            })?;
+        if let Some(fault) = fault {
+            audit(&self.audit_log, AuditAction::Deleted, &actor, &fault);
+        }
+
+        Ok(Response::new(proto::DeleteFaultResponse {}))
+    }
+
+    #[tracing::instrument(name = "gRPC ListFaults", skip(self, request))]
+    async fn list_faults(
+        &self,
+        request: Request<proto::ListFaultsRequest>,
+    ) -> Result<Response<proto::ListFaultsResponse>, Status> {
+        self.authorize(&request, Method::GET)?;
+        let command = request.into_inner().command;
+        info!("Fetch all faults");
+
+        let mut faults = self.fault_store.get_all_faults().await.map_err(|err| {
+            error!("Error fetching all faults: {}", err);
+            Status::internal(err.message)
+        })?;
+        if let Some(command) = command {
+            faults.retain(|fault| fault.command.eq_ignore_ascii_case(&command));
+        }
+        faults.sort_by_key(|fault| std::cmp::Reverse(fault.last_modified));
+
+        Ok(Response::new(proto::ListFaultsResponse {
+            faults: faults.into_iter().map(proto::Fault::from).collect(),
+        }))
+    }
+}
+
+/// Runs the gRPC fault configuration server on the given address.
+pub async fn run(
+    server_listener_addr: SocketAddr,
+    fault_service: FaultGrpcService,
+) -> Result<(), anyhow::Error> {
+    info!(
+        "gRPC fault config server listening on: {}",
+        server_listener_addr
+    );
+    tonic::transport::Server::builder()
+        .add_service(FaultServiceServer::new(fault_service))
+        .serve(server_listener_addr)
+        .await?;
+
+    Ok(())
+}
+
+/// Maps the error response of the REST API to the gRPC status of the same meaning.
+fn status(err: ServerErrorResponse) -> Status {
+    let message = err.message().to_string();
+    match err.status() {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::CONFLICT => Status::already_exists(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        _ => Status::internal(message),
+    }
+}
+
+/// Copies the request metadata to the HTTP headers the [AuthConfig] authorizes.
+fn http_headers(metadata: &MetadataMap) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in metadata.clone().into_headers().iter() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+
+    headers
+}
+
+impl From<Fault> for proto::Fault {
+    fn from(fault: Fault) -> Self {
+        proto::Fault {
+            name: fault.name,
+            description: fault.description,
+            fault_type: proto::FaultType::from(fault.fault_type) as i32,
+            duration: fault.duration,
+            error_msg: fault.error_msg,
+            command: fault.command,
+            last_modified: fault.last_modified.map(|ts| ts.to_rfc3339()),
+            expires_at: fault.expires_at.map(|ts| ts.to_rfc3339()),
+            activate_at: fault.activate_at.map(|ts| ts.to_rfc3339()),
+            enabled: Some(fault.enabled),
+            one_shot: fault.one_shot,
+            max_hits: fault.max_hits,
+            jitter_ms: fault.jitter_ms,
+            adaptive_delay_step_ms: fault.adaptive_delay_step_ms,
+            max_adaptive_duration_ms: fault.max_adaptive_duration_ms,
+            burst_count: fault.burst_count,
+            burst_interval_ms: fault.burst_interval_ms,
+            truncate_after_bytes: fault.truncate_after_bytes,
+            bytes_per_ms: fault.bytes_per_ms,
+            key_pattern: fault.key_pattern,
+            tags: fault.tags,
+            group: fault.group,
+            client_ip_filter: fault
+                .client_ip_filter
+                .unwrap_or_default()
+                .iter()
+                .map(IpAddr::to_string)
+                .collect(),
+            database: fault.database.map(u32::from),
+            proxy_port: fault.proxy_port.map(u32::from),
+            priority: Some(fault.priority),
+            probability: fault.probability,
+            sub_faults: fault
+                .sub_faults
+                .unwrap_or_default()
+                .into_iter()
+                .map(|sub_fault| proto::SubFault {
+                    fault_type: proto::FaultType::from(sub_fault.fault_type) as i32,
+                    duration: sub_fault.duration,
+                    error_msg: sub_fault.error_msg,
+                })
+                .collect(),
+            mutation_pattern: fault.mutation_pattern,
+            mutation_replacement: fault.mutation_replacement,
+            arg_matchers: fault
+                .arg_matchers
+                .unwrap_or_default()
+                .into_iter()
+                .map(|arg_matcher| proto::ArgMatcher {
+                    position: arg_matcher.position as u64,
+                    value: arg_matcher.value,
+                    match_type: proto::MatchType::from(arg_matcher.match_type) as i32,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::Fault> for Fault {
+    type Error = Status;
+
+    fn try_from(fault: proto::Fault) -> Result<Self, Self::Error> {
+        let client_ip_filter = fault
+            .client_ip_filter
+            .iter()
+            .map(|ip| {
+                ip.parse::<IpAddr>().map_err(|_| {
+                    Status::invalid_argument(format!("Invalid client_ip_filter IP {}", ip))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let sub_faults = fault
+            .sub_faults
+            .into_iter()
+            .map(|sub_fault| {
+                Ok(SubFault {
+                    fault_type: fault_variant(sub_fault.fault_type)?,
+                    duration: sub_fault.duration,
+                    error_msg: sub_fault.error_msg,
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+        let arg_matchers = fault
+            .arg_matchers
+            .into_iter()
+            .map(|arg_matcher| {
+                Ok(ArgMatcher::new(
+                    usize::try_from(arg_matcher.position).map_err(|_| {
+                        Status::invalid_argument("The arg_matchers position is out of range")
+                    })?,
+                    arg_matcher.value,
+                    proto::MatchType::try_from(arg_matcher.match_type)
+                        .map_err(|_| Status::invalid_argument("Unknown arg_matchers match_type"))?
+                        .into(),
+                ))
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(Fault {
+            name: fault.name,
+            description: fault.description,
+            fault_type: fault_variant(fault.fault_type)?,
+            duration: fault.duration,
+            error_msg: fault.error_msg,
+            command: fault.command,
+            last_modified: fault
+                .last_modified
+                .as_deref()
+                .map(|ts| timestamp("last_modified", ts))
+                .transpose()?,
+            expires_at: fault
+                .expires_at
+                .as_deref()
+                .map(|ts| timestamp("expires_at", ts))
+                .transpose()?,
+            activate_at: fault
+                .activate_at
+                .as_deref()
+                .map(|ts| timestamp("activate_at", ts))
+                .transpose()?,
+            enabled: fault.enabled.unwrap_or_else(default_enabled),
+            one_shot: fault.one_shot,
+            max_hits: fault.max_hits,
+            jitter_ms: fault.jitter_ms,
+            adaptive_delay_step_ms: fault.adaptive_delay_step_ms,
+            max_adaptive_duration_ms: fault.max_adaptive_duration_ms,
+            burst_count: fault.burst_count,
+            burst_interval_ms: fault.burst_interval_ms,
+            truncate_after_bytes: fault.truncate_after_bytes,
+            bytes_per_ms: fault.bytes_per_ms,
+            key_pattern: fault.key_pattern,
+            key_glob: Default::default(),
+            tags: fault.tags,
+            group: fault.group,
+            client_ip_filter: Some(client_ip_filter).filter(|ips| !ips.is_empty()),
+            database: fault
+                .database
+                .map(u8::try_from)
+                .transpose()
+                .map_err(|_| Status::invalid_argument("The database is out of range"))?,
+            proxy_port: fault
+                .proxy_port
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| Status::invalid_argument("The proxy_port is out of range"))?,
+            priority: fault.priority.unwrap_or_else(default_priority),
+            probability: fault.probability,
+            sub_faults: Some(sub_faults).filter(|sub_faults| !sub_faults.is_empty()),
+            mutation_pattern: fault.mutation_pattern,
+            mutation_replacement: fault.mutation_replacement,
+            arg_matchers: Some(arg_matchers).filter(|arg_matchers| !arg_matchers.is_empty()),
+        })
+    }
+}
+
+/// Returns the fault variant of the `fault_type` enum value, which must be specified.
+fn fault_variant(fault_type: i32) -> Result<FaultVariants, Status> {
+    match proto::FaultType::try_from(fault_type) {
+        Ok(proto::FaultType::Delay) => Ok(FaultVariants::Delay),
+        Ok(proto::FaultType::Error) => Ok(FaultVariants::Error),
+        Ok(proto::FaultType::DropConn) => Ok(FaultVariants::DropConn),
+        Ok(proto::FaultType::Timeout) => Ok(FaultVariants::Timeout),
+        Ok(proto::FaultType::PartialResponse) => Ok(FaultVariants::PartialResponse),
+        Ok(proto::FaultType::SlowDrain) => Ok(FaultVariants::SlowDrain),
+        Ok(proto::FaultType::Composite) => Ok(FaultVariants::Composite),
+        Ok(proto::FaultType::MutateResponse) => Ok(FaultVariants::MutateResponse),
+        Ok(proto::FaultType::TcpReset) => Ok(FaultVariants::TcpReset),
+        Ok(proto::FaultType::Unspecified) | Err(_) => Err(Status::invalid_argument(format!(
+            "Unknown fault_type {}",
+            fault_type
+        ))),
+    }
+}
+
+/// Parses the RFC 3339 timestamp of the given field.
+fn timestamp(field: &str, ts: &str) -> Result<DateTime<Utc>, Status> {
+    DateTime::parse_from_rfc3339(ts)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|err| Status::invalid_argument(format!("Invalid {} {}: {}", field, ts, err)))
+}
+
+impl From<FaultVariants> for proto::FaultType {
+    fn from(fault_type: FaultVariants) -> Self {
+        match fault_type {
+            FaultVariants::Delay => proto::FaultType::Delay,
+            FaultVariants::Error => proto::FaultType::Error,
+            FaultVariants::DropConn => proto::FaultType::DropConn,
+            FaultVariants::Timeout => proto::FaultType::Timeout,
+            FaultVariants::PartialResponse => proto::FaultType::PartialResponse,
+            FaultVariants::SlowDrain => proto::FaultType::SlowDrain,
+            FaultVariants::Composite => proto::FaultType::Composite,
+            FaultVariants::MutateResponse => proto::FaultType::MutateResponse,
+            FaultVariants::TcpReset => proto::FaultType::TcpReset,
+        }
+    }
+}
+
+impl From<MatchType> for proto::MatchType {
+    fn from(match_type: MatchType) -> Self {
+        match match_type {
+            MatchType::Exact => proto::MatchType::Exact,
+            MatchType::Prefix => proto::MatchType::Prefix,
+            MatchType::Suffix => proto::MatchType::Suffix,
+            MatchType::Contains => proto::MatchType::Contains,
+            MatchType::Regex => proto::MatchType::Regex,
+        }
+    }
+}
+
+impl From<proto::MatchType> for MatchType {
+    fn from(match_type: proto::MatchType) -> Self {
+        match match_type {
+            proto::MatchType::Exact => MatchType::Exact,
+            proto::MatchType::Prefix => MatchType::Prefix,
+            proto::MatchType::Suffix => MatchType::Suffix,
+            proto::MatchType::Contains => MatchType::Contains,
+            proto::MatchType::Regex => MatchType::Regex,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::NopAuditLogger;
+    use crate::config::Secret;
+    use crate::fault_config_server::auth::Role;
+    use crate::store::mem_store::MemStore;
+
+    fn new_service(fault_store: DB) -> FaultGrpcService {
+        FaultGrpcService::new(
+            fault_store,
+            NopAuditLogger::new_log(),
+            FaultSelectionStrategy::Priority,
+            AuthConfig::default(),
+        )
+    }
+
+    fn store_fault_request(fault: Fault) -> Request<proto::StoreFaultRequest> {
+        Request::new(proto::StoreFaultRequest {
+            fault: Some(fault.into()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_store_fault() {
+        let fault_store = MemStore::new_db();
+        let service = new_service(fault_store.clone());
+
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        service
+            .store_fault(store_fault_request(fault.clone()))
+            .await
+            .unwrap();
+        let stored_fault = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+        assert!(stored_fault.last_modified.is_some());
+        assert_eq!(
+            Fault {
+                last_modified: None,
+                ..stored_fault
+            },
+            fault
+        );
+
+        // a second fault of the same command conflicts with the stored one
+        let mut conflicting_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        conflicting_fault.name = "another delay".to_string();
+        let err = service
+            .store_fault(store_fault_request(conflicting_fault))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::AlreadyExists);
+
+        let mut invalid_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        invalid_fault.max_hits = Some(0);
+        let err = service
+            .store_fault(store_fault_request(invalid_fault))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let mut unspecified_fault = proto::Fault::from(Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        });
+        unspecified_fault.fault_type = proto::FaultType::Unspecified as i32;
+        for request in [
+            proto::StoreFaultRequest {
+                fault: Some(unspecified_fault),
+            },
+            proto::StoreFaultRequest { fault: None },
+        ] {
+            let err = service
+                .store_fault(Request::new(request))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_fault_defaults() {
+        let fault_store = MemStore::new_db();
+        let service = new_service(fault_store.clone());
+
+        // the unset enabled and priority get the defaults of the REST API
+        let fault = proto::Fault {
+            enabled: None,
+            priority: None,
+            ..Fault {
+                description: Some("inject a delay of 10 milliseconds".to_string()),
+                duration: Some(20),
+                ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+            }
+            .into()
+        };
+        service
+            .store_fault(Request::new(proto::StoreFaultRequest {
+                fault: Some(fault),
+            }))
+            .await
+            .unwrap();
+
+        let stored_fault = fault_store
+            .get_by_fault_name("delay 10 milliseconds")
+            .await
+            .unwrap();
+        let rest_fault: Fault = serde_json::from_value(serde_json::json!({
+            "name": "rest",
+            "fault_type": "Delay",
+            "command": "SET"
+        }))
+        .unwrap();
+        assert!(stored_fault.enabled);
+        assert_eq!(stored_fault.enabled, rest_fault.enabled);
+        assert_eq!(stored_fault.priority, 100);
+        assert_eq!(stored_fault.priority, rest_fault.priority);
+    }
+
+    #[tokio::test]
+    async fn test_get_fault() {
+        let fault_store = MemStore::new_db();
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let service = new_service(fault_store);
+
+        let response = service
+            .get_fault(Request::new(proto::GetFaultRequest {
+                name: fault.name.clone(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(Fault::try_from(response.into_inner()).unwrap(), fault);
+
+        let err = service
+            .get_fault(Request::new(proto::GetFaultRequest {
+                name: "unknown".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_delete_fault() {
+        let fault_store = MemStore::new_db();
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let service = new_service(fault_store.clone());
+
+        // deleting a fault is idempotent
+        for _ in 0..2 {
+            service
+                .delete_fault(Request::new(proto::DeleteFaultRequest {
+                    name: fault.name.clone(),
+                }))
+                .await
+                .unwrap();
+            assert!(fault_store.get_by_fault_name(&fault.name).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_faults() {
+        let fault_store = MemStore::new_db();
+        for (name, command, minutes_ago) in [
+            ("get_delay", "GET", 2),
+            ("set_delay", "SET", 1),
+            ("del_delay", "DEL", 3),
+        ] {
+            let mut fault = Fault {
+                description: Some("inject a delay of 10 milliseconds".to_string()),
+                duration: Some(20),
+                ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+            };
+            fault.name = name.to_string();
+            fault.command = command.to_string();
+            fault.last_modified = Some(Utc::now() - chrono::Duration::minutes(minutes_ago));
+            fault_store.store(name, &fault).await.unwrap();
+        }
+        let service = new_service(fault_store);
+
+        for (command, expected_names) in [
+            (None, vec!["set_delay", "get_delay", "del_delay"]),
+            (Some("get"), vec!["get_delay"]),
+        ] {
+            let response = service
+                .list_faults(Request::new(proto::ListFaultsRequest {
+                    command: command.map(str::to_string),
+                }))
+                .await
+                .unwrap();
+            let names: Vec<String> = response
+                .into_inner()
+                .faults
+                .into_iter()
+                .map(|fault| fault.name)
+                .collect();
+            assert_eq!(names, expected_names);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorization() {
+        let service = FaultGrpcService::new(
+            MemStore::new_db(),
+            NopAuditLogger::new_log(),
+            FaultSelectionStrategy::Priority,
+            AuthConfig {
+                api_key: Some(Secret("s3cr3t".to_string())),
+                api_keys: std::collections::HashMap::from([(
+                    Secret("r3ad".to_string()),
+                    Role::ReadOnly,
+                )]),
+                jwt: None,
+            },
+        );
+        let with_api_key = |mut request: Request<proto::ListFaultsRequest>, api_key: &str| {
+            request
+                .metadata_mut()
+                .insert("x-api-key", api_key.parse().unwrap());
+            request
+        };
+
+        let err = service
+            .list_faults(Request::new(proto::ListFaultsRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+
+        for api_key in ["s3cr3t", "r3ad"] {
+            service
+                .list_faults(with_api_key(
+                    Request::new(proto::ListFaultsRequest::default()),
+                    api_key,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let mut request = store_fault_request(Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        });
+        request
+            .metadata_mut()
+            .insert("x-api-key", "r3ad".parse().unwrap());
+        let err = service.store_fault(request).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_fault_conversion() {
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.fault_type = FaultVariants::Composite;
+        fault.last_modified = Some(Utc::now());
+        fault.client_ip_filter = Some(vec!["10.0.0.1".parse().unwrap(), "::1".parse().unwrap()]);
+        fault.database = Some(3);
+        fault.proxy_port = Some(6351);
+        fault.sub_faults = Some(vec![SubFault {
+            fault_type: FaultVariants::Error,
+            duration: None,
+            error_msg: Some("ERR injected".to_string()),
+        }]);
+        fault.arg_matchers = Some(vec![ArgMatcher::new(
+            2,
+            "^user:".to_string(),
+            MatchType::Regex,
+        )]);
+
+        assert_eq!(
+            Fault::try_from(proto::Fault::from(fault.clone())).unwrap(),
+            fault
+        );
+
+        let mut invalid_fault = proto::Fault::from(fault);
+        invalid_fault.database = Some(256);
+        assert_eq!(
+            Fault::try_from(invalid_fault).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
@@ -1,89 +1,567 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use tokio::net::TcpListener;
+use futures::future::join_all;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock, Semaphore};
 use tokio::{join, signal};
-use tracing::{debug, error, info};
+use tracing::{error, info, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Registry};
 
 #[macro_use]
 extern crate serde_derive;
 
+mod audit;
 mod config;
+mod events;
+mod experiment;
 mod fault_config_server;
+mod grpc;
+mod metrics;
 mod proxy;
+mod reload;
 mod store;
+mod telemetry;
 
-fn init_tracing(log_level: &str) {
-    LogTracer::init().expect("Unable to setup log tracer!");
+/// Builds the layer that logs to the given writer in the given `log_format`, i.e. the Bunyan JSON
+/// format for `json`, multi-line human-readable text for `text` and single-line text for
+/// `compact`. The `text` logs are colored when `ansi` is true.
+fn log_layer<S, W>(log_format: &str, make_writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    match log_format {
+        "text" => fmt::layer()
+            .with_writer(make_writer)
+            .with_ansi(ansi)
+            .boxed(),
+        "compact" => fmt::layer()
+            .compact()
+            .with_writer(make_writer)
+            .with_ansi(false)
+            .boxed(),
+        // the log format is validated at startup, hence it is `json`
+        _ => {
+            let app_name =
+                concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION")).to_string();
+            JsonStorageLayer
+                .and_then(BunyanFormattingLayer::new(app_name, make_writer))
+                .boxed()
+        }
+    }
+}
+
+/// Opens the log file that is rotated daily, i.e. the logs of each day are appended to the file
+/// of the given path suffixed by the date, e.g. `/var/log/red-monkey.log.2022-02-14`.
+fn log_file_appender(log_file: &str) -> RollingFileAppender {
+    let path = Path::new(log_file);
+    let file_name = path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .expect("LOG_FILE is not a file path");
+
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(file_name)
+        .build(path.parent().unwrap_or_else(|| Path::new(".")))
+        .expect("Error opening the log file")
+}
+
+/// Builds the subscriber that logs the spans to stdout in the given `log_format`, and to the
+/// `log_file` if set, and, when `otel_endpoint` is set, exports them to the OpenTelemetry
+/// collector.
+///
+/// The logs are written to the log file by a background thread, which is flushed and stopped when
+/// the returned guard is dropped.
+fn new_subscriber(
+    log_level: &str,
+    log_format: &str,
+    log_file: Option<&str>,
+    otel_endpoint: Option<&str>,
+    otel_service_name: &str,
+) -> (
+    impl Subscriber + Send + Sync,
+    reload::LogLevelHandle,
+    Option<SdkTracerProvider>,
+    Option<WorkerGuard>,
+) {
+    let (log_file_layer, log_file_guard) = match log_file {
+        Some(log_file) => {
+            let (make_writer, log_file_guard) =
+                tracing_appender::non_blocking(log_file_appender(log_file));
+            (
+                Some(log_layer(log_format, make_writer, false)),
+                Some(log_file_guard),
+            )
+        }
+        None => (None, None),
+    };
 
-    let app_name = concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION")).to_string();
-    let bunyan_formatting_layer = BunyanFormattingLayer::new(app_name, std::io::stdout);
+    let (otel_layer, tracer_provider) = match otel_endpoint {
+        Some(otel_endpoint) => {
+            let (otel_layer, tracer_provider) =
+                telemetry::otel_layer(otel_endpoint, otel_service_name)
+                    .expect("Error configuring the OpenTelemetry exporter");
+            (Some(otel_layer), Some(tracer_provider))
+        }
+        None => (None, None),
+    };
 
+    let (log_filter, log_level_handle) =
+        tracing_subscriber::reload::Layer::new(EnvFilter::new(log_level));
     let subscriber = Registry::default()
-        .with(EnvFilter::new(log_level))
-        .with(JsonStorageLayer)
-        .with(bunyan_formatting_layer);
+        .with(log_filter)
+        .with(log_layer(
+            log_format,
+            std::io::stdout,
+            std::io::stdout().is_terminal(),
+        ))
+        .with(log_file_layer)
+        .with(otel_layer);
+
+    (
+        subscriber,
+        log_level_handle,
+        tracer_provider,
+        log_file_guard,
+    )
+}
+
+/// Sets up the global subscriber of [new_subscriber].
+///
+/// Returns the tracer provider of the OpenTelemetry exporter, if any, to be shut down on exit, and
+/// the guard of the log file writer, if any, to be dropped on exit.
+fn init_tracing(
+    log_level: &str,
+    log_format: &str,
+    log_file: Option<&str>,
+    otel_endpoint: Option<&str>,
+    otel_service_name: &str,
+) -> (
+    reload::LogLevelHandle,
+    Option<SdkTracerProvider>,
+    Option<WorkerGuard>,
+) {
+    LogTracer::init().expect("Unable to setup log tracer!");
 
+    let (subscriber, log_level_handle, tracer_provider, log_file_guard) = new_subscriber(
+        log_level,
+        log_format,
+        log_file,
+        otel_endpoint,
+        otel_service_name,
+    );
     tracing::subscriber::set_global_default(subscriber)
         .expect("Error setting subscriber to global default");
+
+    (log_level_handle, tracer_provider, log_file_guard)
+}
+
+/// Opens the RocksDB fault store at the given path.
+#[cfg(feature = "rocksdb")]
+fn new_rocks_store(rocksdb_path: &str) -> store::fault_store::DB {
+    store::rocks_store::RocksStore::new_db(rocksdb_path)
+        .expect("Error opening the RocksDB fault store")
+}
+
+/// The RocksDB fault store is only available with the `rocksdb` feature, hence `ROCKSDB_PATH`
+/// fails the validation of the config without it, see [config::ConfigError::RocksDbUnsupported].
+#[cfg(not(feature = "rocksdb"))]
+fn new_rocks_store(_rocksdb_path: &str) -> store::fault_store::DB {
+    unreachable!("ROCKSDB_PATH is rejected by the config validation without the rocksdb feature")
 }
 
-#[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
+fn main() -> Result<(), anyhow::Error> {
     let config = config::get_config().expect("Error reading configuration");
-    init_tracing(&config.log_level);
+    if let Err(errors) = config.validate() {
+        // the logging is not set up yet, as it depends on the config
+        eprintln!("Invalid configuration:");
+        for err in errors {
+            eprintln!("  - {}", err);
+        }
+        std::process::exit(1);
+    }
+
+    // the worker threads match the accept loops, one per CPU core by default
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.accept_threads())
+        .enable_all()
+        .build()?
+        .block_on(run(config))
+}
+
+/// Runs the fault config server and the proxy listeners until the ctrl-c signal.
+async fn run(config: config::Config) -> Result<(), anyhow::Error> {
+    let (log_level_handle, tracer_provider, log_file_guard) = init_tracing(
+        &config.log_level,
+        &config.log_format,
+        config.log_file.as_deref(),
+        config.otel_endpoint.as_deref(),
+        &config.otel_service_name,
+    );
     info!("red-monkey configs: {:?}", config);
 
-    let fault_store = store::mem_store::MemStore::new_db();
+    let fault_store = match (
+        &config.fault_store_redis_url,
+        &config.postgres_url,
+        &config.rocksdb_path,
+        &config.sqlite_path,
+    ) {
+        (Some(redis_url), ..) => store::redis_store::RedisStore::new_db(redis_url)
+            .expect("Error configuring the Redis fault store"),
+        (None, Some(postgres_url), ..) => {
+            store::postgres_store::PostgresStore::new_db(&postgres_url.0, config.postgres_pool_size)
+                .await
+                .expect("Error connecting to the PostgreSQL fault store")
+        }
+        (None, None, Some(rocksdb_path), _) => new_rocks_store(rocksdb_path),
+        (None, None, None, Some(sqlite_path)) => {
+            store::sqlite_store::SqliteStore::new_db(sqlite_path)
+                .await
+                .expect("Error opening the SQLite fault store")
+        }
+        (None, None, None, None) => {
+            store::mem_store::MemStore::new_db_with_history(config.max_history_versions)
+        }
+    };
+    let playbook_store = store::mem_store::MemPlaybookStore::new_db();
+    let audit_log = match &config.audit_log_path {
+        Some(audit_log_path) => audit::FileAuditLogger::new_log(audit_log_path)
+            .expect("Error opening the audit log file"),
+        None => audit::NopAuditLogger::new_log(),
+    };
+    let experiment_log = match &config.experiment_log_path {
+        Some(experiment_log_path) => Some(
+            experiment::ExperimentLog::open(experiment_log_path)
+                .await
+                .expect("Error opening the experiment log file"),
+        ),
+        None => None,
+    };
+    let metrics = Arc::new(
+        metrics::Metrics::with_request_duration_buckets(
+            config
+                .latency_histogram_buckets
+                .as_deref()
+                .unwrap_or(metrics::REQUEST_DURATION_BUCKETS),
+        )
+        .expect("Error registering metrics"),
+    );
+    let statsd = metrics::statsd_client(
+        config.statsd_host.as_deref(),
+        config.statsd_port.unwrap_or(metrics::DEFAULT_STATSD_PORT),
+        &config.statsd_prefix,
+    )
+    .expect("Error configuring the StatsD client");
 
     let origin_server_config = proxy::connection::OriginServerConfig {
-        server_addr: config.redis_address.clone(),
+        server_addrs: config.redis_addresses(),
         is_tls_conn: true,
+        pool_size: config.upstream_pool_size,
     };
 
-    let conn = proxy::connection::Connection::new(
-        origin_server_config,
-        proxy::faulter::Faulter::new(fault_store.clone()),
-    )
-    .expect("Error configuring proxy");
+    let selection_strategy =
+        store::fault_store::FaultSelectionStrategy::new(config.allow_multiple_per_command);
+    let fault_event_sender = events::fault_event_sender();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut faulter = proxy::faulter::Faulter::new(fault_store.clone(), metrics.clone())
+        .with_playbook_store(playbook_store.clone())
+        .with_statsd(Arc::new(statsd))
+        .with_selection_strategy(selection_strategy)
+        .with_bypass_commands(config.bypass_commands.clone())
+        .with_fault_events(fault_event_sender.clone())
+        .with_shutdown(shutdown_rx.clone());
+    if let Some(experiment_log) = &experiment_log {
+        faulter = faulter.with_experiment_log(experiment_log.clone());
+    }
+    if let Some(nats_url) = &config.nats_url {
+        let nats_publisher =
+            events::nats_publisher::NatsPublisher::connect(nats_url, &config.nats_subject_prefix)
+                .await
+                .expect("Error connecting to the NATS server");
+        faulter = faulter.with_fault_event_publisher(Arc::new(nats_publisher));
+    }
+
+    let mut conn = proxy::connection::Connection::new(origin_server_config, faulter)
+        .expect("Error configuring proxy")
+        .with_tls_config(&proxy::connection::UpstreamTlsConfig {
+            client_cert_path: config.upstream_client_cert_path.clone(),
+            client_key_path: config.upstream_client_key_path.clone(),
+            ca_cert_path: config.upstream_ca_cert_path.clone(),
+            min_version: config
+                .tls_min_version
+                .as_deref()
+                .map(config::parse_tls_version)
+                .transpose()
+                .expect("Error parsing TLS_MIN_VERSION"),
+            max_version: config
+                .tls_max_version
+                .as_deref()
+                .map(config::parse_tls_version)
+                .transpose()
+                .expect("Error parsing TLS_MAX_VERSION"),
+            cipher_suites: config.tls_cipher_suites.clone(),
+        })
+        .expect("Error configuring the upstream TLS connections")
+        .with_request_logging(config.request_logging_enabled)
+        .with_tcp_keepalive(config.tcp_keepalive_secs.map(Duration::from_secs))
+        .with_idle_timeout(config.idle_connection_timeout_secs.map(Duration::from_secs))
+        .with_upstream_keep_alive(config.upstream_keep_alive)
+        .with_upstream_socks5_proxy(config.upstream_socks5_proxy.clone())
+        .with_lb_strategy(
+            config
+                .lb_strategy()
+                .expect("Error parsing UPSTREAM_LB_STRATEGY"),
+        )
+        .with_proxy_protocol(config.proxy_protocol_enabled)
+        .with_client_id_mode(
+            config
+                .client_id_mode()
+                .expect("Error parsing CLIENT_ID_HEADER_MODE"),
+        )
+        .with_tcp_buffer_sizes(proxy::connection::TcpBufferSizes {
+            send: config.tcp_send_buffer_size,
+            recv: config.tcp_recv_buffer_size,
+        });
+
+    if let Some(health_check_interval_ms) = config.health_check_interval_ms {
+        let health_monitor = proxy::upstream_health::UpstreamHealthMonitor::new(
+            &config.redis_addresses(),
+            Duration::from_millis(health_check_interval_ms),
+            config.health_check_recovery_attempts,
+        );
+        conn = conn.with_health_monitor(health_monitor);
+    }
+
+    if let Some(threshold) = config.circuit_breaker_threshold {
+        conn = conn.with_circuit_breaker(proxy::circuit_breaker::CircuitBreaker::new(
+            proxy::circuit_breaker::CircuitBreakerConfig {
+                threshold,
+                window: Duration::from_millis(config.circuit_breaker_window_ms),
+                open_duration: Duration::from_millis(config.circuit_breaker_open_ms),
+            },
+        ));
+    }
 
-    let fault_config_server_port = config.fault_config_server_port;
+    conn.refill_pool();
+    conn.spawn_health_checks();
+
+    tokio::spawn(store::sweeper::run(
+        fault_store.clone(),
+        store::sweeper::SWEEP_INTERVAL,
+    ));
+
+    let fault_config_server_addr = config
+        .fault_config_server_addr()
+        .expect("Error parsing the fault config server bind address");
+    let cors_allowed_origins = config.cors_allowed_origins();
+    let auth_config = fault_config_server::auth::AuthConfig {
+        api_key: config.api_key.clone(),
+        api_keys: config
+            .api_keys()
+            .expect("Error parsing the fault config API keys"),
+        jwt: config
+            .jwt_secret
+            .clone()
+            .map(|secret| fault_config_server::auth::JwtConfig {
+                secret,
+                issuer: config.jwt_issuer.clone(),
+            }),
+    };
+    if let Some(grpc_port) = config.grpc_port {
+        let grpc_server_addr = SocketAddr::new(fault_config_server_addr.ip(), grpc_port);
+        let fault_service = grpc::FaultGrpcService::new(
+            fault_store.clone(),
+            audit_log.clone(),
+            selection_strategy,
+            auth_config.clone(),
+        );
+        tokio::spawn(async move {
+            grpc::run(grpc_server_addr, fault_service)
+                .await
+                .expect("Failed to run the gRPC fault configuration server");
+        });
+    }
+    let mut replay_engine = experiment::replay::ReplayEngine::new(fault_store.clone());
+    if let Some(experiment_log_path) = &config.experiment_log_path {
+        replay_engine = replay_engine.with_experiment_log_path(experiment_log_path);
+    }
+    let server_log_level_handle = log_level_handle.clone();
+    let import_max_bytes = config.import_max_bytes;
     let fault_config_server_future = tokio::spawn(async move {
-        fault_config_server::server::run(fault_config_server_port, fault_store)
-            .await
-            .expect("Failed to run fault configuration server");
+        fault_config_server::server::run(
+            fault_config_server_addr,
+            fault_store,
+            playbook_store,
+            audit_log,
+            selection_strategy,
+            experiment_log,
+            replay_engine,
+            metrics,
+            cors_allowed_origins,
+            auth_config,
+            server_log_level_handle,
+            fault_config_server::handler::ImportLimit(import_max_bytes),
+            fault_event_sender,
+        )
+        .await
+        .expect("Failed to run fault configuration server");
     });
 
-    let proxy_listener_addr =
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), config.proxy_port);
-    info!("Proxy listening on: {}", proxy_listener_addr);
-    let listener = TcpListener::bind(&proxy_listener_addr)
-        .await
-        .expect("Error binding the proxy port");
-
-    let proxy_future = tokio::spawn(async move {
-        loop {
-            tokio::select! {
-                Ok((socket, _addr)) = listener.accept() => {
-                let conn = conn.clone();
-
-                tokio::spawn(async move {
-                    debug!("handling tcp connection");
-                    conn.handle(socket).await.unwrap_or_else(|err| {
-                        error!("error handling connection: {:?}", err);
-                    });
-                });
-                }
-                _ = signal::ctrl_c() => {
-                    info!("shutting down proxy");
-                    return;
+    // the listeners are paired with their port, which scopes the faults they apply
+    let mut listeners = Vec::new();
+    match &config.proxy_unix_socket {
+        Some(socket_path) => {
+            info!("Proxy listening on unix socket: {}", socket_path);
+            listeners.push((
+                proxy::server::Listener::bind_unix(socket_path)
+                    .expect("Error binding the proxy unix socket"),
+                None,
+                config
+                    .max_concurrent_connections
+                    .map(|permits| Arc::new(Semaphore::new(permits))),
+            ));
+        }
+        None => {
+            let proxy_listener_addrs = config
+                .proxy_addrs()
+                .expect("Error parsing the proxy bind address");
+            for proxy_listener_addr in proxy_listener_addrs {
+                info!("Proxy listening on: {}", proxy_listener_addr);
+                let connection_permits = config
+                    .max_concurrent_connections
+                    .map(|permits| Arc::new(Semaphore::new(permits)));
+
+                // each accept loop has its own listener of the address, see ACCEPT_THREADS
+                for _ in 0..config.accept_threads() {
+                    let listener = proxy::server::Listener::bind_reuse_port(proxy_listener_addr);
+                    listeners.push((
+                        listener.expect("Error binding the proxy port"),
+                        Some(proxy_listener_addr.port()),
+                        connection_permits.clone(),
+                    ));
                 }
             }
         }
+    }
+
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let rate_limiter = config
+        .max_connections_per_second
+        .map(proxy::rate_limiter::RateLimiter::start);
+    reload::spawn_sighup_handler(reload::Reloader {
+        config: Arc::new(RwLock::new(config)),
+        log_level_handle,
+        conn: conn.clone(),
+    })
+    .expect("Error registering the SIGHUP handler");
+
+    tokio::spawn(async move {
+        let _ = signal::ctrl_c().await;
+        info!("received ctrl-c signal");
+        let _ = shutdown_tx.send(true);
     });
 
-    let _ = join!(fault_config_server_future, proxy_future);
+    let proxy_futures = listeners
+        .into_iter()
+        .map(|(listener, proxy_port, connection_permits)| {
+            tokio::spawn(proxy::server::run(
+                listener,
+                conn.clone().with_proxy_port(proxy_port),
+                rate_limiter.clone(),
+                connection_permits,
+                shutdown_rx.clone(),
+                shutdown_timeout,
+            ))
+        });
+
+    let _ = join!(fault_config_server_future, join_all(proxy_futures));
+
+    if let Some(tracer_provider) = tracer_provider {
+        if let Err(err) = tracer_provider.shutdown() {
+            error!("Error flushing the OpenTelemetry spans: {}", err);
+        }
+    }
+    // flushes the buffered logs to the log file
+    drop(log_file_guard);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_subscriber() {
+        for log_format in config::LOG_FORMATS {
+            let (subscriber, log_level_handle, tracer_provider, log_file_guard) =
+                new_subscriber("info", log_format, None, None, "red-monkey-test");
+            assert!(tracer_provider.is_none());
+            assert!(log_file_guard.is_none());
+
+            tracing::subscriber::with_default(subscriber, || {
+                let span = tracing::info_span!("Handling connection", log_format);
+                let _entered = span.enter();
+                info!("logged in the {} format", log_format);
+
+                log_level_handle
+                    .reload(EnvFilter::new("debug"))
+                    .expect(log_format);
+            });
+        }
+    }
+
+    #[test]
+    fn test_new_subscriber_log_file() {
+        let log_dir =
+            std::env::temp_dir().join(format!("red-monkey-logs-{}", uuid::Uuid::new_v4()));
+        let log_file = log_dir.join("red-monkey.log");
+
+        for log_format in config::LOG_FORMATS {
+            let (subscriber, _, _, log_file_guard) = new_subscriber(
+                "info",
+                log_format,
+                log_file.to_str(),
+                None,
+                "red-monkey-test",
+            );
+            assert!(log_file_guard.is_some());
+
+            tracing::subscriber::with_default(subscriber, || {
+                info!("logged to the file in the {} format", log_format);
+            });
+            drop(log_file_guard);
+        }
+
+        // the file name is suffixed by the date
+        let log_files: Vec<_> = std::fs::read_dir(&log_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(log_files.len(), 1);
+        assert!(log_files[0]
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("red-monkey.log."));
+
+        let logs = std::fs::read_to_string(&log_files[0]).unwrap();
+        for log_format in config::LOG_FORMATS {
+            assert!(logs.contains(&format!("logged to the file in the {} format", log_format)));
+        }
+
+        std::fs::remove_dir_all(log_dir).unwrap();
+    }
+}
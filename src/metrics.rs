@@ -0,0 +1,233 @@
+use cadence::{MetricError, NopMetricSink, StatsdClient, UdpMetricSink};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::time::Instant;
+
+/// The buckets of the delay duration histogram in milliseconds
+const DELAY_DURATION_BUCKETS: &[f64] = &[
+    10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// The default buckets of the request duration histogram in milliseconds, see
+/// `latency_histogram_buckets`
+pub const REQUEST_DURATION_BUCKETS: &[f64] = &[
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// The port of the StatsD server when `statsd_port` is not set
+pub const DEFAULT_STATSD_PORT: u16 = 8125;
+
+/// Metrics holds the Prometheus metrics of red-monkey and the registry they are registered with.
+///
+/// The metric objects are cheap to clone and share their underlying values, hence the same
+/// Metrics can be updated by the proxy and gathered by the fault config server.
+pub struct Metrics {
+    registry: Registry,
+    /// The number of applied faults by `fault_name` and `fault_type`
+    pub faults_total: IntCounterVec,
+    /// The number of Redis commands proxied
+    pub proxy_requests_total: IntCounter,
+    /// The duration of the injected delays in milliseconds
+    pub delay_duration_milliseconds: Histogram,
+    /// The duration of the proxied requests in milliseconds by `command`, including the round
+    /// trip to the origin server and the injected faults
+    pub request_duration_milliseconds: HistogramVec,
+    /// The number of faults that are enabled and not expired
+    pub active_faults: IntGauge,
+}
+
+impl Metrics {
+    /// Creates the metrics and registers them with a new registry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [prometheus::Error] when a metric fails to be created or registered.
+    pub fn new() -> Result<Self, prometheus::Error> {
+        Metrics::with_request_duration_buckets(REQUEST_DURATION_BUCKETS)
+    }
+
+    /// Creates the metrics like [Metrics::new], with the given buckets of the request duration
+    /// histogram in milliseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [prometheus::Error] when a metric fails to be created or registered, e.g. when the
+    /// buckets are not in increasing order.
+    pub fn with_request_duration_buckets(
+        request_duration_buckets: &[f64],
+    ) -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let faults_total = IntCounterVec::new(
+            Opts::new("red_monkey_faults_total", "Number of applied faults"),
+            &["fault_name", "fault_type"],
+        )?;
+        let proxy_requests_total = IntCounter::new(
+            "red_monkey_proxy_requests_total",
+            "Number of Redis commands proxied",
+        )?;
+        let delay_duration_milliseconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "red_monkey_delay_duration_milliseconds",
+                "Duration of the injected delays in milliseconds",
+            )
+            .buckets(DELAY_DURATION_BUCKETS.to_vec()),
+        )?;
+        let request_duration_opts = HistogramOpts::new(
+            "red_monkey_request_duration_milliseconds",
+            "Duration of the proxied requests in milliseconds",
+        )
+        .buckets(request_duration_buckets.to_vec());
+        // the buckets of a histogram vec are only checked once a command is observed
+        Histogram::with_opts(request_duration_opts.clone())?;
+        let request_duration_milliseconds = HistogramVec::new(request_duration_opts, &["command"])?;
+        let active_faults = IntGauge::new(
+            "red_monkey_active_faults",
+            "Number of faults that are enabled and not expired",
+        )?;
+
+        registry.register(Box::new(faults_total.clone()))?;
+        registry.register(Box::new(proxy_requests_total.clone()))?;
+        registry.register(Box::new(delay_duration_milliseconds.clone()))?;
+        registry.register(Box::new(request_duration_milliseconds.clone()))?;
+        registry.register(Box::new(active_faults.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            faults_total,
+            proxy_requests_total,
+            delay_duration_milliseconds,
+            request_duration_milliseconds,
+            active_faults,
+        })
+    }
+
+    /// Starts timing a request of the given Redis command, see [RequestTimer].
+    pub fn start_request_timer(&self, command: &str) -> RequestTimer {
+        RequestTimer {
+            histogram: self
+                .request_duration_milliseconds
+                .with_label_values(&[command]),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Encodes the registered metrics in the Prometheus text format.
+    ///
+    /// Returns the content type of the format along with the encoded metrics on success.
+    pub fn encode(&self) -> Result<(String, Vec<u8>), prometheus::Error> {
+        let encoder = TextEncoder::new();
+        let mut buffer = Vec::new();
+        encoder.encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok((encoder.format_type().to_string(), buffer))
+    }
+}
+
+/// RequestTimer observes the time elapsed since it is started in the request duration histogram
+/// when it is dropped, such that the request is timed whichever way its handling ends.
+pub struct RequestTimer {
+    histogram: Histogram,
+    started_at: Instant,
+}
+
+impl Drop for RequestTimer {
+    fn drop(&mut self) {
+        self.histogram
+            .observe(self.started_at.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+/// Creates the StatsD client that sends the metrics over UDP to the StatsD server at the given
+/// host and port, each metric name prefixed with `prefix`.
+///
+/// When the host is not set, the client discards the metrics.
+///
+/// # Errors
+///
+/// Returns [MetricError] when the UDP socket fails to be bound or the address can't be resolved.
+pub fn statsd_client(
+    host: Option<&str>,
+    port: u16,
+    prefix: &str,
+) -> Result<StatsdClient, MetricError> {
+    let host = match host {
+        Some(host) => host,
+        None => return Ok(StatsdClient::from_sink(prefix, NopMetricSink)),
+    };
+
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_nonblocking(true)?;
+    let sink = UdpMetricSink::from((host, port), socket)?;
+
+    Ok(StatsdClient::from_sink(prefix, sink))
+}
+
+/// Returns the given value as a segment of a StatsD metric name, replacing the characters that
+/// are reserved by the StatsD line protocol and the whitespaces with `_`.
+pub fn statsd_name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | '.' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new().unwrap();
+        metrics
+            .faults_total
+            .with_label_values(&["get_delay", "Delay"])
+            .inc();
+        metrics.proxy_requests_total.inc();
+        metrics.delay_duration_milliseconds.observe(120.0);
+        metrics
+            .request_duration_milliseconds
+            .with_label_values(&["GET"])
+            .observe(3.0);
+        metrics.active_faults.set(1);
+
+        let (content_type, buffer) = metrics.encode().unwrap();
+        let body = String::from_utf8(buffer).unwrap();
+
+        assert!(content_type.starts_with("text/plain"));
+        assert!(body
+            .contains(r#"red_monkey_faults_total{fault_name="get_delay",fault_type="Delay"} 1"#));
+        assert!(body.contains("red_monkey_proxy_requests_total 1"));
+        assert!(body.contains(r#"red_monkey_delay_duration_milliseconds_bucket{le="250"} 1"#));
+        assert!(body.contains(
+            r#"red_monkey_request_duration_milliseconds_bucket{command="GET",le="5"} 1"#
+        ));
+        assert!(body.contains("red_monkey_active_faults 1"));
+    }
+
+    #[test]
+    fn test_request_duration_buckets() {
+        let metrics = Metrics::with_request_duration_buckets(&[10.0, 100.0]).unwrap();
+        metrics
+            .request_duration_milliseconds
+            .with_label_values(&["SET"])
+            .observe(20.0);
+
+        let (_, buffer) = metrics.encode().unwrap();
+        let body = String::from_utf8(buffer).unwrap();
+        assert!(body.contains(
+            r#"red_monkey_request_duration_milliseconds_bucket{command="SET",le="10"} 0"#
+        ));
+        assert!(body.contains(
+            r#"red_monkey_request_duration_milliseconds_bucket{command="SET",le="100"} 1"#
+        ));
+
+        assert!(Metrics::with_request_duration_buckets(&[100.0, 10.0]).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// CircuitBreakerConfig holds the thresholds of the [CircuitBreaker].
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive failures that opens the circuit
+    pub threshold: u32,
+    /// The window within which the consecutive failures must occur
+    pub window: Duration,
+    /// The time the circuit stays open before a request is let through to test the upstream
+    pub open_duration: Duration,
+}
+
+/// CircuitState is the state of the [CircuitBreaker].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitState {
+    /// The requests are let through to the upstream
+    Closed,
+    /// The requests are rejected without reaching the upstream
+    Open,
+    /// A single trial request is let through to test whether the upstream is back
+    HalfOpen,
+}
+
+/// CircuitBreaker stops the requests from waiting on an unreachable upstream.
+///
+/// After `threshold` consecutive upstream connection failures within `window`, the circuit opens
+/// and the requests are rejected right away for `open_duration`. Then, the circuit is half-open
+/// and lets a single trial request through. The circuit closes when the trial request succeeds,
+/// and opens again otherwise.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    first_failure_at: Option<Instant>,
+    opened_at: Option<Instant>,
+    is_trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    /// Creates a new closed CircuitBreaker.
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            config,
+            inner: Arc::new(Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                first_failure_at: None,
+                opened_at: None,
+                is_trial_in_flight: false,
+            })),
+        }
+    }
+
+    /// Returns true if a request may be sent to the upstream. The outcome of an allowed request
+    /// must be reported with [CircuitBreaker::record_success] or
+    /// [CircuitBreaker::record_failure].
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Records a successful upstream connection, which closes the circuit.
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state != CircuitState::Closed {
+            info!("upstream is reachable again, closing the circuit");
+        }
+
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.first_failure_at = None;
+        inner.opened_at = None;
+        inner.is_trial_in_flight = false;
+    }
+
+    /// Records a failed upstream connection, which may open the circuit.
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    #[cfg(test)]
+    fn state_at(&self, now: Instant) -> CircuitState {
+        let mut inner = self.inner.lock().unwrap();
+        self.half_open_if_elapsed(&mut inner, now);
+        inner.state
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        self.half_open_if_elapsed(&mut inner, now);
+
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if inner.is_trial_in_flight => false,
+            CircuitState::HalfOpen => {
+                inner.is_trial_in_flight = true;
+                true
+            }
+        }
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.state == CircuitState::HalfOpen {
+            warn!("upstream is still unreachable, opening the circuit again");
+            Self::open(&mut inner, now);
+            return;
+        }
+
+        let is_within_window = inner.first_failure_at.is_some_and(|first_failure_at| {
+            now.saturating_duration_since(first_failure_at) <= self.config.window
+        });
+        if !is_within_window {
+            inner.consecutive_failures = 0;
+            inner.first_failure_at = Some(now);
+        }
+        inner.consecutive_failures += 1;
+
+        if inner.state == CircuitState::Closed
+            && inner.consecutive_failures >= self.config.threshold
+        {
+            warn!(
+                "{} consecutive upstream failures, opening the circuit",
+                inner.consecutive_failures
+            );
+            Self::open(&mut inner, now);
+        }
+    }
+
+    fn half_open_if_elapsed(&self, inner: &mut Inner, now: Instant) {
+        let is_elapsed = inner.opened_at.is_some_and(|opened_at| {
+            now.saturating_duration_since(opened_at) >= self.config.open_duration
+        });
+
+        if inner.state == CircuitState::Open && is_elapsed {
+            info!("testing the upstream, the circuit is half-open");
+            inner.state = CircuitState::HalfOpen;
+            inner.is_trial_in_flight = false;
+        }
+    }
+
+    fn open(inner: &mut Inner, now: Instant) {
+        inner.state = CircuitState::Open;
+        inner.opened_at = Some(now);
+        inner.consecutive_failures = 0;
+        inner.first_failure_at = None;
+        inner.is_trial_in_flight = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            threshold: 3,
+            window: Duration::from_secs(10),
+            open_duration: Duration::from_secs(5),
+        })
+    }
+
+    #[test]
+    fn test_circuit_opens_after_threshold() {
+        let circuit_breaker = circuit_breaker();
+        let now = Instant::now();
+
+        for i in 0..2 {
+            assert!(circuit_breaker.try_acquire_at(now));
+            circuit_breaker.record_failure_at(now + Duration::from_secs(i));
+        }
+        assert_eq!(circuit_breaker.state_at(now), CircuitState::Closed);
+
+        circuit_breaker.record_failure_at(now + Duration::from_secs(2));
+        assert_eq!(circuit_breaker.state_at(now), CircuitState::Open);
+        assert!(!circuit_breaker.try_acquire_at(now + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_failures_outside_window() {
+        let circuit_breaker = circuit_breaker();
+        let now = Instant::now();
+
+        circuit_breaker.record_failure_at(now);
+        circuit_breaker.record_failure_at(now + Duration::from_secs(1));
+        circuit_breaker.record_failure_at(now + Duration::from_secs(11));
+        assert_eq!(circuit_breaker.state_at(now), CircuitState::Closed);
+
+        // a success resets the consecutive failures
+        circuit_breaker.record_failure_at(now + Duration::from_secs(12));
+        circuit_breaker.record_success();
+        circuit_breaker.record_failure_at(now + Duration::from_secs(13));
+        assert_eq!(circuit_breaker.state_at(now), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_transitions() {
+        let circuit_breaker = circuit_breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            circuit_breaker.record_failure_at(now);
+        }
+
+        let half_open_at = now + Duration::from_secs(5);
+        assert_eq!(
+            circuit_breaker.state_at(half_open_at),
+            CircuitState::HalfOpen
+        );
+        assert!(circuit_breaker.try_acquire_at(half_open_at));
+        // only a single trial request is let through
+        assert!(!circuit_breaker.try_acquire_at(half_open_at));
+
+        circuit_breaker.record_failure_at(half_open_at);
+        assert_eq!(circuit_breaker.state_at(half_open_at), CircuitState::Open);
+
+        let half_open_at = half_open_at + Duration::from_secs(5);
+        assert!(circuit_breaker.try_acquire_at(half_open_at));
+        circuit_breaker.record_success();
+        assert_eq!(circuit_breaker.state_at(half_open_at), CircuitState::Closed);
+        assert!(circuit_breaker.try_acquire_at(half_open_at));
+    }
+}
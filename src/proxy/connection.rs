@@ -1,114 +1,628 @@
-use crate::proxy::faulter::{Context, Faulter, RequestAction};
-use crate::proxy::resp_util::get_host_name;
-use anyhow::anyhow;
+use crate::proxy::circuit_breaker::CircuitBreaker;
+use crate::proxy::connection_pool::ConnectionPool;
+use crate::proxy::faulter::{ClientIdMode, ClientIdentity, Context, Faulter, RequestAction};
+use crate::proxy::load_balancer::{ActiveConnections, LbStrategy, ServerStreamGuard};
+use crate::proxy::logging::{RequestResponseLogger, MAX_LOGGED_PAYLOAD_BYTES};
+use crate::proxy::proxy_protocol;
+use crate::proxy::resp_util::{self, get_host_name};
+use crate::proxy::upstream_health::UpstreamHealthMonitor;
+use crate::telemetry;
+use anyhow::{anyhow, Context as _};
 use bytes::Bytes;
-use futures::stream::{Stream, StreamExt, TryStreamExt};
+use futures::stream::StreamExt;
+use rand::Rng;
+use socket2::{SockRef, Socket, TcpKeepalive};
 use std::borrow::Borrow;
-use std::net::ToSocketAddrs;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
     io,
-    io::{AsyncRead, AsyncWrite, AsyncWriteExt, Result as TokioResult},
-    net::TcpStream,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Result as TokioResult},
+    net::{TcpStream, UnixStream},
 };
-use tokio_native_tls::{native_tls::TlsConnector, TlsStream};
+use tokio_native_tls::{
+    native_tls::{self, Certificate, Identity},
+    TlsConnector, TlsStream,
+};
+use tokio_socks::tcp::Socks5Stream;
 use tokio_util::codec;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn, Instrument};
 use uuid::Uuid;
 
+/// The error returned to the clients while the circuit breaker is open
+const UPSTREAM_UNAVAILABLE: &str = "ERR upstream unavailable";
+
+/// The `command` label of the request duration of a request whose command can't be decoded
+const UNKNOWN_COMMAND: &str = "UNKNOWN";
+
 /// Connection is the core of the proxy.
 ///
 /// Handles client's connection as follows.
 ///
 /// - Checks if it has to apply any fault by checking the request payload against the
-/// configured faults.  
+///   configured faults.
 /// - If the request matches with any fault, it executes it.
 /// - If no fault matches with the request payload, the request will be proxied to the origin
-/// server without any changes to the request payload.
+///   server without any changes to the request payload.
 #[derive(Clone)]
 pub struct Connection {
     faulter: Faulter,
     origin_server_config: OriginServerConfig,
+    pool: ConnectionPool,
+    health_monitor: Option<UpstreamHealthMonitor>,
+    circuit_breaker: Option<CircuitBreaker>,
+    tls_connector: TlsConnector,
+    request_logging: bool,
+    tcp_keepalive: Option<Duration>,
+    tcp_buffer_sizes: TcpBufferSizes,
+    idle_timeout: Option<Duration>,
+    upstream_keep_alive: bool,
+    proxy_protocol: bool,
+    client_id_mode: ClientIdMode,
+    upstream_socks5_proxy: Option<String>,
+    lb_strategy: LbStrategy,
+    active_connections: ActiveConnections,
+    next_upstream: Arc<AtomicUsize>,
 }
 
 /// OriginServerConfig represents the configuration needed to connect to the origin server
 #[derive(Clone)]
 pub struct OriginServerConfig {
-    pub server_addr: String,
+    /// The addresses of the origin servers across which the connections are load balanced, see
+    /// [Connection::with_lb_strategy]
+    pub server_addrs: Vec<String>,
     pub is_tls_conn: bool,
+    /// The number of idle connections to the origin server kept ready in the pool. Zero disables
+    /// the pooling.
+    pub pool_size: usize,
+}
+
+/// ConnectionState tracks the state of a client connection that the faults depend on.
+#[derive(Debug, Default)]
+pub struct ConnectionState {
+    /// The logical database selected by the client with `SELECT`, `0` by default
+    pub database: u8,
+    pub transaction: TransactionState,
+    /// Whether a command altering the session of the origin server connection kept alive across
+    /// the requests, e.g. `SELECT`, was proxied, see [resp_util::SESSION_COMMANDS]
+    pub upstream_session: bool,
+}
+
+/// TransactionState tracks the `MULTI` / `EXEC` transaction of a client connection.
+///
+/// The commands queued by `MULTI` are not faulted right away, as Redis only replies `+QUEUED` to
+/// them. Their faults are applied to their results in the reply of `EXEC` instead.
+#[derive(Debug, Default)]
+pub struct TransactionState {
+    pub in_multi: bool,
+    /// The RESP encoded commands queued since `MULTI`
+    pub queued_commands: Vec<String>,
+}
+
+/// UpstreamTlsConfig holds the optional client certificate and CA certificate of the TLS
+/// connections to the origin servers.
+#[derive(Clone, Debug, Default)]
+pub struct UpstreamTlsConfig {
+    /// The path of the PEM encoded client certificate, optionally followed by its intermediate
+    /// certificates
+    pub client_cert_path: Option<String>,
+    /// The path of the PEM encoded PKCS#8 private key (`BEGIN PRIVATE KEY`) of the client
+    /// certificate
+    pub client_key_path: Option<String>,
+    /// The path of the PEM encoded CA certificate that the origin server certificates must be
+    /// signed by. It replaces the root certificates of the system.
+    pub ca_cert_path: Option<String>,
+    /// The minimum TLS version, the default of the platform TLS library when not set
+    pub min_version: Option<native_tls::Protocol>,
+    /// The maximum TLS version, the latest supported version when not set
+    pub max_version: Option<native_tls::Protocol>,
+    /// The cipher suites, which the platform TLS library doesn't support configuring
+    pub cipher_suites: Option<Vec<String>>,
+}
+
+impl UpstreamTlsConfig {
+    /// Builds the TLS connector of the origin server connections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when only one of the client certificate and key is set, when a file can't
+    /// be read, or when the certificate or the key is malformed.
+    fn connector(&self) -> Result<TlsConnector, anyhow::Error> {
+        let mut builder = native_tls::TlsConnector::builder();
+
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(client_cert_path), Some(client_key_path)) => {
+                let client_cert = read_tls_file(client_cert_path, "client certificate")?;
+                let client_key = read_tls_file(client_key_path, "client key")?;
+                let identity = Identity::from_pkcs8(&client_cert, &client_key).map_err(|err| {
+                    anyhow!(
+                        "error loading the client certificate {} and key {}: {}",
+                        client_cert_path,
+                        client_key_path,
+                        err
+                    )
+                })?;
+                builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow!(
+                    "both the client certificate and the client key must be set for mutual TLS"
+                ))
+            }
+        }
+
+        if let Some(ca_cert_path) = &self.ca_cert_path {
+            let ca_cert = read_tls_file(ca_cert_path, "CA certificate")?;
+            let ca_cert = Certificate::from_pem(&ca_cert).map_err(|err| {
+                anyhow!("error loading the CA certificate {}: {}", ca_cert_path, err)
+            })?;
+            builder
+                .add_root_certificate(ca_cert)
+                .disable_built_in_roots(true);
+        }
+
+        builder
+            .min_protocol_version(self.min_version)
+            .max_protocol_version(self.max_version);
+
+        if let Some(cipher_suites) = &self.cipher_suites {
+            warn!(
+                "ignoring the TLS cipher suites {:?}, the platform TLS library doesn't support configuring them",
+                cipher_suites
+            );
+        }
+
+        Ok(TlsConnector::from(builder.build()?))
+    }
 }
 
-trait AsyncReadWrite: AsyncRead + AsyncWrite + Unpin + Send {}
+/// Reads the TLS file at the given path, naming the file in the error.
+fn read_tls_file(path: &str, name: &str) -> Result<Vec<u8>, anyhow::Error> {
+    std::fs::read(path).map_err(|err| anyhow!("error reading the {} {}: {}", name, path, err))
+}
+
+pub trait AsyncReadWrite: AsyncRead + AsyncWrite + Unpin + Send {}
 
 /// The TcpStream and TlsStream implements the trait AsyncReadWrite which is a super trait of
 /// tokio::io::AsyncRead and tokio::io::AsyncWrite.
 impl AsyncReadWrite for TcpStream {}
 impl AsyncReadWrite for TlsStream<TcpStream> {}
+impl AsyncReadWrite for UnixStream {}
 
-/// into_bytes_stream converts the given object (implements AsyncRead) into Stream of Bytes.
+/// into_bytes_stream converts the given object (implements AsyncRead) into Stream of byte
+/// buffers. The object can be taken back with `into_inner`.
 ///
 /// # Arguments
 /// - R implements tokio::io::AsyncRead
-fn into_bytes_stream<R>(r: R) -> impl Stream<Item = TokioResult<Bytes>>
+fn into_bytes_stream<R>(r: R) -> codec::FramedRead<R, codec::BytesCodec>
 where
     R: AsyncRead,
 {
-    codec::FramedRead::new(r, codec::BytesCodec::new()).map_ok(|bytes| bytes.freeze())
+    codec::FramedRead::new(r, codec::BytesCodec::new())
+}
+
+/// Enables the TCP keepalive probes on the socket of the stream once it is idle for `time`.
+fn set_tcp_keepalive(stream: &TcpStream, time: Duration) -> std::io::Result<()> {
+    SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))
+}
+
+/// TcpBufferSizes are the sizes of the socket send and receive buffers in bytes, the OS default
+/// being kept for the ones that are not set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TcpBufferSizes {
+    pub send: Option<usize>,
+    pub recv: Option<usize>,
+}
+
+/// Sets the socket buffer sizes of the stream and logs the sizes granted by the OS, which may
+/// differ from the requested ones, e.g. Linux doubles them and caps them at `net.core.wmem_max`
+/// and `net.core.rmem_max`.
+fn set_tcp_buffer_sizes(stream: &TcpStream, sizes: TcpBufferSizes) -> std::io::Result<()> {
+    let socket = SockRef::from(stream);
+    if let Some(send) = sizes.send {
+        socket.set_send_buffer_size(send)?;
+        info!(
+            "TCP send buffer size of {} bytes requested, {} bytes granted",
+            send,
+            socket.send_buffer_size()?
+        );
+    }
+    if let Some(recv) = sizes.recv {
+        socket.set_recv_buffer_size(recv)?;
+        info!(
+            "TCP receive buffer size of {} bytes requested, {} bytes granted",
+            recv,
+            socket.recv_buffer_size()?
+        );
+    }
+
+    Ok(())
+}
+
+/// copy_slowly copies the reader into the writer in chunks of `bytes_per_ms` bytes, sleeping for a
+/// millisecond after each chunk. It returns the number of bytes copied.
+async fn copy_slowly<R, W>(reader: &mut R, writer: &mut W, bytes_per_ms: u64) -> TokioResult<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; bytes_per_ms.max(1) as usize];
+    let mut copied = 0;
+
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(copied);
+        }
+
+        writer.write_all(&buf[0..n]).await?;
+        writer.flush().await?;
+        copied += n as u64;
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
 }
 
 impl Connection {
     /// Creates a new Connection object
     ///
-    /// # To be improved
-    ///
-    /// - Currently, we create a new TCP connection to the origin server for each non-fault request.
-    /// But, this could turn out to be a costly op on a large number of requests. This can be improved
-    /// by TCP connection pooling.
+    /// Each non-fault request uses its own connection to the origin server, unless the upstream
+    /// keep-alive is enabled, see [Connection::with_upstream_keep_alive]. When
+    /// `origin_server_config.pool_size` is non-zero, the connections are established ahead of
+    /// time and kept in a [ConnectionPool], such that the handshake doesn't add to the latency of
+    /// the request.
     pub fn new(
         origin_server_config: OriginServerConfig,
         faulter: Faulter,
     ) -> Result<Self, anyhow::Error> {
+        if origin_server_config.server_addrs.is_empty() {
+            return Err(anyhow!("no origin server address is configured"));
+        }
+
         Ok(Connection {
+            pool: ConnectionPool::new(origin_server_config.pool_size),
             origin_server_config,
             faulter,
+            health_monitor: None,
+            circuit_breaker: None,
+            tls_connector: UpstreamTlsConfig::default().connector()?,
+            request_logging: false,
+            tcp_keepalive: None,
+            tcp_buffer_sizes: TcpBufferSizes::default(),
+            idle_timeout: None,
+            upstream_keep_alive: false,
+            proxy_protocol: false,
+            client_id_mode: ClientIdMode::default(),
+            upstream_socks5_proxy: None,
+            lb_strategy: LbStrategy::default(),
+            active_connections: ActiveConnections::default(),
+            next_upstream: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Skips the origin servers that the given monitor reports as unhealthy.
+    pub fn with_health_monitor(mut self, health_monitor: UpstreamHealthMonitor) -> Self {
+        self.health_monitor = Some(health_monitor);
+        self
+    }
+
+    /// Rejects the requests with an `upstream unavailable` error while the circuit breaker is open.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Connects to the origin servers over TLS with the given client certificate and CA
+    /// certificate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the TLS files can't be loaded, see [UpstreamTlsConfig].
+    pub fn with_tls_config(
+        mut self,
+        tls_config: &UpstreamTlsConfig,
+    ) -> Result<Self, anyhow::Error> {
+        self.tls_connector = tls_config.connector()?;
+        Ok(self)
+    }
+
+    /// Logs the proxied request payloads and the response sizes, see [RequestResponseLogger].
+    pub fn with_request_logging(mut self, request_logging: bool) -> Self {
+        self.request_logging = request_logging;
+        self
+    }
+
+    /// Enables the TCP keepalive probes on the client connections after they are idle for the
+    /// given duration.
+    pub fn with_tcp_keepalive(mut self, tcp_keepalive: Option<Duration>) -> Self {
+        self.tcp_keepalive = tcp_keepalive;
+        self
+    }
+
+    /// Sets the socket buffer sizes of the client connections and of the origin server
+    /// connections.
+    pub fn with_tcp_buffer_sizes(mut self, tcp_buffer_sizes: TcpBufferSizes) -> Self {
+        self.tcp_buffer_sizes = tcp_buffer_sizes;
+        self
+    }
+
+    /// Closes the client connections that don't send a request within the given duration.
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Keeps the client connections open after the requests without a fault, and reuses the
+    /// origin server connection of a client connection across its requests, see
+    /// [Connection::proxy_kept_alive]. Otherwise, the client connection is closed once the response
+    /// of its first request is proxied.
+    pub fn with_upstream_keep_alive(mut self, upstream_keep_alive: bool) -> Self {
+        self.upstream_keep_alive = upstream_keep_alive;
+        self
+    }
+
+    /// Connects to the origin servers through the SOCKS5 proxy of the given `host:port` address,
+    /// see [Connection::connect_server]. The origin servers are connected to directly when it is
+    /// None.
+    pub fn with_upstream_socks5_proxy(mut self, upstream_socks5_proxy: Option<String>) -> Self {
+        self.upstream_socks5_proxy = upstream_socks5_proxy;
+        self
+    }
+
+    /// Picks the origin server of the new upstream connections with the given strategy, in the
+    /// round-robin order by default. With [LbStrategy::LeastConnections], the connections are
+    /// counted as active from their checkout until they are dropped.
+    pub fn with_lb_strategy(mut self, lb_strategy: LbStrategy) -> Self {
+        self.lb_strategy = lb_strategy;
+        self
+    }
+
+    /// Reads the proxy protocol v2 header prepended to the client connections by a load balancer,
+    /// see [proxy_protocol::read_header]. The original client address of the header identifies
+    /// the client with [ClientIdMode::ProxyProtocol].
+    pub fn with_proxy_protocol(mut self, proxy_protocol: bool) -> Self {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
+    /// Resolves the [ClientIdentity] the faults are matched against with the given mode, the TCP
+    /// peer address by default.
+    pub fn with_client_id_mode(mut self, client_id_mode: ClientIdMode) -> Self {
+        self.client_id_mode = client_id_mode;
+        self
+    }
+
+    /// Applies only the faults that are not scoped to another proxy port than the given port of the
+    /// listener the connections are accepted on, see [Faulter::with_proxy_port].
+    pub fn with_proxy_port(mut self, proxy_port: Option<u16>) -> Self {
+        self.faulter = self.faulter.with_proxy_port(proxy_port);
+        self
+    }
+
+    /// Returns the address of the next origin server picked by the [LbStrategy], skipping the
+    /// unhealthy ones. When no origin server is healthy, the unhealthy ones are still tried.
+    fn next_server_addr(&self) -> &str {
+        let server_addrs = &self.origin_server_config.server_addrs;
+        let start = match self.lb_strategy {
+            LbStrategy::RoundRobin | LbStrategy::LeastConnections => {
+                self.next_upstream.fetch_add(1, Ordering::Relaxed)
+            }
+            LbStrategy::Random => rand::thread_rng().gen_range(0..server_addrs.len()),
+        };
+        let nth_server_addr = |i: usize| server_addrs[(start + i) % server_addrs.len()].as_str();
+
+        let mut healthy_server_addrs =
+            (0..server_addrs.len())
+                .map(nth_server_addr)
+                .filter(|server_addr| {
+                    self.health_monitor
+                        .as_ref()
+                        .is_none_or(|monitor| monitor.is_healthy(server_addr))
+                });
+        let server_addr = match self.lb_strategy {
+            LbStrategy::LeastConnections => healthy_server_addrs
+                .min_by_key(|server_addr| self.active_connections.count(server_addr)),
+            LbStrategy::RoundRobin | LbStrategy::Random => healthy_server_addrs.next(),
+        };
+
+        server_addr.unwrap_or_else(|| nth_server_addr(0))
+    }
+
+    /// Fills the connection pool with idle connections to every origin server until it is full.
+    pub async fn fill_pool(&self) {
+        for server_addr in &self.origin_server_config.server_addrs {
+            while self.pool.idle_count(server_addr).await < self.pool.max_size() {
+                match self.create_server_stream(server_addr).await {
+                    Ok(stream) => {
+                        if !self.pool.put(server_addr, stream).await {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        error!("error filling the upstream connection pool: {:?}", err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills the connection pool in the background, see [Connection::fill_pool], unless it is
+    /// already being filled. A single task connects to the origin servers at a time, such that the
+    /// concurrent checkouts don't open connections that the full pool then drops, nor keep dialling
+    /// an origin server that is down.
+    pub fn refill_pool(&self) {
+        if self.pool.max_size() == 0 || !self.pool.start_refill() {
+            return;
+        }
+
+        let conn = self.clone();
+        tokio::spawn(async move {
+            conn.fill_pool().await;
+            conn.pool.end_refill();
+        });
+    }
+
+    /// Spawns the health checks of the origin servers, if any, see
+    /// [Connection::with_health_monitor]. The origin servers are pinged over the connections of
+    /// this Connection, hence it is called once the Connection is configured.
+    pub fn spawn_health_checks(&self) {
+        if let Some(health_monitor) = &self.health_monitor {
+            health_monitor.spawn(self.clone());
+        }
+    }
+
+    /// Changes the number of idle connections to the origin server kept in the pool, and fills the
+    /// pool up to the new size.
+    pub async fn resize_pool(&self, pool_size: usize) {
+        self.pool.resize(pool_size).await;
+        self.refill_pool();
+    }
+
+    /// Borrows a connection to the origin server from the pool, unless `from_pool` is false, and
+    /// refills the pool in the background. When the pool is exhausted or disabled, a new
+    /// connection is created.
+    ///
+    /// Returns the connection along with whether it is taken from the pool, in which case it may
+    /// fail on its first use if the origin server closed it in the meantime.
+    ///
+    /// With [LbStrategy::LeastConnections], the connection is counted as active as soon as its
+    /// origin server is picked, such that the concurrent checkouts spread across the servers, and
+    /// until the returned [ServerStreamGuard] is dropped.
+    async fn checkout_server_stream(
+        &self,
+        from_pool: bool,
+    ) -> Result<(Box<dyn AsyncReadWrite>, bool), anyhow::Error> {
+        let server_addr = self.next_server_addr();
+        let active_connection = (self.lb_strategy == LbStrategy::LeastConnections)
+            .then(|| self.active_connections.track(server_addr));
+
+        let (stream, pooled) = if self.pool.max_size() == 0 || !from_pool {
+            (self.create_server_stream(server_addr).await?, false)
+        } else {
+            let pooled_stream = self.pool.get(server_addr).await;
+            self.refill_pool();
+            match pooled_stream {
+                Some(stream) => (stream, true),
+                None => (self.create_server_stream(server_addr).await?, false),
+            }
+        };
+
+        let stream: Box<dyn AsyncReadWrite> = match active_connection {
+            Some(active_connection) => Box::new(ServerStreamGuard::new(stream, active_connection)),
+            None => stream,
+        };
+        Ok((stream, pooled))
+    }
+
+    /// Returns the origin server connection kept alive by the previous requests of the client
+    /// connection, or checks out a new one, see [Connection::checkout_server_stream]. The outcome
+    /// is recorded by the circuit breaker.
+    ///
+    /// Returns the connection along with whether it is reused, i.e. kept alive or taken from the
+    /// pool, rather than just established.
+    async fn server_stream(
+        &self,
+        kept_alive_stream: Option<Box<dyn AsyncReadWrite>>,
+        from_pool: bool,
+    ) -> Result<(Box<dyn AsyncReadWrite>, bool), anyhow::Error> {
+        let server_stream = match kept_alive_stream {
+            Some(server_stream) => Ok((server_stream, true)),
+            None => self.checkout_server_stream(from_pool).await,
+        };
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            match server_stream {
+                Ok(_) => circuit_breaker.record_success(),
+                Err(_) => circuit_breaker.record_failure(),
+            }
+        }
+
+        server_stream.map_err(|err| {
+            error!("error creating server stream: {:?}", err);
+            err
         })
     }
 
+    /// Sets the configured buffer sizes of the socket of the stream, see
+    /// [Connection::with_tcp_buffer_sizes]. A failure is only logged, the OS default sizes being
+    /// good enough to proxy the connection.
+    fn set_tcp_buffer_sizes(&self, stream: &TcpStream, peer: &str) {
+        if self.tcp_buffer_sizes == TcpBufferSizes::default() {
+            return;
+        }
+        if let Err(err) = set_tcp_buffer_sizes(stream, self.tcp_buffer_sizes) {
+            error!(
+                "error setting the TCP buffer sizes of the {} socket: {}",
+                peer, err
+            );
+        }
+    }
+
+    /// Connects to the origin server of server_addr over TCP, through the SOCKS5 proxy if any, see
+    /// [Connection::with_upstream_socks5_proxy]. The host name of server_addr is then resolved by
+    /// the SOCKS5 proxy rather than locally.
+    ///
+    /// # Errors
+    ///
+    /// - When the server of server_addr is not reachable, this method will return error like
+    ///   `ConnectionRefused`.
+    /// - When the SOCKS5 proxy is not reachable or fails to connect to the server.
+    async fn connect_server(&self, server_addr: &str) -> Result<TcpStream, anyhow::Error> {
+        let tcp_stream = match &self.upstream_socks5_proxy {
+            Some(socks5_proxy) => {
+                debug!(
+                    "connecting to {} through the SOCKS5 proxy {}",
+                    server_addr, socks5_proxy
+                );
+                Socks5Stream::connect(socks5_proxy.as_str(), server_addr)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "error connecting to {} through the SOCKS5 proxy {}",
+                            server_addr, socks5_proxy
+                        )
+                    })?
+                    .into_inner()
+            }
+            None => TcpStream::connect(server_addr).await?,
+        };
+        self.set_tcp_buffer_sizes(&tcp_stream, "origin server");
+
+        Ok(tcp_stream)
+    }
+
     /// Creates a new TCP server stream object.
     ///
     /// # Errors
     ///
     /// - When the server of server_addr is not reachable, this method will return error like
-    /// `ConnectionRefused`.
-    async fn new_tcp_stream(&self) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
-        let tcp_stream = TcpStream::connect(&self.origin_server_config.server_addr).await?;
+    ///   `ConnectionRefused`.
+    async fn new_tcp_stream(
+        &self,
+        server_addr: &str,
+    ) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
+        let tcp_stream = self.connect_server(server_addr).await?;
         Ok(Box::new(tcp_stream))
     }
 
-    /// Creates a new TLS over TCP server stream object.
+    /// Creates a new TLS over TCP server stream object. The client certificate, if any, is
+    /// presented to the server, see [Connection::with_tls_config]. With a SOCKS5 proxy, the TLS
+    /// session is tunneled end to end through it.
     ///
     /// # Errors
     /// - When the server of server_addr is not reachable, this method will return error like
-    /// `ConnectionRefused`.
-    async fn new_tls_stream(&self) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
-        let server_addr = self
-            .origin_server_config
-            .server_addr
-            .as_str()
-            .to_socket_addrs()?
-            .next()
-            .ok_or_else(|| {
-                anyhow!(
-                    "error failed to resolve server address: {}",
-                    self.origin_server_config.server_addr,
-                )
-            })?;
-
-        let tcp_stream = TcpStream::connect(&server_addr).await?;
-        let tls_connector = TlsConnector::builder().build()?;
-        let tls_connector = tokio_native_tls::TlsConnector::from(tls_connector);
+    ///   `ConnectionRefused`.
+    async fn new_tls_stream(
+        &self,
+        server_addr: &str,
+    ) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
+        let tcp_stream = self.connect_server(server_addr).await?;
 
-        let host_name = get_host_name(&self.origin_server_config.server_addr.clone())?;
-        let tls_stream = tls_connector.connect(&host_name, tcp_stream).await?;
+        let host_name = get_host_name(server_addr)?;
+        let tls_stream = self.tls_connector.connect(&host_name, tcp_stream).await?;
 
         Ok(Box::new(tls_stream))
     }
@@ -118,153 +632,799 @@ impl Connection {
     /// If TLS connection is enabled, this method returns a TLS connection over TCP stream to the
     /// server.
     /// Else, it returns a raw TCP connection stream to the server.
-    async fn create_server_stream(&self) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
+    pub(crate) async fn create_server_stream(
+        &self,
+        server_addr: &str,
+    ) -> Result<Box<dyn AsyncReadWrite>, anyhow::Error> {
         let stream = if self.origin_server_config.is_tls_conn {
-            info!(
-                "establishing tls connection to {}",
-                self.origin_server_config.server_addr
-            );
-            self.new_tls_stream().await?
+            info!("establishing tls connection to {}", server_addr);
+            self.new_tls_stream(server_addr).await?
         } else {
-            info!(
-                "establishing tcp connection to {}",
-                self.origin_server_config.server_addr
-            );
-            self.new_tcp_stream().await?
+            info!("establishing tcp connection to {}", server_addr);
+            self.new_tcp_stream(server_addr).await?
         };
 
         Ok(stream)
     }
 
-    /// handle is the core of the proxy connection handling. It handles the connection between
-    /// the client and the origin server. When no faults are configured, handle will act as a typical
-    /// proxy; forwards all the requests to the server.
+    /// apply_faults checks and executes the faults for every command in the request payload.
     ///
-    /// Based on the endpoint, handle will decided to establish a tcp connection or a tls
-    /// connection over the tcp stream. In the connection pipeline, it checks if the request
-    /// matches with any fault plan. If so, appropriate fault (delay / custom error) will be applied.
+    /// Redis clients may pipeline multiple commands in a single request payload, either RESP
+    /// encoded or as inline commands, e.g. `SET foo bar\r\n`. Each command is checked against the
+    /// configured faults independently. When a command's fault results in [RequestAction::Exit],
+    /// the rest of the pipeline is not processed and the connection is closed. Otherwise, the
+    /// first action that alters the response (e.g. partial response) is returned, or
+    /// [RequestAction::Fallthrough] if there is none.
     ///
-    #[tracing::instrument(
-    name = "Handling connection",
-        skip(self),
-        fields(
-            request_id = %Uuid::new_v4(),
-        )
-    )]
-    pub async fn handle(self, mut inbound_stream: TcpStream) -> Result<(), anyhow::Error> {
-        let (client_read_stream, mut client_write_stream) = inbound_stream.split();
+    /// The commands of a `MULTI` / `EXEC` transaction are queued in the transaction state until
+    /// `EXEC`, see [Connection::apply_queued_faults]. `MULTI`, `EXEC` and `DISCARD` are not
+    /// faulted.
+    ///
+    /// The database selected by `SELECT` is tracked in the connection state, such that the faults
+    /// scoped to a database only apply to the commands sent to it.
+    ///
+    /// Every command of the request payload is counted once in `proxy_requests_total`, including
+    /// the commands of a transaction, whether they are faulted or not.
+    async fn apply_faults<'a, 'b>(
+        &self,
+        ctx: &mut Context<'a, 'b>,
+        state: &mut ConnectionState,
+        req_payload_str: &str,
+    ) -> Result<RequestAction, anyhow::Error> {
+        let commands = resp_util::split_pipeline(req_payload_str).map_err(|err| {
+            error!("error splitting the request payload into commands: {}", err);
+            err
+        })?;
+        debug!("request payload has {} command(s)", commands.len());
 
-        // convert the AsyncRead into a stream of byte buffers
-        let mut client_stream = into_bytes_stream(client_read_stream).map(|buf| buf);
+        let mut response_action = RequestAction::Fallthrough;
 
-        let mut req_bytes: Bytes = Bytes::new();
-        if let Some(data) = client_stream.next().await {
-            match data {
-                Ok(data) => req_bytes = data,
-                Err(err) => {
-                    error!("error converting request bytes into streams: {}", err);
-                    return Err(err.into());
+        for (reply_index, command) in commands.into_iter().enumerate() {
+            self.faulter.metrics().proxy_requests_total.inc();
+
+            let decoded_command = resp_util::decode(&command);
+            let redis_command = decoded_command
+                .as_ref()
+                .ok()
+                .and_then(|val| resp_util::fetch_redis_command(val.clone()).ok())
+                .unwrap_or_default();
+
+            if redis_command.eq_ignore_ascii_case("SELECT") {
+                match decoded_command
+                    .as_ref()
+                    .ok()
+                    .and_then(resp_util::fetch_redis_key)
+                    .and_then(|database| database.parse().ok())
+                {
+                    Some(database) => {
+                        debug!("client selected database {}", database);
+                        state.database = database;
+                    }
+                    None => debug!("invalid database of the SELECT command"),
                 }
             }
-        };
 
-        let req_payload_str = std::str::from_utf8(&req_bytes)?;
-        debug!("request payload bytes: {:?}", req_payload_str);
+            let transaction = &mut state.transaction;
+            if redis_command.eq_ignore_ascii_case("MULTI") {
+                transaction.in_multi = true;
+                transaction.queued_commands.clear();
+                continue;
+            }
 
-        let fault = self
-            .faulter
-            .check_fault(req_payload_str)
-            .await
-            .map_err(|err| {
-                error!("error checking fault for a given request: {}", err);
-                err
-            })?;
+            if transaction.in_multi {
+                if redis_command.eq_ignore_ascii_case("DISCARD") {
+                    *transaction = TransactionState::default();
+                } else if redis_command.eq_ignore_ascii_case("EXEC") {
+                    let queued_commands = std::mem::take(&mut transaction.queued_commands);
+                    transaction.in_multi = false;
 
-        let mut ctx = Context {
-            client_tcp_write_stream: &mut client_write_stream,
-        };
+                    match self
+                        .apply_queued_faults(ctx, state.database, reply_index, queued_commands)
+                        .await?
+                    {
+                        RequestAction::Exit => return Ok(RequestAction::Exit),
+                        RequestAction::Fallthrough => {}
+                        action => {
+                            if response_action == RequestAction::Fallthrough {
+                                response_action = action;
+                            }
+                        }
+                    }
+                } else {
+                    transaction.queued_commands.push(command);
+                }
+                continue;
+            }
+
+            let fault = self
+                .faulter
+                .check_fault(
+                    &ctx.request_id,
+                    &command,
+                    ctx.client_identity.addr(),
+                    state.database,
+                )
+                .await
+                .map_err(|err| {
+                    error!("error checking fault for a given request: {}", err);
+                    err
+                })?;
+
+            match self.faulter.execute_fault(ctx, fault).await? {
+                RequestAction::Exit => return Ok(RequestAction::Exit),
+                RequestAction::Fallthrough => {}
+                action => {
+                    if response_action == RequestAction::Fallthrough {
+                        response_action = action;
+                    }
+                }
+            }
+        }
+
+        Ok(response_action)
+    }
+
+    /// Applies the faults of the commands queued in a transaction once it is executed by the
+    /// `EXEC` command, whose reply is the `exec_reply_index`th reply of the request, on the given
+    /// database.
+    ///
+    /// An error fault fails only its command, i.e. the error replaces the result of the command in
+    /// the reply of `EXEC`. The other faults are executed as for the commands outside of a
+    /// transaction, see [Connection::apply_faults].
+    async fn apply_queued_faults<'a, 'b>(
+        &self,
+        ctx: &mut Context<'a, 'b>,
+        database: u8,
+        exec_reply_index: usize,
+        queued_commands: Vec<String>,
+    ) -> Result<RequestAction, anyhow::Error> {
+        let mut response_action = RequestAction::Fallthrough;
+        let mut errors = Vec::with_capacity(queued_commands.len());
+
+        for command in queued_commands {
+            let fault = self
+                .faulter
+                .check_fault(
+                    &ctx.request_id,
+                    &command,
+                    ctx.client_identity.addr(),
+                    database,
+                )
+                .await?;
 
-        match self.faulter.execute_fault(&mut ctx, fault).await? {
-            RequestAction::Exit => {
-                info!("exiting  request processing");
-                return Ok(());
+            let error = self
+                .faulter
+                .execute_queued_error_fault(ctx.client_identity.addr(), fault.as_ref())
+                .await?;
+            if error.is_some() {
+                errors.push(error);
+                continue;
             }
-            RequestAction::Fallthrough => {
-                info!("continuing request processing");
+            errors.push(None);
+
+            match self.faulter.execute_fault(ctx, fault).await? {
+                RequestAction::Exit => return Ok(RequestAction::Exit),
+                RequestAction::Fallthrough => {}
+                action => {
+                    if response_action == RequestAction::Fallthrough {
+                        response_action = action;
+                    }
+                }
             }
         }
 
-        let server_stream = self.create_server_stream().await.map_err(|err| {
-            error!("error creating server stream: {:?}", err);
-            err
-        })?;
+        if response_action == RequestAction::Fallthrough && errors.iter().any(Option::is_some) {
+            response_action = RequestAction::FailQueuedCommands {
+                exec_reply_index,
+                errors,
+            };
+        }
 
-        let (mut server_read_stream, mut server_write_stream) = tokio::io::split(server_stream);
+        Ok(response_action)
+    }
 
-        let client_to_server = async {
-            io::copy(&mut req_bytes.borrow(), &mut server_write_stream).await?;
-            info!("request proxied to the server");
-            server_write_stream.shutdown().await
+    /// handle handles the connection of a TCP client, see [Connection::handle_stream].
+    ///
+    /// The client socket is duplicated before the stream is handed over, such that the TCP reset
+    /// fault can still set the socket options once the stream is split.
+    ///
+    /// When the proxy protocol is enabled, the header is read off the stream beforehand, see
+    /// [Connection::with_proxy_protocol]. A connection with an invalid header is closed. The
+    /// identity of the client is then resolved by the [ClientIdMode].
+    pub async fn handle(self, mut inbound_stream: TcpStream) -> Result<(), anyhow::Error> {
+        let client_addr = inbound_stream.peer_addr().ok();
+        let proxy_protocol_addr = if self.proxy_protocol {
+            let header = proxy_protocol::read_header(&mut inbound_stream);
+            let header = match self.idle_timeout {
+                Some(idle_timeout) => match tokio::time::timeout(idle_timeout, header).await {
+                    Ok(header) => header,
+                    Err(_) => {
+                        info!("closing the connection idle for {:?}", idle_timeout);
+                        return Ok(());
+                    }
+                },
+                None => header.await,
+            };
+            header.map_err(|err| {
+                error!(
+                    "error reading the proxy protocol header of {:?}: {:?}",
+                    client_addr, err
+                );
+                err
+            })?
+        } else {
+            None
         };
+        if let Some(tcp_keepalive) = self.tcp_keepalive {
+            if let Err(err) = set_tcp_keepalive(&inbound_stream, tcp_keepalive) {
+                error!(
+                    "error enabling the TCP keepalive of the client socket: {}",
+                    err
+                );
+            }
+        }
+        self.set_tcp_buffer_sizes(&inbound_stream, "client");
+        let client_socket = SockRef::from(&inbound_stream)
+            .try_clone()
+            .map_err(|err| error!("error duplicating the client socket: {}", err))
+            .ok();
+        let client_identity = self
+            .client_id_mode
+            .client_identity(client_addr, proxy_protocol_addr);
+        self.handle_stream(inbound_stream, client_addr, client_identity, client_socket)
+            .await
+    }
 
-        let server_to_client = async {
-            io::copy(&mut server_read_stream, &mut client_write_stream).await?;
-            info!("response proxied to the client");
-            client_write_stream.shutdown().await
+    /// handle_stream is the core of the proxy connection handling. It handles the connection
+    /// between the client and the origin server. When no faults are configured, handle will act as
+    /// a typical proxy; forwards all the requests to the server.
+    ///
+    /// Based on the endpoint, handle will decided to establish a tcp connection or a tls
+    /// connection over the tcp stream. In the connection pipeline, it checks if the request
+    /// matches with any fault plan. If so, appropriate fault (delay / custom error) will be applied.
+    ///
+    /// A request id is generated once per connection and recorded on the `Handling connection`
+    /// span. The same id is handed over to the faulter so that the `Check fault` and
+    /// `Executing fault` child spans carry it as well. When the spans are exported to
+    /// OpenTelemetry, the request id is the trace id as well.
+    ///
+    /// The client connection can be any stream, e.g. a TCP or a Unix domain socket stream, while the
+    /// origin server is always reached over TCP / TLS. The faults are matched against the given
+    /// client identity, while the client address is only logged.
+    ///
+    /// A request with a `SUBSCRIBE`, `PSUBSCRIBE`, `UNSUBSCRIBE` or `PUNSUBSCRIBE` command switches
+    /// the connection into the PubSub mode, where it is piped to the origin server as is and no
+    /// fault is injected, see [Connection::pipe].
+    ///
+    /// The duration of the other requests, from the time they are read to the time their response
+    /// is proxied, is recorded in the `red_monkey_request_duration_milliseconds` histogram by the
+    /// first command of the request, whether a fault is applied or not.
+    pub async fn handle_stream<S>(
+        self,
+        inbound_stream: S,
+        client_addr: Option<SocketAddr>,
+        client_identity: ClientIdentity,
+        client_socket: Option<Socket>,
+    ) -> Result<(), anyhow::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let request_id = Uuid::new_v4();
+        let span = tracing::info_span!(
+            "Handling connection",
+            request_id = %request_id,
+            client_addr = ?client_addr,
+            client_identity = ?client_identity,
+            client_socket = ?client_socket,
+        );
+        telemetry::set_trace_id(&span, &request_id);
+
+        self.handle_request(request_id, inbound_stream, client_identity, client_socket)
+            .instrument(span)
+            .await
+    }
+
+    /// Pipes the client connection in the PubSub mode, or after the `HELLO` handshake, to the
+    /// origin server with `copy_bidirectional`, without injecting any fault.
+    ///
+    /// Once subscribed, the server pushes the published messages at any time and the client may
+    /// send further (un)subscribe commands on the same connection. Likewise, a RESP3 server
+    /// pushes messages, e.g. the client side caching invalidations, at any time once `HELLO 3`
+    /// is negotiated. The request / response framing of the other commands doesn't apply, hence
+    /// the connection is proxied as is until either side closes it.
+    ///
+    /// The origin server connection kept alive by the previous requests, if any, is piped, such that
+    /// the session of the connection is kept.
+    async fn pipe<S>(
+        &self,
+        req_bytes: Bytes,
+        mut inbound_stream: S,
+        kept_alive_stream: Option<Box<dyn AsyncReadWrite>>,
+    ) -> Result<(), anyhow::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut server_stream = match kept_alive_stream {
+            Some(server_stream) => server_stream,
+            None => {
+                self.checkout_server_stream(true)
+                    .await
+                    .map_err(|err| {
+                        error!("error creating server stream: {:?}", err);
+                        err
+                    })?
+                    .0
+            }
         };
+        server_stream.write_all(&req_bytes).await?;
 
-        let _ = tokio::try_join!(client_to_server, server_to_client)?;
+        let (client_to_server, server_to_client) =
+            io::copy_bidirectional(&mut inbound_stream, &mut server_stream).await?;
+        info!(
+            "Passthrough connection closed after proxying {} bytes to the server and {} bytes to the client",
+            req_bytes.len() as u64 + client_to_server,
+            server_to_client
+        );
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use crate::{
-        proxy,
-        store::{
-            self,
-            fault_store::{Fault, FaultVariants, DB},
-        },
-    };
-    use std::io::{Read, Write};
-    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener};
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::thread;
-    use std::time::Instant;
-    use tokio::io::AsyncReadExt;
-    use tokio::net::TcpStream;
+    /// Proxies the request to the origin server and its response to the client as is with
+    /// `copy_bidirectional`, which polls both directions in a single loop.
+    ///
+    /// The request bytes stand for the read half of the client connection, hence the write half of
+    /// the server stream is shut down once the request is proxied, like the response is followed by
+    /// the shutdown of the client write half.
+    async fn proxy<W, S>(
+        &self,
+        request_id: Uuid,
+        req_bytes: Bytes,
+        client_write_stream: W,
+        server_stream: S,
+    ) -> Result<(), anyhow::Error>
+    where
+        W: AsyncWrite + Unpin,
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut client_stream = io::join(
+            req_bytes.borrow(),
+            RequestResponseLogger::new(client_write_stream),
+        );
+        let mut server_stream = self.request_logger(server_stream);
 
-    /// An individual test in the connection module can run upto 2 TCP servers - mock origin server
-    /// and red-monkey server. By default, cargo runs the tests in parallel, hence we are using an
-    /// Atomic usize integer that can be safely shared across threads to give an exclusive port number
-    /// to each TCP server with the use of `fetch_add` method.  
-    static PORT: AtomicUsize = AtomicUsize::new(0);
+        io::copy_bidirectional(&mut client_stream, &mut server_stream).await?;
+        info!("request and response proxied");
 
-    /// next_test_ip4 returns a socket address with an increasing port number that internally
-    /// uses an `AtomicUsize` for the port number. Hence, calling this function from different
-    /// threads is guarenteed to return a mutually exclusive increasing port number.
-    pub fn next_test_ip4() -> SocketAddr {
-        let port = PORT.fetch_add(1, Ordering::SeqCst) as u16 + 10000;
-        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), port))
-    }
+        if self.request_logging {
+            server_stream.log_request(&request_id);
+            client_stream.writer().log_response(&request_id);
+        }
+
+        Ok(())
+    }
+
+    /// Proxies the request over the origin server connection kept alive by the previous requests
+    /// of the client connection, or over a new one, and keeps both connections open for the next
+    /// request, see [Connection::with_upstream_keep_alive]. This saves the TCP / TLS handshake per
+    /// request of the clients that send several requests over a connection.
+    ///
+    /// As the origin server connection stays open, the end of the response can't be told by its
+    /// closing. Instead, the response is read until it holds a reply per command of the request,
+    /// see [resp_util::message_len], and then written to the client at once.
+    ///
+    /// When the kept alive connection, or the connection taken from the pool, fails before any reply
+    /// is read, e.g. the origin server closed it while it was idle, the request is retried once
+    /// over a new connection. The retry is skipped
+    /// once a command like `SELECT` or `AUTH` altered the session of the kept alive connection, see
+    /// [resp_util::SESSION_COMMANDS], and the error closes the client connection instead.
+    async fn proxy_kept_alive<W>(
+        &self,
+        request_id: Uuid,
+        req_bytes: &[u8],
+        command_count: usize,
+        kept_alive_stream: &mut Option<Box<dyn AsyncReadWrite>>,
+        state: &mut ConnectionState,
+        client_write_stream: &mut W,
+    ) -> Result<(), anyhow::Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let (mut server_stream, reused) =
+            self.server_stream(kept_alive_stream.take(), true).await?;
+        let mut response = Vec::new();
+        let mut result = self
+            .exchange(
+                request_id,
+                req_bytes,
+                command_count,
+                &mut server_stream,
+                &mut response,
+            )
+            .await;
+
+        if let Err(err) = &result {
+            if reused && response.is_empty() && !state.upstream_session {
+                warn!(
+                    "error reusing the server stream, retrying over a new stream: {:?}",
+                    err
+                );
+                server_stream = self.server_stream(None, false).await?.0;
+                result = self
+                    .exchange(
+                        request_id,
+                        req_bytes,
+                        command_count,
+                        &mut server_stream,
+                        &mut response,
+                    )
+                    .await;
+            }
+        }
+        result?;
+
+        let mut client_write_stream = RequestResponseLogger::new(client_write_stream);
+        client_write_stream.write_all(&response).await?;
+        client_write_stream.flush().await?;
+        info!("response proxied to the client over the kept alive connection");
+        if self.request_logging {
+            client_write_stream.log_response(&request_id);
+        }
+
+        state.upstream_session |= resp_util::has_session_command(std::str::from_utf8(req_bytes)?);
+        *kept_alive_stream = Some(server_stream);
+        Ok(())
+    }
+
+    /// Writes the request to the origin server and reads its response into the given buffer until
+    /// it holds a reply per command of the request.
+    async fn exchange(
+        &self,
+        request_id: Uuid,
+        req_bytes: &[u8],
+        command_count: usize,
+        server_stream: &mut Box<dyn AsyncReadWrite>,
+        response: &mut Vec<u8>,
+    ) -> Result<(), anyhow::Error> {
+        let mut server_write_stream = self.request_logger(&mut *server_stream);
+        server_write_stream.write_all(req_bytes).await?;
+        server_write_stream.flush().await?;
+        info!("request proxied to the server over the kept alive connection");
+        if self.request_logging {
+            server_write_stream.log_request(&request_id);
+        }
+
+        // the replies are framed as they are read, without scanning the complete ones again
+        let mut framed_len = 0;
+        let mut framed_replies = 0;
+        while framed_replies < command_count {
+            match resp_util::message_len(&response[framed_len..])? {
+                Some(reply_len) => {
+                    framed_len += reply_len;
+                    framed_replies += 1;
+                }
+                None => {
+                    response.reserve(8 * 1024);
+                    if server_stream.read_buf(response).await? == 0 {
+                        return Err(anyhow!(
+                            "the server closed the connection after {} of {} replies",
+                            framed_replies,
+                            command_count
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wraps the writer of the request to the origin server in a [RequestResponseLogger], which
+    /// captures the request payload when the request logging is enabled.
+    fn request_logger<W>(&self, server_write_stream: W) -> RequestResponseLogger<W> {
+        if self.request_logging {
+            RequestResponseLogger::capturing(server_write_stream, MAX_LOGGED_PAYLOAD_BYTES)
+        } else {
+            RequestResponseLogger::new(server_write_stream)
+        }
+    }
+
+    /// Handles the request of the client connection within the `Handling connection` span, see
+    /// [Connection::handle_stream].
+    async fn handle_request<S>(
+        self,
+        request_id: Uuid,
+        inbound_stream: S,
+        client_identity: ClientIdentity,
+        client_socket: Option<Socket>,
+    ) -> Result<(), anyhow::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let (client_read_stream, mut client_write_stream) = tokio::io::split(inbound_stream);
+
+        // convert the AsyncRead into a stream of byte buffers
+        let mut client_stream = into_bytes_stream(client_read_stream);
+
+        let mut client_socket = client_socket;
+        let mut state = ConnectionState::default();
+        let mut kept_alive_stream: Option<Box<dyn AsyncReadWrite>> = None;
+        let mut kept_alive = false;
+
+        let (req_bytes, action, _request_timer) = loop {
+            let next_request = match self.idle_timeout {
+                Some(idle_timeout) => {
+                    match tokio::time::timeout(idle_timeout, client_stream.next()).await {
+                        Ok(next_request) => next_request,
+                        Err(_) => {
+                            info!("closing the connection idle for {:?}", idle_timeout);
+                            return Ok(());
+                        }
+                    }
+                }
+                None => client_stream.next().await,
+            };
+
+            let req_bytes = match next_request {
+                Some(Ok(data)) => data.freeze(),
+                Some(Err(err)) => {
+                    error!("error converting request bytes into streams: {}", err);
+                    return Err(err.into());
+                }
+                None if kept_alive => {
+                    info!("kept alive connection closed by the client");
+                    return Ok(());
+                }
+                None => Bytes::new(),
+            };
+
+            let req_payload_str = std::str::from_utf8(&req_bytes)?;
+            debug!("request payload bytes: {:?}", req_payload_str);
+
+            if resp_util::is_pubsub(req_payload_str) {
+                info!("switching to the PubSub passthrough mode");
+                // the bytes codec hands the whole read buffer over, hence no client byte is left
+                // behind
+                let inbound_stream = client_stream.into_inner().unsplit(client_write_stream);
+                return self
+                    .pipe(req_bytes, inbound_stream, kept_alive_stream)
+                    .await;
+            }
+
+            if resp_util::is_hello(req_payload_str) {
+                info!("switching to the passthrough mode after the HELLO handshake");
+                let inbound_stream = client_stream.into_inner().unsplit(client_write_stream);
+                return self
+                    .pipe(req_bytes, inbound_stream, kept_alive_stream)
+                    .await;
+            }
+
+            // the request is timed until its response is proxied, or it is cut short by a fault
+            let command = resp_util::decode(req_payload_str)
+                .and_then(resp_util::fetch_redis_command)
+                .map(|command| command.to_ascii_uppercase())
+                .unwrap_or_else(|_| UNKNOWN_COMMAND.to_string());
+            let request_timer = self.faulter.metrics().start_request_timer(&command);
+
+            let mut ctx = Context {
+                request_id,
+                client_identity,
+                client_write_stream: &mut client_write_stream,
+                client_socket: client_socket.take(),
+            };
+
+            let action = self
+                .apply_faults(&mut ctx, &mut state, req_payload_str)
+                .await?;
+            client_socket = ctx.client_socket.take();
+            match action {
+                RequestAction::Exit => {
+                    info!("exiting  request processing");
+                    return Ok(());
+                }
+                RequestAction::Fallthrough => {
+                    info!("continuing request processing");
+                }
+                RequestAction::PartialResponse {
+                    truncate_after_bytes,
+                } => {
+                    info!(
+                        "continuing request processing with the response truncated after {} bytes",
+                        truncate_after_bytes
+                    );
+                }
+                RequestAction::SlowDrain { bytes_per_ms } => {
+                    info!(
+                        "continuing request processing with the response drained at {} bytes/ms",
+                        bytes_per_ms
+                    );
+                }
+                RequestAction::MutateResponse {
+                    ref mutation_pattern,
+                    ..
+                } => {
+                    info!(
+                        "continuing request processing with the response mutated by {}",
+                        mutation_pattern
+                    );
+                }
+                RequestAction::FailQueuedCommands { ref errors, .. } => {
+                    info!(
+                        "continuing request processing with {} queued command(s) failed",
+                        errors.iter().filter(|error| error.is_some()).count()
+                    );
+                }
+            };
+
+            if let Some(circuit_breaker) = &self.circuit_breaker {
+                if !circuit_breaker.try_acquire() {
+                    info!("rejecting the request as the circuit is open");
+                    let err_msg =
+                        resp_util::encode_error_message(UPSTREAM_UNAVAILABLE.to_string())?;
+                    client_write_stream.write_all(&err_msg).await?;
+                    client_write_stream.shutdown().await?;
+                    return Ok(());
+                }
+            }
+
+            let command_count = resp_util::complete_command_count(req_payload_str);
+            match command_count {
+                Some(command_count)
+                    if self.upstream_keep_alive && action == RequestAction::Fallthrough =>
+                {
+                    self.proxy_kept_alive(
+                        request_id,
+                        &req_bytes,
+                        command_count,
+                        &mut kept_alive_stream,
+                        &mut state,
+                        &mut client_write_stream,
+                    )
+                    .await?;
+                    kept_alive = true;
+                }
+                _ => break (req_bytes, action, request_timer),
+            }
+        };
+        // release the duplicate of the client socket, the connection is closed with the stream
+        drop(client_socket);
+
+        let (server_stream, _) = self.server_stream(kept_alive_stream, true).await?;
+
+        if action == RequestAction::Fallthrough {
+            return self
+                .proxy(request_id, req_bytes, client_write_stream, server_stream)
+                .await;
+        }
+
+        let (mut server_read_stream, server_write_stream) = tokio::io::split(server_stream);
+        let mut server_write_stream = self.request_logger(server_write_stream);
+        let mut client_write_stream = RequestResponseLogger::new(client_write_stream);
+
+        let client_to_server = async {
+            io::copy(&mut req_bytes.borrow(), &mut server_write_stream).await?;
+            info!("request proxied to the server");
+            server_write_stream.shutdown().await
+        };
+
+        let server_to_client = async {
+            match action {
+                RequestAction::PartialResponse {
+                    truncate_after_bytes,
+                } => {
+                    io::copy(
+                        &mut (&mut server_read_stream).take(truncate_after_bytes),
+                        &mut client_write_stream,
+                    )
+                    .await?;
+                    info!("truncated response proxied to the client");
+                }
+                RequestAction::SlowDrain { bytes_per_ms } => {
+                    copy_slowly(
+                        &mut server_read_stream,
+                        &mut client_write_stream,
+                        bytes_per_ms,
+                    )
+                    .await?;
+                    info!("response slowly drained to the client");
+                }
+                RequestAction::MutateResponse {
+                    mutation_pattern,
+                    mutation_replacement,
+                } => {
+                    let mutation_pattern = regex::bytes::Regex::new(&mutation_pattern)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+                    // the whole response is buffered as a match may span several reads
+                    let mut response = Vec::new();
+                    server_read_stream.read_to_end(&mut response).await?;
+                    let response =
+                        mutation_pattern.replace_all(&response, mutation_replacement.as_bytes());
+                    client_write_stream.write_all(&response).await?;
+                    info!("mutated response proxied to the client");
+                }
+                RequestAction::FailQueuedCommands {
+                    exec_reply_index,
+                    errors,
+                } => {
+                    // the whole response is buffered to decode the reply of EXEC
+                    let mut response = Vec::new();
+                    server_read_stream.read_to_end(&mut response).await?;
+                    let response =
+                        resp_util::replace_array_elements(&response, exec_reply_index, &errors)
+                            .unwrap_or_else(|err| {
+                                error!("error failing the queued commands: {}", err);
+                                response
+                            });
+                    client_write_stream.write_all(&response).await?;
+                    info!("response with the failed queued commands proxied to the client");
+                }
+                _ => {
+                    io::copy(&mut server_read_stream, &mut client_write_stream).await?;
+                    info!("response proxied to the client");
+                }
+            }
+            client_write_stream.shutdown().await
+        };
+
+        let _ = tokio::try_join!(client_to_server, server_to_client)?;
+
+        if self.request_logging {
+            server_write_stream.log_request(&request_id);
+            client_write_stream.log_response(&request_id);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::proxy::upstream_health::tests::run_mock_ping_server;
+    use crate::{
+        metrics::Metrics,
+        proxy,
+        store::{
+            self,
+            fault_store::{ArgMatcher, Fault, FaultVariants, MatchType, SubFault, DB},
+        },
+    };
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+    use tokio::net::TcpStream;
+
+    /// An individual test in the connection module can run upto 2 TCP servers - mock origin server
+    /// and red-monkey server. By default, cargo runs the tests in parallel, hence we are using an
+    /// Atomic usize integer that can be safely shared across threads to give an exclusive port number
+    /// to each TCP server with the use of `fetch_add` method.  
+    static PORT: AtomicUsize = AtomicUsize::new(0);
+
+    /// next_test_ip4 returns a socket address with an increasing port number that internally
+    /// uses an `AtomicUsize` for the port number. Hence, calling this function from different
+    /// threads is guarenteed to return a mutually exclusive increasing port number.
+    pub fn next_test_ip4() -> SocketAddr {
+        let port = PORT.fetch_add(1, Ordering::SeqCst) as u16 + 10000;
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), port))
+    }
 
     /// The mock origin server is a simple echo server. The `handle` method of
     /// `Connection` is agnostic of the application layer protocol of the origin server. Hence to
     /// test the proxy module, the echo origin server should suffice.
     pub fn run_mock_origin_server(origin_server_addr: SocketAddr) {
         debug!("binding origin server to {} address", origin_server_addr);
-        let listener = TcpListener::bind(&origin_server_addr).unwrap();
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
 
         thread::spawn(move || {
-            for (mut socket, _addr) in listener.accept() {
+            for mut socket in listener.incoming().flatten() {
                 let mut buf = [0; 1028];
                 thread::spawn(move || {
                     match socket.read(&mut buf) {
                         Ok(n) => {
-                            socket.write(&buf[0..n]).unwrap();
+                            socket.write_all(&buf[0..n]).unwrap();
                         }
                         Err(err) => {
                             panic!("error reading data from tcp socket: {}", err);
@@ -278,9 +1438,35 @@ pub mod tests {
     /// Runs a mock proxy server asynchronously that calls the `handle` method of `Connection`, which
     /// is the core handler of the proxy.  
     async fn run_red_monkey_server(red_monkey_server_addr: SocketAddr, fault_store: DB) {
+        run_red_monkey_server_with_pool(red_monkey_server_addr, fault_store, 0).await;
+    }
+
+    /// Runs a mock proxy server like `run_red_monkey_server` with an upstream connection pool of
+    /// the given size.
+    async fn run_red_monkey_server_with_pool(
+        red_monkey_server_addr: SocketAddr,
+        fault_store: DB,
+        pool_size: usize,
+    ) {
         let origin_server_addr = next_test_ip4();
         run_mock_origin_server(origin_server_addr);
+        run_red_monkey_server_with_origin(
+            red_monkey_server_addr,
+            fault_store,
+            origin_server_addr,
+            pool_size,
+        )
+        .await;
+    }
 
+    /// Runs a mock proxy server like `run_red_monkey_server_with_pool` in front of the given
+    /// origin server.
+    async fn run_red_monkey_server_with_origin(
+        red_monkey_server_addr: SocketAddr,
+        fault_store: DB,
+        origin_server_addr: SocketAddr,
+        pool_size: usize,
+    ) {
         debug!(
             "binding red-monkey server to {} address",
             red_monkey_server_addr
@@ -290,13 +1476,14 @@ pub mod tests {
             .unwrap();
 
         let origin_server_config = OriginServerConfig {
-            server_addr: origin_server_addr.to_string(),
+            server_addrs: vec![origin_server_addr.to_string()],
             is_tls_conn: false,
+            pool_size,
         };
 
         let connection = Connection::new(
             origin_server_config,
-            proxy::faulter::Faulter::new(fault_store),
+            proxy::faulter::Faulter::new(fault_store, Arc::new(Metrics::new().unwrap())),
         )
         .unwrap();
 
@@ -344,26 +1531,244 @@ pub mod tests {
         };
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_pooled_throughput() {
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+
+        for pool_size in [0, 4] {
+            let red_monkey_server_addr = next_test_ip4();
+            let fault_store = store::mem_store::MemStore::new_db();
+            run_red_monkey_server_with_pool(red_monkey_server_addr, fault_store, pool_size).await;
+
+            let start = Instant::now();
+            for _ in 0..20 {
+                let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+                stream.write_all(write_buffer).await.unwrap();
+
+                let mut read_buffer = Vec::new();
+                stream.read_to_end(&mut read_buffer).await.unwrap();
+                assert_eq!(read_buffer, write_buffer);
+            }
+
+            debug!(
+                "proxied 20 requests with an upstream pool of size {} in {:?}",
+                pool_size,
+                start.elapsed()
+            );
+        }
+    }
+
+    /// Benchmarks the throughput of the proxied responses against an origin server that answers
+    /// every request with a large response. Run it with `cargo test -- --ignored`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "benchmark"]
+    async fn test_proxy_throughput() {
+        const RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+        const REQUESTS: usize = 10;
+
+        let origin_server_addr = next_test_ip4();
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut buf = [0; 1028];
+                    if socket.read(&mut buf).is_ok() {
+                        let _ = socket.write_all(&vec![b'+'; RESPONSE_BYTES]);
+                    }
+                });
+            }
+        });
+
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+        run_red_monkey_server_with_origin(
+            red_monkey_server_addr,
+            fault_store,
+            origin_server_addr,
+            0,
+        )
+        .await;
+
+        let start = Instant::now();
+        for _ in 0..REQUESTS {
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+
+            let mut read_buffer = Vec::new();
+            stream.read_to_end(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer.len(), RESPONSE_BYTES);
+        }
+
+        let elapsed = start.elapsed();
+        let mib_per_sec =
+            (REQUESTS * RESPONSE_BYTES) as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+        debug!(
+            "proxied {} responses of {} bytes in {:?}, {:.1} MiB/s",
+            REQUESTS, RESPONSE_BYTES, elapsed, mib_per_sec
+        );
+        // a conservative floor even for the debug builds, far below the loopback throughput
+        assert!(mib_per_sec > 20.0, "throughput of {:.1} MiB/s", mib_per_sec);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_skip_unhealthy_upstream() {
+        let healthy_addr = next_test_ip4();
+        let is_up = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        run_mock_ping_server(healthy_addr, is_up);
+        let server_addrs = vec![healthy_addr.to_string(), next_test_ip4().to_string()];
+
+        let health_monitor =
+            UpstreamHealthMonitor::new(&server_addrs, Duration::from_millis(50), 1);
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: server_addrs.clone(),
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(
+                store::mem_store::MemStore::new_db(),
+                Arc::new(Metrics::new().unwrap()),
+            ),
+        )
+        .unwrap();
+
+        // both the upstreams are used before the health checks
+        let mut connection = connection.with_health_monitor(health_monitor.clone());
+        assert_ne!(connection.next_server_addr(), connection.next_server_addr());
+
+        connection.spawn_health_checks();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        for _ in 0..4 {
+            assert_eq!(connection.next_server_addr(), server_addrs[0]);
+        }
+
+        // the unhealthy upstreams are still tried when none is healthy
+        connection.origin_server_config.server_addrs = vec![server_addrs[1].clone()];
+        assert_eq!(connection.next_server_addr(), server_addrs[1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_load_balancing() {
+        let server_addrs: Vec<String> = (0..3)
+            .map(|_| {
+                let origin_server_addr = next_test_ip4();
+                run_mock_origin_server(origin_server_addr);
+                origin_server_addr.to_string()
+            })
+            .collect();
+        let new_connection = |lb_strategy: LbStrategy| {
+            Connection::new(
+                OriginServerConfig {
+                    server_addrs: server_addrs.clone(),
+                    is_tls_conn: false,
+                    pool_size: 0,
+                },
+                proxy::faulter::Faulter::new(
+                    store::mem_store::MemStore::new_db(),
+                    Arc::new(Metrics::new().unwrap()),
+                ),
+            )
+            .unwrap()
+            .with_lb_strategy(lb_strategy)
+        };
+
+        // the servers are picked in turn
+        let connection = new_connection(LbStrategy::RoundRobin);
+        let picked: Vec<&str> = (0..6).map(|_| connection.next_server_addr()).collect();
+        assert_eq!(picked, [&server_addrs[..], &server_addrs[..]].concat());
+
+        // the server with the fewest active connections is picked, whichever is in turn
+        let connection = new_connection(LbStrategy::LeastConnections);
+        let mut streams = Vec::new();
+        for _ in 0..3 {
+            streams.push(connection.checkout_server_stream(true).await.unwrap().0);
+        }
+        for server_addr in &server_addrs {
+            assert_eq!(connection.active_connections.count(server_addr), 1);
+        }
+        drop(streams.remove(1));
+        for _ in 0..3 {
+            assert_eq!(connection.next_server_addr(), server_addrs[1]);
+        }
+        streams.push(connection.checkout_server_stream(true).await.unwrap().0);
+        assert_eq!(connection.active_connections.count(&server_addrs[1]), 1);
+        drop(streams);
+        for server_addr in &server_addrs {
+            assert_eq!(connection.active_connections.count(server_addr), 0);
+        }
+
+        // every server is eventually picked at random
+        let connection = new_connection(LbStrategy::Random);
+        let picked: std::collections::HashSet<&str> =
+            (0..100).map(|_| connection.next_server_addr()).collect();
+        assert_eq!(picked.len(), server_addrs.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_circuit_breaker() {
+        let red_monkey_server_addr = next_test_ip4();
+        let listener = tokio::net::TcpListener::bind(&red_monkey_server_addr)
+            .await
+            .unwrap();
+
+        // nothing listens on the origin server address
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![next_test_ip4().to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(
+                store::mem_store::MemStore::new_db(),
+                Arc::new(Metrics::new().unwrap()),
+            ),
+        )
+        .unwrap()
+        .with_circuit_breaker(CircuitBreaker::new(
+            proxy::circuit_breaker::CircuitBreakerConfig {
+                threshold: 2,
+                window: Duration::from_secs(10),
+                open_duration: Duration::from_secs(10),
+            },
+        ));
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _addr) = listener.accept().await.unwrap();
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    let _ = connection.handle(socket).await;
+                });
+            }
+        });
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(write_buffer).await.unwrap();
+
+            let mut read_buffer = Vec::new();
+            let _ = stream.read_to_end(&mut read_buffer).await;
+            responses.push(read_buffer);
+        }
+
+        assert!(responses[0].is_empty());
+        assert!(responses[1].is_empty());
+        assert_eq!(responses[2], b"-ERR upstream unavailable\r\n");
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_proxy_drop_fault() {
         let red_monkey_server_addr = next_test_ip4();
         let fault_store = store::mem_store::MemStore::new_db();
 
         let fault = Fault {
-            name: "drop_conn_for_set_cmd".to_string(),
             description: Some("SET drop connection error".to_string()),
-            fault_type: FaultVariants::DropConn,
-            error_msg: None,
-            duration: None,
-            command: "SET".to_string(),
-            last_modified: None,
+            ..Fault::fixture("drop_conn_for_set_cmd", FaultVariants::DropConn, "SET")
         };
 
-        fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
-            .unwrap();
+        fault_store.store(&fault.name, &fault).await.unwrap();
 
         run_red_monkey_server(red_monkey_server_addr, fault_store).await;
 
@@ -384,25 +1789,52 @@ pub mod tests {
     }
 
     #[tokio::test(flavor = "multi_thread")]
-    async fn test_proxy_custom_error_fault() {
+    async fn test_proxy_arg_matchers_fault() {
         let red_monkey_server_addr = next_test_ip4();
         let fault_store = store::mem_store::MemStore::new_db();
 
         let fault = Fault {
-            name: "set_custom_err".to_string(),
-            description: Some("SET custom error".to_string()),
-            fault_type: FaultVariants::Error,
+            description: Some("SET mykey error".to_string()),
             error_msg: Some("SET FAILED".to_string()),
-            duration: None,
-            command: "SET".to_string(),
-            last_modified: None,
+            arg_matchers: Some(vec![ArgMatcher::new(
+                1,
+                "mykey".to_string(),
+                MatchType::Exact,
+            )]),
+            ..Fault::fixture("set_mykey_err", FaultVariants::Error, "SET")
         };
 
-        fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
-            .unwrap();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let set_mykey = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let set_otherkey = b"*3\r\n$3\r\nset\r\n$8\r\notherkey\r\n$1\r\n1\r\n";
+        let mut responses = Vec::new();
+        for write_buffer in [&set_mykey[..], &set_otherkey[..]] {
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(write_buffer).await.unwrap();
+
+            let mut read_buffer = Vec::new();
+            stream.read_to_end(&mut read_buffer).await.unwrap();
+            responses.push(read_buffer);
+        }
+
+        assert_eq!(responses[0], b"-SET FAILED\r\n");
+        assert_eq!(responses[1], set_otherkey);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_tcp_reset_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET connection reset".to_string()),
+            ..Fault::fixture("tcp_reset_for_set_cmd", FaultVariants::TcpReset, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
 
         run_red_monkey_server(red_monkey_server_addr, fault_store).await;
 
@@ -412,35 +1844,23 @@ pub mod tests {
 
         let mut read_buffer = [0; 32];
         match stream.read(&mut read_buffer).await {
-            Ok(n) => {
-                assert_eq!(read_buffer[0..n], *b"-SET FAILED\r\n");
-            }
-            Err(err) => {
-                panic!("error reading data from tcp socket: {}", err);
-            }
+            Ok(n) => panic!("expected a connection reset, read {} bytes", n),
+            Err(err) => assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset),
         }
     }
 
     #[tokio::test(flavor = "multi_thread")]
-    async fn test_proxy_delay_fault() {
+    async fn test_proxy_custom_error_fault() {
         let red_monkey_server_addr = next_test_ip4();
         let fault_store = store::mem_store::MemStore::new_db();
 
         let fault = Fault {
-            name: "delay_fault".to_string(),
-            description: Some("SET delay fault".to_string()),
-            fault_type: FaultVariants::Delay,
-            error_msg: None,
-            duration: Some(20),
-            command: "SET".to_string(),
-            last_modified: None,
+            description: Some("SET custom error".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            ..Fault::fixture("set_custom_err", FaultVariants::Error, "SET")
         };
 
-        fault_store
-            .write()
-            .await
-            .store(&fault.name, &fault)
-            .unwrap();
+        fault_store.store(&fault.name, &fault).await.unwrap();
 
         run_red_monkey_server(red_monkey_server_addr, fault_store).await;
 
@@ -448,21 +1868,1536 @@ pub mod tests {
         let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
         stream.write_all(write_buffer).await.unwrap();
 
-        let start = Instant::now();
-
         let mut read_buffer = [0; 32];
         match stream.read(&mut read_buffer).await {
             Ok(n) => {
-                let duration = start.elapsed();
-                assert_eq!(
-                    read_buffer[0..n],
-                    *b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n"
-                );
-                assert_eq!(duration.as_millis() < 20, false);
+                assert_eq!(read_buffer[0..n], *b"-SET FAILED\r\n");
             }
             Err(err) => {
                 panic!("error reading data from tcp socket: {}", err);
             }
         }
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_client_ip_filter_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET error for a single client".to_string()),
+            error_msg: Some("SET FAILED for {client_ip}".to_string()),
+            client_ip_filter: Some(vec!["127.0.0.2".parse().unwrap()]),
+            ..Fault::fixture("set_err_for_client", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut responses = Vec::new();
+        for client_ip in ["127.0.0.1", "127.0.0.2"] {
+            let socket = tokio::net::TcpSocket::new_v4().unwrap();
+            socket
+                .bind(SocketAddr::new(client_ip.parse().unwrap(), 0))
+                .unwrap();
+            let mut stream = socket.connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(write_buffer).await.unwrap();
+
+            let mut read_buffer = [0; 64];
+            let n = stream.read(&mut read_buffer).await.unwrap();
+            responses.push(read_buffer[0..n].to_vec());
+        }
+
+        assert_eq!(responses[0], write_buffer);
+        assert_eq!(responses[1], b"-SET FAILED for 127.0.0.2\r\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_composite_delay_error_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET delay then error".to_string()),
+            sub_faults: Some(vec![
+                SubFault {
+                    fault_type: FaultVariants::Delay,
+                    duration: Some(500),
+                    error_msg: None,
+                },
+                SubFault {
+                    fault_type: FaultVariants::Error,
+                    duration: None,
+                    error_msg: Some("SET FAILED".to_string()),
+                },
+            ]),
+            ..Fault::fixture("composite_delay_error", FaultVariants::Composite, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let start = Instant::now();
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = [0; 32];
+        let n = stream.read(&mut read_buffer).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert_eq!(read_buffer[0..n], *b"-SET FAILED\r\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_composite_delay_drop_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET delay then drop connection".to_string()),
+            sub_faults: Some(vec![
+                SubFault {
+                    fault_type: FaultVariants::Delay,
+                    duration: Some(500),
+                    error_msg: None,
+                },
+                SubFault {
+                    fault_type: FaultVariants::DropConn,
+                    duration: None,
+                    error_msg: None,
+                },
+            ]),
+            ..Fault::fixture("composite_delay_drop", FaultVariants::Composite, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let start = Instant::now();
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = [0; 32];
+        let n = stream.read(&mut read_buffer).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert_eq!(n, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_one_shot_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET one-shot error".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            one_shot: true,
+            ..Fault::fixture("one_shot_set_err", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store.clone()).await;
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = [0; 32];
+        let n = stream.read(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer[0..n], *b"-SET FAILED\r\n");
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = [0; 32];
+        let n = stream.read(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer[0..n], *write_buffer);
+
+        let fault = fault_store.get_by_fault_name(&fault.name).await.unwrap();
+        assert!(!fault.enabled);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_max_hits_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET error for three requests".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            max_hits: Some(3),
+            ..Fault::fixture("max_hits_set_err", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut faulted = 0;
+
+        for _ in 0..5 {
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(write_buffer).await.unwrap();
+
+            let mut read_buffer = [0; 32];
+            let n = stream.read(&mut read_buffer).await.unwrap();
+            if read_buffer[0..n] == *b"-SET FAILED\r\n" {
+                faulted += 1;
+            } else {
+                assert_eq!(read_buffer[0..n], *write_buffer);
+            }
+        }
+
+        assert_eq!(faulted, 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_timeout_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET timeout".to_string()),
+            ..Fault::fixture("timeout_set", FaultVariants::Timeout, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        // the connection is neither closed nor answered
+        let mut read_buffer = [0; 32];
+        let res = tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            stream.read(&mut read_buffer),
+        )
+        .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_partial_response_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET partial response".to_string()),
+            truncate_after_bytes: Some(10),
+            ..Fault::fixture(
+                "partial_set_response",
+                FaultVariants::PartialResponse,
+                "SET",
+            )
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer[0..10]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_slow_drain_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET slow drain".to_string()),
+            bytes_per_ms: Some(1),
+            ..Fault::fixture("slow_drain_set", FaultVariants::SlowDrain, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let start = Instant::now();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        let duration = start.elapsed();
+
+        assert_eq!(read_buffer, write_buffer);
+        // a byte per millisecond takes at least as many milliseconds as the response length
+        assert!(duration.as_millis() >= write_buffer.len() as u128);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_mutate_response_fault() {
+        // the origin server replies to every request like a Redis server to a SET command
+        let origin_server_addr = next_test_ip4();
+        let origin_listener = tokio::net::TcpListener::bind(origin_server_addr)
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _addr) = origin_listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    let _ = socket.write_all(b"+OK\r\n").await;
+                });
+            }
+        });
+
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("SET reply mutation".to_string()),
+            mutation_pattern: Some(r"\+OK".to_string()),
+            mutation_replacement: Some("+MOCKED".to_string()),
+            ..Fault::fixture("mock_set_reply", FaultVariants::MutateResponse, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(fault_store, Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap();
+
+        let red_monkey_server_addr = next_test_ip4();
+        let listener = tokio::net::TcpListener::bind(&red_monkey_server_addr)
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _addr) = listener.accept().await.unwrap();
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    connection.handle(socket).await.unwrap();
+                });
+            }
+        });
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"+MOCKED\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_copy_slowly() {
+        let response = [7u8; 40];
+
+        let start = Instant::now();
+        let mut writer = Vec::new();
+        let copied = copy_slowly(&mut &response[..], &mut writer, 4)
+            .await
+            .unwrap();
+        let duration = start.elapsed();
+
+        assert_eq!(copied, 40);
+        assert_eq!(writer, response);
+        assert!(duration.as_millis() >= 40 / 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_pipeline() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET delay fault".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("set_delay", FaultVariants::Delay, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store.clone()).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer =
+            b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n";
+        let start = Instant::now();
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+
+        // SET is delayed and both the commands are proxied to the server
+        assert!(start.elapsed().as_millis() >= 20);
+        assert_eq!(read_buffer, write_buffer);
+
+        assert_eq!(
+            fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_pipeline_exit() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET custom error".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            ..Fault::fixture("set_custom_err", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer =
+            b"*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"-SET FAILED\r\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_delay_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET delay fault".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay_fault", FaultVariants::Delay, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let start = Instant::now();
+
+        let mut read_buffer = [0; 32];
+        match stream.read(&mut read_buffer).await {
+            Ok(n) => {
+                let duration = start.elapsed();
+                assert_eq!(
+                    read_buffer[0..n],
+                    *b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n"
+                );
+                assert!(duration.as_millis() >= 20);
+            }
+            Err(err) => {
+                panic!("error reading data from tcp socket: {}", err);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_request_duration() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            duration: Some(20),
+            ..Fault::fixture("delay_fault", FaultVariants::Delay, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+        let metrics =
+            Arc::new(Metrics::with_request_duration_buckets(&[10.0, 20.0, 1000.0]).unwrap());
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(fault_store, metrics.clone()),
+        )
+        .unwrap();
+
+        for write_buffer in [
+            &b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n"[..],
+            &b"*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n"[..],
+        ] {
+            let (mut client_stream, proxy_stream) = tokio::io::duplex(1024);
+            let handle = tokio::spawn(connection.clone().handle_stream(
+                proxy_stream,
+                None,
+                ClientIdentity::Unknown,
+                None,
+            ));
+            client_stream.write_all(write_buffer).await.unwrap();
+            let mut read_buffer = Vec::new();
+            client_stream.read_to_end(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer, write_buffer);
+            handle.await.unwrap().unwrap();
+        }
+
+        // the delayed SET is counted in the (20, 1000] bucket, the GET is not delayed
+        let (_, buffer) = metrics.encode().unwrap();
+        let body = String::from_utf8(buffer).unwrap();
+        for bucket in [
+            r#"red_monkey_request_duration_milliseconds_bucket{command="SET",le="20"} 0"#,
+            r#"red_monkey_request_duration_milliseconds_bucket{command="SET",le="1000"} 1"#,
+            r#"red_monkey_request_duration_milliseconds_count{command="GET"} 1"#,
+        ] {
+            assert!(body.contains(bucket), "{} not in {}", bucket, body);
+        }
+    }
+
+    /// Runs a mock echo origin server over TLS that requires the clients to present a certificate
+    /// signed by the test CA.
+    fn run_mock_mutual_tls_server(origin_server_addr: SocketAddr) {
+        use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor
+            .set_private_key_file("testdata/tls/server.key", SslFiletype::PEM)
+            .unwrap();
+        acceptor
+            .set_certificate_chain_file("testdata/tls/server.crt")
+            .unwrap();
+        acceptor.set_ca_file("testdata/tls/ca.crt").unwrap();
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = Arc::new(acceptor.build());
+
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+        thread::spawn(move || {
+            for socket in listener.incoming().flatten() {
+                let acceptor = acceptor.clone();
+                thread::spawn(move || {
+                    if let Ok(mut stream) = acceptor.accept(socket) {
+                        let mut buf = [0; 1028];
+                        if let Ok(n) = stream.read(&mut buf) {
+                            let _ = stream.write_all(&buf[0..n]);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mutual_tls_stream() {
+        let origin_server_addr = next_test_ip4();
+        run_mock_mutual_tls_server(origin_server_addr);
+
+        let new_connection = |tls_config: &UpstreamTlsConfig| {
+            Connection::new(
+                OriginServerConfig {
+                    server_addrs: vec![origin_server_addr.to_string()],
+                    is_tls_conn: true,
+                    pool_size: 0,
+                },
+                proxy::faulter::Faulter::new(
+                    store::mem_store::MemStore::new_db(),
+                    Arc::new(Metrics::new().unwrap()),
+                ),
+            )
+            .unwrap()
+            .with_tls_config(tls_config)
+        };
+
+        let mut tls_config = UpstreamTlsConfig {
+            client_cert_path: Some("testdata/tls/client.crt".to_string()),
+            client_key_path: Some("testdata/tls/client.key".to_string()),
+            ca_cert_path: Some("testdata/tls/ca.crt".to_string()),
+            ..Default::default()
+        };
+        let connection = new_connection(&tls_config).unwrap();
+
+        let write_buffer = b"*1\r\n$4\r\nping\r\n";
+        let mut stream = connection
+            .create_server_stream(&origin_server_addr.to_string())
+            .await
+            .unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = [0; 32];
+        let n = stream.read(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer[0..n], *write_buffer);
+
+        // without the client certificate, the server rejects the handshake
+        tls_config.client_cert_path = None;
+        tls_config.client_key_path = None;
+        let connection = new_connection(&tls_config).unwrap();
+        let echoed = match connection
+            .create_server_stream(&origin_server_addr.to_string())
+            .await
+        {
+            Ok(mut stream) => {
+                let _ = stream.write_all(write_buffer).await;
+                matches!(stream.read(&mut read_buffer).await, Ok(n) if n > 0)
+            }
+            Err(_) => false,
+        };
+        assert!(!echoed);
+
+        // the server requires TLS 1.2 or later
+        tls_config.client_cert_path = Some("testdata/tls/client.crt".to_string());
+        tls_config.client_key_path = Some("testdata/tls/client.key".to_string());
+        tls_config.max_version = Some(native_tls::Protocol::Tlsv11);
+        let connection = new_connection(&tls_config).unwrap();
+        assert!(connection
+            .create_server_stream(&origin_server_addr.to_string())
+            .await
+            .is_err());
+
+        // a client certificate without its key is rejected
+        tls_config.client_key_path = None;
+        let err = new_connection(&tls_config).err().unwrap();
+        assert!(err.to_string().contains("client key"));
+
+        tls_config.client_key_path = Some("testdata/tls/missing.key".to_string());
+        let err = new_connection(&tls_config).err().unwrap();
+        assert!(err.to_string().contains("testdata/tls/missing.key"));
+
+        // a certificate is not a valid key
+        tls_config.client_key_path = Some("testdata/tls/ca.crt".to_string());
+        let err = new_connection(&tls_config).err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("error loading the client certificate"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tcp_keepalive() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        set_tcp_keepalive(&stream, Duration::from_secs(30)).unwrap();
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tcp_buffer_sizes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let socket = SockRef::from(&stream);
+        let default_recv_size = socket.recv_buffer_size().unwrap();
+
+        let sizes = TcpBufferSizes {
+            send: Some(64 * 1024),
+            recv: None,
+        };
+        set_tcp_buffer_sizes(&stream, sizes).unwrap();
+        // the OS may round or double the requested size, but never grants less than half of it
+        assert!(socket.send_buffer_size().unwrap() >= 32 * 1024);
+        assert_eq!(socket.recv_buffer_size().unwrap(), default_recv_size);
+
+        let sizes = TcpBufferSizes {
+            send: None,
+            recv: Some(128 * 1024),
+        };
+        set_tcp_buffer_sizes(&stream, sizes).unwrap();
+        assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_idle_connection_timeout() {
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+
+        let red_monkey_server_addr = next_test_ip4();
+        let listener = tokio::net::TcpListener::bind(&red_monkey_server_addr)
+            .await
+            .unwrap();
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(
+                store::mem_store::MemStore::new_db(),
+                Arc::new(Metrics::new().unwrap()),
+            ),
+        )
+        .unwrap()
+        .with_tcp_keepalive(Some(Duration::from_secs(30)))
+        .with_idle_timeout(Some(Duration::from_millis(200)));
+        tokio::spawn(async move {
+            loop {
+                let (socket, _addr) = listener.accept().await.unwrap();
+                let connection = connection.clone();
+                tokio::spawn(async move { connection.handle(socket).await.unwrap() });
+            }
+        });
+
+        // an idle client is disconnected once the timeout elapses
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert!(read_buffer.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // a client that sends its request in time is proxied
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_inline_command_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET custom error".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            ..Fault::fixture("set_custom_err", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(b"sEt foo bar\r\n").await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"-SET FAILED\r\n");
+
+        // an inline command without a fault is proxied as is
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(b"PING\r\n").await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"PING\r\n");
+    }
+
+    /// Runs a mock origin server that replies the given response to the first read of every
+    /// connection.
+    fn run_mock_reply_server(origin_server_addr: SocketAddr, response: &'static [u8]) {
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut buf = [0; 1028];
+                    if socket.read(&mut buf).is_ok() {
+                        let _ = socket.write_all(response);
+                    }
+                });
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_transaction_fault() {
+        let red_monkey_server_addr = next_test_ip4();
+        let origin_server_addr = next_test_ip4();
+        run_mock_reply_server(
+            origin_server_addr,
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n+OK\r\n$1\r\n1\r\n",
+        );
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("SET custom error".to_string()),
+            error_msg: Some("SET FAILED".to_string()),
+            ..Fault::fixture("set_custom_err", FaultVariants::Error, "SET")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server_with_origin(
+            red_monkey_server_addr,
+            fault_store,
+            origin_server_addr,
+            0,
+        )
+        .await;
+
+        // the SET command fails within the reply of EXEC only
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream
+            .write_all(b"MULTI\r\nSET mykey 1\r\nGET mykey\r\nEXEC\r\n")
+            .await
+            .unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(
+            read_buffer,
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n-SET FAILED\r\n$1\r\n1\r\n"
+        );
+
+        // a discarded transaction is not faulted
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream
+            .write_all(b"MULTI\r\nSET mykey 1\r\nDISCARD\r\nGET mykey\r\n")
+            .await
+            .unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(
+            read_buffer,
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n+OK\r\n$1\r\n1\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_faults_transaction_state() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            error_msg: Some("SET FAILED".to_string()),
+            ..Fault::fixture("set_custom_err", FaultVariants::Error, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![next_test_ip4().to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(fault_store, metrics.clone()),
+        )
+        .unwrap();
+
+        let mut client_write_stream = Vec::new();
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut client_write_stream,
+            client_socket: None,
+        };
+
+        let mut state = ConnectionState::default();
+        let action = connection
+            .apply_faults(&mut ctx, &mut state, "MULTI\r\nSET mykey 1\r\n")
+            .await
+            .unwrap();
+        assert_eq!(action, RequestAction::Fallthrough);
+        assert!(state.transaction.in_multi);
+        assert_eq!(
+            state.transaction.queued_commands,
+            vec!["*3\r\n$3\r\nSET\r\n$5\r\nmykey\r\n$1\r\n1\r\n".to_string()]
+        );
+
+        connection
+            .apply_faults(&mut ctx, &mut state, "DISCARD\r\n")
+            .await
+            .unwrap();
+        assert!(!state.transaction.in_multi);
+        assert!(state.transaction.queued_commands.is_empty());
+
+        // each command is counted once, the faulted command of the transaction included
+        connection
+            .apply_faults(
+                &mut ctx,
+                &mut state,
+                "MULTI\r\nSET mykey 1\r\nGET mykey\r\nEXEC\r\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(metrics.proxy_requests_total.get(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_apply_faults_database() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            error_msg: Some("GET FAILED".to_string()),
+            database: Some(1),
+            ..Fault::fixture("db 1 GET error", FaultVariants::Error, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![next_test_ip4().to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(fault_store, Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap();
+
+        // the fault of the database 1 doesn't fire on the default database 0
+        let mut client_write_stream = Vec::new();
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut client_write_stream,
+            client_socket: None,
+        };
+        let mut state = ConnectionState::default();
+        let action = connection
+            .apply_faults(
+                &mut ctx,
+                &mut state,
+                "GET mykey\r\nSELECT 2\r\nGET mykey\r\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(action, RequestAction::Fallthrough);
+        assert_eq!(state.database, 2);
+
+        let action = connection
+            .apply_faults(&mut ctx, &mut state, "SELECT 1\r\nGET mykey\r\n")
+            .await
+            .unwrap();
+        assert_eq!(action, RequestAction::Exit);
+        assert_eq!(state.database, 1);
+        drop(ctx);
+        assert_eq!(client_write_stream, b"-GET FAILED\r\n");
+    }
+
+    /// Runs a mock Redis server that confirms a subscription with a published message, and echoes
+    /// the other requests until the client closes the connection.
+    fn run_mock_pubsub_server(origin_server_addr: SocketAddr) {
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut buf = [0; 1028];
+                    while let Ok(n) = socket.read(&mut buf) {
+                        if n == 0 {
+                            break;
+                        }
+
+                        let response: &[u8] = if buf[0..n].starts_with(b"SUBSCRIBE") {
+                            b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n"
+                        } else {
+                            &buf[0..n]
+                        };
+                        socket.write_all(response).unwrap();
+
+                        if buf[0..n].starts_with(b"SUBSCRIBE") {
+                            thread::sleep(Duration::from_millis(50));
+                            socket
+                                .write_all(b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n")
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_pubsub_passthrough() {
+        let red_monkey_server_addr = next_test_ip4();
+        let origin_server_addr = next_test_ip4();
+        run_mock_pubsub_server(origin_server_addr);
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let fault = Fault {
+            description: Some("Drop every command".to_string()),
+            ..Fault::fixture("drop_all", FaultVariants::DropConn, "*")
+        };
+
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server_with_origin(
+            red_monkey_server_addr,
+            fault_store,
+            origin_server_addr,
+            0,
+        )
+        .await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(b"SUBSCRIBE news\r\n").await.unwrap();
+
+        // the confirmation and the message pushed later on are both proxied
+        let expected = b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n";
+        let mut read_buffer = vec![0; expected.len()];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, expected);
+
+        // the connection stays open for the further commands, which are not faulted
+        stream.write_all(b"UNSUBSCRIBE news\r\n").await.unwrap();
+        let mut read_buffer = [0; 18];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(&read_buffer, b"UNSUBSCRIBE news\r\n");
+    }
+
+    /// Runs a mock Redis 6+ server that replies to `HELLO 3` with a RESP3 map and pushes a client
+    /// side caching invalidation afterwards, and echoes the other requests until the client
+    /// closes the connection.
+    fn run_mock_resp3_server(origin_server_addr: SocketAddr) {
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut buf = [0; 1028];
+                    while let Ok(n) = socket.read(&mut buf) {
+                        if n == 0 {
+                            break;
+                        }
+
+                        let is_hello = buf[0..n].starts_with(b"*2\r\n$5\r\nHELLO");
+                        let response: &[u8] = if is_hello {
+                            b"%2\r\n$6\r\nserver\r\n$5\r\nredis\r\n$5\r\nproto\r\n:3\r\n"
+                        } else {
+                            &buf[0..n]
+                        };
+                        socket.write_all(response).unwrap();
+
+                        if is_hello {
+                            thread::sleep(Duration::from_millis(50));
+                            socket
+                                .write_all(b">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nkey\r\n")
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_resp3_hello_passthrough() {
+        let red_monkey_server_addr = next_test_ip4();
+        let origin_server_addr = next_test_ip4();
+        run_mock_resp3_server(origin_server_addr);
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.name = "drop_all".to_string();
+        fault.fault_type = FaultVariants::DropConn;
+        fault.command = "*".to_string();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server_with_origin(
+            red_monkey_server_addr,
+            fault_store,
+            origin_server_addr,
+            0,
+        )
+        .await;
+
+        // a RESP3 client negotiates the protocol version with HELLO 3 first
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream
+            .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
+            .await
+            .unwrap();
+
+        // the HELLO reply and the message pushed later on are both proxied
+        let expected = b"%2\r\n$6\r\nserver\r\n$5\r\nredis\r\n$5\r\nproto\r\n:3\r\n>2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nkey\r\n";
+        let mut read_buffer = vec![0; expected.len()];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, expected);
+
+        // the connection stays open for the further commands, which are not faulted
+        let request = b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n";
+        stream.write_all(request).await.unwrap();
+        let mut read_buffer = vec![0; request.len()];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, request);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_auth_passthrough() {
+        let red_monkey_server_addr = next_test_ip4();
+        let fault_store = store::mem_store::MemStore::new_db();
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.name = "fail_all".to_string();
+        fault.fault_type = FaultVariants::Error;
+        fault.error_msg = Some("FAILED".to_string());
+        fault.command = "*".to_string();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        run_red_monkey_server(red_monkey_server_addr, fault_store).await;
+
+        // AUTH is proxied to the echo origin server despite the wildcard fault
+        let request = b"*2\r\n$4\r\nAUTH\r\n$6\r\ns3cr3t\r\n";
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, request);
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n")
+            .await
+            .unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"-FAILED\r\n");
+    }
+
+    /// Runs a mock origin server that keeps the connections open and replies `+OK` to every
+    /// command of every read, counting the accepted connections.
+    fn run_mock_keep_alive_server(origin_server_addr: SocketAddr) -> Arc<AtomicUsize> {
+        let listener = TcpListener::bind(origin_server_addr).unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+
+        let accepted_conns = accepted.clone();
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                accepted_conns.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut buf = [0; 1028];
+                    while let Ok(n) = socket.read(&mut buf) {
+                        if n == 0 {
+                            break;
+                        }
+                        let request = std::str::from_utf8(&buf[..n]).unwrap();
+                        let command_count = resp_util::complete_command_count(request).unwrap();
+                        if socket.write_all(&b"+OK\r\n".repeat(command_count)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        accepted
+    }
+
+    /// Runs a mock proxy server in front of the given origin server with the upstream keep-alive
+    /// enabled or not.
+    async fn run_keep_alive_red_monkey_server(
+        red_monkey_server_addr: SocketAddr,
+        origin_server_addr: SocketAddr,
+        upstream_keep_alive: bool,
+    ) {
+        let listener = tokio::net::TcpListener::bind(&red_monkey_server_addr)
+            .await
+            .unwrap();
+
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(
+                store::mem_store::MemStore::new_db(),
+                Arc::new(Metrics::new().unwrap()),
+            ),
+        )
+        .unwrap()
+        .with_upstream_keep_alive(upstream_keep_alive);
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _addr) = listener.accept().await.unwrap();
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    let _ = connection.handle(socket).await;
+                });
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_upstream_keep_alive() {
+        let origin_server_addr = next_test_ip4();
+        let accepted = run_mock_keep_alive_server(origin_server_addr);
+        let red_monkey_server_addr = next_test_ip4();
+        run_keep_alive_red_monkey_server(red_monkey_server_addr, origin_server_addr, true).await;
+
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        for (request, response) in [
+            (
+                &b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n"[..],
+                &b"+OK\r\n"[..],
+            ),
+            (
+                b"*1\r\n$4\r\nping\r\n*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n",
+                b"+OK\r\n+OK\r\n",
+            ),
+            (b"PING\r\n", b"+OK\r\n"),
+        ] {
+            stream.write_all(request).await.unwrap();
+            let mut read_buffer = vec![0; response.len()];
+            stream.read_exact(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer, response);
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        // each client connection has its own origin server connection
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(b"PING\r\n").await.unwrap();
+        let mut read_buffer = vec![0; 5];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_upstream_keep_alive_reconnect() {
+        // the echo origin server closes its connection after every response
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+        let red_monkey_server_addr = next_test_ip4();
+        run_keep_alive_red_monkey_server(red_monkey_server_addr, origin_server_addr, true).await;
+
+        let request = b"*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n";
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        for _ in 0..3 {
+            stream.write_all(request).await.unwrap();
+            let mut read_buffer = vec![0; request.len()];
+            stream.read_exact(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer, request);
+        }
+
+        // the origin server connection can't be replaced once a database is selected on it
+        let select = b"*2\r\n$6\r\nselect\r\n$1\r\n1\r\n";
+        stream.write_all(select).await.unwrap();
+        let mut read_buffer = vec![0; select.len()];
+        stream.read_exact(&mut read_buffer).await.unwrap();
+
+        stream.write_all(request).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert!(read_buffer.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_upstream_keep_alive_stale_pooled_stream() {
+        let origin_server_addr = next_test_ip4();
+        let accepted = run_mock_keep_alive_server(origin_server_addr);
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 1,
+            },
+            proxy::faulter::Faulter::new(
+                store::mem_store::MemStore::new_db(),
+                Arc::new(Metrics::new().unwrap()),
+            ),
+        )
+        .unwrap()
+        .with_upstream_keep_alive(true);
+
+        // the pooled stream is closed by its server once the request is read, before any reply
+        let stale_server_addr = next_test_ip4();
+        let listener = TcpListener::bind(stale_server_addr).unwrap();
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                let _ = socket.read(&mut [0; 1028]);
+            }
+        });
+        let stale_stream = TcpStream::connect(stale_server_addr).await.unwrap();
+        assert!(
+            connection
+                .pool
+                .put(&origin_server_addr.to_string(), Box::new(stale_stream))
+                .await
+        );
+
+        // the request is retried over a new connection to the origin server
+        let mut kept_alive_stream = None;
+        let mut client_write_stream = Vec::new();
+        connection
+            .proxy_kept_alive(
+                Uuid::new_v4(),
+                b"PING\r\n",
+                1,
+                &mut kept_alive_stream,
+                &mut ConnectionState::default(),
+                &mut client_write_stream,
+            )
+            .await
+            .unwrap();
+        assert_eq!(client_write_stream, b"+OK\r\n");
+        assert!(kept_alive_stream.is_some());
+        assert!(accepted.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_upstream_keep_alive_disabled() {
+        let origin_server_addr = next_test_ip4();
+        let accepted = run_mock_keep_alive_server(origin_server_addr);
+        let red_monkey_server_addr = next_test_ip4();
+        run_keep_alive_red_monkey_server(red_monkey_server_addr, origin_server_addr, false).await;
+
+        // the client connection is closed after the response of its first request
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(b"PING\r\n").await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"+OK\r\n");
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    /// Benchmarks the requests sent over a single kept alive client connection against the same
+    /// requests sent over a client connection each, which is the only way without the upstream
+    /// keep-alive.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_upstream_keep_alive_throughput() {
+        const REQUESTS: usize = 200;
+        let request = b"*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n";
+
+        let origin_server_addr = next_test_ip4();
+        let accepted = run_mock_keep_alive_server(origin_server_addr);
+
+        let red_monkey_server_addr = next_test_ip4();
+        run_keep_alive_red_monkey_server(red_monkey_server_addr, origin_server_addr, false).await;
+        let start = Instant::now();
+        for _ in 0..REQUESTS {
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream.write_all(request).await.unwrap();
+            let mut read_buffer = Vec::new();
+            stream.read_to_end(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer, b"+OK\r\n");
+        }
+        let per_request_elapsed = start.elapsed();
+
+        let red_monkey_server_addr = next_test_ip4();
+        run_keep_alive_red_monkey_server(red_monkey_server_addr, origin_server_addr, true).await;
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        for _ in 0..REQUESTS {
+            stream.write_all(request).await.unwrap();
+            let mut read_buffer = [0; 5];
+            stream.read_exact(&mut read_buffer).await.unwrap();
+            assert_eq!(&read_buffer, b"+OK\r\n");
+        }
+        let kept_alive_elapsed = start.elapsed();
+
+        debug!(
+            "proxied {} requests in {:?} over a connection each, in {:?} over a kept alive connection",
+            REQUESTS, per_request_elapsed, kept_alive_elapsed
+        );
+        assert_eq!(accepted.load(Ordering::SeqCst), REQUESTS + 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_protocol_client_ip_filter_fault() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.name = "set_err_for_client".to_string();
+        fault.fault_type = FaultVariants::Error;
+        fault.error_msg = Some("SET FAILED for {client_ip}".to_string());
+        fault.command = "SET".to_string();
+        fault.client_ip_filter = Some(vec!["203.0.113.7".parse().unwrap()]);
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+        let red_monkey_server_addr = next_test_ip4();
+        let listener = tokio::net::TcpListener::bind(&red_monkey_server_addr)
+            .await
+            .unwrap();
+        let connection = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            proxy::faulter::Faulter::new(fault_store, Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap()
+        .with_proxy_protocol(true)
+        .with_client_id_mode(ClientIdMode::ProxyProtocol);
+        tokio::spawn(async move {
+            loop {
+                let (socket, _addr) = listener.accept().await.unwrap();
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    let _ = connection.handle(socket).await;
+                });
+            }
+        });
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut responses = Vec::new();
+        for original_client_addr in ["198.51.100.1:50000", "203.0.113.7:50000"] {
+            let header = proxy_protocol::tests::proxy_header(
+                original_client_addr.parse().unwrap(),
+                red_monkey_server_addr,
+            );
+            let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+            stream
+                .write_all(&[&header[..], write_buffer].concat())
+                .await
+                .unwrap();
+
+            let mut read_buffer = Vec::new();
+            stream.read_to_end(&mut read_buffer).await.unwrap();
+            responses.push(read_buffer);
+        }
+        assert_eq!(responses[0], write_buffer);
+        assert_eq!(responses[1], b"-SET FAILED for 203.0.113.7\r\n");
+
+        // a connection without the header is proxied as is
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+
+        // a connection with an invalid header is closed
+        let mut stream = TcpStream::connect(red_monkey_server_addr).await.unwrap();
+        stream
+            .write_all(&[&proxy_protocol::SIGNATURE[..], &[0x11, 0x11, 0, 0]].concat())
+            .await
+            .unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert!(read_buffer.is_empty());
+    }
+
+    /// Runs a mock SOCKS5 proxy without authentication that relays the connections to their
+    /// target, and sends the target address of each connection on the returned channel.
+    async fn run_mock_socks5_proxy(
+        socks5_proxy_addr: SocketAddr,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
+        let listener = tokio::net::TcpListener::bind(socks5_proxy_addr)
+            .await
+            .unwrap();
+        let (targets_tx, targets_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut inbound, _addr) = listener.accept().await.unwrap();
+                let targets_tx = targets_tx.clone();
+                tokio::spawn(async move {
+                    // the greeting offers the authentication methods, "no authentication" is picked
+                    let mut greeting = [0; 2];
+                    inbound.read_exact(&mut greeting).await.unwrap();
+                    let mut methods = vec![0; greeting[1] as usize];
+                    inbound.read_exact(&mut methods).await.unwrap();
+                    assert!(methods.contains(&0));
+                    inbound.write_all(&[5, 0]).await.unwrap();
+
+                    // the CONNECT request to an IPv4 address or a domain name
+                    let mut request = [0; 4];
+                    inbound.read_exact(&mut request).await.unwrap();
+                    assert_eq!(request[..3], [5, 1, 0]);
+                    let host = match request[3] {
+                        1 => {
+                            let mut ip = [0; 4];
+                            inbound.read_exact(&mut ip).await.unwrap();
+                            Ipv4Addr::from(ip).to_string()
+                        }
+                        3 => {
+                            let mut domain = vec![0; inbound.read_u8().await.unwrap() as usize];
+                            inbound.read_exact(&mut domain).await.unwrap();
+                            String::from_utf8(domain).unwrap()
+                        }
+                        address_type => panic!("unexpected address type {}", address_type),
+                    };
+                    let target = format!("{}:{}", host, inbound.read_u16().await.unwrap());
+                    targets_tx.send(target.clone()).unwrap();
+
+                    let mut outbound = TcpStream::connect(&target).await.unwrap();
+                    inbound
+                        .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                        .await
+                        .unwrap();
+                    let _ = io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                });
+            }
+        });
+
+        targets_rx
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_upstream_socks5_proxy() {
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+        let tls_origin_server_addr = next_test_ip4();
+        run_mock_mutual_tls_server(tls_origin_server_addr);
+        let socks5_proxy_addr = next_test_ip4();
+        let mut targets = run_mock_socks5_proxy(socks5_proxy_addr).await;
+
+        let new_connection = |server_addr: &str, is_tls_conn: bool| {
+            Connection::new(
+                OriginServerConfig {
+                    server_addrs: vec![server_addr.to_string()],
+                    is_tls_conn,
+                    pool_size: 0,
+                },
+                proxy::faulter::Faulter::new(
+                    store::mem_store::MemStore::new_db(),
+                    Arc::new(Metrics::new().unwrap()),
+                ),
+            )
+            .unwrap()
+            .with_tls_config(&UpstreamTlsConfig {
+                client_cert_path: Some("testdata/tls/client.crt".to_string()),
+                client_key_path: Some("testdata/tls/client.key".to_string()),
+                ca_cert_path: Some("testdata/tls/ca.crt".to_string()),
+                ..Default::default()
+            })
+            .unwrap()
+            .with_upstream_socks5_proxy(Some(socks5_proxy_addr.to_string()))
+        };
+
+        // the host name of the TCP server is resolved by the proxy, and the TLS session is
+        // tunneled through it
+        let write_buffer = b"*1\r\n$4\r\nping\r\n";
+        for (server_addr, is_tls_conn) in [
+            (format!("localhost:{}", origin_server_addr.port()), false),
+            (tls_origin_server_addr.to_string(), true),
+        ] {
+            let connection = new_connection(&server_addr, is_tls_conn);
+            let mut stream = connection.create_server_stream(&server_addr).await.unwrap();
+            stream.write_all(write_buffer).await.unwrap();
+            let mut read_buffer = [0; 32];
+            let n = stream.read(&mut read_buffer).await.unwrap();
+            assert_eq!(read_buffer[0..n], *write_buffer);
+            assert_eq!(targets.recv().await.unwrap(), server_addr);
+        }
+
+        // the server is not connected to directly when the proxy is unreachable
+        let server_addr = origin_server_addr.to_string();
+        let connection = new_connection(&server_addr, false)
+            .with_upstream_socks5_proxy(Some(next_test_ip4().to_string()));
+        let err = connection
+            .create_server_stream(&server_addr)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("SOCKS5 proxy"), "{}", err);
+    }
 }
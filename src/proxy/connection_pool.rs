@@ -0,0 +1,183 @@
+use crate::proxy::connection::AsyncReadWrite;
+use futures::task::noop_waker_ref;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+type IdleConns = HashMap<String, Vec<Box<dyn AsyncReadWrite>>>;
+
+/// ConnectionPool holds idle, ready-to-use upstream connections keyed by the server address.
+///
+/// The proxy half-closes the upstream connection once the request is forwarded, hence a borrowed
+/// connection is never returned to the pool. Instead, the pool is refilled in the background so
+/// that the TCP/TLS handshake with the origin server is kept off the request path. A single task
+/// refills the pool at a time, see [ConnectionPool::start_refill].
+#[derive(Clone)]
+pub struct ConnectionPool {
+    idle_conns: Arc<Mutex<IdleConns>>,
+    max_size: Arc<AtomicUsize>,
+    refilling: Arc<AtomicBool>,
+}
+
+impl ConnectionPool {
+    /// Creates a new ConnectionPool that holds at most `max_size` idle connections per server
+    /// address.
+    pub fn new(max_size: usize) -> Self {
+        ConnectionPool {
+            idle_conns: Arc::new(Mutex::new(HashMap::new())),
+            max_size: Arc::new(AtomicUsize::new(max_size)),
+            refilling: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the maximum number of idle connections per server address.
+    pub fn max_size(&self) -> usize {
+        self.max_size.load(Ordering::SeqCst)
+    }
+
+    /// Changes the maximum number of idle connections per server address. The idle connections
+    /// beyond the new size are dropped.
+    pub async fn resize(&self, max_size: usize) {
+        let mut idle_conns = self.idle_conns.lock().await;
+        self.max_size.store(max_size, Ordering::SeqCst);
+
+        for conns in idle_conns.values_mut() {
+            conns.truncate(max_size);
+        }
+    }
+
+    /// Marks the pool as being refilled. Returns false when it already is, in which case the
+    /// caller leaves the refill to the task that marked it, such that the concurrent checkouts
+    /// don't race to connect to the origin server.
+    pub fn start_refill(&self) -> bool {
+        !self.refilling.swap(true, Ordering::SeqCst)
+    }
+
+    /// Marks the refill started by [ConnectionPool::start_refill] as done.
+    pub fn end_refill(&self) {
+        self.refilling.store(false, Ordering::SeqCst);
+    }
+
+    /// Takes an idle connection to the given server address out of the pool. The connections the
+    /// origin server closed while they were idle, e.g. after its `timeout`, are dropped.
+    ///
+    /// Returns None when the pool is exhausted.
+    pub async fn get(&self, server_addr: &str) -> Option<Box<dyn AsyncReadWrite>> {
+        let mut conn = None;
+        if let Some(conns) = self.idle_conns.lock().await.get_mut(server_addr) {
+            while let Some(mut idle_conn) = conns.pop() {
+                if is_open(&mut idle_conn) {
+                    conn = Some(idle_conn);
+                    break;
+                }
+                debug!("dropping the closed upstream connection to {}", server_addr);
+            }
+        }
+
+        debug!(
+            "upstream connection to {} taken from the pool: {}",
+            server_addr,
+            conn.is_some()
+        );
+        conn
+    }
+
+    /// Puts an idle connection to the given server address in the pool.
+    ///
+    /// Returns false and drops the connection when the pool is full.
+    pub async fn put(&self, server_addr: &str, conn: Box<dyn AsyncReadWrite>) -> bool {
+        let mut idle_conns = self.idle_conns.lock().await;
+        let conns = idle_conns.entry(server_addr.to_string()).or_default();
+
+        if conns.len() >= self.max_size() {
+            return false;
+        }
+
+        conns.push(conn);
+        true
+    }
+
+    /// Returns the number of idle connections to the given server address.
+    pub async fn idle_count(&self, server_addr: &str) -> usize {
+        self.idle_conns
+            .lock()
+            .await
+            .get(server_addr)
+            .map_or(0, |conns| conns.len())
+    }
+}
+
+/// Returns true if the idle connection is still open, i.e. a read of it would block. A read that
+/// completes tells that the origin server closed the connection, or that it sent something that
+/// isn't the reply of any request.
+fn is_open(conn: &mut Box<dyn AsyncReadWrite>) -> bool {
+    let mut buf = [0; 1];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    matches!(
+        Pin::new(conn).poll_read(&mut cx, &mut read_buf),
+        Poll::Pending
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::connection::tests::{next_test_ip4, run_mock_origin_server};
+    use tokio::net::TcpStream;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_connection_pool() {
+        let server_addr = next_test_ip4();
+        run_mock_origin_server(server_addr);
+        let server_addr = server_addr.to_string();
+
+        let pool = ConnectionPool::new(2);
+        assert!(pool.get(&server_addr).await.is_none());
+
+        for _ in 0..2 {
+            let conn = TcpStream::connect(&server_addr).await.unwrap();
+            assert!(pool.put(&server_addr, Box::new(conn)).await);
+        }
+
+        let conn = TcpStream::connect(&server_addr).await.unwrap();
+        assert!(!pool.put(&server_addr, Box::new(conn)).await);
+        assert_eq!(pool.idle_count(&server_addr).await, 2);
+
+        assert!(pool.get(&server_addr).await.is_some());
+        assert_eq!(pool.idle_count(&server_addr).await, 1);
+        assert_eq!(pool.idle_count("127.0.0.1:1").await, 0);
+
+        pool.resize(0).await;
+        assert_eq!(pool.max_size(), 0);
+        assert_eq!(pool.idle_count(&server_addr).await, 0);
+
+        // a single refill runs at a time
+        assert!(pool.start_refill());
+        assert!(!pool.start_refill());
+        pool.end_refill();
+        assert!(pool.start_refill());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_connection_pool_closed_conn() {
+        // the origin server closes the connections right away
+        let listener = std::net::TcpListener::bind(next_test_ip4()).unwrap();
+        let server_addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || for _ in listener.incoming() {});
+
+        let pool = ConnectionPool::new(1);
+        let conn = TcpStream::connect(&server_addr).await.unwrap();
+        assert!(pool.put(&server_addr, Box::new(conn)).await);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert!(pool.get(&server_addr).await.is_none());
+        assert_eq!(pool.idle_count(&server_addr).await, 0);
+    }
+}
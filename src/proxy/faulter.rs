@@ -1,15 +1,46 @@
+use crate::events::{FaultEvent, FaultEventPublisher, FaultEventSender};
+use crate::experiment::{ExperimentEvent, ExperimentLog};
+use crate::metrics::{self, Metrics};
 use crate::proxy::resp_util;
-use crate::store::fault_store::{Fault, FaultVariants, DB};
+use crate::store::fault_store::{
+    Fault, FaultRequest, FaultSelectionStrategy, FaultVariants, PlaybookDB, SubFault, DB,
+};
+use cadence::prelude::*;
+use cadence::{NopMetricSink, StatsdClient};
+use rand::Rng;
+use socket2::Socket;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::{str, time};
+use strum_macros::EnumString;
+use tokio::sync::watch;
 use tokio::time::sleep;
-use tokio::{io, io::AsyncWriteExt};
-use tracing::{debug, error, info};
+use tokio::{
+    io,
+    io::{AsyncWrite, AsyncWriteExt},
+};
+use tracing::{debug, error, field, info, Span};
+use uuid::Uuid;
 
 /// Faulter implements the logic that determines whether any of the configured fault is to be
 /// executed for a request. Also, it takes care of executing the matched or chosen fault.
 #[derive(Clone)]
 pub struct Faulter {
     fault_store: DB,
+    playbook_store: Option<PlaybookDB>,
+    metrics: Arc<Metrics>,
+    statsd: Arc<StatsdClient>,
+    experiment_log: Option<ExperimentLog>,
+    fault_event_sender: Option<FaultEventSender>,
+    fault_event_publisher: Option<Arc<dyn FaultEventPublisher>>,
+    selection_strategy: FaultSelectionStrategy,
+    /// The port of the proxy listener the faulter applies the faults of, None for a Unix domain
+    /// socket listener
+    proxy_port: Option<u16>,
+    /// The shutdown signal of the proxy, which releases the connections held by timeout faults
+    shutdown: watch::Receiver<bool>,
+    /// The commands that are never faulted along with the [resp_util::BYPASS_COMMANDS]
+    bypass_commands: Arc<Vec<String>>,
 }
 
 /// Context holds the relevant object that is required to execute fault of certain type.
@@ -17,7 +48,71 @@ pub struct Faulter {
 /// Note: As we add more fault configuration like injecting faults based on client IP address,
 /// those data can be held in the Context struct.
 pub struct Context<'a, 'b> {
-    pub client_tcp_write_stream: &'a mut tokio::net::tcp::WriteHalf<'b>,
+    pub request_id: Uuid,
+    /// The client the faults are matched against, e.g. by their `client_ip_filter`
+    pub client_identity: ClientIdentity,
+    pub client_write_stream: &'a mut (dyn AsyncWrite + Unpin + Send + 'b),
+    /// A duplicate of the client TCP socket to reset the connection, None for the clients of a
+    /// Unix domain socket
+    pub client_socket: Option<Socket>,
+}
+
+/// ClientIdMode tells how the [ClientIdentity] of a client connection is resolved, see the
+/// `CLIENT_ID_HEADER_MODE` config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ClientIdMode {
+    /// The TCP peer address of the client connection, which is the address of the last proxy in
+    /// front of red-monkey, if any
+    #[default]
+    Socket,
+    /// The original client address of the proxy protocol header prepended by a trusted load
+    /// balancer, see [crate::proxy::proxy_protocol]
+    ProxyProtocol,
+    /// The clients are not identified
+    None,
+}
+
+impl ClientIdMode {
+    /// Resolves the identity of a client connection from its TCP peer address and the original
+    /// client address of its proxy protocol header, if any.
+    pub fn client_identity(
+        &self,
+        peer_addr: Option<SocketAddr>,
+        proxy_protocol_addr: Option<SocketAddr>,
+    ) -> ClientIdentity {
+        match self {
+            ClientIdMode::Socket => {
+                peer_addr.map_or(ClientIdentity::Unknown, ClientIdentity::Socket)
+            }
+            ClientIdMode::ProxyProtocol => {
+                proxy_protocol_addr.map_or(ClientIdentity::Unknown, ClientIdentity::ProxyProtocol)
+            }
+            ClientIdMode::None => ClientIdentity::Unknown,
+        }
+    }
+}
+
+/// ClientIdentity is the client of a connection as resolved by the [ClientIdMode] of the proxy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientIdentity {
+    /// The TCP peer address of the client connection
+    Socket(SocketAddr),
+    /// The original client address of the proxy protocol header of the connection
+    ProxyProtocol(SocketAddr),
+    /// The client is not identified, e.g. a client of a Unix domain socket or a connection without
+    /// a proxy protocol header. No fault with a `client_ip_filter` matches it.
+    Unknown,
+}
+
+impl ClientIdentity {
+    /// Returns the address of the client, None when it is unknown.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        match self {
+            ClientIdentity::Socket(addr) | ClientIdentity::ProxyProtocol(addr) => Some(*addr),
+            ClientIdentity::Unknown => None,
+        }
+    }
 }
 
 /// RequestAction tells what the request processor (proxy handler) should do after a fault is
@@ -26,33 +121,169 @@ pub struct Context<'a, 'b> {
 pub enum RequestAction {
     Exit,
     Fallthrough,
+    /// Proxy the request, but forward only the given number of response bytes to the client
+    PartialResponse {
+        truncate_after_bytes: u64,
+    },
+    /// Proxy the request, but forward the response to the client at the given byte rate
+    SlowDrain {
+        bytes_per_ms: u64,
+    },
+    /// Proxy the request, but replace the matches of the pattern in the response
+    MutateResponse {
+        mutation_pattern: String,
+        mutation_replacement: String,
+    },
+    /// Proxy the request, but replace the results of the failed commands of a `MULTI` / `EXEC`
+    /// transaction in the reply of `EXEC`, which is the `exec_reply_index`th reply of the request
+    FailQueuedCommands {
+        exec_reply_index: usize,
+        errors: Vec<Option<String>>,
+    },
 }
 
 impl Faulter {
-    pub fn new(fault_store: DB) -> Self {
-        Faulter { fault_store }
+    pub fn new(fault_store: DB, metrics: Arc<Metrics>) -> Self {
+        Faulter {
+            fault_store,
+            playbook_store: None,
+            metrics,
+            statsd: Arc::new(StatsdClient::from_sink("", NopMetricSink)),
+            experiment_log: None,
+            fault_event_sender: None,
+            fault_event_publisher: None,
+            selection_strategy: FaultSelectionStrategy::default(),
+            proxy_port: None,
+            // the sender is dropped, hence a timeout fault holds the connection forever
+            shutdown: watch::channel(false).1,
+            bypass_commands: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Makes the faulter report the applied faults and the injected delays to the given StatsD
+    /// client as well.
+    pub fn with_statsd(mut self, statsd: Arc<StatsdClient>) -> Self {
+        self.statsd = statsd;
+        self
+    }
+
+    /// Makes the faulter record every injected fault to the given experiment log.
+    pub fn with_experiment_log(mut self, experiment_log: ExperimentLog) -> Self {
+        self.experiment_log = Some(experiment_log);
+        self
+    }
+
+    /// Makes the faulter publish every injected fault to the live event streams of the given
+    /// sender.
+    pub fn with_fault_events(mut self, fault_event_sender: FaultEventSender) -> Self {
+        self.fault_event_sender = Some(fault_event_sender);
+        self
+    }
+
+    /// Makes the faulter publish every executed fault to the message bus of the given publisher.
+    pub fn with_fault_event_publisher(
+        mut self,
+        fault_event_publisher: Arc<dyn FaultEventPublisher>,
+    ) -> Self {
+        self.fault_event_publisher = Some(fault_event_publisher);
+        self
+    }
+
+    /// Makes the faulter choose the fault of a request among the faults of its command with the
+    /// given strategy.
+    pub fn with_selection_strategy(mut self, selection_strategy: FaultSelectionStrategy) -> Self {
+        self.selection_strategy = selection_strategy;
+        self
+    }
+
+    /// Returns the metrics the faulter reports to.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Makes the faulter apply only the faults that are not scoped to another proxy port than the
+    /// given port of its listener, see [Fault::matches_proxy_port].
+    pub fn with_proxy_port(mut self, proxy_port: Option<u16>) -> Self {
+        self.proxy_port = proxy_port;
+        self
+    }
+
+    /// Makes the timeout faults release the connections they hold once the shutdown is signalled
+    /// through the given watch channel of the proxy, see [crate::proxy::server::run].
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Makes the faulter exempt the given commands from all the faults, like the
+    /// [resp_util::BYPASS_COMMANDS].
+    pub fn with_bypass_commands(mut self, bypass_commands: Vec<String>) -> Self {
+        self.bypass_commands = Arc::new(bypass_commands);
+        self
+    }
+
+    /// Returns true if the Redis command is exempt from all the faults, ignoring the case.
+    fn is_bypass_command(&self, redis_command: &str) -> bool {
+        resp_util::is_bypass_command(redis_command)
+            || self
+                .bypass_commands
+                .iter()
+                .any(|bypass_command| bypass_command.eq_ignore_ascii_case(redis_command))
+    }
+
+    /// Makes the faulter apply the active playbooks of the given store before the individual
+    /// faults.
+    pub fn with_playbook_store(mut self, playbook_store: PlaybookDB) -> Self {
+        self.playbook_store = Some(playbook_store);
+        self
     }
 
     /// check_fault checks if the request matches with any fault configuration.
     ///
+    /// An active playbook takes precedence over the individual faults: the fault of its current
+    /// step, if any, is the only fault that can be applied to the request.
+    ///
     /// # Arguments
+    /// request_id - id of the connection being handled, recorded on the span
     /// req_body - request body
-    #[tracing::instrument(name = "Check fault", skip(self, req_body))]
-    pub async fn check_fault(&self, req_body: &str) -> Result<Option<Fault>, anyhow::Error> {
+    /// client_addr - address of the client, matched against the `client_ip_filter` of the fault
+    /// database - the logical database the client is on, matched against the `database` of the
+    /// fault
+    #[tracing::instrument(
+        name = "Check fault",
+        skip(self, request_id, req_body),
+        fields(request_id = field::Empty)
+    )]
+    pub async fn check_fault(
+        &self,
+        request_id: &Uuid,
+        req_body: &str,
+        client_addr: Option<SocketAddr>,
+        database: u8,
+    ) -> Result<Option<Fault>, anyhow::Error> {
+        Span::current().record("request_id", field::display(request_id));
+
         let redis_command: String;
+        let redis_key: Option<String>;
+        let redis_args: Vec<Option<String>>;
         let result = resp_util::decode(req_body);
 
         match result {
-            Ok(val) => match resp_util::fetch_redis_command(val) {
-                Ok(command) => {
-                    debug!("redis command: {}", command);
-                    redis_command = command;
-                }
-                Err(err) => {
-                    error!("error fetching redis command from req: {:?}", err);
-                    return Err(err);
+            Ok(val) => {
+                redis_key = resp_util::fetch_redis_key(&val);
+                redis_args = resp_util::fetch_redis_args(&val);
+
+                match resp_util::fetch_redis_command(val) {
+                    Ok(command) => {
+                        debug!("redis command: {}", command);
+                        redis_command = command;
+                    }
+                    Err(err) => {
+                        error!("error fetching redis command from req: {:?}", err);
+                        return Err(err);
+                    }
                 }
-            },
+            }
 
             Err(err) => {
                 error!("error decoding request body: {:?}", err);
@@ -60,13 +291,45 @@ impl Faulter {
             }
         };
 
-        let fault_store = self.fault_store.read().await;
+        // the PubSub connections are proxied as is, see Connection::pipe
+        if resp_util::is_pubsub_command(&redis_command) {
+            debug!("not faulting the PubSub command: {}", redis_command);
+            return Ok(None);
+        }
+
+        if self.is_bypass_command(&redis_command) {
+            debug!("not faulting the bypass command: {}", redis_command);
+            return Ok(None);
+        }
+
+        let request = FaultRequest {
+            command: &redis_command,
+            key: redis_key.as_deref(),
+            args: &redis_args,
+            client_addr,
+            database,
+            proxy_port: self.proxy_port,
+        };
 
-        let fault_config = fault_store.get_by_redis_cmd(redis_command.as_str());
-        match fault_config {
-            Some(fault) => Ok(Some(fault)),
-            None => Ok(None),
+        if let Some(step) = self
+            .playbook_store
+            .as_ref()
+            .and_then(|playbook_store| playbook_store.next_step())
+        {
+            debug!("playbook step: {:?}", step);
+            let fault = match step.fault_name {
+                Some(fault_name) => self.fault_store.get_by_fault_name(&fault_name).await.ok(),
+                None => None,
+            };
+            return Ok(fault.filter(|fault| request.matches(fault)));
         }
+
+        // the faults whose scope doesn't match the request are not candidates, such that they
+        // don't hide the other faults of the command
+        Ok(self
+            .fault_store
+            .get_by_redis_cmd(&request, self.selection_strategy)
+            .await)
     }
 
     /// Executes the fault that is passed as an argument.
@@ -74,35 +337,373 @@ impl Faulter {
     /// # Arguments
     /// - ctx - Context holds the write half of the client TCP stream
     /// - fault - Optional `Fault`. If the fault is optional it means no fault matched to be executed
-    #[tracing::instrument(name = "Executing fault", skip(self, ctx))]
+    #[tracing::instrument(
+        name = "Executing fault",
+        skip(self, ctx),
+        fields(request_id = field::Empty)
+    )]
     pub async fn execute_fault<'a, 'b, 'c>(
         &self,
         ctx: &'a mut Context<'b, 'c>,
         fault: Option<Fault>,
     ) -> Result<RequestAction, anyhow::Error> {
+        Span::current().record("request_id", field::display(&ctx.request_id));
+
         let fault = match fault {
             Some(f) => f,
             None => {
                 return Ok(RequestAction::Fallthrough);
             }
         };
+        if fault.one_shot && !self.claim_fault(&fault.name).await {
+            return Ok(RequestAction::Fallthrough);
+        }
+        // the delay is planned before the hit is reserved, which records it in the fault stats
+        let delay_ms = match fault.fault_type {
+            FaultVariants::Delay => self
+                .delay_duration(&fault)
+                .await
+                .map(|duration| add_jitter(duration, fault.jitter_ms)),
+            _ => None,
+        };
+        if !self.reserve_hit(&fault, delay_ms).await {
+            return Ok(RequestAction::Fallthrough);
+        }
+        self.count_fault(&fault);
+        self.record_experiment_event(ctx.client_identity.addr(), &fault)
+            .await;
+        self.publish_fault_event(&fault);
+        // the event is published once the fault is executed, which consumes the fault
+        let bus_event = self
+            .fault_event_publisher
+            .as_ref()
+            .map(|_| FaultEvent::new(&fault));
 
-        match fault.fault_type {
+        let action = match fault.fault_type {
             FaultVariants::DropConn => {
                 info!("executing drop fault: dropping the client connection");
-                ctx.client_tcp_write_stream.shutdown().await?;
-                Ok(RequestAction::Exit)
+                ctx.client_write_stream.shutdown().await?;
+                RequestAction::Exit
+            }
+            FaultVariants::TcpReset => {
+                info!("executing tcp reset fault: resetting the client connection");
+                execute_tcp_reset_fault(ctx).await?;
+                RequestAction::Exit
             }
             FaultVariants::Delay => {
                 info!("executing delay fault");
-                execute_delay_fault(fault.duration).await;
-                Ok(RequestAction::Fallthrough)
+                if let Some(delay) = execute_delay_fault(delay_ms, None).await {
+                    self.record_delay(&fault.name, delay);
+                }
+                RequestAction::Fallthrough
             }
             FaultVariants::Error => {
                 info!("executing error fault");
-                execute_error_fault(ctx, fault).await?;
+                execute_error_fault(
+                    ctx,
+                    fault.error_msg,
+                    fault.burst_count,
+                    fault.burst_interval_ms,
+                )
+                .await?;
+                RequestAction::Exit
+            }
+            FaultVariants::PartialResponse => {
+                info!("executing partial response fault");
+                RequestAction::PartialResponse {
+                    truncate_after_bytes: fault
+                        .truncate_after_bytes
+                        .ok_or(FaulterErrors::TruncateAfterBytesMissingError)?,
+                }
+            }
+            FaultVariants::SlowDrain => {
+                info!("executing slow drain fault");
+                RequestAction::SlowDrain {
+                    bytes_per_ms: fault
+                        .bytes_per_ms
+                        .ok_or(FaulterErrors::BytesPerMsMissingError)?,
+                }
+            }
+            FaultVariants::MutateResponse => {
+                info!("executing mutate response fault");
+                RequestAction::MutateResponse {
+                    mutation_pattern: fault
+                        .mutation_pattern
+                        .ok_or(FaulterErrors::MutationPatternMissingError)?,
+                    mutation_replacement: fault.mutation_replacement.unwrap_or_default(),
+                }
+            }
+            FaultVariants::Composite => {
+                info!("executing composite fault");
+                let sub_faults = fault
+                    .sub_faults
+                    .ok_or(FaulterErrors::SubFaultsMissingError)?;
+
+                let mut action = RequestAction::Fallthrough;
+                for sub_fault in sub_faults {
+                    action = self.execute_sub_fault(ctx, &fault.name, sub_fault).await?;
+                    if action == RequestAction::Exit {
+                        break;
+                    }
+                }
+                action
+            }
+            FaultVariants::Timeout => {
+                info!("executing timeout fault: holding the client connection");
+                // The timeout fault holds the connection until the proxy shuts down, hence the
+                // fault is published before it is executed.
+                self.publish_to_bus(bus_event).await;
+                execute_timeout_fault(self.shutdown.clone()).await;
+                return Ok(RequestAction::Exit);
+            }
+        };
+
+        self.publish_to_bus(bus_event).await;
+
+        Ok(action)
+    }
+
+    /// Executes the error fault of a command queued in a `MULTI` / `EXEC` transaction. The error
+    /// replaces the result of the command in the reply of `EXEC` rather than being written to the
+    /// client, hence the error message is returned instead.
+    ///
+    /// Returns None when the fault is not an error fault, which is executed with
+    /// [Faulter::execute_fault] instead.
+    pub async fn execute_queued_error_fault(
+        &self,
+        client_addr: Option<SocketAddr>,
+        fault: Option<&Fault>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let fault = match fault {
+            Some(fault) if fault.fault_type == FaultVariants::Error => fault,
+            _ => return Ok(None),
+        };
+        if fault.one_shot && !self.claim_fault(&fault.name).await {
+            return Ok(None);
+        }
+        if !self.reserve_hit(fault, None).await {
+            return Ok(None);
+        }
+
+        info!("executing error fault of the queued command");
+        self.count_fault(fault);
+        self.record_experiment_event(client_addr, fault).await;
+        self.publish_fault_event(fault);
+        let error_msg = error_message(client_addr, fault.error_msg.clone())?;
+        if self.fault_event_publisher.is_some() {
+            self.publish_to_bus(Some(FaultEvent::new(fault))).await;
+        }
+
+        Ok(Some(error_msg))
+    }
+
+    /// Records the duration of the delay injected by the fault in the Prometheus and StatsD
+    /// metrics. The delay of a sub-fault is recorded under the name of its composite fault.
+    fn record_delay(&self, fault_name: &str, delay: time::Duration) {
+        self.metrics
+            .delay_duration_milliseconds
+            .observe(delay.as_millis() as f64);
+        if let Err(err) = self.statsd.time(
+            &format!("delay.{}", metrics::statsd_name(fault_name)),
+            delay.as_millis() as u64,
+        ) {
+            debug!("error sending the delay metric to StatsD: {}", err);
+        }
+    }
+
+    /// Counts the executed fault in the Prometheus and StatsD metrics.
+    fn count_fault(&self, fault: &Fault) {
+        self.metrics
+            .faults_total
+            .with_label_values(&[&fault.name, &format!("{:?}", fault.fault_type)])
+            .inc();
+        if let Err(err) = self.statsd.incr(&format!(
+            "faults.{}.{:?}",
+            metrics::statsd_name(&fault.name),
+            fault.fault_type
+        )) {
+            debug!("error sending the fault metric to StatsD: {}", err);
+        }
+    }
+
+    /// Records the injected fault to the experiment log, if any. A failure to record the event is
+    /// only logged as the fault is injected anyway.
+    async fn record_experiment_event(&self, client_addr: Option<SocketAddr>, fault: &Fault) {
+        let experiment_log = match &self.experiment_log {
+            Some(experiment_log) => experiment_log,
+            None => return,
+        };
+
+        let event = ExperimentEvent {
+            ts: chrono::Utc::now(),
+            fault_name: fault.name.clone(),
+            command: fault.command.clone(),
+            client_ip: client_addr.map(|addr| addr.ip().to_string()),
+            action: format!("{:?}", fault.fault_type),
+        };
+        if let Err(err) = experiment_log.record(&event).await {
+            error!(
+                "error recording fault {} to the experiment log: {}",
+                fault.name, err
+            );
+        }
+    }
+
+    /// Publishes the injected fault to the live event streams, if any stream is subscribed.
+    fn publish_fault_event(&self, fault: &Fault) {
+        let fault_event_sender = match &self.fault_event_sender {
+            Some(fault_event_sender) if fault_event_sender.receiver_count() > 0 => {
+                fault_event_sender
+            }
+            _ => return,
+        };
+
+        // the streams may all be gone since, in which case the event is dropped
+        let _ = fault_event_sender.send(FaultEvent::new(fault));
+    }
+
+    /// Publishes the event of the executed fault to the message bus, if any. A failure to publish
+    /// the event is only logged as the fault is executed anyway.
+    async fn publish_to_bus(&self, event: Option<FaultEvent>) {
+        let (fault_event_publisher, event) = match (&self.fault_event_publisher, event) {
+            (Some(fault_event_publisher), Some(event)) => (fault_event_publisher, event),
+            _ => return,
+        };
+
+        if let Err(err) = fault_event_publisher.publish_fault_event(&event).await {
+            error!(
+                "error publishing fault {} to the message bus: {}",
+                event.fault_name, err
+            );
+        }
+    }
+
+    /// Executes a sub-fault of the composite fault of the given name. Only the delay, error and
+    /// drop connection faults can be chained.
+    async fn execute_sub_fault<'a, 'b, 'c>(
+        &self,
+        ctx: &'a mut Context<'b, 'c>,
+        fault_name: &str,
+        sub_fault: SubFault,
+    ) -> Result<RequestAction, anyhow::Error> {
+        match sub_fault.fault_type {
+            FaultVariants::Delay => {
+                info!("executing delay sub-fault");
+                if let Some(delay) = execute_delay_fault(sub_fault.duration, None).await {
+                    self.record_delay(fault_name, delay);
+                }
+                Ok(RequestAction::Fallthrough)
+            }
+            FaultVariants::Error => {
+                info!("executing error sub-fault");
+                execute_error_fault(ctx, sub_fault.error_msg, None, None).await?;
+                Ok(RequestAction::Exit)
+            }
+            FaultVariants::DropConn => {
+                info!("executing drop sub-fault: dropping the client connection");
+                ctx.client_write_stream.shutdown().await?;
                 Ok(RequestAction::Exit)
             }
+            fault_type => Err(FaulterErrors::UnsupportedSubFaultError(fault_type).into()),
+        }
+    }
+
+    /// Returns the duration of the delay fault. With `adaptive_delay_step_ms`, the duration grows by
+    /// the step on each previous hit of the fault, up to `max_adaptive_duration_ms`.
+    async fn delay_duration(&self, fault: &Fault) -> Option<u64> {
+        let step = match fault.adaptive_delay_step_ms {
+            Some(step) => step,
+            None => return fault.duration,
+        };
+
+        let hit_count = match self.fault_store.get_fault_hit_count(&fault.name).await {
+            Ok(hit_count) => hit_count,
+            Err(err) => {
+                error!("error fetching hit count of fault {}: {}", fault.name, err);
+                0
+            }
+        };
+
+        fault.duration.map(|duration| {
+            adaptive_duration(duration, step, fault.max_adaptive_duration_ms, hit_count)
+        })
+    }
+
+    /// Claims a one-shot fault before it is executed by disabling it, see
+    /// [FaultStore::claim_fault]. Only the request that wins the claim executes the fault, such
+    /// that the concurrent requests matching the fault are not all faulted. The fault is not
+    /// executed when it fails to be claimed.
+    ///
+    /// [FaultStore::claim_fault]: crate::store::fault_store::FaultStore::claim_fault
+    async fn claim_fault(&self, fault_name: &str) -> bool {
+        match self.fault_store.claim_fault(fault_name).await {
+            Ok(true) => {
+                info!("one-shot fault {} claimed", fault_name);
+                true
+            }
+            Ok(false) => {
+                debug!("one-shot fault {} is already claimed", fault_name);
+                false
+            }
+            Err(err) => {
+                error!("error claiming one-shot fault {}: {}", fault_name, err);
+                false
+            }
+        }
+    }
+
+    /// Records the hit of the fault, with the delay it is going to inject if any, before it is
+    /// executed. The hit of a fault with `max_hits` is reserved atomically, see
+    /// [FaultStore::reserve_hit], such that the concurrent requests matching the fault are faulted
+    /// exactly `max_hits` times; the fault is disabled on its last hit.
+    ///
+    /// Returns whether the fault is executed: a fault that already reached its `max_hits`, or whose
+    /// hit fails to be reserved, is not. A failure to record the hit of a fault without
+    /// `max_hits` is only logged.
+    ///
+    /// [FaultStore::reserve_hit]: crate::store::fault_store::FaultStore::reserve_hit
+    async fn reserve_hit(&self, fault: &Fault, delay_ms: Option<u64>) -> bool {
+        let max_hits = match fault.max_hits {
+            Some(max_hits) => max_hits,
+            None => {
+                match self.fault_store.record_hit(&fault.name, delay_ms).await {
+                    Ok(hit_count) => debug!("fault {} hit count: {}", fault.name, hit_count),
+                    Err(err) => error!("error recording hit of fault {}: {}", fault.name, err),
+                }
+                return true;
+            }
+        };
+
+        match self
+            .fault_store
+            .reserve_hit(&fault.name, max_hits, delay_ms)
+            .await
+        {
+            Ok(Some(hit_count)) => {
+                debug!("fault {} hit count: {}", fault.name, hit_count);
+                if hit_count >= max_hits {
+                    self.disable_fault(&fault.name).await;
+                }
+                true
+            }
+            Ok(None) => {
+                debug!("fault {} already reached its max hits", fault.name);
+                false
+            }
+            Err(err) => {
+                error!("error reserving hit of fault {}: {}", fault.name, err);
+                false
+            }
+        }
+    }
+
+    /// Disables a fault on its last hit, such that the subsequent requests are not faulted. A
+    /// failure to disable the fault is only logged as its hits are already limited by
+    /// `max_hits`.
+    async fn disable_fault(&self, fault_name: &str) {
+        match self.fault_store.disable_fault(fault_name).await {
+            Ok(_) => info!("fault {} disabled", fault_name),
+            Err(err) => error!("error disabling fault {}: {}", fault_name, err),
         }
     }
 }
@@ -113,44 +714,133 @@ impl Faulter {
 /// large number of sleep fault execution. `tokio::time::sleep` helps us to sleep asynchronously,
 /// such that the current thread of fault execution won't be blocked. More about tokio sleep can be
 /// found here - <https://docs.rs/tokio/0.3.1/tokio/time/fn.sleep.html>.
+///
+/// The given duration is the effective duration of the fault, e.g. including the adaptive step
+/// of its previous hits, see [adaptive_duration].
+///
+/// When `jitter_ms` is set, a uniformly distributed random value in `[0, jitter_ms]` is added to
+/// the sleep duration to avoid an unrealistic uniform latency spike.
+///
+/// Returns the injected delay, or None when no duration is set.
 #[tracing::instrument(name = "Injecting delay fault")]
-pub async fn execute_delay_fault(sleep_duration: Option<u64>) {
-    if let Some(sleep_duration) = sleep_duration {
-        let sleep_duration = time::Duration::from_millis(sleep_duration);
+pub async fn execute_delay_fault(
+    sleep_duration: Option<u64>,
+    jitter_ms: Option<u64>,
+) -> Option<time::Duration> {
+    let sleep_duration = time::Duration::from_millis(add_jitter(sleep_duration?, jitter_ms));
 
-        info!("Sleeping for {:?}", sleep_duration);
-        sleep(sleep_duration).await;
-    };
+    info!("Sleeping for {:?}", sleep_duration);
+    sleep(sleep_duration).await;
+    Some(sleep_duration)
+}
+
+/// Executes the timeout fault by holding the client connection open without sending any bytes
+/// back, simulating a hung upstream. The connection is only released when the shutdown of the
+/// proxy is signalled through the given watch channel.
+#[tracing::instrument(name = "Injecting timeout fault", skip(shutdown))]
+pub async fn execute_timeout_fault(mut shutdown: watch::Receiver<bool>) {
+    // The shutdown may already be signalled by the time the fault is executed
+    let is_shutdown = shutdown.wait_for(|is_shutdown| *is_shutdown).await.is_ok();
+    if !is_shutdown {
+        // The sender is gone without signalling the shutdown, hence it never will
+        std::future::pending::<()>().await;
+    }
+    info!("releasing the connection held by the timeout fault on shutdown");
+}
+
+/// Returns the duration of an adaptive delay fault that has already been hit `hit_count` times,
+/// i.e. `duration + hit_count * step`, capped at `max_duration` when it is set.
+fn adaptive_duration(duration: u64, step: u64, max_duration: Option<u64>, hit_count: u64) -> u64 {
+    let duration = duration.saturating_add(hit_count.saturating_mul(step));
+    match max_duration {
+        Some(max_duration) => duration.min(max_duration),
+        None => duration,
+    }
+}
+
+/// Adds a uniformly distributed random jitter in `[0, jitter_ms]` to the given duration.
+fn add_jitter(duration: u64, jitter_ms: Option<u64>) -> u64 {
+    match jitter_ms {
+        Some(jitter_ms) if jitter_ms > 0 => {
+            duration.saturating_add(rand::thread_rng().gen_range(0..=jitter_ms))
+        }
+        _ => duration,
+    }
+}
+
+/// Executes the TCP reset fault by setting a zero linger timeout on the client socket, which makes
+/// the OS send a TCP RST instead of a FIN once the connection is closed on exit. The write half is
+/// not shut down as it would send a FIN first.
+///
+/// The clients of a Unix domain socket have no TCP connection to reset, hence their connection is
+/// closed gracefully.
+#[tracing::instrument(name = "Injecting tcp reset fault", skip(ctx))]
+pub async fn execute_tcp_reset_fault<'a, 'b, 'c>(
+    ctx: &'a mut Context<'b, 'c>,
+) -> Result<(), anyhow::Error> {
+    match &ctx.client_socket {
+        Some(client_socket) => client_socket.set_linger(Some(time::Duration::ZERO))?,
+        None => ctx.client_write_stream.shutdown().await?,
+    }
+
+    Ok(())
+}
+
+/// The placeholder of the error message that is replaced by the client IP address
+const CLIENT_IP_PLACEHOLDER: &str = "{client_ip}";
+
+/// Returns the error message of the custom error fault, with the `{client_ip}` placeholder replaced
+/// by the client IP address, or `unknown` when the client is not identified.
+fn error_message(
+    client_addr: Option<SocketAddr>,
+    error_msg: Option<String>,
+) -> Result<String, anyhow::Error> {
+    let client_ip = client_addr.map_or_else(|| "unknown".to_string(), |addr| addr.ip().to_string());
+
+    Ok(error_msg
+        .ok_or_else(|| Box::new(FaulterErrors::EncodeErrMsgError))?
+        .replace(CLIENT_IP_PLACEHOLDER, &client_ip))
 }
 
 /// Executes the given custom error fault.
 ///
+/// - The `{client_ip}` placeholder in the error message is replaced by the client IP address, or
+///   `unknown` when the client is not identified, see [ClientIdentity].
 /// - The error message will be RESP encoded.
-/// - The encoded error message is then written in the client TCP write direction.
-/// - The client TCP  write half is closed.
+/// - The encoded error message is then written in the client TCP write direction. With
+///   `burst_count`, the error message is written `burst_count` times, waiting `burst_interval_ms`
+///   between the writes, such that the client sees a burst of errors.
+/// - The client TCP  write half is closed after the last error message.
 #[tracing::instrument(name = "Applying error fault", skip(ctx))]
 pub async fn execute_error_fault<'a, 'b, 'c>(
     ctx: &'a mut Context<'b, 'c>,
-    fault: Fault,
+    error_msg: Option<String>,
+    burst_count: Option<u32>,
+    burst_interval_ms: Option<u64>,
 ) -> Result<(), anyhow::Error> {
-    let encoded_err_msg = resp_util::encode_error_message(
-        fault
-            .error_msg
-            .ok_or_else(|| Box::new(FaulterErrors::EncodeErrMsgError))?,
-    )?;
+    let encoded_err_msg =
+        resp_util::encode_error_message(error_message(ctx.client_identity.addr(), error_msg)?)?;
+    let burst_count = burst_count.unwrap_or(1).max(1);
+    let burst_interval = time::Duration::from_millis(burst_interval_ms.unwrap_or(0));
 
     let server_to_client = async {
-        io::copy(
-            &mut String::from_utf8_lossy(&encoded_err_msg)
-                .to_string()
-                .as_str()
-                .as_bytes(),
-            &mut ctx.client_tcp_write_stream,
-        )
-        .await?;
+        for burst in 1..=burst_count {
+            io::copy(
+                &mut String::from_utf8_lossy(&encoded_err_msg)
+                    .to_string()
+                    .as_bytes(),
+                &mut ctx.client_write_stream,
+            )
+            .await?;
+            ctx.client_write_stream.flush().await?;
+            debug!("error value {}/{} wrote to the client", burst, burst_count);
+
+            if burst < burst_count {
+                sleep(burst_interval).await;
+            }
+        }
 
-        debug!("error value wrote to the client");
-        ctx.client_tcp_write_stream.shutdown().await
+        ctx.client_write_stream.shutdown().await
     };
 
     server_to_client.await?;
@@ -158,10 +848,21 @@ pub async fn execute_error_fault<'a, 'b, 'c>(
     Ok(())
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum FaulterErrors {
     #[error("Error decoding request body to RESP values")]
     EncodeErrMsgError,
+    #[error("Error as truncate_after_bytes is not set for the partial response fault")]
+    TruncateAfterBytesMissingError,
+    #[error("Error as bytes_per_ms is not set for the slow drain fault")]
+    BytesPerMsMissingError,
+    #[error("Error as mutation_pattern is not set for the mutate response fault")]
+    MutationPatternMissingError,
+    #[error("Error as sub_faults is not set for the composite fault")]
+    SubFaultsMissingError,
+    #[error("Error as {0:?} is not supported as a sub-fault of the composite fault")]
+    UnsupportedSubFaultError(FaultVariants),
 }
 
 #[cfg(test)]
@@ -169,7 +870,9 @@ mod tests {
     use super::*;
     use crate::proxy::connection::tests::{next_test_ip4, run_mock_origin_server};
     use crate::store;
-    use crate::store::fault_store::DB;
+    use crate::store::fault_store::{
+        ArgMatcher, FaultPlaybook, MatchType, PlaybookStep, DB, WILDCARD_COMMAND,
+    };
     use chrono::{Duration, Utc};
     use std::time::Instant;
     use tokio::io::{AsyncReadExt, ErrorKind};
@@ -178,31 +881,20 @@ mod tests {
     async fn get_mock_fault_store() -> DB {
         let mock_faults = vec![
             Fault {
-                name: "delay 1 second".to_string(),
                 description: Some("inject a delay of 1 second".to_string()),
-                fault_type: FaultVariants::Delay,
                 duration: Some(1000),
-                error_msg: None,
-                command: "GET".to_string(),
                 last_modified: Some(Utc::now()),
+                ..Fault::fixture("delay 1 second", FaultVariants::Delay, "GET")
             },
             Fault {
-                name: "SET Error".to_string(),
                 description: Some("inject set error".to_string()),
-                fault_type: FaultVariants::Error,
-                duration: None,
                 error_msg: Some("SET ERROR".to_string()),
-                command: "SET".to_string(),
                 last_modified: Some(Utc::now() + Duration::minutes(1)),
+                ..Fault::fixture("SET Error", FaultVariants::Error, "SET")
             },
             Fault {
-                name: "drop_conn_for_ping_cmd".to_string(),
                 description: Some("PING error".to_string()),
-                fault_type: FaultVariants::DropConn,
-                error_msg: None,
-                duration: None,
-                command: "PING".to_string(),
-                last_modified: None,
+                ..Fault::fixture("drop_conn_for_ping_cmd", FaultVariants::DropConn, "PING")
             },
         ];
 
@@ -210,9 +902,8 @@ mod tests {
 
         for fault in mock_faults {
             fault_store
-                .write()
-                .await
                 .store(fault.name.as_str(), &fault)
+                .await
                 .unwrap();
         }
 
@@ -222,13 +913,18 @@ mod tests {
     #[tokio::test]
     async fn test_check_fault() {
         let fault_store = get_mock_fault_store().await;
-        let faulter = Faulter::new(fault_store);
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
 
         let res = faulter
-            .check_fault("*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n")
+            .check_fault(
+                &Uuid::new_v4(),
+                "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n",
+                None,
+                0,
+            )
             .await;
 
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         let fault = res.unwrap().unwrap();
 
         assert_eq!(fault.name, "SET Error".to_string());
@@ -236,85 +932,903 @@ mod tests {
         assert_eq!(fault.command, "SET".to_string());
     }
 
-    #[tokio::test]
-    async fn test_check_fault_no_match() {
-        let fault_store = store::mem_store::MemStore::new_db();
-        let faulter = Faulter::new(fault_store);
+    /// RequestIdLayer captures the `request_id` recorded on the spans, by span name
+    #[derive(Clone, Default)]
+    struct RequestIdLayer {
+        request_ids: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+    }
 
-        let res = faulter
-            .check_fault("*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n")
-            .await;
+    struct RequestIdVisitor(Option<String>);
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(res.unwrap(), None);
+    impl field::Visit for RequestIdVisitor {
+        fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "request_id" {
+                self.0 = Some(format!("{:?}", value));
+            }
+        }
     }
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn test_execute_delay_fault() {
-        let fault_store = get_mock_fault_store().await;
-        let faulter = Faulter::new(fault_store);
+    impl<S> tracing_subscriber::Layer<S> for RequestIdLayer
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut visitor = RequestIdVisitor(None);
+            values.record(&mut visitor);
+            if let (Some(request_id), Some(span)) = (visitor.0, ctx.span(id)) {
+                self.request_ids
+                    .lock()
+                    .unwrap()
+                    .push((span.name().to_string(), request_id));
+            }
+        }
+    }
 
-        let mock_server_addr = next_test_ip4();
-        debug!("mock server address: {}", mock_server_addr);
+    #[tokio::test]
+    async fn test_request_id_spans() {
+        use tracing_subscriber::layer::SubscriberExt;
 
-        run_mock_origin_server(mock_server_addr);
+        let layer = RequestIdLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
 
-        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+        let fault_store = get_mock_fault_store().await;
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+        let request_id = Uuid::new_v4();
+        let fault = faulter
+            .check_fault(
+                &request_id,
+                "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n",
+                None,
+                0,
+            )
             .await
             .unwrap();
-        let (_, mut write_stream) = stream.split();
-
+        let mut write_stream = tokio::io::sink();
         let mut ctx = Context {
-            client_tcp_write_stream: &mut write_stream,
+            request_id,
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
         };
+        faulter.execute_fault(&mut ctx, fault).await.unwrap();
 
-        let fault = faulter
-            .check_fault("*2\r\n$3\r\nget\r\n$4\r\nkey1\r\n")
-            .await
-            .unwrap();
-
-        let start = Instant::now();
-        let action = faulter.execute_fault(&mut ctx, fault).await;
-        let duration = start.elapsed();
-
-        assert_eq!(action.is_ok(), true);
-        assert_eq!(action.unwrap(), RequestAction::Fallthrough);
-
-        debug!("elapsed duration is: {:?}", duration.as_secs());
-
-        assert_eq!(duration.as_millis() < 1000, false);
+        assert_eq!(
+            *layer.request_ids.lock().unwrap(),
+            vec![
+                ("Check fault".to_string(), request_id.to_string()),
+                ("Executing fault".to_string(), request_id.to_string()),
+            ]
+        );
     }
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn test_execute_error_fault() {
-        let fault_store = get_mock_fault_store().await;
-        let faulter = Faulter::new(fault_store);
-
-        let mock_server_addr = next_test_ip4();
-        debug!("mock server address: {}", mock_server_addr);
+    #[tokio::test]
+    async fn test_check_fault_key_pattern() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            duration: Some(10),
+            key_pattern: Some("user:*".to_string()),
+            ..Fault::fixture("delay user keys", FaultVariants::Delay, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
 
-        run_mock_origin_server(mock_server_addr);
+        let res = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$6\r\nuser:1\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.unwrap().name, fault.name);
 
-        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+        let res = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$7\r\norder:1\r\n",
+                None,
+                0,
+            )
             .await
             .unwrap();
-        let (mut read_stream, mut write_stream) = stream.split();
+        assert_eq!(res, None);
 
-        let mut ctx = Context {
-            client_tcp_write_stream: &mut write_stream,
-        };
+        let mut fault = fault;
+        fault.key_pattern = Some("user:1".to_string());
+        fault_store.store(&fault.name, &fault).await.unwrap();
 
-        let fault = faulter
-            .check_fault("*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n")
+        let res = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$6\r\nuser:1\r\n",
+                None,
+                0,
+            )
             .await
             .unwrap();
+        assert_eq!(res.unwrap().name, fault.name);
 
-        let action = faulter.execute_fault(&mut ctx, fault).await;
-        assert_eq!(action.is_ok(), true);
-        assert_eq!(action.unwrap(), RequestAction::Exit);
+        let res = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$6\r\nuser:2\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res, None);
+    }
 
-        let mut read_buffer = [0; 32];
-        match read_stream.read(&mut read_buffer).await {
+    #[tokio::test]
+    async fn test_check_fault_key_pattern_fallback() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut get_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        get_fault.name = "get delay".to_string();
+        get_fault.command = "GET".to_string();
+        get_fault.last_modified = Some(Utc::now() - Duration::minutes(1));
+        fault_store
+            .store(&get_fault.name, &get_fault)
+            .await
+            .unwrap();
+        // the fault of the user keys is the most recently modified one
+        let mut user_fault = get_fault.clone();
+        user_fault.name = "get user delay".to_string();
+        user_fault.key_pattern = Some("user:*".to_string());
+        user_fault.last_modified = Some(Utc::now());
+        fault_store
+            .store(&user_fault.name, &user_fault)
+            .await
+            .unwrap();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        // the fault scoped to the user keys doesn't hide the plain fault of the other keys
+        for (req_body, fault_name) in [
+            ("*2\r\n$3\r\nget\r\n$6\r\nuser:1\r\n", "get user delay"),
+            ("*2\r\n$3\r\nget\r\n$7\r\norder:1\r\n", "get delay"),
+        ] {
+            let res = faulter
+                .check_fault(&Uuid::new_v4(), req_body, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(res.unwrap().name, fault_name, "{}", req_body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_weighted_key_pattern() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.command = "GET".to_string();
+        for key_pattern in ["user:*", "order:*"] {
+            fault.name = format!("get {}", key_pattern);
+            fault.key_pattern = Some(key_pattern.to_string());
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_selection_strategy(FaultSelectionStrategy::Weighted);
+
+        // the fault of the order keys is not sampled for the user keys
+        for _ in 0..100 {
+            let res = faulter
+                .check_fault(
+                    &Uuid::new_v4(),
+                    "*2\r\n$3\r\nget\r\n$6\r\nuser:1\r\n",
+                    None,
+                    0,
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.unwrap().name, "get user:*");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_client_ip_filter() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        for (client_ip, minutes_ago) in [("10.0.0.1", 0), ("10.0.0.2", 1)] {
+            fault.name = format!("set delay {}", client_ip);
+            fault.client_ip_filter = Some(vec![client_ip.parse().unwrap()]);
+            fault.last_modified = Some(Utc::now() - Duration::minutes(minutes_ago));
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+        let set_key = "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n";
+
+        // the fault scoped to a client doesn't hide the fault of another client
+        for (client_addr, fault_name) in [
+            ("10.0.0.1:50000", Some("set delay 10.0.0.1")),
+            ("10.0.0.2:50000", Some("set delay 10.0.0.2")),
+            ("10.0.0.3:50000", None),
+        ] {
+            let res = faulter
+                .check_fault(
+                    &Uuid::new_v4(),
+                    set_key,
+                    Some(client_addr.parse().unwrap()),
+                    0,
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                res.map(|fault| fault.name).as_deref(),
+                fault_name,
+                "{}",
+                client_addr
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_proxy_port() {
+        let fault_store = get_mock_fault_store().await;
+        let mut fault = fault_store.get_by_fault_name("SET Error").await.unwrap();
+        fault.name = "SET Error 6352".to_string();
+        fault.proxy_port = Some(6352);
+        fault.priority = 1;
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let set_key = "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n";
+
+        // the fault scoped to another port doesn't shadow the faults of the other ports
+        for (proxy_port, fault_name) in [
+            (Some(6352), "SET Error 6352"),
+            (Some(6351), "SET Error"),
+            (None, "SET Error"),
+        ] {
+            let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()))
+                .with_proxy_port(proxy_port);
+            let res = faulter
+                .check_fault(&Uuid::new_v4(), set_key, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(res.unwrap().name, fault_name, "{:?}", proxy_port);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_arg_matchers() {
+        let fault_store = get_mock_fault_store().await;
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
+        let set_user_key = "*3\r\n$3\r\nset\r\n$6\r\nuser:1\r\n$5\r\nvalue\r\n";
+
+        for (position, value, match_type, is_faulted) in [
+            (1, "user:1", MatchType::Exact, true),
+            (1, "user", MatchType::Exact, false),
+            (1, "user:", MatchType::Prefix, true),
+            (1, ":1", MatchType::Suffix, true),
+            (2, "alu", MatchType::Contains, true),
+            (2, "^val[a-z]+$", MatchType::Regex, true),
+            (2, "^[0-9]+$", MatchType::Regex, false),
+            (3, "value", MatchType::Exact, false),
+        ] {
+            let mut fault = fault_store.get_by_fault_name("SET Error").await.unwrap();
+            fault.arg_matchers = Some(vec![
+                ArgMatcher::new(0, "set".to_string(), MatchType::Exact),
+                ArgMatcher::new(position, value.to_string(), match_type),
+            ]);
+            fault_store.store(&fault.name, &fault).await.unwrap();
+
+            let res = faulter
+                .check_fault(&Uuid::new_v4(), set_user_key, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(res.is_some(), is_faulted, "{} at {}", value, position);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_arg_matchers_fallback() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        for (value, minutes_ago) in [("^order:", 0), ("^user:", 1)] {
+            fault.name = format!("set delay {}", value);
+            fault.arg_matchers = Some(vec![ArgMatcher::new(
+                1,
+                value.to_string(),
+                MatchType::Regex,
+            )]);
+            fault.last_modified = Some(Utc::now() - Duration::minutes(minutes_ago));
+            fault_store.store(&fault.name, &fault).await.unwrap();
+        }
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        // the newer fault whose arguments don't match doesn't shadow the older one
+        for _ in 0..3 {
+            let res = faulter
+                .check_fault(
+                    &Uuid::new_v4(),
+                    "*3\r\n$3\r\nset\r\n$6\r\nuser:1\r\n$5\r\nvalue\r\n",
+                    None,
+                    0,
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.unwrap().name, "set delay ^user:");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_playbook() {
+        let fault_store = get_mock_fault_store().await;
+        let playbook_store = store::mem_store::MemPlaybookStore::new_db();
+        playbook_store
+            .store_playbook(&FaultPlaybook {
+                name: "three ok then two errors".to_string(),
+                steps: vec![
+                    PlaybookStep {
+                        fault_name: None,
+                        repeat_count: 3,
+                    },
+                    PlaybookStep {
+                        fault_name: Some("SET Error".to_string()),
+                        repeat_count: 2,
+                    },
+                ],
+                loop_playbook: true,
+            })
+            .unwrap();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_playbook_store(playbook_store);
+
+        let mut faulted = Vec::new();
+        for _ in 0..10 {
+            let fault = faulter
+                .check_fault(
+                    &Uuid::new_v4(),
+                    "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n",
+                    None,
+                    0,
+                )
+                .await
+                .unwrap();
+            faulted.push(fault.is_some());
+        }
+
+        assert_eq!(
+            faulted,
+            [false, false, false, true, true, false, false, false, true, true]
+        );
+
+        // the fault of the step only applies to the requests it matches
+        let fault = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$4\r\nkey1\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(fault, None);
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_no_match() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        let res = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n",
+                None,
+                0,
+            )
+            .await;
+
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_hello() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = get_mock_fault_store()
+            .await
+            .get_by_fault_name("SET Error")
+            .await
+            .unwrap();
+        fault.command = WILDCARD_COMMAND.to_string();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        for req_body in [
+            "*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n",
+            "*2\r\n$5\r\nhello\r\n$1\r\n2\r\n",
+        ] {
+            let fault = faulter
+                .check_fault(&Uuid::new_v4(), req_body, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(fault, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_bypass_commands() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = get_mock_fault_store()
+            .await
+            .get_by_fault_name("SET Error")
+            .await
+            .unwrap();
+        fault.command = WILDCARD_COMMAND.to_string();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_bypass_commands(vec!["client".to_string()]);
+
+        for req_body in [
+            "*2\r\n$4\r\nAUTH\r\n$6\r\ns3cr3t\r\n",
+            "*3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$6\r\ns3cr3t\r\n",
+            "*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n",
+            "*1\r\n$4\r\nquit\r\n",
+            "*1\r\n$5\r\nRESET\r\n",
+            "*3\r\n$6\r\nCLIENT\r\n$7\r\nSETNAME\r\n$3\r\napp\r\n",
+        ] {
+            let fault = faulter
+                .check_fault(&Uuid::new_v4(), req_body, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(fault, None, "{}", req_body);
+        }
+
+        // the wildcard fault still matches the other commands
+        let fault = faulter
+            .check_fault(&Uuid::new_v4(), "*1\r\n$4\r\nping\r\n", None, 0)
+            .await
+            .unwrap();
+        assert!(fault.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_check_fault_pubsub() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let mut fault = get_mock_fault_store()
+            .await
+            .get_by_fault_name("SET Error")
+            .await
+            .unwrap();
+        fault.command = WILDCARD_COMMAND.to_string();
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        for req_body in [
+            "*2\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n",
+            "*2\r\n$10\r\nPSUBSCRIBE\r\n$5\r\nnews*\r\n",
+            "*1\r\n$11\r\nunsubscribe\r\n",
+            "*1\r\n$12\r\nPUNSUBSCRIBE\r\n",
+        ] {
+            let fault = faulter
+                .check_fault(&Uuid::new_v4(), req_body, None, 0)
+                .await
+                .unwrap();
+            assert_eq!(fault, None);
+        }
+
+        // the wildcard fault still matches the other commands
+        let fault = faulter
+            .check_fault(&Uuid::new_v4(), "*1\r\n$4\r\nping\r\n", None, 0)
+            .await
+            .unwrap();
+        assert!(fault.is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_delay_fault() {
+        let fault_store = get_mock_fault_store().await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let faulter = Faulter::new(fault_store, metrics.clone());
+
+        let mock_server_addr = next_test_ip4();
+        debug!("mock server address: {}", mock_server_addr);
+
+        run_mock_origin_server(mock_server_addr);
+
+        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+            .await
+            .unwrap();
+        let (_, mut write_stream) = stream.split();
+
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+
+        let fault = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$4\r\nkey1\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        let action = faulter.execute_fault(&mut ctx, fault).await;
+        let duration = start.elapsed();
+
+        assert!(action.is_ok());
+        assert_eq!(action.unwrap(), RequestAction::Fallthrough);
+
+        debug!("elapsed duration is: {:?}", duration.as_secs());
+
+        assert!(duration.as_millis() >= 1000);
+
+        assert_eq!(
+            metrics
+                .faults_total
+                .with_label_values(&["delay 1 second", "Delay"])
+                .get(),
+            1
+        );
+        assert_eq!(metrics.delay_duration_milliseconds.get_sample_count(), 1);
+        assert_eq!(metrics.delay_duration_milliseconds.get_sample_sum(), 1000.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_fault_statsd() {
+        let statsd_server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        statsd_server
+            .set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        let statsd = metrics::statsd_client(
+            Some("127.0.0.1"),
+            statsd_server.local_addr().unwrap().port(),
+            "red_monkey",
+        )
+        .unwrap();
+
+        let fault_store = get_mock_fault_store().await;
+        let mut fault = fault_store
+            .get_by_fault_name("delay 1 second")
+            .await
+            .unwrap();
+        fault.duration = Some(10);
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_statsd(Arc::new(statsd));
+
+        let mut write_stream = tokio::io::sink();
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+        let action = faulter.execute_fault(&mut ctx, Some(fault)).await.unwrap();
+        assert_eq!(action, RequestAction::Fallthrough);
+
+        let mut buf = [0; 512];
+        let len = statsd_server.recv(&mut buf).unwrap();
+        assert_eq!(
+            str::from_utf8(&buf[..len]).unwrap(),
+            "red_monkey.faults.delay_1_second.Delay:1|c"
+        );
+        let len = statsd_server.recv(&mut buf).unwrap();
+        assert_eq!(
+            str::from_utf8(&buf[..len]).unwrap(),
+            "red_monkey.delay.delay_1_second:10|ms"
+        );
+
+        // the delay of a sub-fault is timed under the name of its composite fault
+        let composite_fault = Fault {
+            sub_faults: Some(vec![SubFault {
+                fault_type: FaultVariants::Delay,
+                duration: Some(10),
+                error_msg: None,
+            }]),
+            ..Fault::fixture("composite delay", FaultVariants::Composite, "GET")
+        };
+        let action = faulter
+            .execute_fault(&mut ctx, Some(composite_fault))
+            .await
+            .unwrap();
+        assert_eq!(action, RequestAction::Fallthrough);
+
+        let len = statsd_server.recv(&mut buf).unwrap();
+        assert_eq!(
+            str::from_utf8(&buf[..len]).unwrap(),
+            "red_monkey.faults.composite_delay.Composite:1|c"
+        );
+        let len = statsd_server.recv(&mut buf).unwrap();
+        assert_eq!(
+            str::from_utf8(&buf[..len]).unwrap(),
+            "red_monkey.delay.composite_delay:10|ms"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_fault_experiment_log() {
+        let experiment_log_path = std::env::temp_dir().join(format!(
+            "red-monkey-experiment-{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        let experiment_log_path = experiment_log_path.to_str().unwrap();
+        let experiment_log = ExperimentLog::open(experiment_log_path).await.unwrap();
+
+        let fault_store = get_mock_fault_store().await;
+        let mut delay_fault = fault_store
+            .get_by_fault_name("delay 1 second")
+            .await
+            .unwrap();
+        delay_fault.duration = Some(10);
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_experiment_log(experiment_log);
+
+        let client_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        let mut write_stream = tokio::io::sink();
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Socket(client_addr),
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+
+        let set_fault = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$1\r\n1\r\n",
+                Some(client_addr),
+                0,
+            )
+            .await
+            .unwrap();
+        let injected_faults = [set_fault.clone(), Some(delay_fault.clone()), None];
+        for fault in injected_faults {
+            faulter.execute_fault(&mut ctx, fault).await.unwrap();
+        }
+
+        // the requests without a fault are not recorded
+        let events: Vec<ExperimentEvent> = std::fs::read_to_string(experiment_log_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        for (event, fault) in events.iter().zip([set_fault.unwrap(), delay_fault]) {
+            assert_eq!(event.fault_name, fault.name);
+            assert_eq!(event.command, fault.command);
+            assert_eq!(event.client_ip.as_deref(), Some("10.0.0.1"));
+            assert_eq!(event.action, format!("{:?}", fault.fault_type));
+        }
+        assert!(events[0].ts <= events[1].ts);
+    }
+
+    /// MockPublisher collects the published fault events.
+    #[derive(Default)]
+    struct MockPublisher {
+        events: std::sync::Mutex<Vec<FaultEvent>>,
+    }
+
+    #[async_trait::async_trait]
+    impl FaultEventPublisher for MockPublisher {
+        async fn publish_fault_event(&self, event: &FaultEvent) -> Result<(), anyhow::Error> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_fault_publisher() {
+        let fault_store = get_mock_fault_store().await;
+        let mut delay_fault = fault_store
+            .get_by_fault_name("delay 1 second")
+            .await
+            .unwrap();
+        delay_fault.duration = Some(10);
+        let mut error_fault = delay_fault.clone();
+        error_fault.name = "set error".to_string();
+        error_fault.command = "SET".to_string();
+        error_fault.fault_type = FaultVariants::Error;
+        error_fault.error_msg = Some("ERR injected".to_string());
+
+        let publisher = Arc::new(MockPublisher::default());
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()))
+            .with_fault_event_publisher(publisher.clone());
+
+        let mut write_stream = tokio::io::sink();
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+        for fault in [Some(delay_fault.clone()), None, Some(error_fault.clone())] {
+            faulter.execute_fault(&mut ctx, fault).await.unwrap();
+        }
+
+        // the requests without a fault are not published
+        let events = publisher.events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        for (event, fault) in events.iter().zip([delay_fault, error_fault]) {
+            assert_eq!(event.fault_name, fault.name);
+            assert_eq!(event.command, fault.command);
+            assert_eq!(event.action, format!("{:?}", fault.fault_type));
+        }
+    }
+
+    #[test]
+    fn test_client_identity() {
+        let peer_addr = "10.0.0.1:50000".parse().unwrap();
+        let proxy_protocol_addr = "203.0.113.7:50000".parse().unwrap();
+
+        assert_eq!("socket".parse(), Ok(ClientIdMode::Socket));
+        assert_eq!("proxy-protocol".parse(), Ok(ClientIdMode::ProxyProtocol));
+        assert_eq!("none".parse(), Ok(ClientIdMode::None));
+        assert!("x-forwarded-for".parse::<ClientIdMode>().is_err());
+
+        assert_eq!(
+            ClientIdMode::Socket.client_identity(Some(peer_addr), Some(proxy_protocol_addr)),
+            ClientIdentity::Socket(peer_addr)
+        );
+        assert_eq!(
+            ClientIdMode::ProxyProtocol.client_identity(Some(peer_addr), Some(proxy_protocol_addr)),
+            ClientIdentity::ProxyProtocol(proxy_protocol_addr)
+        );
+        // the peer address of a connection without the header is not trusted
+        assert_eq!(
+            ClientIdMode::ProxyProtocol.client_identity(Some(peer_addr), None),
+            ClientIdentity::Unknown
+        );
+        assert_eq!(
+            ClientIdMode::None.client_identity(Some(peer_addr), Some(proxy_protocol_addr)),
+            ClientIdentity::Unknown
+        );
+        assert_eq!(ClientIdentity::Unknown.addr(), None);
+        assert_eq!(
+            error_message(None, Some("{client_ip}".to_string())).unwrap(),
+            "unknown"
+        );
+    }
+
+    #[test]
+    fn test_add_jitter() {
+        assert_eq!(add_jitter(20, None), 20);
+        assert_eq!(add_jitter(20, Some(0)), 20);
+
+        for _ in 0..1000 {
+            let duration = add_jitter(20, Some(30));
+            assert!((20..=50).contains(&duration));
+        }
+    }
+
+    #[test]
+    fn test_adaptive_duration() {
+        assert_eq!(adaptive_duration(10, 20, None, 0), 10);
+        assert_eq!(adaptive_duration(10, 20, None, 4), 90);
+        assert_eq!(adaptive_duration(10, 20, Some(50), 4), 50);
+        assert_eq!(adaptive_duration(10, u64::MAX, None, 2), u64::MAX);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_adaptive_delay_fault() {
+        let fault_store = get_mock_fault_store().await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let faulter = Faulter::new(fault_store.clone(), metrics.clone());
+
+        let mut fault = fault_store
+            .get_by_fault_name("delay 1 second")
+            .await
+            .unwrap();
+        fault.duration = Some(10);
+        fault.adaptive_delay_step_ms = Some(20);
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let mock_server_addr = next_test_ip4();
+        run_mock_origin_server(mock_server_addr);
+
+        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+            .await
+            .unwrap();
+        let (_, mut write_stream) = stream.split();
+
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+
+        let mut delay_sum = 0.0;
+        for _ in 0..4 {
+            faulter
+                .execute_fault(&mut ctx, Some(fault.clone()))
+                .await
+                .unwrap();
+            delay_sum = metrics.delay_duration_milliseconds.get_sample_sum();
+        }
+
+        // the 5th hit is delayed by the duration and 4 steps
+        let start = Instant::now();
+        faulter.execute_fault(&mut ctx, Some(fault)).await.unwrap();
+        assert!(start.elapsed().as_millis() >= 90);
+        assert_eq!(
+            metrics.delay_duration_milliseconds.get_sample_sum() - delay_sum,
+            90.0
+        );
+
+        let stats = fault_store.get_fault_stats("delay 1 second").await.unwrap();
+        assert_eq!(stats.hit_count, 5);
+        assert_eq!(stats.total_delay_ms, 10 + 30 + 50 + 70 + 90);
+    }
+
+    #[tokio::test]
+    async fn test_execute_delay_fault_with_jitter() {
+        for _ in 0..5 {
+            let start = Instant::now();
+            execute_delay_fault(Some(20), Some(30)).await;
+            let duration = start.elapsed();
+
+            assert!(duration.as_millis() >= 20);
+            // allow some headroom over the maximum jitter for the scheduling overhead
+            assert!(duration.as_millis() < 20 + 30 + 50);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_error_fault() {
+        let fault_store = get_mock_fault_store().await;
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
+
+        let mock_server_addr = next_test_ip4();
+        debug!("mock server address: {}", mock_server_addr);
+
+        run_mock_origin_server(mock_server_addr);
+
+        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+            .await
+            .unwrap();
+        let (mut read_stream, mut write_stream) = stream.split();
+
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+
+        let fault = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*3\r\n$3\r\nset\r\n$4\r\nkey1\r\n$8\r\nvalue100\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let action = faulter.execute_fault(&mut ctx, fault).await;
+        assert!(action.is_ok());
+        assert_eq!(action.unwrap(), RequestAction::Exit);
+
+        let mut read_buffer = [0; 32];
+        match read_stream.read(&mut read_buffer).await {
             Ok(n) => {
                 assert_eq!(read_buffer[0..n], *b"-SET ERROR\r\n");
 
@@ -330,10 +1844,216 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_execute_burst_error_fault() {
+        let fault_store = get_mock_fault_store().await;
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
+
+        let mut fault = fault_store.get_by_fault_name("SET Error").await.unwrap();
+        fault.burst_count = Some(3);
+        fault.burst_interval_ms = Some(10);
+
+        let (mut client_stream, mut proxy_stream) = tokio::io::duplex(1024);
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut proxy_stream,
+            client_socket: None,
+        };
+
+        let start = Instant::now();
+        let action = faulter.execute_fault(&mut ctx, Some(fault)).await.unwrap();
+        assert_eq!(action, RequestAction::Exit);
+        assert!(start.elapsed().as_millis() >= 20);
+
+        // the client stream is only closed after the last error of the burst
+        let mut response = Vec::new();
+        client_stream.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response, b"-SET ERROR\r\n".repeat(3));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_timeout_fault() {
+        let fault_store = store::mem_store::MemStore::new_db();
+        let fault = Fault {
+            description: Some("GET timeout".to_string()),
+            ..Fault::fixture("timeout_get", FaultVariants::Timeout, "GET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()))
+            .with_shutdown(shutdown_rx);
+
+        let mock_server_addr = next_test_ip4();
+        run_mock_origin_server(mock_server_addr);
+
+        let mut stream = TcpStream::connect(mock_server_addr.to_string())
+            .await
+            .unwrap();
+        let (_, mut write_stream) = stream.split();
+
+        let mut ctx = Context {
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
+        };
+
+        let fault = faulter
+            .check_fault(
+                &Uuid::new_v4(),
+                "*2\r\n$3\r\nget\r\n$4\r\nkey1\r\n",
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let action = tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            faulter.execute_fault(&mut ctx, fault.clone()),
+        )
+        .await;
+        assert!(action.is_err());
+
+        // the connection is released once the shutdown is signalled
+        shutdown_tx.send(true).unwrap();
+        let action = tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            faulter.execute_fault(&mut ctx, fault),
+        )
+        .await;
+        assert_eq!(action.unwrap().unwrap(), RequestAction::Exit);
+
+        assert_eq!(
+            fault_store
+                .get_fault_hit_count("timeout_get")
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_fault_hit_count() {
+        let fault_store = get_mock_fault_store().await;
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
+
+        let mock_server_addr = next_test_ip4();
+        run_mock_origin_server(mock_server_addr);
+
+        for _ in 0..3 {
+            let mut stream = TcpStream::connect(mock_server_addr.to_string())
+                .await
+                .unwrap();
+            let (_, mut write_stream) = stream.split();
+
+            let mut ctx = Context {
+                request_id: Uuid::new_v4(),
+                client_identity: ClientIdentity::Unknown,
+                client_write_stream: &mut write_stream,
+                client_socket: None,
+            };
+
+            let fault = faulter
+                .check_fault(&Uuid::new_v4(), "*1\r\n$4\r\nping\r\n", None, 0)
+                .await
+                .unwrap();
+            faulter.execute_fault(&mut ctx, fault).await.unwrap();
+        }
+
+        assert_eq!(
+            fault_store
+                .get_fault_hit_count("drop_conn_for_ping_cmd")
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            fault_store.get_fault_hit_count("SET Error").await.unwrap(),
+            0
+        );
+    }
+
+    /// Executes the given fault for the given number of concurrent requests, and returns the
+    /// number of the requests that were faulted, i.e. got the error of the fault.
+    async fn execute_concurrently(faulter: &Faulter, fault: &Fault, requests: usize) -> usize {
+        let handles: Vec<_> = (0..requests)
+            .map(|_| {
+                let faulter = faulter.clone();
+                let fault = fault.clone();
+                tokio::spawn(async move {
+                    let mut write_stream = tokio::io::sink();
+                    let mut ctx = Context {
+                        request_id: Uuid::new_v4(),
+                        client_identity: ClientIdentity::Unknown,
+                        client_write_stream: &mut write_stream,
+                        client_socket: None,
+                    };
+                    faulter.execute_fault(&mut ctx, Some(fault)).await.unwrap()
+                })
+            })
+            .collect();
+
+        let mut faulted = 0;
+        for handle in handles {
+            if handle.await.unwrap() == RequestAction::Exit {
+                faulted += 1;
+            }
+        }
+        faulted
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_one_shot_fault_concurrently() {
+        let fault_store = get_mock_fault_store().await;
+        let mut fault = fault_store.get_by_fault_name("SET Error").await.unwrap();
+        fault.one_shot = true;
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
+
+        // the requests that matched the fault before it is disabled are not all faulted
+        assert_eq!(execute_concurrently(&faulter, &fault, 20).await, 1);
+        assert!(
+            !fault_store
+                .get_by_fault_name(&fault.name)
+                .await
+                .unwrap()
+                .enabled
+        );
+        assert_eq!(
+            fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_max_hits_fault_concurrently() {
+        let fault_store = get_mock_fault_store().await;
+        let mut fault = fault_store.get_by_fault_name("SET Error").await.unwrap();
+        fault.max_hits = Some(3);
+        fault_store.store(&fault.name, &fault).await.unwrap();
+        let faulter = Faulter::new(fault_store.clone(), Arc::new(Metrics::new().unwrap()));
+
+        // the requests that matched the fault before it is disabled don't overshoot max_hits
+        assert_eq!(execute_concurrently(&faulter, &fault, 20).await, 3);
+        assert!(
+            !fault_store
+                .get_by_fault_name(&fault.name)
+                .await
+                .unwrap()
+                .enabled
+        );
+        assert_eq!(
+            fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+            3
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_execute_drop_fault() {
         let fault_store = get_mock_fault_store().await;
-        let faulter = Faulter::new(fault_store);
+        let faulter = Faulter::new(fault_store, Arc::new(Metrics::new().unwrap()));
 
         let mock_server_addr = next_test_ip4();
         debug!("mock server address: {}", mock_server_addr);
@@ -346,12 +2066,18 @@ mod tests {
         let (mut read_stream, mut write_stream) = stream.split();
 
         let mut ctx = Context {
-            client_tcp_write_stream: &mut write_stream,
+            request_id: Uuid::new_v4(),
+            client_identity: ClientIdentity::Unknown,
+            client_write_stream: &mut write_stream,
+            client_socket: None,
         };
 
-        let fault = faulter.check_fault("*1\r\n$4\r\nping\r\n").await.unwrap();
+        let fault = faulter
+            .check_fault(&Uuid::new_v4(), "*1\r\n$4\r\nping\r\n", None, 0)
+            .await
+            .unwrap();
         let action = faulter.execute_fault(&mut ctx, fault).await;
-        assert_eq!(action.is_ok(), true);
+        assert!(action.is_ok());
         assert_eq!(action.unwrap(), RequestAction::Exit);
 
         let mut read_buffer = [0; 32];
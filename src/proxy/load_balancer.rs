@@ -0,0 +1,165 @@
+use crate::proxy::connection::AsyncReadWrite;
+use dashmap::DashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use strum_macros::EnumString;
+use tokio::io::{self, AsyncRead, AsyncWrite, ReadBuf};
+
+/// LbStrategy tells how the origin server of a new upstream connection is picked among the
+/// healthy ones, see the `UPSTREAM_LB_STRATEGY` config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum LbStrategy {
+    /// The origin servers are picked in turn
+    #[default]
+    RoundRobin,
+    /// The origin server with the fewest active connections is picked, the ties being broken in
+    /// the round-robin order
+    LeastConnections,
+    /// An origin server is picked at random
+    Random,
+}
+
+/// ActiveConnections counts the active upstream connections per origin server address, for the
+/// [LbStrategy::LeastConnections] strategy.
+#[derive(Clone, Default)]
+pub struct ActiveConnections {
+    counts: Arc<DashMap<String, AtomicU32>>,
+}
+
+impl ActiveConnections {
+    /// Returns the number of the active connections to the origin server.
+    pub fn count(&self, server_addr: &str) -> u32 {
+        self.counts
+            .get(server_addr)
+            .map_or(0, |count| count.load(Ordering::SeqCst))
+    }
+
+    /// Counts a new active connection to the origin server until the returned [ActiveConnection]
+    /// is dropped.
+    pub fn track(&self, server_addr: &str) -> ActiveConnection {
+        self.counts
+            .entry(server_addr.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::SeqCst);
+
+        ActiveConnection {
+            active_connections: self.clone(),
+            server_addr: server_addr.to_string(),
+        }
+    }
+}
+
+/// ActiveConnection is an active connection to an origin server counted by [ActiveConnections].
+/// The count is decremented on drop.
+pub struct ActiveConnection {
+    active_connections: ActiveConnections,
+    server_addr: String,
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        if let Some(count) = self.active_connections.counts.get(&self.server_addr) {
+            count.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// ServerStreamGuard wraps an origin server stream, such that the connection is counted as active
+/// until the stream is dropped.
+pub struct ServerStreamGuard {
+    stream: Box<dyn AsyncReadWrite>,
+    _active_connection: ActiveConnection,
+}
+
+impl ServerStreamGuard {
+    /// Creates a new ServerStreamGuard of the stream counted by the given active connection.
+    pub fn new(stream: Box<dyn AsyncReadWrite>, active_connection: ActiveConnection) -> Self {
+        ServerStreamGuard {
+            stream,
+            _active_connection: active_connection,
+        }
+    }
+}
+
+impl AsyncRead for ServerStreamGuard {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ServerStreamGuard {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl AsyncReadWrite for ServerStreamGuard {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_lb_strategy() {
+        assert_eq!("round-robin".parse(), Ok(LbStrategy::RoundRobin));
+        assert_eq!(
+            "least-connections".parse(),
+            Ok(LbStrategy::LeastConnections)
+        );
+        assert_eq!("random".parse(), Ok(LbStrategy::Random));
+        assert!("weighted".parse::<LbStrategy>().is_err());
+        assert_eq!(LbStrategy::default(), LbStrategy::RoundRobin);
+    }
+
+    #[tokio::test]
+    async fn test_server_stream_guard() {
+        let active_connections = ActiveConnections::default();
+        assert_eq!(active_connections.count("localhost:6379"), 0);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _addr) = listener.accept().await.unwrap();
+        let first = active_connections.track("localhost:6379");
+        let mut stream = ServerStreamGuard::new(Box::new(client), first);
+        let second = active_connections.track("localhost:6379");
+        assert_eq!(active_connections.count("localhost:6379"), 2);
+        assert_eq!(active_connections.count("localhost:6380"), 0);
+
+        // the stream is proxied as is
+        stream.write_all(b"+PING\r\n").await.unwrap();
+        let mut read_buffer = [0; 7];
+        server.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(&read_buffer, b"+PING\r\n");
+        server.write_all(b"+PONG\r\n").await.unwrap();
+        stream.read_exact(&mut read_buffer).await.unwrap();
+        assert_eq!(&read_buffer, b"+PONG\r\n");
+
+        drop(stream);
+        assert_eq!(active_connections.count("localhost:6379"), 1);
+        drop(second);
+        assert_eq!(active_connections.count("localhost:6379"), 0);
+    }
+}
@@ -0,0 +1,200 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{self, AsyncRead, AsyncWrite, ReadBuf};
+use tracing::debug;
+use uuid::Uuid;
+
+/// The maximum number of bytes of the request payload that are logged
+pub const MAX_LOGGED_PAYLOAD_BYTES: usize = 512;
+
+/// CircularBuffer keeps the last `capacity` bytes written to it, such that capturing a large
+/// payload takes no more memory than the logged part of it.
+#[derive(Debug)]
+pub struct CircularBuffer {
+    buf: Vec<u8>,
+    capacity: usize,
+    /// The position of the oldest byte once the buffer is full
+    next: usize,
+}
+
+impl CircularBuffer {
+    pub fn new(capacity: usize) -> Self {
+        CircularBuffer {
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+        }
+    }
+
+    /// Appends the given bytes, overwriting the oldest bytes once the buffer is full.
+    pub fn extend(&mut self, data: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // only the trailing bytes of a write larger than the buffer are kept
+        let data = &data[data.len().saturating_sub(self.capacity)..];
+        for &byte in data {
+            if self.buf.len() < self.capacity {
+                self.buf.push(byte);
+            } else {
+                self.buf[self.next] = byte;
+            }
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    /// Returns the buffered bytes from the oldest to the latest.
+    pub fn contents(&self) -> Vec<u8> {
+        if self.buf.len() < self.capacity {
+            return self.buf.clone();
+        }
+
+        [&self.buf[self.next..], &self.buf[..self.next]].concat()
+    }
+}
+
+/// RequestResponseLogger wraps the writer of the proxied bytes, either the request to the origin
+/// server or the response to the client. It counts the written bytes and, when capturing, tees
+/// them into a [CircularBuffer] instead of cloning the whole payload. The reads of a wrapped
+/// stream pass through as is, such that the whole stream can be proxied.
+pub struct RequestResponseLogger<W> {
+    inner: W,
+    captured: Option<CircularBuffer>,
+    written: u64,
+}
+
+impl<W> RequestResponseLogger<W> {
+    /// Wraps the writer, only counting the written bytes.
+    pub fn new(inner: W) -> Self {
+        RequestResponseLogger {
+            inner,
+            captured: None,
+            written: 0,
+        }
+    }
+
+    /// Wraps the writer, counting the written bytes and capturing the last `capacity` of them.
+    pub fn capturing(inner: W, capacity: usize) -> Self {
+        RequestResponseLogger {
+            inner,
+            captured: Some(CircularBuffer::new(capacity)),
+            written: 0,
+        }
+    }
+
+    /// Returns the captured bytes, None when the writer is not capturing.
+    pub fn captured(&self) -> Option<Vec<u8>> {
+        self.captured.as_ref().map(CircularBuffer::contents)
+    }
+
+    /// Logs the captured request payload at the `RESP_REQUEST` target.
+    pub fn log_request(&self, request_id: &Uuid) {
+        let payload = self.captured().unwrap_or_default();
+        debug!(
+            target: "RESP_REQUEST",
+            %request_id,
+            bytes = self.written,
+            truncated = self.written > payload.len() as u64,
+            "request payload: {:?}",
+            String::from_utf8_lossy(&payload)
+        );
+    }
+
+    /// Logs the number of response bytes at the `RESP_REQUEST` target.
+    pub fn log_response(&self, request_id: &Uuid) {
+        debug!(
+            target: "RESP_REQUEST",
+            %request_id,
+            bytes = self.written,
+            "response proxied"
+        );
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for RequestResponseLogger<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(n)) = poll {
+            self.written += n as u64;
+            if let Some(captured) = self.captured.as_mut() {
+                captured.extend(&buf[..n]);
+            }
+        }
+
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RequestResponseLogger<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[test]
+    fn test_circular_buffer() {
+        let mut buffer = CircularBuffer::new(4);
+        buffer.extend(b"ab");
+        assert_eq!(buffer.contents(), b"ab");
+
+        buffer.extend(b"cdef");
+        assert_eq!(buffer.contents(), b"cdef");
+
+        buffer.extend(b"g");
+        assert_eq!(buffer.contents(), b"defg");
+
+        buffer.extend(b"0123456789");
+        assert_eq!(buffer.contents(), b"6789");
+
+        let mut buffer = CircularBuffer::new(0);
+        buffer.extend(b"ab");
+        assert!(buffer.contents().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_response_logger() {
+        let payload = vec![b'x'; MAX_LOGGED_PAYLOAD_BYTES * 2 + 1];
+
+        let mut logger = RequestResponseLogger::capturing(Vec::new(), MAX_LOGGED_PAYLOAD_BYTES);
+        logger.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+        logger.write_all(&payload).await.unwrap();
+        logger.shutdown().await.unwrap();
+
+        assert_eq!(logger.written, 14 + payload.len() as u64);
+        assert_eq!(
+            logger.captured().unwrap(),
+            &payload[..MAX_LOGGED_PAYLOAD_BYTES]
+        );
+        assert_eq!(logger.inner.len() as u64, logger.written);
+        logger.log_request(&Uuid::new_v4());
+
+        let mut logger = RequestResponseLogger::new(Vec::new());
+        logger.write_all(b"+PONG\r\n").await.unwrap();
+        assert_eq!(logger.written, 7);
+        assert!(logger.captured().is_none());
+        logger.log_response(&Uuid::new_v4());
+    }
+}
@@ -1,3 +1,11 @@
+pub mod circuit_breaker;
 pub mod connection;
+pub mod connection_pool;
 pub mod faulter;
+pub mod load_balancer;
+pub mod logging;
+pub mod proxy_protocol;
+pub mod rate_limiter;
 pub mod resp_util;
+pub mod server;
+pub mod upstream_health;
@@ -0,0 +1,223 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+/// The signature the proxy protocol v2 header starts with
+pub const SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// The length of the fixed part of the header: the signature, the version and command byte, the
+/// address family and protocol byte, and the length of the addresses
+const FIXED_HEADER_LEN: usize = 16;
+
+/// The `LOCAL` command of a connection established by the load balancer itself, e.g. for health
+/// checks, whose addresses are ignored
+const LOCAL_COMMAND: u8 = 0x0;
+/// The `PROXY` command of a connection relayed on behalf of a client
+const PROXY_COMMAND: u8 = 0x1;
+
+/// The IPv4 family, over TCP or UDP
+const INET_FAMILY: u8 = 0x1;
+/// The IPv6 family, over TCP or UDP
+const INET6_FAMILY: u8 = 0x2;
+
+/// Parses the proxy protocol v2 header at the start of the buffer.
+///
+/// Returns Ok(Some((source_addr, header_len))) when the header is complete, where the source
+/// address is None for the `LOCAL` command and for the address families other than IPv4 and IPv6,
+/// e.g. Unix domain sockets. Returns Ok(None) when the header is incomplete.
+///
+/// # Errors
+///
+/// Returns [ProxyProtocolError] when the buffer doesn't start with a valid proxy protocol v2
+/// header.
+pub fn parse_header(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyProtocolError> {
+    let signature_len = buf.len().min(SIGNATURE.len());
+    if buf[..signature_len] != SIGNATURE[..signature_len] {
+        return Err(ProxyProtocolError::InvalidSignature);
+    }
+    if buf.len() < FIXED_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let version = buf[12] >> 4;
+    if version != 2 {
+        return Err(ProxyProtocolError::UnsupportedVersion(version));
+    }
+    let command = buf[12] & 0x0F;
+    let family = buf[13] >> 4;
+    let addrs_len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    let header_len = FIXED_HEADER_LEN + addrs_len;
+    if buf.len() < header_len {
+        return Ok(None);
+    }
+    let addrs = &buf[FIXED_HEADER_LEN..header_len];
+
+    let source_addr = match (command, family) {
+        (LOCAL_COMMAND, _) => None,
+        (PROXY_COMMAND, INET_FAMILY) => {
+            // the source and destination addresses are followed by the source and destination ports
+            if addrs.len() < 12 {
+                return Err(ProxyProtocolError::InvalidAddressesLength(addrs.len()));
+            }
+            let ip = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            Some(SocketAddr::new(ip.into(), port))
+        }
+        (PROXY_COMMAND, INET6_FAMILY) => {
+            if addrs.len() < 36 {
+                return Err(ProxyProtocolError::InvalidAddressesLength(addrs.len()));
+            }
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&addrs[..16]);
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+        }
+        (PROXY_COMMAND, _) => None,
+        (command, _) => return Err(ProxyProtocolError::UnsupportedCommand(command)),
+    };
+
+    Ok(Some((source_addr, header_len)))
+}
+
+/// Reads the proxy protocol v2 header off the start of the client stream, if any, and returns the
+/// original address of the client. A stream that doesn't start with the first byte of the
+/// [SIGNATURE], which no RESP request does, is left untouched.
+///
+/// # Errors
+///
+/// Returns an error when the stream can't be read or the header is invalid, see [parse_header].
+pub async fn read_header(stream: &mut TcpStream) -> Result<Option<SocketAddr>, anyhow::Error> {
+    let mut first_byte = [0; 1];
+    if stream.peek(&mut first_byte).await? == 0 || first_byte[0] != SIGNATURE[0] {
+        return Ok(None);
+    }
+
+    let mut header = vec![0; FIXED_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    loop {
+        match parse_header(&header)? {
+            Some((source_addr, _)) => return Ok(source_addr),
+            None => {
+                let addrs_len = u16::from_be_bytes([header[14], header[15]]) as usize;
+                let mut addrs = vec![0; addrs_len];
+                stream.read_exact(&mut addrs).await?;
+                header.extend_from_slice(&addrs);
+            }
+        }
+    }
+}
+
+/// ProxyProtocolError is a representation of the invalid proxy protocol v2 headers.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ProxyProtocolError {
+    #[error("Error as the proxy protocol signature is invalid")]
+    InvalidSignature,
+    #[error("Error as the proxy protocol version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Error as the proxy protocol command {0} is not supported")]
+    UnsupportedCommand(u8),
+    #[error("Error as the proxy protocol addresses of {0} bytes are too short")]
+    InvalidAddressesLength(usize),
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Returns the proxy protocol v2 header of a TCP connection relayed from the given source
+    /// address to the given destination address.
+    pub fn proxy_header(source_addr: SocketAddr, destination_addr: SocketAddr) -> Vec<u8> {
+        let (family, mut addrs) = match (source_addr.ip(), destination_addr.ip()) {
+            (std::net::IpAddr::V4(source), std::net::IpAddr::V4(destination)) => {
+                (0x11, [source.octets(), destination.octets()].concat())
+            }
+            (std::net::IpAddr::V6(source), std::net::IpAddr::V6(destination)) => {
+                (0x21, [source.octets(), destination.octets()].concat())
+            }
+            _ => panic!("the source and destination addresses must be of the same family"),
+        };
+        addrs.extend_from_slice(&source_addr.port().to_be_bytes());
+        addrs.extend_from_slice(&destination_addr.port().to_be_bytes());
+
+        let mut header = SIGNATURE.to_vec();
+        header.push(0x21);
+        header.push(family);
+        header.extend_from_slice(&(addrs.len() as u16).to_be_bytes());
+        header.extend_from_slice(&addrs);
+        header
+    }
+
+    #[test]
+    fn test_parse_header_ipv4() {
+        let source_addr = "203.0.113.7:50000".parse().unwrap();
+        let header = proxy_header(source_addr, "10.0.0.1:6350".parse().unwrap());
+        assert_eq!(header.len(), 28);
+
+        assert_eq!(
+            parse_header(&header).unwrap(),
+            Some((Some(source_addr), header.len()))
+        );
+        // the request following the header is not part of it
+        let request = [&header[..], b"*1\r\n$4\r\nping\r\n"].concat();
+        assert_eq!(
+            parse_header(&request).unwrap(),
+            Some((Some(source_addr), header.len()))
+        );
+        for len in 0..header.len() {
+            assert_eq!(parse_header(&header[..len]).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_header_ipv6() {
+        let source_addr = "[2001:db8::7]:50000".parse().unwrap();
+        let header = proxy_header(source_addr, "[2001:db8::1]:6350".parse().unwrap());
+        assert_eq!(header.len(), 52);
+
+        assert_eq!(
+            parse_header(&header).unwrap(),
+            Some((Some(source_addr), header.len()))
+        );
+        assert_eq!(parse_header(&header[..40]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_header_local() {
+        let mut header = SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(parse_header(&header).unwrap(), Some((None, 16)));
+    }
+
+    #[test]
+    fn test_parse_header_invalid() {
+        assert_eq!(
+            parse_header(b"*1\r\n$4\r\nping\r\n"),
+            Err(ProxyProtocolError::InvalidSignature)
+        );
+
+        let mut header = proxy_header(
+            "203.0.113.7:50000".parse().unwrap(),
+            "10.0.0.1:6350".parse().unwrap(),
+        );
+        header[12] = 0x11;
+        assert_eq!(
+            parse_header(&header),
+            Err(ProxyProtocolError::UnsupportedVersion(1))
+        );
+
+        header[12] = 0x22;
+        assert_eq!(
+            parse_header(&header),
+            Err(ProxyProtocolError::UnsupportedCommand(2))
+        );
+
+        header[12] = 0x21;
+        header[15] = 4;
+        assert_eq!(
+            parse_header(&header),
+            Err(ProxyProtocolError::InvalidAddressesLength(4))
+        );
+    }
+}
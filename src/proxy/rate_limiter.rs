@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// The shortest interval at which the [RateLimiter] bucket is refilled, such that a high rate
+/// doesn't wake the refill task up too often.
+const MIN_REFILL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// RateLimiter is a token bucket that limits the number of new proxy connections per second.
+///
+/// The bucket holds up to a second worth of tokens and a connection takes a token. A background
+/// task refills the bucket at the configured rate, and it stops once the rate limiter is dropped.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    tokens: Arc<AtomicU64>,
+}
+
+impl RateLimiter {
+    /// Creates a full RateLimiter of `max_per_second` connections and spawns its refill task,
+    /// hence it must be called within a Tokio runtime.
+    pub fn start(max_per_second: u32) -> Self {
+        let capacity = u64::from(max_per_second.max(1));
+        let rate_limiter = RateLimiter {
+            tokens: Arc::new(AtomicU64::new(capacity)),
+        };
+
+        let refill_interval = (Duration::from_secs(1) / capacity as u32).max(MIN_REFILL_INTERVAL);
+        let tokens_per_refill = ((capacity as u128 * refill_interval.as_nanos())
+            / Duration::from_secs(1).as_nanos())
+        .max(1) as u64;
+        tokio::spawn(refill(
+            Arc::downgrade(&rate_limiter.tokens),
+            capacity,
+            refill_interval,
+            tokens_per_refill,
+        ));
+
+        rate_limiter
+    }
+
+    /// Takes a token, returning false when the bucket is empty.
+    pub fn try_acquire(&self) -> bool {
+        self.tokens
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |tokens| {
+                tokens.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
+
+/// Adds `tokens_per_refill` tokens to the bucket every `refill_interval`, up to `capacity`, until
+/// the bucket is dropped.
+async fn refill(
+    tokens: Weak<AtomicU64>,
+    capacity: u64,
+    refill_interval: Duration,
+    tokens_per_refill: u64,
+) {
+    let mut ticker = tokio::time::interval(refill_interval);
+    // the first tick completes right away, while the bucket starts full
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+
+        let tokens = match tokens.upgrade() {
+            Some(tokens) => tokens,
+            None => return,
+        };
+        let _ = tokens.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |available| {
+            Some((available + tokens_per_refill).min(capacity))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter() {
+        let rate_limiter = RateLimiter::start(5);
+        for _ in 0..5 {
+            assert!(rate_limiter.try_acquire());
+        }
+        assert!(!rate_limiter.try_acquire());
+        assert_eq!(rate_limiter.tokens.load(Ordering::SeqCst), 0);
+
+        // a token is refilled every 200ms
+        tokio::time::sleep(Duration::from_millis(210)).await;
+        assert_eq!(rate_limiter.tokens.load(Ordering::SeqCst), 1);
+        assert!(rate_limiter.try_acquire());
+        assert!(!rate_limiter.try_acquire());
+
+        // the bucket holds no more than a second worth of tokens
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(rate_limiter.tokens.load(Ordering::SeqCst), 5);
+
+        // a high rate is refilled in batches
+        let rate_limiter = RateLimiter::start(1000);
+        while rate_limiter.try_acquire() {}
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        assert_eq!(rate_limiter.tokens.load(Ordering::SeqCst), 10);
+    }
+}
@@ -1,7 +1,6 @@
 #![allow(clippy::enum_variant_names)]
 use anyhow::anyhow;
 use resp::{Decoder, Value};
-use tracing::error;
 use url::Url;
 
 /// Decodes the request body into Redis RESP values
@@ -40,6 +39,277 @@ pub fn decode(req_body: &str) -> Result<Value, anyhow::Error> {
     }
 }
 
+/// Returns true if the request body is an inline command, e.g. `PING\r\n`, rather than a RESP
+/// message, which starts with one of the `*`, `$`, `+`, `-` or `:` type bytes.
+pub fn is_inline(req_body: &str) -> bool {
+    !matches!(
+        req_body.as_bytes().first(),
+        None | Some(b'*' | b'$' | b'+' | b'-' | b':')
+    )
+}
+
+/// Decodes an inline command, e.g. `SET foo bar\r\n`, into the resp::Value::Array of its space
+/// separated tokens, as if the command was sent in the multi-bulk format
+///
+/// Returns Ok(resp::Value) on success
+///
+/// # Errors
+///
+/// Returns [RespErrors::RespArrEmptyError] when the command has no token
+///
+/// # Example
+/// ``` no_run
+/// let val = resp_util::decode_inline("SET foo bar\r\n")?;
+/// assert_eq!(resp_util::fetch_redis_command(val)?, "SET");
+/// ```
+pub fn decode_inline(req_body: &str) -> Result<Value, anyhow::Error> {
+    let tokens: Vec<Value> = req_body
+        .split_whitespace()
+        .map(|token| Value::Bulk(token.to_string()))
+        .collect();
+
+    if tokens.is_empty() {
+        return Err(RespErrors::RespArrEmptyError.into());
+    }
+
+    Ok(Value::Array(tokens))
+}
+
+/// The commands that switch the connection into the PubSub mode, or leave it
+pub const PUBSUB_COMMANDS: [&str; 4] = ["SUBSCRIBE", "PSUBSCRIBE", "UNSUBSCRIBE", "PUNSUBSCRIBE"];
+
+/// Returns true if the Redis command is one of the [PUBSUB_COMMANDS], ignoring the case.
+pub fn is_pubsub_command(redis_command: &str) -> bool {
+    PUBSUB_COMMANDS
+        .iter()
+        .any(|pubsub_command| pubsub_command.eq_ignore_ascii_case(redis_command))
+}
+
+/// Returns true if any command of the request body is one of the [PUBSUB_COMMANDS]. A request
+/// body that can't be decoded is not a PubSub request.
+pub fn is_pubsub(req_body: &str) -> bool {
+    split_pipeline(req_body)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|command| fetch_redis_command(decode(command).ok()?).ok())
+        .any(|redis_command| is_pubsub_command(&redis_command))
+}
+
+/// The commands that are never faulted, including by the wildcard faults, as they authenticate,
+/// negotiate or close the connection between the client and the origin server
+pub const BYPASS_COMMANDS: [&str; 4] = ["AUTH", "QUIT", "RESET", "HELLO"];
+
+/// Returns true if the Redis command is one of the [BYPASS_COMMANDS], ignoring the case.
+pub fn is_bypass_command(redis_command: &str) -> bool {
+    BYPASS_COMMANDS
+        .iter()
+        .any(|bypass_command| bypass_command.eq_ignore_ascii_case(redis_command))
+}
+
+/// Returns true if the first command of the request body is `HELLO`, with which the Redis 6+
+/// clients negotiate the protocol version, e.g. `HELLO 3` for RESP3. A request body that can't be
+/// decoded is not a `HELLO` request.
+pub fn is_hello(req_body: &str) -> bool {
+    split_pipeline(req_body)
+        .unwrap_or_default()
+        .first()
+        .and_then(|command| fetch_redis_command(decode(command).ok()?).ok())
+        .is_some_and(|redis_command| redis_command.eq_ignore_ascii_case("HELLO"))
+}
+
+/// Splits the request body into the individual RESP encoded commands
+///
+/// Redis clients may pipeline multiple commands in a single request. Every complete RESP message
+/// in the request body is decoded and re-encoded as a separate command. An incomplete trailing
+/// message is ignored. Inline commands are sent one per line, and each of them is re-encoded in the
+/// multi-bulk format, see [decode_inline].
+///
+/// Returns Ok(Vec<String>) on success
+///
+/// # Errors
+///
+/// Returns [RespErrors::DecoderFeedError] when feeding request body to the decoder fails
+/// or [RespErrors::DecodeError] when the request body has no complete RESP message
+///
+/// # Example
+/// ``` no_run
+/// let commands = resp_util::split_pipeline("*1\r\n$4\r\nping\r\n*1\r\n$4\r\nping\r\n")?;
+/// assert_eq!(commands.len(), 2);
+/// ```
+pub fn split_pipeline(req_body: &str) -> Result<Vec<String>, anyhow::Error> {
+    let mut commands = Vec::new();
+
+    if is_inline(req_body) {
+        for line in req_body.lines().filter(|line| !line.trim().is_empty()) {
+            commands.push(String::from_utf8_lossy(&decode_inline(line)?.encode()).to_string());
+        }
+    } else {
+        let mut decoder = Decoder::new();
+
+        if let Err(err) = decoder.feed(req_body.as_bytes()) {
+            return Err(RespErrors::DecoderFeedError(err.to_string()).into());
+        }
+
+        while let Some(val) = decoder.read() {
+            commands.push(String::from_utf8_lossy(&val.encode()).to_string());
+        }
+    }
+
+    if commands.is_empty() {
+        return Err(RespErrors::DecodeError.into());
+    }
+
+    Ok(commands)
+}
+
+/// Replaces the elements of the `reply_index`th reply of the response, which must be a
+/// resp::Value::Array, with the given errors. The elements without an error are kept.
+///
+/// Returns the re-encoded response on success
+///
+/// # Errors
+///
+/// Returns [RespErrors::DecoderFeedError] when feeding the response to the decoder fails
+/// or [RespErrors::UnsupportedRespValError] when the response has no such array reply
+///
+/// # Example
+/// ``` no_run
+/// let response = resp_util::replace_array_elements(
+///     b"+OK\r\n+QUEUED\r\n*1\r\n+OK\r\n",
+///     2,
+///     &[Some("ERR failed".to_string())],
+/// )?;
+/// assert_eq!(response, b"+OK\r\n+QUEUED\r\n*1\r\n-ERR failed\r\n");
+/// ```
+pub fn replace_array_elements(
+    response: &[u8],
+    reply_index: usize,
+    errors: &[Option<String>],
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut decoder = Decoder::new();
+    if let Err(err) = decoder.feed(response) {
+        return Err(RespErrors::DecoderFeedError(err.to_string()).into());
+    }
+
+    let mut replies = Vec::new();
+    while let Some(val) = decoder.read() {
+        replies.push(val);
+    }
+
+    match replies.get_mut(reply_index) {
+        Some(Value::Array(elements)) => {
+            for (element, error) in elements.iter_mut().zip(errors) {
+                if let Some(error) = error {
+                    *element = Value::Error(error.clone());
+                }
+            }
+        }
+        _ => return Err(RespErrors::UnsupportedRespValError.into()),
+    }
+
+    Ok(replies.iter().flat_map(Value::encode).collect())
+}
+
+/// The commands that alter the session of the origin server connection, such that a connection
+/// proxying them can't be replaced by a new one without the client noticing
+pub const SESSION_COMMANDS: [&str; 5] = ["AUTH", "SELECT", "CLIENT", "MULTI", "WATCH"];
+
+/// Returns true if any command of the request body is one of the [SESSION_COMMANDS], ignoring
+/// the case.
+pub fn has_session_command(req_body: &str) -> bool {
+    split_pipeline(req_body)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|command| fetch_redis_command(decode(command).ok()?).ok())
+        .any(|redis_command| {
+            SESSION_COMMANDS
+                .iter()
+                .any(|session_command| session_command.eq_ignore_ascii_case(&redis_command))
+        })
+}
+
+/// Returns the length in bytes of the RESP2 message at the start of the buffer, or None when the
+/// message is incomplete. The message is framed without being decoded, hence a large bulk string
+/// is skipped over by its length.
+///
+/// # Errors
+///
+/// Returns [RespErrors::InvalidMessageError] when the buffer doesn't start with a RESP2 message
+///
+/// # Example
+/// ``` no_run
+/// assert_eq!(resp_util::message_len(b"*1\r\n$4\r\nping\r\n+OK")?, Some(14));
+/// assert_eq!(resp_util::message_len(b"$4\r\npi")?, None);
+/// ```
+pub fn message_len(buf: &[u8]) -> Result<Option<usize>, anyhow::Error> {
+    let line_len = match buf.windows(2).position(|window| window == b"\r\n") {
+        Some(line_end) => line_end + 2,
+        None => return Ok(None),
+    };
+    let parse_len = || -> Result<i64, anyhow::Error> {
+        std::str::from_utf8(&buf[1..line_len - 2])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| RespErrors::InvalidMessageError.into())
+    };
+
+    match buf[0] {
+        b'+' | b'-' | b':' => Ok(Some(line_len)),
+        b'$' => {
+            let len = parse_len()?;
+            if len < 0 {
+                return Ok(Some(line_len));
+            }
+            let message_len = line_len + len as usize + 2;
+            Ok((buf.len() >= message_len).then_some(message_len))
+        }
+        b'*' => {
+            let mut message_len = line_len;
+            for _ in 0..parse_len()?.max(0) {
+                match message_len_at(buf, message_len)? {
+                    Some(element_len) => message_len += element_len,
+                    None => return Ok(None),
+                }
+            }
+            Ok(Some(message_len))
+        }
+        _ => Err(RespErrors::InvalidMessageError.into()),
+    }
+}
+
+/// Returns the [message_len] of the message at the given position of the buffer.
+fn message_len_at(buf: &[u8], pos: usize) -> Result<Option<usize>, anyhow::Error> {
+    if pos >= buf.len() {
+        return Ok(None);
+    }
+    message_len(&buf[pos..])
+}
+
+/// Returns the number of the commands of the request body if it holds only complete commands,
+/// either RESP encoded or inline. None is returned when the last command is incomplete, e.g. it is
+/// split across several reads of the client connection.
+pub fn complete_command_count(req_body: &str) -> Option<usize> {
+    if req_body.is_empty() {
+        return None;
+    }
+    if is_inline(req_body) {
+        return req_body.ends_with('\n').then(|| {
+            req_body
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .count()
+        });
+    }
+
+    let mut count = 0;
+    let mut pos = 0;
+    while pos < req_body.len() {
+        pos += message_len_at(req_body.as_bytes(), pos).ok()??;
+        count += 1;
+    }
+    Some(count)
+}
+
 /// Fetches the Redis command from the resp::Value::Array
 ///
 /// Returns Ok(redis_command) on success
@@ -81,6 +351,47 @@ pub fn fetch_redis_command(resp_vals: resp::Value) -> Result<String, anyhow::Err
     }
 }
 
+/// Fetches the Redis key, i.e. the first argument of the Redis command, from the
+/// resp::Value::Array
+///
+/// Returns None when the command has no arguments or the first argument is not a string.
+///
+/// # Example
+/// ``` no_run
+///  let val = resp_util::decode("*2\r\n$3\r\nget\r\n$3\r\nfoo\r\n")?;
+///  assert_eq!(resp_util::fetch_redis_key(&val), Some("foo".to_string()));
+/// ```
+pub fn fetch_redis_key(resp_vals: &resp::Value) -> Option<String> {
+    fetch_redis_args(resp_vals).into_iter().nth(1).flatten()
+}
+
+/// Fetches the Redis command and its arguments from the resp::Value::Array
+///
+/// The element of an argument that is not a string is None, such that the position of the other
+/// arguments is kept. An empty vector is returned when the value is not an array.
+///
+/// # Example
+/// ``` no_run
+///  let val = resp_util::decode("*2\r\n$3\r\nget\r\n$3\r\nfoo\r\n")?;
+///  assert_eq!(
+///      resp_util::fetch_redis_args(&val),
+///      vec![Some("get".to_string()), Some("foo".to_string())]
+///  );
+/// ```
+pub fn fetch_redis_args(resp_vals: &resp::Value) -> Vec<Option<String>> {
+    match resp_vals {
+        Value::Array(arr) => arr
+            .iter()
+            .map(|val| match val {
+                Value::Bulk(v) => Some(v.clone()),
+                Value::String(v) => Some(v.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 /// Encodes the error message into Redis RESP Error message. The RESP Error message
 /// follows a format like this "-Error message\r\n"
 ///
@@ -98,11 +409,12 @@ pub fn encode_error_message(err_message: String) -> Result<Vec<u8>, anyhow::Erro
 }
 
 pub fn get_host_name(redis_server_addr: &str) -> Result<String, anyhow::Error> {
-    let mut parsed_redis_url = Url::parse(redis_server_addr)?;
-
-    if parsed_redis_url.host_str() == None {
-        parsed_redis_url = Url::parse(&format!("redis://{}", redis_server_addr))?;
-    }
+    // an address without a scheme, e.g. `127.0.0.1:6379`, is either not a URL or is parsed with
+    // the host as the scheme
+    let parsed_redis_url = match Url::parse(redis_server_addr) {
+        Ok(parsed_redis_url) if parsed_redis_url.host_str().is_some() => parsed_redis_url,
+        _ => Url::parse(&format!("redis://{}", redis_server_addr))?,
+    };
 
     let host_name = parsed_redis_url
         .host_str()
@@ -123,6 +435,8 @@ pub enum RespErrors {
     UnsupportedRespArrValError,
     #[error("Error as resp value type not supported for redis command")]
     UnsupportedRespValError,
+    #[error("Error as the message is not a valid RESP2 message")]
+    InvalidMessageError,
 }
 
 #[cfg(test)]
@@ -133,11 +447,32 @@ mod tests {
     fn test_decode() {
         let buf = "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
         let res = resp_util::decode(buf);
-        assert_eq!(false, res.is_err());
+        assert!(res.is_ok());
 
         let buf = "hello world; this is not a valid resp message";
         let res = resp_util::decode(buf);
-        assert_eq!(true, res.is_err());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_split_pipeline() {
+        let buf = "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n";
+        let res = resp_util::split_pipeline(buf).unwrap();
+        assert_eq!(
+            res,
+            vec![
+                "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n",
+                "*2\r\n$3\r\nget\r\n$5\r\nmykey\r\n",
+            ]
+        );
+
+        let buf = "*1\r\n$4\r\nping\r\n*2\r\n$3\r\nget";
+        let res = resp_util::split_pipeline(buf).unwrap();
+        assert_eq!(res, vec!["*1\r\n$4\r\nping\r\n"]);
+
+        // the only message is incomplete
+        let buf = "*1\r\n$4\r\nping";
+        assert!(resp_util::split_pipeline(buf).is_err());
     }
 
     #[test]
@@ -145,27 +480,222 @@ mod tests {
         let buf = "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
         let res = resp_util::decode(buf).unwrap();
         let res = resp_util::fetch_redis_command(res);
-        assert_eq!(true, res.is_ok());
+        assert!(res.is_ok());
         assert_eq!("set", res.unwrap());
 
         let buf = "$-1\r\n";
         let res = resp_util::decode(buf).unwrap();
         let res = resp_util::fetch_redis_command(res);
-        assert_eq!(false, res.is_ok());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_fetch_redis_key() {
+        let buf = "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let res = resp_util::decode(buf).unwrap();
+        assert_eq!(resp_util::fetch_redis_key(&res), Some("mykey".to_string()));
+
+        let buf = "*1\r\n$4\r\nping\r\n";
+        let res = resp_util::decode(buf).unwrap();
+        assert_eq!(resp_util::fetch_redis_key(&res), None);
+    }
+
+    #[test]
+    fn test_fetch_redis_args() {
+        let buf = "*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n:1\r\n";
+        let res = resp_util::decode(buf).unwrap();
+        assert_eq!(
+            resp_util::fetch_redis_args(&res),
+            vec![Some("set".to_string()), Some("mykey".to_string()), None]
+        );
+
+        let buf = "$-1\r\n";
+        let res = resp_util::decode(buf).unwrap();
+        assert!(resp_util::fetch_redis_args(&res).is_empty());
+    }
+
+    #[test]
+    fn test_decode_inline() {
+        assert!(resp_util::is_inline("PING\r\n"));
+        assert!(!resp_util::is_inline("*1\r\n$4\r\nping\r\n"));
+        assert!(!resp_util::is_inline(""));
+
+        let val = resp_util::decode_inline("PING\r\n").unwrap();
+        assert_eq!(
+            val,
+            resp::Value::Array(vec![resp::Value::Bulk("PING".to_string())])
+        );
+
+        let val = resp_util::decode_inline("SET foo  bar\r\n").unwrap();
+        assert_eq!(
+            resp_util::fetch_redis_args(&val),
+            vec![
+                Some("SET".to_string()),
+                Some("foo".to_string()),
+                Some("bar".to_string())
+            ]
+        );
+        assert_eq!(
+            val.encode(),
+            b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n".to_vec()
+        );
+
+        let val = resp_util::decode_inline("sEt foo bar").unwrap();
+        assert_eq!(resp_util::fetch_redis_command(val).unwrap(), "sEt");
+
+        assert!(resp_util::decode_inline("\r\n").is_err());
+    }
+
+    #[test]
+    fn test_split_inline_pipeline() {
+        let commands = resp_util::split_pipeline("PING\r\nget foo\r\n\r\n").unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                "*1\r\n$4\r\nPING\r\n".to_string(),
+                "*2\r\n$3\r\nget\r\n$3\r\nfoo\r\n".to_string()
+            ]
+        );
+        assert!(resp_util::split_pipeline(" \r\n").is_err());
+    }
+
+    #[test]
+    fn test_replace_array_elements() {
+        let response = b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n+OK\r\n$1\r\n1\r\n";
+        let replaced =
+            resp_util::replace_array_elements(response, 3, &[Some("SET FAILED".to_string()), None])
+                .unwrap();
+        assert_eq!(
+            replaced,
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n-SET FAILED\r\n$1\r\n1\r\n".to_vec()
+        );
+
+        assert!(resp_util::replace_array_elements(response, 0, &[None]).is_err());
+        assert!(resp_util::replace_array_elements(response, 4, &[None]).is_err());
+    }
+
+    #[test]
+    fn test_is_pubsub() {
+        assert!(resp_util::is_pubsub_command("SUBSCRIBE"));
+        assert!(resp_util::is_pubsub_command("pSubscribe"));
+        assert!(resp_util::is_pubsub_command("unsubscribe"));
+        assert!(resp_util::is_pubsub_command("PUNSUBSCRIBE"));
+        assert!(!resp_util::is_pubsub_command("PUBLISH"));
+
+        assert!(resp_util::is_pubsub(
+            "*2\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n"
+        ));
+        assert!(resp_util::is_pubsub("PING\r\nSUBSCRIBE news\r\n"));
+        assert!(!resp_util::is_pubsub("PUBLISH news hello\r\n"));
+        assert!(!resp_util::is_pubsub("*1\r\n$4\r\nping"));
+    }
+
+    #[test]
+    fn test_is_hello() {
+        assert!(resp_util::is_bypass_command("HELLO"));
+        assert!(resp_util::is_bypass_command("hello"));
+        assert!(resp_util::is_bypass_command("auth"));
+        assert!(resp_util::is_bypass_command("QUIT"));
+        assert!(resp_util::is_bypass_command("Reset"));
+        assert!(!resp_util::is_bypass_command("PING"));
+
+        assert!(resp_util::is_hello("*2\r\n$5\r\nhello\r\n$1\r\n3\r\n"));
+        assert!(resp_util::is_hello("HELLO 3\r\nPING\r\n"));
+        assert!(!resp_util::is_hello("PING\r\nHELLO 3\r\n"));
+        assert!(!resp_util::is_hello("*1\r\n$5\r\nhello"));
+    }
+
+    #[test]
+    fn test_message_len() {
+        for message in [
+            "+OK\r\n",
+            "-ERR unknown command\r\n",
+            ":42\r\n",
+            "$5\r\nhello\r\n",
+            "$0\r\n\r\n",
+            "$-1\r\n",
+            "*-1\r\n",
+            "*0\r\n",
+            "*2\r\n$3\r\nfoo\r\n*2\r\n:1\r\n$-1\r\n",
+        ] {
+            let buf = format!("{}+NEXT\r\n", message);
+            assert_eq!(
+                resp_util::message_len(buf.as_bytes()).unwrap(),
+                Some(message.len()),
+                "{}",
+                message
+            );
+            for len in 0..message.len() {
+                assert_eq!(
+                    resp_util::message_len(&message.as_bytes()[..len]).unwrap(),
+                    None,
+                    "{:?}",
+                    &message[..len]
+                );
+            }
+        }
+
+        // the bulk string is framed by its length, even when it holds a line break
+        assert_eq!(
+            resp_util::message_len(b"$6\r\nfo\r\nbr\r\n").unwrap(),
+            Some(12)
+        );
+        assert!(resp_util::message_len(b"hello\r\n").is_err());
+        assert!(resp_util::message_len(b"$x\r\n").is_err());
+    }
+
+    #[test]
+    fn test_complete_command_count() {
+        assert_eq!(
+            resp_util::complete_command_count("*1\r\n$4\r\nping\r\n*2\r\n$3\r\nget\r\n$1\r\na\r\n"),
+            Some(2)
+        );
+        assert_eq!(
+            resp_util::complete_command_count("*2\r\n$3\r\nget\r\n$1\r\n"),
+            None
+        );
+        assert_eq!(
+            resp_util::complete_command_count("PING\r\nGET a\r\n"),
+            Some(2)
+        );
+        assert_eq!(resp_util::complete_command_count("GET a"), None);
+        assert_eq!(resp_util::complete_command_count(""), None);
+    }
+
+    #[test]
+    fn test_has_session_command() {
+        assert!(resp_util::has_session_command(
+            "*1\r\n$4\r\nping\r\n*2\r\n$6\r\nselect\r\n$1\r\n1\r\n"
+        ));
+        assert!(resp_util::has_session_command("AUTH s3cr3t\r\n"));
+        assert!(!resp_util::has_session_command("*1\r\n$4\r\nping\r\n"));
+    }
+
+    #[test]
+    fn test_get_host_name() {
+        assert_eq!(
+            resp_util::get_host_name("localhost:6379").unwrap(),
+            "localhost"
+        );
+        assert_eq!(
+            resp_util::get_host_name("127.0.0.1:6379").unwrap(),
+            "127.0.0.1"
+        );
+        assert_eq!(
+            resp_util::get_host_name("rediss://redis.example.com:6380").unwrap(),
+            "redis.example.com"
+        );
     }
 
     #[test]
     fn test_encode_error_message() {
         let error_message = "Error message".to_string();
         let res = resp_util::encode_error_message(error_message);
-        assert_eq!(true, res.is_ok());
-        match res {
-            Ok(v) => {
-                let expected_val = "-Error message\r\n".to_string();
-                let actual_val = String::from_utf8(v).unwrap();
-                assert_eq!(expected_val, actual_val);
-            }
-            Err(_) => {}
+        assert!(res.is_ok());
+        if let Ok(v) = res {
+            let expected_val = "-Error message\r\n".to_string();
+            let actual_val = String::from_utf8(v).unwrap();
+            assert_eq!(expected_val, actual_val);
         };
     }
 }
@@ -0,0 +1,540 @@
+use crate::proxy::connection::Connection;
+use crate::proxy::faulter::ClientIdentity;
+use crate::proxy::rate_limiter::RateLimiter;
+use crate::proxy::resp_util;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::sync::{watch, Semaphore};
+use tracing::{debug, error, info, warn};
+
+/// Listener accepts the client connections of the proxy over TCP or a Unix domain socket.
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// The maximum number of pending connections of a listener bound by [Listener::bind_reuse_port]
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// InboundStream is a client connection accepted by the [Listener].
+enum InboundStream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl InboundStream {
+    /// Replies the RESP error to the client and closes the connection.
+    async fn reject(mut self, err_message: &str) -> std::io::Result<()> {
+        let err_message = resp_util::encode_error_message(err_message.to_string())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+        match &mut self {
+            InboundStream::Tcp(socket) => {
+                socket.write_all(&err_message).await?;
+                socket.shutdown().await
+            }
+            InboundStream::Unix(socket) => {
+                socket.write_all(&err_message).await?;
+                socket.shutdown().await
+            }
+        }
+    }
+}
+
+impl Listener {
+    /// Binds a Unix domain socket listener to the given path. A socket file left behind by a
+    /// previous run is replaced, but any other file at the path fails the binding.
+    pub fn bind_unix(path: &str) -> std::io::Result<Self> {
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(Listener::Unix(UnixListener::bind(path)?))
+    }
+
+    /// Binds a TCP listener to the given address with `SO_REUSEPORT`, such that several listeners
+    /// bound to the same address accept its connections in parallel, the kernel spreading the new
+    /// connections across them.
+    pub fn bind_reuse_port(addr: SocketAddr) -> std::io::Result<Self> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(LISTEN_BACKLOG)?;
+
+        Ok(Listener::Tcp(TcpListener::from_std(socket.into())?))
+    }
+
+    async fn accept(&self) -> std::io::Result<(InboundStream, String)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((InboundStream::Tcp(socket), addr.to_string()))
+            }
+            Listener::Unix(listener) => {
+                let (socket, _addr) = listener.accept().await?;
+                Ok((InboundStream::Unix(socket), "unix socket".to_string()))
+            }
+        }
+    }
+
+    /// Stops listening, removing the socket file of a Unix domain socket listener.
+    fn close(self) {
+        let socket_path = match &self {
+            Listener::Tcp(_) => None,
+            Listener::Unix(listener) => listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(PathBuf::from)),
+        };
+        drop(self);
+
+        if let Some(socket_path) = socket_path {
+            if let Err(err) = std::fs::remove_file(&socket_path) {
+                error!("error removing socket file {:?}: {}", socket_path, err);
+            }
+        }
+    }
+}
+
+/// The interval at which the number of in-flight connections is checked while draining them on
+/// shutdown.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Accepts the client connections on the listener and proxies them until the shutdown is
+/// signalled through the `shutdown` watch channel. When the rate limiter runs out of tokens, the
+/// new connections are closed right away instead of being proxied. When the connection permits
+/// run out, the new connections get a `-ERR too many connections` reply and are closed. The
+/// permits are shared by the listeners of the same address, see [Listener::bind_reuse_port], such
+/// that they limit the in-flight connections of the address.
+///
+/// On shutdown, the listener is closed such that no new connection is accepted. The in-flight
+/// connections are given `shutdown_timeout` to complete before they are cancelled, and `run`
+/// returns once all of them are done.
+pub async fn run(
+    listener: Listener,
+    conn: Connection,
+    rate_limiter: Option<RateLimiter>,
+    connection_permits: Option<Arc<Semaphore>>,
+    mut shutdown: watch::Receiver<bool>,
+    shutdown_timeout: Duration,
+) {
+    let active_conns = Arc::new(AtomicUsize::new(0));
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (socket, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        error!("error accepting connection: {}", err);
+                        continue;
+                    }
+                };
+
+                if let Some(rate_limiter) = &rate_limiter {
+                    if !rate_limiter.try_acquire() {
+                        debug!("connection rate limit exceeded, closing connection from {}", addr);
+                        drop(socket);
+                        continue;
+                    }
+                }
+
+                // the permit is released once the connection is handled
+                let connection_permit = match &connection_permits {
+                    Some(connection_permits) => match connection_permits.clone().try_acquire_owned() {
+                        Ok(connection_permit) => Some(connection_permit),
+                        Err(_) => {
+                            warn!("too many concurrent connections, rejecting connection from {}", addr);
+                            tokio::spawn(async move {
+                                if let Err(err) = socket.reject("ERR too many connections").await {
+                                    debug!("error rejecting connection from {}: {}", addr, err);
+                                }
+                            });
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+
+                let conn = conn.clone();
+                let mut shutdown = shutdown.clone();
+                let active_conn = ActiveConnection::new(active_conns.clone());
+
+                tokio::spawn(async move {
+                    debug!("handling tcp connection from {}", addr);
+                    let handled = async move {
+                        match socket {
+                            InboundStream::Tcp(socket) => conn.handle(socket).await,
+                            InboundStream::Unix(socket) => conn.handle_stream(socket, None, ClientIdentity::Unknown, None).await,
+                        }
+                    };
+
+                    tokio::select! {
+                        result = handled => {
+                            if let Err(err) = result {
+                                error!("error handling connection: {:?}", err);
+                            }
+                        }
+                        _ = cancelled(&mut shutdown, shutdown_timeout) => {
+                            warn!("cancelled connection from {} on shutdown", addr);
+                        }
+                    }
+                    drop(active_conn);
+                    drop(connection_permit);
+                });
+            }
+            _ = shutdown.changed() => {
+                break;
+            }
+        }
+    }
+
+    listener.close();
+    info!(
+        "shutting down proxy, draining {} in-flight connections",
+        active_conns.load(Ordering::SeqCst)
+    );
+
+    while active_conns.load(Ordering::SeqCst) > 0 {
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+    info!("proxy shut down");
+}
+
+/// Completes `shutdown_timeout` after the shutdown is signalled.
+async fn cancelled(shutdown: &mut watch::Receiver<bool>, shutdown_timeout: Duration) {
+    // The shutdown may already be signalled by the time the connection is accepted
+    if shutdown.wait_for(|is_shutdown| *is_shutdown).await.is_err() {
+        // The sender is gone without signalling the shutdown, hence it never will
+        std::future::pending::<()>().await;
+    }
+    tokio::time::sleep(shutdown_timeout).await;
+}
+
+/// ActiveConnection counts an in-flight connection for as long as it is alive, even when the
+/// handler task panics.
+struct ActiveConnection(Arc<AtomicUsize>);
+
+impl ActiveConnection {
+    fn new(active_conns: Arc<AtomicUsize>) -> Self {
+        active_conns.fetch_add(1, Ordering::SeqCst);
+        ActiveConnection(active_conns)
+    }
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::proxy::connection::tests::{next_test_ip4, run_mock_origin_server};
+    use crate::proxy::connection::OriginServerConfig;
+    use crate::proxy::faulter::Faulter;
+    use crate::store::fault_store::{Fault, FaultVariants};
+    use crate::store::mem_store::MemStore;
+    use std::net::SocketAddr;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpStream, UnixStream};
+    use tokio::task::JoinHandle;
+
+    /// Runs the proxy on `proxy_addr` with a `delay_ms` delay fault on the SET command and returns
+    /// the shutdown sender and the handle of `run`.
+    async fn run_proxy_with_delay(
+        proxy_addr: SocketAddr,
+        delay_ms: u64,
+        shutdown_timeout: Duration,
+    ) -> (watch::Sender<bool>, JoinHandle<()>) {
+        let listener = Listener::Tcp(TcpListener::bind(proxy_addr).await.unwrap());
+        run_proxy_on_listener(listener, None, None, delay_ms, shutdown_timeout).await
+    }
+
+    /// Runs the proxy like [run_proxy_with_delay] on the given listener, limiting the new
+    /// connections with the rate limiter and the maximum number of concurrent connections, if
+    /// any.
+    async fn run_proxy_on_listener(
+        listener: Listener,
+        rate_limiter: Option<RateLimiter>,
+        max_concurrent_connections: Option<usize>,
+        delay_ms: u64,
+        shutdown_timeout: Duration,
+    ) -> (watch::Sender<bool>, JoinHandle<()>) {
+        let origin_server_addr = next_test_ip4();
+        run_mock_origin_server(origin_server_addr);
+
+        let fault_store = MemStore::new_db();
+        let fault = Fault {
+            duration: Some(delay_ms),
+            ..Fault::fixture("delay_fault", FaultVariants::Delay, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let conn = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec![origin_server_addr.to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            Faulter::new(fault_store, Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let handle = tokio::spawn(run(
+            listener,
+            conn,
+            rate_limiter,
+            max_concurrent_connections.map(|permits| Arc::new(Semaphore::new(permits))),
+            shutdown_rx,
+            shutdown_timeout,
+        ));
+
+        (shutdown_tx, handle)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_graceful_shutdown_drains_connections() {
+        let proxy_addr = next_test_ip4();
+        let (shutdown_tx, handle) =
+            run_proxy_with_delay(proxy_addr, 500, Duration::from_secs(10)).await;
+
+        let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        // let the request reach the delay fault before shutting down
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown_tx.send(true).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(TcpStream::connect(proxy_addr).await.is_err());
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_graceful_shutdown_timeout() {
+        let proxy_addr = next_test_ip4();
+        let (shutdown_tx, handle) =
+            run_proxy_with_delay(proxy_addr, 10_000, Duration::from_millis(200)).await;
+
+        let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+        stream
+            .write_all(b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let start = Instant::now();
+        shutdown_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // the cancelled connection is closed without a response
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert!(read_buffer.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_ipv6() {
+        let port = next_test_ip4().port();
+        let (_shutdown_tx, _handle) = run_proxy_with_delay(
+            format!("[::]:{}", port).parse().unwrap(),
+            0,
+            Duration::from_secs(1),
+        )
+        .await;
+
+        let mut stream = TcpStream::connect(format!("[::1]:{}", port)).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proxy_unix_socket() {
+        let socket_path =
+            std::env::temp_dir().join(format!("red-monkey-{}.sock", uuid::Uuid::new_v4()));
+        let socket_path = socket_path.to_str().unwrap().to_string();
+        let listener = Listener::bind_unix(&socket_path).unwrap();
+        let (shutdown_tx, handle) =
+            run_proxy_on_listener(listener, None, None, 0, Duration::from_secs(1)).await;
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        stream.write_all(write_buffer).await.unwrap();
+
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+
+        // the socket file is removed on shutdown
+        shutdown_tx.send(true).unwrap();
+        handle.await.unwrap();
+        assert!(!std::path::Path::new(&socket_path).exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_connection_rate_limit() {
+        let max_connections_per_second = 10;
+        let proxy_addr = next_test_ip4();
+        let listener = Listener::Tcp(TcpListener::bind(proxy_addr).await.unwrap());
+        let (_shutdown_tx, _handle) = run_proxy_on_listener(
+            listener,
+            Some(RateLimiter::start(max_connections_per_second)),
+            None,
+            0,
+            Duration::from_secs(1),
+        )
+        .await;
+
+        let start = Instant::now();
+        let mut streams = Vec::new();
+        for _ in 0..100 {
+            streams.push(TcpStream::connect(proxy_addr).await.unwrap());
+        }
+        let elapsed = start.elapsed();
+
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut handled = 0;
+        for mut stream in streams {
+            // the rejected connections are closed without a response
+            let _ = stream.write_all(write_buffer).await;
+            let mut read_buffer = Vec::new();
+            if stream.read_to_end(&mut read_buffer).await.is_ok() && read_buffer == write_buffer {
+                handled += 1;
+            }
+        }
+
+        // the full bucket lets a burst of a second worth of connections through, then a token is
+        // refilled every 100ms
+        let refilled = (elapsed.as_millis() / 100) as u32 + 1;
+        assert!(handled >= max_connections_per_second);
+        assert!(handled <= max_connections_per_second + refilled);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_max_concurrent_connections() {
+        let proxy_addr = next_test_ip4();
+        let listener = Listener::Tcp(TcpListener::bind(proxy_addr).await.unwrap());
+        let (_shutdown_tx, _handle) =
+            run_proxy_on_listener(listener, None, Some(100), 0, Duration::from_secs(1)).await;
+
+        // the connections are in flight until the clients send a request
+        let mut streams = Vec::new();
+        for _ in 0..100 {
+            streams.push(TcpStream::connect(proxy_addr).await.unwrap());
+        }
+
+        let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, b"-ERR too many connections\r\n");
+
+        // the permit of a handled connection is released
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let mut stream = streams.pop().unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+        stream.write_all(write_buffer).await.unwrap();
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await.unwrap();
+        assert_eq!(read_buffer, write_buffer);
+    }
+
+    /// Sends `clients` concurrent SET requests to the proxy and checks that they are echoed back.
+    async fn send_concurrent_requests(proxy_addr: SocketAddr, clients: usize) {
+        let write_buffer = b"*3\r\n$3\r\nset\r\n$5\r\nmykey\r\n$1\r\n1\r\n";
+        let requests = (0..clients).map(|_| {
+            tokio::spawn(async move {
+                let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+                stream.write_all(write_buffer).await.unwrap();
+
+                let mut read_buffer = Vec::new();
+                stream.read_to_end(&mut read_buffer).await.unwrap();
+                assert_eq!(read_buffer, write_buffer);
+            })
+        });
+        for request in futures::future::join_all(requests).await {
+            request.unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bind_reuse_port() {
+        let proxy_addr = next_test_ip4();
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let listener = Listener::bind_reuse_port(proxy_addr).unwrap();
+            handles
+                .push(run_proxy_on_listener(listener, None, None, 0, Duration::from_secs(1)).await);
+        }
+
+        // a listener without SO_REUSEPORT can't share the address
+        assert!(TcpListener::bind(proxy_addr).await.is_err());
+
+        send_concurrent_requests(proxy_addr, 20).await;
+    }
+
+    /// Benchmarks the throughput of the concurrent connections accepted by a single listener
+    /// against 4 listeners bound with `SO_REUSEPORT`. Run it with `cargo test -- --ignored`.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[ignore = "benchmark"]
+    async fn test_accept_threads_throughput() {
+        const CLIENTS: usize = 200;
+
+        for accept_threads in [1, 4] {
+            let proxy_addr = next_test_ip4();
+            let mut handles = Vec::new();
+            for _ in 0..accept_threads {
+                let listener = Listener::bind_reuse_port(proxy_addr).unwrap();
+                handles.push(
+                    run_proxy_on_listener(listener, None, None, 0, Duration::from_secs(1)).await,
+                );
+            }
+
+            let start = Instant::now();
+            send_concurrent_requests(proxy_addr, CLIENTS).await;
+            debug!(
+                "proxied {} concurrent connections with {} accept loop(s) in {:?}",
+                CLIENTS,
+                accept_threads,
+                start.elapsed()
+            );
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::proxy::connection::Connection;
+use anyhow::anyhow;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// The RESP encoded PING command sent to the upstreams
+const PING: &[u8] = b"*1\r\n$4\r\nPING\r\n";
+
+/// The maximum length of the first line of the reply to PING that is read
+const MAX_REPLY_LEN: usize = 512;
+
+/// UpstreamHealthMonitor periodically pings the upstream Redis servers, such that the connections
+/// are only load balanced across the healthy ones.
+///
+/// An upstream is marked unhealthy as soon as a ping fails, and marked healthy again after
+/// `recovery_attempts` consecutive successful pings. Every upstream starts healthy.
+///
+/// The pings are sent over the connections of the proxy to the upstreams, i.e. over TLS and
+/// through the SOCKS5 proxy as configured, see [Connection::spawn_health_checks]. Any RESP reply,
+/// an error like `-NOAUTH` included, tells that the upstream is alive.
+#[derive(Clone)]
+pub struct UpstreamHealthMonitor {
+    health: Arc<DashMap<String, bool>>,
+    interval: Duration,
+    recovery_attempts: u32,
+}
+
+impl UpstreamHealthMonitor {
+    /// Creates a new UpstreamHealthMonitor that pings the given upstreams every `interval`.
+    pub fn new(server_addrs: &[String], interval: Duration, recovery_attempts: u32) -> Self {
+        let health = server_addrs
+            .iter()
+            .map(|server_addr| (server_addr.clone(), true))
+            .collect();
+
+        UpstreamHealthMonitor {
+            health: Arc::new(health),
+            interval,
+            recovery_attempts,
+        }
+    }
+
+    /// Returns false if the upstream is marked unhealthy. Unmonitored upstreams are healthy.
+    pub fn is_healthy(&self, server_addr: &str) -> bool {
+        self.health.get(server_addr).is_none_or(|healthy| *healthy)
+    }
+
+    /// Spawns a task that pings the upstreams forever over the connections of the given
+    /// Connection.
+    pub fn spawn(&self, connection: Connection) -> JoinHandle<()> {
+        let monitor = self.clone();
+
+        tokio::spawn(async move {
+            let mut successful_pings = HashMap::new();
+            let mut ticker = tokio::time::interval(monitor.interval);
+
+            loop {
+                ticker.tick().await;
+                monitor.check(&connection, &mut successful_pings).await;
+            }
+        })
+    }
+
+    /// Pings every upstream once and updates its health. `successful_pings` holds the number of
+    /// consecutive successful pings of the unhealthy upstreams.
+    async fn check(&self, connection: &Connection, successful_pings: &mut HashMap<String, u32>) {
+        let server_addrs: Vec<String> = self.health.iter().map(|e| e.key().clone()).collect();
+
+        for server_addr in server_addrs {
+            // a ping must not outlast the interval of the health checks
+            let result = ping(connection, &server_addr, self.interval).await;
+            let healthy = self.is_healthy(&server_addr);
+
+            match result {
+                Ok(_) if healthy => {}
+                Ok(_) => {
+                    let count = successful_pings.entry(server_addr.clone()).or_insert(0);
+                    *count += 1;
+                    debug!(
+                        "upstream {} answered {} of {} recovery pings",
+                        server_addr, count, self.recovery_attempts
+                    );
+
+                    if *count >= self.recovery_attempts {
+                        info!("upstream {} is healthy again", server_addr);
+                        successful_pings.remove(&server_addr);
+                        self.health.insert(server_addr, true);
+                    }
+                }
+                Err(err) => {
+                    if healthy {
+                        warn!("upstream {} is unhealthy: {}", server_addr, err);
+                    }
+                    successful_pings.remove(&server_addr);
+                    self.health.insert(server_addr, false);
+                }
+            }
+        }
+    }
+}
+
+/// Sends the PING command to the upstream over a new connection of the given Connection, and
+/// expects a RESP reply, e.g. `+PONG` or `-NOAUTH Authentication required.` from a server with
+/// `requirepass`.
+async fn ping(
+    connection: &Connection,
+    server_addr: &str,
+    timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    let ping = async {
+        let mut stream = connection.create_server_stream(server_addr).await?;
+        stream.write_all(PING).await?;
+
+        let mut reply = Vec::new();
+        while !reply.ends_with(b"\r\n") {
+            if reply.len() >= MAX_REPLY_LEN {
+                return Err(anyhow!("reply to PING is too long"));
+            }
+            let mut byte = [0; 1];
+            if stream.read(&mut byte).await? == 0 {
+                return Err(anyhow!(
+                    "upstream closed the connection without replying to PING"
+                ));
+            }
+            reply.push(byte[0]);
+        }
+
+        if !is_resp_reply(&reply) {
+            return Err(anyhow!(
+                "unexpected reply to PING: {:?}",
+                String::from_utf8_lossy(&reply)
+            ));
+        }
+
+        Ok(())
+    };
+
+    tokio::time::timeout(timeout, ping)
+        .await
+        .map_err(|_| anyhow!("PING timed out"))?
+}
+
+/// Returns whether the line starts with the type of a RESP2 or RESP3 reply, e.g. `+` for a simple
+/// string or `-` for an error.
+fn is_resp_reply(line: &[u8]) -> bool {
+    line.len() > 2 && b"+-:$*_,#!=(%~>|".contains(&line[0])
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::proxy::connection::tests::next_test_ip4;
+    use crate::proxy::connection::{OriginServerConfig, UpstreamTlsConfig};
+    use crate::proxy::faulter::Faulter;
+    use crate::store::mem_store::MemStore;
+    use std::io::{Read, Write};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// The RESP encoded reply of the PING command
+    const PONG: &[u8] = b"+PONG\r\n";
+
+    /// Runs a mock Redis server that replies to PING while `is_up` is set, and closes the
+    /// connections right away otherwise.
+    pub fn run_mock_ping_server(server_addr: SocketAddr, is_up: Arc<AtomicBool>) {
+        let listener = std::net::TcpListener::bind(server_addr).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                if !is_up.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                tokio::spawn(async move {
+                    let mut buf = [0; PING.len()];
+                    if socket.read_exact(&mut buf).await.is_ok() {
+                        let _ = socket.write_all(PONG).await;
+                    }
+                });
+            }
+        });
+    }
+
+    /// Runs a mock Redis server over TLS that replies to PING with the given reply.
+    fn run_mock_tls_ping_server(server_addr: SocketAddr, reply: &'static [u8]) {
+        use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor
+            .set_private_key_file("testdata/tls/server.key", SslFiletype::PEM)
+            .unwrap();
+        acceptor
+            .set_certificate_chain_file("testdata/tls/server.crt")
+            .unwrap();
+        let acceptor = Arc::new(acceptor.build());
+
+        let listener = std::net::TcpListener::bind(server_addr).unwrap();
+        std::thread::spawn(move || {
+            for socket in listener.incoming().flatten() {
+                let acceptor = acceptor.clone();
+                std::thread::spawn(move || {
+                    if let Ok(mut stream) = acceptor.accept(socket) {
+                        let mut buf = [0; PING.len()];
+                        if stream.read_exact(&mut buf).is_ok() {
+                            let _ = stream.write_all(reply);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Returns a Connection to the given upstreams, over TLS with the test CA if `is_tls_conn`.
+    fn new_connection(server_addrs: &[String], is_tls_conn: bool) -> Connection {
+        Connection::new(
+            OriginServerConfig {
+                server_addrs: server_addrs.to_vec(),
+                is_tls_conn,
+                pool_size: 0,
+            },
+            Faulter::new(MemStore::new_db(), Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap()
+        .with_tls_config(&UpstreamTlsConfig {
+            ca_cert_path: Some("testdata/tls/ca.crt".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_upstream_health_monitor() {
+        let server_addr = next_test_ip4();
+        let is_up = Arc::new(AtomicBool::new(true));
+        run_mock_ping_server(server_addr, is_up.clone());
+        let server_addrs = vec![server_addr.to_string()];
+        let server_addr = &server_addrs[0];
+        let connection = new_connection(&server_addrs, false);
+
+        let monitor = UpstreamHealthMonitor::new(&server_addrs, Duration::from_millis(500), 2);
+        let mut successful_pings = HashMap::new();
+
+        monitor.check(&connection, &mut successful_pings).await;
+        assert!(monitor.is_healthy(server_addr));
+
+        is_up.store(false, Ordering::SeqCst);
+        monitor.check(&connection, &mut successful_pings).await;
+        assert!(!monitor.is_healthy(server_addr));
+
+        is_up.store(true, Ordering::SeqCst);
+        monitor.check(&connection, &mut successful_pings).await;
+        assert!(!monitor.is_healthy(server_addr));
+
+        // a failed ping resets the recovery
+        is_up.store(false, Ordering::SeqCst);
+        monitor.check(&connection, &mut successful_pings).await;
+        is_up.store(true, Ordering::SeqCst);
+        monitor.check(&connection, &mut successful_pings).await;
+        assert!(!monitor.is_healthy(server_addr));
+
+        monitor.check(&connection, &mut successful_pings).await;
+        assert!(monitor.is_healthy(server_addr));
+        assert!(monitor.is_healthy("127.0.0.1:1"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ping_tls_upstream() {
+        let pong_addr = next_test_ip4();
+        run_mock_tls_ping_server(pong_addr, PONG);
+        let noauth_addr = next_test_ip4();
+        run_mock_tls_ping_server(noauth_addr, b"-NOAUTH Authentication required.\r\n");
+        let server_addrs = vec![pong_addr.to_string(), noauth_addr.to_string()];
+        let timeout = Duration::from_millis(500);
+
+        // any RESP reply over TLS tells that the upstream is alive
+        let connection = new_connection(&server_addrs, true);
+        for server_addr in &server_addrs {
+            assert!(ping(&connection, server_addr, timeout).await.is_ok());
+        }
+
+        // the TLS upstream doesn't reply to a plain TCP PING
+        let connection = new_connection(&server_addrs, false);
+        assert!(ping(&connection, &server_addrs[0], timeout).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ping_unreachable_upstream() {
+        let server_addrs = vec![next_test_ip4().to_string()];
+        let connection = new_connection(&server_addrs, false);
+        assert!(
+            ping(&connection, &server_addrs[0], Duration::from_millis(500))
+                .await
+                .is_err()
+        );
+    }
+}
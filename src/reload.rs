@@ -0,0 +1,206 @@
+use crate::config::{self, Config};
+use crate::proxy::connection::Connection;
+use anyhow::anyhow;
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// LogLevelHandle changes the log level of the global subscriber at runtime.
+pub type LogLevelHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Reloader applies the config fields that can be changed without a restart, i.e. `log_level`
+/// and `upstream_pool_size`, from the re-read config.
+#[derive(Clone)]
+pub struct Reloader {
+    pub config: Arc<RwLock<Config>>,
+    pub log_level_handle: LogLevelHandle,
+    pub conn: Connection,
+}
+
+impl Reloader {
+    /// Re-reads the config from the config file specified at startup and applies its mutable
+    /// fields. The changes of the other fields are ignored with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error and keeps the current config when the config can't be read or is invalid,
+    /// see [Config::validate]. Every validation error is logged.
+    pub async fn reload(&self) -> Result<(), anyhow::Error> {
+        let mut config = self.config.write().await;
+        let new_config = config::read_config(config.config_file.as_deref())?;
+        if let Err(errors) = new_config.validate() {
+            for err in &errors {
+                error!("invalid reloaded config: {}", err);
+            }
+            return Err(anyhow!(
+                "the reloaded config has {} error(s), keeping the current config",
+                errors.len()
+            ));
+        }
+        let log_filter = EnvFilter::try_new(&new_config.log_level)?;
+
+        for field in config.changed_immutable_fields(&new_config) {
+            warn!("ignoring the change of {} as it requires a restart", field);
+        }
+
+        if config.log_level != new_config.log_level {
+            self.log_level_handle.reload(log_filter)?;
+            info!(
+                "log level changed from {} to {}",
+                config.log_level, new_config.log_level
+            );
+            config.log_level = new_config.log_level;
+        }
+
+        if config.upstream_pool_size != new_config.upstream_pool_size {
+            info!(
+                "upstream pool size changed from {} to {}",
+                config.upstream_pool_size, new_config.upstream_pool_size
+            );
+            let pool_size = new_config.upstream_pool_size;
+            config.upstream_pool_size = pool_size;
+            let conn = self.conn.clone();
+            tokio::spawn(async move { conn.resize_pool(pool_size).await });
+        }
+
+        Ok(())
+    }
+}
+
+/// Reloads the config on every `SIGHUP` signal.
+///
+/// The signal handler is registered before this function returns, such that no signal is missed.
+pub fn spawn_sighup_handler(reloader: Reloader) -> std::io::Result<JoinHandle<()>> {
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    Ok(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("received SIGHUP, reloading config");
+            if let Err(err) = reloader.reload().await {
+                error!("error reloading config: {:?}", err);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::proxy::connection::OriginServerConfig;
+    use crate::proxy::faulter::Faulter;
+    use crate::store::mem_store::MemStore;
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_on_sighup() {
+        let config_file =
+            std::env::temp_dir().join(format!("red-monkey-{}.env", uuid::Uuid::new_v4()));
+        let config_file = config_file.to_str().unwrap().to_string();
+        let write_config = |log_level: &str, proxy_port: u16| {
+            std::fs::write(
+                &config_file,
+                format!(
+                    "REDIS_ADDRESS=127.0.0.1:6379\nIS_REDIS_TLS_CONN=false\nLOG_LEVEL={}\nPROXY_PORT={}\n",
+                    log_level, proxy_port
+                ),
+            )
+            .unwrap();
+        };
+        write_config("info", 6350);
+        let config = config::read_config(Some(&config_file)).unwrap();
+
+        let (log_filter, log_level_handle) = reload::Layer::new(EnvFilter::new(&config.log_level));
+        let _subscriber = Registry::default().with(log_filter);
+
+        let conn = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec!["127.0.0.1:1".to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            Faulter::new(MemStore::new_db(), Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap();
+        let config = Arc::new(RwLock::new(config));
+        spawn_sighup_handler(Reloader {
+            config: config.clone(),
+            log_level_handle: log_level_handle.clone(),
+            conn,
+        })
+        .unwrap();
+
+        write_config("debug", 6351);
+        std::process::Command::new("kill")
+            .args(["-HUP", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(
+            log_level_handle
+                .with_current(|filter| filter.to_string())
+                .unwrap(),
+            "debug"
+        );
+        let config = config.read().await;
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.proxy_port, 6350);
+
+        std::fs::remove_file(&config_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_invalid_config() {
+        let config_file =
+            std::env::temp_dir().join(format!("red-monkey-{}.env", uuid::Uuid::new_v4()));
+        let config_file = config_file.to_str().unwrap().to_string();
+        std::fs::write(
+            &config_file,
+            "REDIS_ADDRESS=127.0.0.1:6379\nIS_REDIS_TLS_CONN=false\nLOG_LEVEL=info\n",
+        )
+        .unwrap();
+        let config = config::read_config(Some(&config_file)).unwrap();
+
+        let (log_filter, log_level_handle) = reload::Layer::new(EnvFilter::new(&config.log_level));
+        let _subscriber = Registry::default().with(log_filter);
+        let conn = Connection::new(
+            OriginServerConfig {
+                server_addrs: vec!["127.0.0.1:1".to_string()],
+                is_tls_conn: false,
+                pool_size: 0,
+            },
+            Faulter::new(MemStore::new_db(), Arc::new(Metrics::new().unwrap())),
+        )
+        .unwrap();
+        let reloader = Reloader {
+            config: Arc::new(RwLock::new(config)),
+            log_level_handle: log_level_handle.clone(),
+            conn,
+        };
+
+        // the log level is valid, but the origin server address is not
+        std::fs::write(
+            &config_file,
+            "REDIS_ADDRESS=localhost\nIS_REDIS_TLS_CONN=false\nLOG_LEVEL=debug\nUPSTREAM_POOL_SIZE=4\n",
+        )
+        .unwrap();
+        assert!(reloader.reload().await.is_err());
+
+        let config = reloader.config.read().await;
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.upstream_pool_size, 0);
+        assert_eq!(
+            log_level_handle
+                .with_current(|filter| filter.to_string())
+                .unwrap(),
+            "info"
+        );
+
+        std::fs::remove_file(&config_file).unwrap();
+    }
+}
@@ -1,14 +1,26 @@
-use chrono::{DateTime, Utc};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::sync::Arc;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
 use strum_macros::EnumString;
-use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
 
-pub type DB = Arc<RwLock<Box<dyn FaultStore + Send + Sync>>>;
+pub type DB = Arc<dyn FaultStore>;
+
+pub type PlaybookDB = Arc<dyn PlaybookStore + Send + Sync>;
+
+/// The command of a fault that applies to all the Redis commands, see [find_by_redis_cmd].
+pub const WILDCARD_COMMAND: &str = "*";
 
 /// Fault represents fault configurations that can be applied on an incoming request
-/// Two types of fault configurations are supported - `delay` and `error`
+/// The supported fault types are the variants of [FaultVariants], e.g. `delay`, `error`, `timeout`
+/// or `composite`.
 ///
 /// ## Example `delay` fault
 ///
@@ -36,7 +48,7 @@ pub type DB = Arc<RwLock<Box<dyn FaultStore + Send + Sync>>>;
 /// }
 /// ```
 ///
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Fault {
     /// name represents the fault name that acts as the primary key in the store
     pub name: String,
@@ -44,7 +56,7 @@ pub struct Fault {
     /// description provides the optional human-friendly description about the fault
     pub description: Option<String>,
 
-    /// fault_type accepts one of the `delay`, `error`, `drop` as the fault type value
+    /// fault_type accepts one of the [FaultVariants] as the fault type value
     pub fault_type: FaultVariants,
 
     /// In the event of `delay` fault, the duration of the delay in milliseconds will be set in
@@ -54,55 +66,1123 @@ pub struct Fault {
     /// In the event of `error` fault, the error string is set in this field
     pub error_msg: Option<String>,
 
-    /// command accepts any valid `redis` command
+    /// command accepts any valid `redis` command or `*` to apply the fault to all the commands.
+    /// See `priority` for choosing between the two.
     pub command: String,
 
     // last_modified holds the timestamp at which the fault is created or last modified
+    #[schema(read_only)]
     pub last_modified: Option<DateTime<Utc>>,
+
+    /// expires_at holds the optional timestamp after which the fault is no longer applied. Expired
+    /// faults are periodically removed from the store by the sweeper.
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// activate_at holds the optional timestamp before which the fault is not applied yet, e.g. to
+    /// schedule a fault for a planned load test
+    pub activate_at: Option<DateTime<Utc>>,
+
+    /// enabled tells whether the fault is applied on the matching requests. A disabled fault stays
+    /// in the store with its configuration intact. Defaults to `true`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// one_shot makes the fault disable itself when it is applied once. The fault is disabled
+    /// before it is executed, such that only one of the concurrent requests gets it. The fault
+    /// stays in the store so that it can be inspected and re-enabled.
+    #[serde(default)]
+    pub one_shot: bool,
+
+    /// max_hits optionally limits the number of times the fault is injected. The hit is reserved
+    /// before the fault is executed, such that the concurrent requests don't overshoot the limit.
+    /// Once the limit is reached, the fault is disabled.
+    pub max_hits: Option<u64>,
+
+    /// In the event of `delay` fault, a random value in `[0, jitter_ms]` milliseconds is added to
+    /// the `duration` of each delay
+    pub jitter_ms: Option<u64>,
+
+    /// In the event of `delay` fault, the milliseconds added to the `duration` on each hit of the
+    /// fault, i.e. the nth hit is delayed by `duration + (n - 1) * adaptive_delay_step_ms`
+    pub adaptive_delay_step_ms: Option<u64>,
+
+    /// max_adaptive_duration_ms optionally caps the duration of the delay that is increased by
+    /// `adaptive_delay_step_ms`
+    pub max_adaptive_duration_ms: Option<u64>,
+
+    /// In the event of `error` fault, the number of times the error is written to the client
+    /// before the connection is closed
+    pub burst_count: Option<u32>,
+
+    /// In the event of `error` fault, the milliseconds waited between the errors of a burst, see
+    /// `burst_count`
+    pub burst_interval_ms: Option<u64>,
+
+    /// In the event of `partial response` fault, the number of bytes of the origin server's
+    /// response that are forwarded to the client before the connection is closed
+    pub truncate_after_bytes: Option<u64>,
+
+    /// In the event of `slow drain` fault, the number of response bytes forwarded to the client
+    /// per millisecond
+    pub bytes_per_ms: Option<u64>,
+
+    /// key_pattern optionally scopes the fault to the Redis keys matching the glob pattern, e.g.
+    /// `user:*`. The key is the first argument of the Redis command.
+    pub key_pattern: Option<String>,
+
+    /// The glob pattern of the `key_pattern`, compiled once
+    #[serde(skip)]
+    pub(crate) key_glob: Compiled<Result<glob::Pattern, String>>,
+
+    /// tags are the optional labels of the fault, e.g. the experiment or the service it belongs
+    /// to. Faults can be listed and deleted by their tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// group optionally names the fault group the fault belongs to. All the faults of a group can
+    /// be enabled or disabled at once.
+    pub group: Option<String>,
+
+    /// client_ip_filter optionally scopes the fault to the clients connecting from the given IP
+    /// addresses. Clients connecting over a Unix domain socket have no IP address, hence they
+    /// never match the filter.
+    #[schema(value_type = Option<Vec<String>>)]
+    pub client_ip_filter: Option<Vec<IpAddr>>,
+
+    /// database optionally scopes the fault to the clients that selected the given logical Redis
+    /// database with `SELECT`. The clients are on the database `0` until they select another one.
+    pub database: Option<u8>,
+
+    /// proxy_port optionally scopes the fault to the clients of the proxy listening on the given
+    /// port, see `PROXY_PORTS`. Clients connecting over a Unix domain socket have no proxy port,
+    /// hence they never match it.
+    pub proxy_port: Option<u16>,
+
+    /// priority decides which fault is applied when several faults match a request, e.g. a
+    /// command fault and the wildcard fault. The fault with the lowest value wins. Defaults to
+    /// `100`.
+    #[serde(default = "default_priority")]
+    pub priority: u32,
+
+    /// probability optionally makes the fault apply to only a share of the matching requests, e.g.
+    /// `0.3` for 30% of them. It must be in `(0, 1]` and defaults to `1`. See
+    /// [FaultSelectionStrategy] for how it is combined with the other faults of the command.
+    pub probability: Option<f64>,
+
+    /// In the event of `composite` fault, the faults that are executed one after the other until
+    /// one of them ends the request, e.g. a `delay` followed by an `error`
+    pub sub_faults: Option<Vec<SubFault>>,
+
+    /// In the event of `mutate response` fault, the regular expression matched against the raw
+    /// bytes of the origin server's response
+    pub mutation_pattern: Option<String>,
+
+    /// In the event of `mutate response` fault, the replacement of the `mutation_pattern` matches.
+    /// It can refer to the capture groups, e.g. `$1`. Defaults to an empty string.
+    pub mutation_replacement: Option<String>,
+
+    /// arg_matchers optionally scopes the fault to the requests whose arguments match all the
+    /// matchers, e.g. the `SET` commands of a specific key
+    pub arg_matchers: Option<Vec<ArgMatcher>>,
+}
+
+/// ArgMatcher matches an argument of the Redis command
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ArgMatcher {
+    /// position of the argument in the command, `0` being the command itself and `1` the key
+    pub position: usize,
+
+    /// value is compared with the argument according to the `match_type`
+    pub value: String,
+
+    /// match_type defaults to `Exact`
+    #[serde(default)]
+    pub match_type: MatchType,
+
+    /// The regular expression of the `Regex` match type, compiled once
+    #[serde(skip)]
+    regex: Compiled<Result<regex::Regex, regex::Error>>,
+}
+
+/// Compiled caches a pattern compiled from a field, e.g. the regular expression of an ArgMatcher
+/// or the glob pattern of the `key_pattern` of a Fault, such that it is not compiled again on
+/// every matched request. The cache is shared by the clones of its owner, e.g. the faults fetched
+/// from the in-memory store once they are validated.
+#[derive(Debug, Clone)]
+pub(crate) struct Compiled<T>(OnceLock<T>);
+
+impl<T> Default for Compiled<T> {
+    fn default() -> Self {
+        Compiled(OnceLock::new())
+    }
+}
+
+impl<T> PartialEq for Compiled<T> {
+    /// The compiled pattern is derived from the field of its owner, hence it is ignored by the
+    /// comparison of the owners
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// MatchType tells how the value of an ArgMatcher is compared with the argument
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub enum MatchType {
+    #[default]
+    Exact,
+    Prefix,
+    Suffix,
+    Contains,
+    /// The value is a regular expression that must match somewhere in the argument
+    Regex,
+}
+
+impl ArgMatcher {
+    /// Creates the matcher of the argument at the given position.
+    pub fn new(position: usize, value: String, match_type: MatchType) -> Self {
+        ArgMatcher {
+            position,
+            value,
+            match_type,
+            regex: Compiled::default(),
+        }
+    }
+
+    /// Returns the value compiled as a regular expression, which is compiled on the first call
+    /// only.
+    pub fn regex(&self) -> &Result<regex::Regex, regex::Error> {
+        self.regex.0.get_or_init(|| regex::Regex::new(&self.value))
+    }
+
+    /// Returns true if the given argument matches. An invalid regular expression matches nothing.
+    pub fn matches(&self, arg: &str) -> bool {
+        match self.match_type {
+            MatchType::Exact => arg == self.value,
+            MatchType::Prefix => arg.starts_with(&self.value),
+            MatchType::Suffix => arg.ends_with(&self.value),
+            MatchType::Contains => arg.contains(&self.value),
+            MatchType::Regex => self.regex().as_ref().is_ok_and(|re| re.is_match(arg)),
+        }
+    }
+}
+
+/// SubFault is a step of the `composite` fault
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct SubFault {
+    /// fault_type accepts one of the `Delay`, `Error` and `DropConn` (or `drop_conn`)
+    /// [FaultVariants]
+    pub fault_type: FaultVariants,
+
+    /// In the event of `delay` sub-fault, the duration of the delay in milliseconds
+    pub duration: Option<u64>,
+
+    /// In the event of `error` sub-fault, the error string
+    pub error_msg: Option<String>,
+}
+
+pub(crate) fn default_enabled() -> bool {
+    true
+}
+
+pub(crate) fn default_priority() -> u32 {
+    100
+}
+
+impl Fault {
+    /// Returns the ETag of the fault, i.e. the hex encoded SHA-256 hash of its JSON serialisation.
+    /// The ETag changes whenever any field of the fault changes.
+    pub fn etag(&self) -> String {
+        // the serialisation of a fault can't fail, it has no map with non-string keys
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&json))
+    }
+
+    /// Returns true if the fault has an `expires_at` timestamp that is in the past.
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= Utc::now(),
+            None => false,
+        }
+    }
+
+    /// Returns true if the fault has no `activate_at` timestamp or it is not after the given time.
+    pub fn is_activated_at(&self, now: DateTime<Utc>) -> bool {
+        self.activate_at
+            .is_none_or(|activate_at| activate_at <= now)
+    }
+
+    /// Returns true if the fault is applied to the matching requests, i.e. it is enabled,
+    /// activated and not expired.
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.is_activated_at(Utc::now()) && !self.is_expired()
+    }
+
+    /// Returns true if the fault command is the given Redis command (case-insensitive) or the
+    /// wildcard (`*`) command.
+    pub fn matches_command(&self, redis_cmd: &str) -> bool {
+        self.command == WILDCARD_COMMAND || self.command.eq_ignore_ascii_case(redis_cmd)
+    }
+
+    /// Returns true if the fault has all the given tags.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// Returns true if the fault has no `client_ip_filter` or the IP address of the given client
+    /// address is in the filter.
+    pub fn matches_client(&self, client_addr: Option<SocketAddr>) -> bool {
+        let client_ip_filter = match &self.client_ip_filter {
+            Some(client_ip_filter) => client_ip_filter,
+            None => return true,
+        };
+
+        // IPv4 clients of an IPv6 listener have an IPv4-mapped IPv6 address
+        client_addr.is_some_and(|client_addr| {
+            let client_ip = client_addr.ip().to_canonical();
+            client_ip_filter
+                .iter()
+                .any(|ip| ip.to_canonical() == client_ip)
+        })
+    }
+
+    /// Returns true if the fault has no `database` or it is the given database.
+    pub fn matches_database(&self, database: u8) -> bool {
+        self.database
+            .is_none_or(|fault_database| fault_database == database)
+    }
+
+    /// Returns true if the fault has no `proxy_port` or it is the given port the client connected
+    /// to.
+    pub fn matches_proxy_port(&self, proxy_port: Option<u16>) -> bool {
+        self.proxy_port
+            .is_none_or(|fault_proxy_port| Some(fault_proxy_port) == proxy_port)
+    }
+
+    /// Returns true if the given Redis key matches the `key_pattern` of the fault. A fault without
+    /// `key_pattern` matches any key, including a command without a key.
+    pub fn matches_key(&self, key: Option<&str>) -> bool {
+        match (self.key_glob(), key) {
+            (None, _) => true,
+            (Some(Ok(pattern)), Some(key)) => pattern.matches(key),
+            _ => false,
+        }
+    }
+
+    /// Returns the `key_pattern` compiled as a glob pattern, which is compiled on the first call
+    /// only. An invalid pattern is rejected when the fault is stored, see `validate_fault`.
+    pub fn key_glob(&self) -> Option<&Result<glob::Pattern, String>> {
+        let key_pattern = self.key_pattern.as_ref()?;
+        Some(
+            self.key_glob
+                .0
+                .get_or_init(|| glob::Pattern::new(key_pattern).map_err(|err| err.to_string())),
+        )
+    }
+
+    /// Sets the `key_pattern` of the fault, dropping the glob pattern compiled from the previous
+    /// one.
+    pub fn set_key_pattern(&mut self, key_pattern: Option<String>) {
+        self.key_pattern = key_pattern;
+        self.key_glob = Compiled::default();
+    }
+
+    /// Returns true if the given arguments of the Redis command match all the `arg_matchers` of the
+    /// fault. An argument that is missing or not a string matches nothing.
+    pub fn matches_args(&self, args: &[Option<String>]) -> bool {
+        self.arg_matchers.iter().flatten().all(|arg_matcher| {
+            args.get(arg_matcher.position)
+                .and_then(Option::as_deref)
+                .is_some_and(|arg| arg_matcher.matches(arg))
+        })
+    }
 }
 
-/// FaultVariants represents the supported fault types
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString)]
+#[cfg(test)]
+impl Fault {
+    /// Returns an enabled fault of the name, fault type and command with every other field left
+    /// unset, which the tests override with the struct update syntax, e.g.
+    /// `Fault { duration: Some(10), ..Fault::fixture("set_delay", FaultVariants::Delay, "SET") }`
+    pub(crate) fn fixture(name: &str, fault_type: FaultVariants, command: &str) -> Fault {
+        Fault {
+            name: name.to_string(),
+            description: None,
+            fault_type,
+            duration: None,
+            error_msg: None,
+            command: command.to_string(),
+            last_modified: None,
+            expires_at: None,
+            activate_at: None,
+            enabled: default_enabled(),
+            one_shot: false,
+            max_hits: None,
+            jitter_ms: None,
+            adaptive_delay_step_ms: None,
+            max_adaptive_duration_ms: None,
+            burst_count: None,
+            burst_interval_ms: None,
+            truncate_after_bytes: None,
+            bytes_per_ms: None,
+            key_pattern: None,
+            key_glob: Compiled::default(),
+            tags: vec![],
+            group: None,
+            client_ip_filter: None,
+            database: None,
+            proxy_port: None,
+            priority: default_priority(),
+            probability: None,
+            sub_faults: None,
+            mutation_pattern: None,
+            mutation_replacement: None,
+            arg_matchers: None,
+        }
+    }
+}
+
+/// FieldDiff is a field of the fault that differs between two versions of the fault, with its
+/// old and new JSON values. The old value of an added field and the new value of a removed field
+/// are None.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+/// FaultDiff lists the fields that differ between two versions of a fault, see [diff_faults].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FaultDiff {
+    /// The fields that are only set in the new version
+    pub added: Vec<FieldDiff>,
+    /// The fields that are only set in the old version
+    pub removed: Vec<FieldDiff>,
+    /// The fields that are set in both versions with different values
+    pub changed: Vec<FieldDiff>,
+}
+
+/// Compares the old version of a fault with the new one, field by field. The faults are compared
+/// as JSON objects, hence the fields are listed by their serialised name, e.g. `fault_type`, and
+/// an unset optional field (`null`) is not set in either version.
+pub fn diff_faults(old: &Fault, new: &Fault) -> FaultDiff {
+    let fields = |fault: &Fault| match serde_json::to_value(fault) {
+        Ok(serde_json::Value::Object(fields)) => fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect(),
+        _ => serde_json::Map::new(),
+    };
+    let old_fields = fields(old);
+    let mut new_fields = fields(new);
+    let mut diff = FaultDiff::default();
+
+    for (field, old_value) in old_fields {
+        match new_fields.remove(&field) {
+            Some(new_value) if new_value == old_value => {}
+            Some(new_value) => diff.changed.push(FieldDiff {
+                field,
+                old: Some(old_value),
+                new: Some(new_value),
+            }),
+            None => diff.removed.push(FieldDiff {
+                field,
+                old: Some(old_value),
+                new: None,
+            }),
+        }
+    }
+    for (field, new_value) in new_fields {
+        diff.added.push(FieldDiff {
+            field,
+            old: None,
+            new: Some(new_value),
+        });
+    }
+
+    diff
+}
+
+/// FaultSearchQuery holds the optional filters of a fault search, see [FaultStore::search].
+///
+/// `q` is a free-text search that matches the faults where it appears in the `name`, the
+/// `description`, the `command` or one of the `tags`, ignoring the case. The other fields are
+/// structured filters, `command` and `fault_type` being matched case-insensitively, the latter
+/// accepting the `drop_conn` and `timeout` aliases of [FaultVariants] as well. A fault must match
+/// all the given filters.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FaultSearchQuery {
+    /// The text searched in the name, the description, the command and the tags of the faults
+    pub q: Option<String>,
+    /// Finds only the faults of the given Redis command
+    pub command: Option<String>,
+    /// Finds only the faults of the given fault type, e.g. `delay` or `drop_conn`
+    pub fault_type: Option<String>,
+    /// Finds only the enabled or the disabled faults
+    pub enabled: Option<bool>,
+}
+
+impl FaultSearchQuery {
+    /// Returns the relevance score of the fault, i.e. the number of the fields that match `q` and
+    /// of the structured filters, or None when the fault doesn't match the query.
+    pub fn score(&self, fault: &Fault) -> Option<usize> {
+        let mut score = 0;
+
+        if let Some(command) = &self.command {
+            if !command.eq_ignore_ascii_case(&fault.command) {
+                return None;
+            }
+            score += 1;
+        }
+        if let Some(fault_type) = &self.fault_type {
+            if fault_type.parse::<FaultVariants>().ok().as_ref() != Some(&fault.fault_type) {
+                return None;
+            }
+            score += 1;
+        }
+        if let Some(enabled) = self.enabled {
+            if enabled != fault.enabled {
+                return None;
+            }
+            score += 1;
+        }
+
+        if let Some(q) = &self.q {
+            let q = q.to_lowercase();
+            let contains_q = |text: &str| text.to_lowercase().contains(&q);
+            let text_matches = [
+                Some(&fault.name),
+                fault.description.as_ref(),
+                Some(&fault.command),
+            ]
+            .iter()
+            .flatten()
+            .copied()
+            .chain(&fault.tags)
+            .filter(|text| contains_q(text))
+            .count();
+            if text_matches == 0 {
+                return None;
+            }
+            score += text_matches;
+        }
+
+        Some(score)
+    }
+
+    /// Returns the faults that match the query, the most relevant first (see
+    /// [FaultSearchQuery::score]), and then by name.
+    pub fn rank(&self, faults: Vec<Fault>) -> Vec<Fault> {
+        let mut ranked_faults: Vec<(usize, Fault)> = faults
+            .into_iter()
+            .filter_map(|fault| self.score(&fault).map(|score| (score, fault)))
+            .collect();
+        ranked_faults.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+
+        ranked_faults.into_iter().map(|(_, fault)| fault).collect()
+    }
+}
+
+/// FaultSelectionStrategy tells how the fault applied to a request is chosen among the faults
+/// that match its command.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FaultSelectionStrategy {
+    /// At most one fault per command is stored. The fault with the lowest `priority` is chosen,
+    /// and applied with its `probability`.
+    #[default]
+    Priority,
+    /// Several faults per command can be stored, e.g. a delay for 30% of the `GET` requests and
+    /// an error for 10% of them. One of the faults with the lowest `priority` that apply to the
+    /// request, see [FaultRequest::matches], is sampled by its `probability`, see
+    /// [sample_by_probability].
+    Weighted,
+}
+
+impl FaultSelectionStrategy {
+    /// Returns [FaultSelectionStrategy::Weighted] when several faults per command are allowed.
+    pub fn new(allow_multiple_per_command: bool) -> Self {
+        if allow_multiple_per_command {
+            FaultSelectionStrategy::Weighted
+        } else {
+            FaultSelectionStrategy::Priority
+        }
+    }
+}
+
+/// FaultRequest is the Redis request that the faults are matched against, see
+/// [find_by_redis_cmd].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultRequest<'a> {
+    /// The Redis command, e.g. `GET`
+    pub command: &'a str,
+    /// The key of the command, i.e. its first argument, if any
+    pub key: Option<&'a str>,
+    /// The arguments of the command, `0` being the command itself, see [ArgMatcher]
+    pub args: &'a [Option<String>],
+    /// The address of the client, if known
+    pub client_addr: Option<SocketAddr>,
+    /// The database the client is on
+    pub database: u8,
+    /// The port of the proxy listener the client connected to, None for a Unix domain socket
+    /// listener
+    pub proxy_port: Option<u16>,
+}
+
+impl<'a> From<&'a str> for FaultRequest<'a> {
+    /// Creates the request of the given command on the database `0`, without key or arguments.
+    fn from(command: &'a str) -> Self {
+        FaultRequest {
+            command,
+            ..FaultRequest::default()
+        }
+    }
+}
+
+impl FaultRequest<'_> {
+    /// Returns true if the fault applies to the request, i.e. the fault is active, and its
+    /// command and all its scopes match the request: the key pattern, the arg matchers, the
+    /// client IP filter, the database and the proxy port.
+    pub fn matches(&self, fault: &Fault) -> bool {
+        fault.is_active()
+            && fault.matches_command(self.command)
+            && fault.matches_key(self.key)
+            && fault.matches_args(self.args)
+            && fault.matches_client(self.client_addr)
+            && fault.matches_database(self.database)
+            && fault.matches_proxy_port(self.proxy_port)
+    }
+}
+
+/// Finds the fault that matches the redis request among the given faults.
+///
+/// The candidates are the faults that apply to the request, see [FaultRequest::matches], i.e.
+/// the disabled, not yet activated and expired faults are skipped, as well as the faults whose
+/// scope doesn't match the request. A fault scoped to some keys, arguments or clients therefore
+/// never hides the faults of the other requests. Among the faults with the same command
+/// (case-insensitive) and the wildcard (`*`) faults, the ones with the lowest `priority` are the
+/// candidates.
+///
+/// - With [FaultSelectionStrategy::Priority], the fault with the same command is chosen over the
+///   wildcard fault, and then the candidate that is the most recently modified.
+/// - With [FaultSelectionStrategy::Weighted], all the candidates are kept.
+///
+/// The fault is then sampled among the candidates by their `probability`, see
+/// [select_weighted_fault], hence a single candidate is applied with its `probability`.
+pub fn find_by_redis_cmd(
+    faults: Vec<Fault>,
+    request: &FaultRequest<'_>,
+    strategy: FaultSelectionStrategy,
+) -> Option<Fault> {
+    let mut candidates: Vec<Fault> = faults
+        .into_iter()
+        .filter(|fault| request.matches(fault))
+        .collect();
+    let min_priority = candidates.iter().map(|fault| fault.priority).min()?;
+    candidates.retain(|fault| fault.priority == min_priority);
+    // the candidates are ordered, such that a sample picks the same fault in every store
+    candidates.sort_by_key(|fault| {
+        (
+            fault.command == WILDCARD_COMMAND,
+            Reverse(fault.last_modified),
+            fault.name.clone(),
+        )
+    });
+    if strategy == FaultSelectionStrategy::Priority {
+        candidates.truncate(1);
+    }
+
+    select_weighted_fault(candidates)
+}
+
+/// Selects one of the candidate faults at random, weighted by their `probability`, see
+/// [sample_by_probability]. No fault is selected for the rest of the probabilities when they add
+/// up to less than `1`.
+pub fn select_weighted_fault(candidates: Vec<Fault>) -> Option<Fault> {
+    sample_by_probability(candidates, rand::thread_rng().gen())
+}
+
+/// Samples one of the faults by their `probability`, a fault without one having a probability of
+/// `1`, with the given uniformly distributed `sample` in `[0, 1)`.
+///
+/// The `[0, 1)` range is split into consecutive intervals, one per fault and as wide as its
+/// probability, and the fault whose interval holds the sample is chosen. When the probabilities
+/// add up to less than `1`, no fault is chosen for the rest of the range, e.g. `0.3` and `0.1`
+/// faults leave 60% of the requests unfaulted. When they add up to more than `1`, the intervals
+/// are scaled down to fit the range, i.e. the faults are chosen proportionally to their
+/// probabilities.
+pub fn sample_by_probability(faults: Vec<Fault>, sample: f64) -> Option<Fault> {
+    let total: f64 = faults
+        .iter()
+        .map(|fault| fault.probability.unwrap_or(1.0))
+        .sum();
+    let mut sample = sample * total.max(1.0);
+
+    for fault in faults {
+        let probability = fault.probability.unwrap_or(1.0);
+        if sample < probability {
+            return Some(fault);
+        }
+        sample -= probability;
+    }
+
+    None
+}
+
+/// FaultVariants represents the supported fault types. They are accepted by their variant name,
+/// e.g. `Delay` or `PartialResponse`, and the drop connection and timeout faults also as
+/// `drop_conn` and `timeout`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, ToSchema)]
+#[strum(ascii_case_insensitive)]
 pub enum FaultVariants {
     Delay,
     Error,
+    #[serde(alias = "drop_conn")]
+    #[strum(serialize = "DropConn", serialize = "drop_conn")]
     DropConn,
+    /// Holds the client connection open without ever sending a response
+    #[serde(alias = "timeout")]
+    #[strum(serialize = "Timeout", serialize = "timeout")]
+    Timeout,
+    /// Forwards only the first `truncate_after_bytes` of the response and closes the connection
+    PartialResponse,
+    /// Forwards the response to the client at a rate of `bytes_per_ms`
+    SlowDrain,
+    /// Executes the `sub_faults` in order, e.g. a delay followed by an error
+    Composite,
+    /// Replaces the `mutation_pattern` matches in the response with the `mutation_replacement`
+    MutateResponse,
+    /// Resets the client connection with a TCP RST instead of closing it gracefully
+    TcpReset,
+}
+
+/// FaultPlaybook is an ordered sequence of faults that rotate per request, e.g. the first three
+/// requests succeed and the next two fail.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct FaultPlaybook {
+    /// name is the unique identifier of the playbook
+    pub name: String,
+
+    /// steps are applied in order, each to the given number of requests
+    pub steps: Vec<PlaybookStep>,
+
+    /// loop_playbook restarts the playbook from the first step after the last step. A playbook
+    /// that doesn't loop is done after the last step.
+    #[serde(default)]
+    pub loop_playbook: bool,
+}
+
+/// PlaybookStep is a step of the FaultPlaybook
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct PlaybookStep {
+    /// fault_name is the name of the fault applied during the step. The requests of a step
+    /// without a fault are not faulted.
+    pub fault_name: Option<String>,
+
+    /// repeat_count is the number of requests the step lasts
+    pub repeat_count: u32,
+}
+
+impl FaultPlaybook {
+    /// Returns the step of the playbook for the request at the given zero-based index, or None
+    /// once a playbook that doesn't loop is done.
+    pub fn step_at(&self, request_index: u64) -> Option<&PlaybookStep> {
+        let request_count: u64 = self.steps.iter().map(|step| step.repeat_count as u64).sum();
+        if request_count == 0 || (!self.loop_playbook && request_index >= request_count) {
+            return None;
+        }
+
+        let mut request_index = request_index % request_count;
+        for step in &self.steps {
+            if request_index < step.repeat_count as u64 {
+                return Some(step);
+            }
+            request_index -= step.repeat_count as u64;
+        }
+
+        None
+    }
+}
+
+/// A trait providing methods for pluggable playbook store
+pub trait PlaybookStore {
+    /// Stores the playbook in the store, keyed by its name, and starts it from the first step
+    fn store_playbook(&self, playbook: &FaultPlaybook) -> Result<bool, StoreError>;
+
+    /// Fetch all the playbooks from the store
+    fn get_all_playbooks(&self) -> Result<Vec<FaultPlaybook>, StoreError>;
+
+    /// Delete the playbook by the given playbook name in the store
+    fn delete_playbook(&self, playbook_name: &str) -> Result<bool, StoreError>;
+
+    /// Advances the first active playbook (ordered by name) by one request and returns the step
+    /// that applies to the request. Returns None when no playbook is active.
+    fn next_step(&self) -> Option<PlaybookStep>;
 }
 
 /// A trait providing methods for pluggable data store
-pub trait FaultStore: FaultStoreClone {
+#[async_trait]
+pub trait FaultStore: Send + Sync {
     /// Stores the fault in the store
-    fn store(&self, key: &str, fault: &Fault) -> Result<bool, StoreError>;
+    async fn store(&self, key: &str, fault: &Fault) -> Result<bool, StoreError>;
+
+    /// Stores the given faults in the store, each keyed by its name
+    async fn store_batch(&self, faults: &[Fault]) -> Result<bool, StoreError> {
+        for fault in faults {
+            self.store(&fault.name, fault).await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Stores the given faults in the store all together or not at all. The batch is checked with
+    /// [validate_batch] before any fault is stored. When a fault fails to be stored, the faults of
+    /// the batch that are already stored are rolled back to their previous version, or deleted
+    /// when they are new.
+    async fn store_batch_atomic(&self, faults: &[Fault]) -> Result<(), BatchStoreError> {
+        validate_batch(faults)?;
+
+        let mut previous_faults = Vec::with_capacity(faults.len());
+        for fault in faults {
+            previous_faults.push(self.get_by_fault_name(&fault.name).await.ok());
+        }
+
+        for (index, fault) in faults.iter().enumerate() {
+            let err = match self.store(&fault.name, fault).await {
+                Ok(_) => continue,
+                Err(err) => err,
+            };
+
+            let mut errors = vec![BatchFaultError::new(&fault.name, &err.message)];
+            for (stored_fault, previous_fault) in faults[..index].iter().zip(&previous_faults) {
+                let rollback = match previous_fault {
+                    Some(previous_fault) => self.store(&stored_fault.name, previous_fault).await,
+                    None => self.delete_fault(&stored_fault.name).await,
+                };
+                if let Err(err) = rollback {
+                    errors.push(BatchFaultError::new(
+                        &stored_fault.name,
+                        &format!("rollback failed: {}", err.message),
+                    ));
+                }
+            }
+
+            return Err(BatchStoreError::StoreError(errors));
+        }
+
+        Ok(())
+    }
 
     /// Fetch the fault by the given fault name from the store
-    fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError>;
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError>;
 
     /// Fetch all the faults from the store
-    fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError>;
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError>;
+
+    /// Search the faults of the store that match the given query, the most relevant first, see
+    /// [FaultSearchQuery]
+    async fn search(&self, query: &FaultSearchQuery) -> Result<Vec<Fault>, StoreError> {
+        Ok(query.rank(self.get_all_faults().await?))
+    }
+
+    /// Fetch all the faults that belong to the given fault group from the store
+    async fn get_faults_by_group(&self, group: &str) -> Result<Vec<Fault>, StoreError> {
+        Ok(self
+            .get_all_faults()
+            .await?
+            .into_iter()
+            .filter(|fault| fault.group.as_deref() == Some(group))
+            .collect())
+    }
 
-    /// Fetch the fault that matches the redis command
-    fn get_by_redis_cmd(&self, redis_cmd: &str) -> Option<Fault>;
+    /// Fetch the ETag of the fault by the given fault name, see [Fault::etag]. Returns None when
+    /// the fault is not found.
+    async fn get_fault_etag(&self, fault_name: &str) -> Option<String> {
+        self.get_by_fault_name(fault_name)
+            .await
+            .ok()
+            .map(|fault| fault.etag())
+    }
+
+    /// Fetch the fault that matches the redis command sent to the given database through the
+    /// given proxy port, chosen with the given strategy, see [find_by_redis_cmd]
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault>;
 
     /// Delete the fault by the given fault name in the store
-    fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError>;
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError>;
+
+    /// Delete all the faults of the store and return the number of deleted faults
+    async fn clear_all(&self) -> Result<usize, StoreError> {
+        let mut deleted = 0;
+        for fault in self.get_all_faults().await? {
+            if self.delete_fault(&fault.name).await? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Disable the fault by the given fault name in the store, keeping its configuration
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError>;
+
+    /// Disable the fault by the given fault name only when it is enabled, as a single atomic
+    /// compare-and-set. Returns true when this caller disabled the fault, i.e. it won the claim of
+    /// a one-shot fault among the concurrent requests, and false when the fault is already
+    /// disabled or not found.
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError>;
+
+    /// Increment the hit counter of the given fault and return the updated count
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError>;
+
+    /// Reserve a hit of the fault that is limited to `max_hits`, before the fault is executed: the
+    /// hit counter is incremented only while it is below `max_hits`, as a single atomic operation,
+    /// such that the concurrent requests can't overshoot the limit. Returns the updated hit count,
+    /// or None when the fault already reached its `max_hits`. The delay is recorded like in
+    /// [FaultStore::record_hit].
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError>;
+
+    /// Fetch the number of times the given fault has been injected
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError>;
+
+    /// Record a hit of the given fault with the delay it injected in milliseconds, if any, and
+    /// return the updated hit count. The stores that don't keep the [FaultStats] only increment
+    /// the hit counter.
+    async fn record_hit(
+        &self,
+        fault_name: &str,
+        _delay_ms: Option<u64>,
+    ) -> Result<u64, StoreError> {
+        self.record_fault_hit(fault_name).await
+    }
+
+    /// Fetch the injection statistics of the given fault. The stores that don't keep the
+    /// [FaultStats] only return the hit count.
+    async fn get_fault_stats(&self, fault_name: &str) -> Result<FaultStats, StoreError> {
+        Ok(FaultStats {
+            hit_count: self.get_fault_hit_count(fault_name).await?,
+            ..FaultStats::default()
+        })
+    }
+
+    /// Fetch the injection statistics of all the faults by fault name
+    async fn get_all_fault_stats(&self) -> Result<HashMap<String, FaultStats>, StoreError> {
+        let mut all_stats = HashMap::new();
+        for fault in self.get_all_faults().await? {
+            let stats = self.get_fault_stats(&fault.name).await?;
+            all_stats.insert(fault.name, stats);
+        }
+
+        Ok(all_stats)
+    }
+
+    /// Fetch the previous versions of the given fault, the most recent first. The version of a
+    /// fault is its index in the history, i.e. `0` is the version before the latest change.
+    async fn get_fault_history(&self, fault_name: &str) -> Result<Vec<Fault>, StoreError> {
+        Err(StoreError::new(
+            format!(
+                "The history of fault {} is not kept by the fault store",
+                fault_name
+            )
+            .as_str(),
+        ))
+    }
+
+    /// Restores the given version of the fault (see [FaultStore::get_fault_history]). The
+    /// replaced configuration becomes the latest version of the history, such that the rollback
+    /// can be undone as well.
+    async fn rollback_fault(&self, fault_name: &str, version: usize) -> Result<(), StoreError> {
+        let fault = self
+            .get_fault_history(fault_name)
+            .await?
+            .into_iter()
+            .nth(version)
+            .ok_or_else(|| {
+                StoreError::new(
+                    format!("Version {} of fault {} not found", version, fault_name).as_str(),
+                )
+            })?;
+        self.store(fault_name, &fault).await?;
+
+        Ok(())
+    }
+}
+
+/// Checks the faults of a batch before any of them is stored: every fault must have a unique
+/// non-empty name within the batch and the fields its fault type is executed with, e.g. the
+/// `duration` of a `delay` fault, must be set.
+///
+/// # Errors
+///
+/// Returns [BatchStoreError::InvalidBatchError] listing the error of every invalid fault.
+pub fn validate_batch(faults: &[Fault]) -> Result<(), BatchStoreError> {
+    let mut errors = Vec::new();
+
+    for (index, fault) in faults.iter().enumerate() {
+        if fault.name.trim().is_empty() {
+            errors.push(BatchFaultError::new(&fault.name, "the name is empty"));
+        } else if faults[..index].iter().any(|f| f.name == fault.name) {
+            errors.push(BatchFaultError::new(
+                &fault.name,
+                "the name is repeated in the batch",
+            ));
+        }
+
+        let is_type_field_set = match fault.fault_type {
+            FaultVariants::Delay => fault.duration.is_some(),
+            FaultVariants::PartialResponse => fault.truncate_after_bytes.is_some(),
+            FaultVariants::SlowDrain => fault.bytes_per_ms.is_some(),
+            FaultVariants::MutateResponse => fault.mutation_pattern.is_some(),
+            FaultVariants::Composite => fault
+                .sub_faults
+                .as_ref()
+                .is_some_and(|sub_faults| !sub_faults.is_empty()),
+            _ => true,
+        };
+        if !is_type_field_set {
+            errors.push(BatchFaultError::new(
+                &fault.name,
+                &format!(
+                    "the {:?} fault lacks the field it is executed with",
+                    fault.fault_type
+                ),
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(BatchStoreError::InvalidBatchError(errors));
+    }
+
+    Ok(())
 }
 
-pub trait FaultStoreClone {
-    fn clone_box(&self) -> Box<dyn FaultStore>;
+/// BatchFaultError is the error of a single fault of a batch, see [BatchStoreError].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchFaultError {
+    pub name: String,
+    pub message: String,
 }
 
-impl<T> FaultStoreClone for T
-where
-    T: FaultStore + 'static + Clone + Send + Sync,
-{
-    fn clone_box(&self) -> Box<dyn FaultStore> {
-        Box::new(self.clone())
+impl BatchFaultError {
+    pub fn new(name: &str, message: &str) -> Self {
+        BatchFaultError {
+            name: name.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// BatchStoreError is the error of [FaultStore::store_batch_atomic], listing the errors of the
+/// faults that failed the batch.
+#[derive(Debug)]
+pub enum BatchStoreError {
+    /// The batch is rejected by [validate_batch], no fault is stored
+    InvalidBatchError(Vec<BatchFaultError>),
+    /// A fault failed to be stored and the batch is rolled back. The errors of the rollback, if
+    /// any, are listed after the error of the fault.
+    StoreError(Vec<BatchFaultError>),
+}
+
+impl BatchStoreError {
+    /// Returns the errors of the faults that failed the batch.
+    pub fn errors(&self) -> &[BatchFaultError] {
+        match self {
+            BatchStoreError::InvalidBatchError(errors) | BatchStoreError::StoreError(errors) => {
+                errors
+            }
+        }
+    }
+}
+
+impl fmt::Display for BatchStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchStoreError::InvalidBatchError(_) => write!(f, "Invalid batch of faults")?,
+            BatchStoreError::StoreError(_) => write!(f, "Error storing the batch of faults")?,
+        }
+
+        for error in self.errors() {
+            write!(f, "; fault {}: {}", error.name, error.message)?;
+        }
+
+        Ok(())
     }
 }
 
-impl Clone for Box<dyn FaultStore> {
-    fn clone(&self) -> Box<dyn FaultStore> {
-        self.clone_box()
+/// FaultStats holds the injection statistics of a fault, see [FaultStore::get_fault_stats].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FaultStats {
+    /// The number of times the fault has been injected
+    pub hit_count: u64,
+    pub first_hit_at: Option<DateTime<Utc>>,
+    pub last_hit_at: Option<DateTime<Utc>>,
+    /// The sum of the delays injected by the fault in milliseconds
+    pub total_delay_ms: u64,
+    /// The number of hits of each minute of the last [RECENT_HITS_WINDOW_HOURS] hours, the oldest
+    /// minute first, see [FaultStats::hits_since]
+    #[serde(skip)]
+    pub hits_per_minute: VecDeque<MinuteHits>,
+}
+
+/// MinuteHits is the number of hits of a fault in the minute that starts at `minute`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinuteHits {
+    pub minute: DateTime<Utc>,
+    pub hits: u64,
+}
+
+/// The number of hours the hits of a fault are counted per minute for, see
+/// [FaultStats::hits_since].
+pub const RECENT_HITS_WINDOW_HOURS: i64 = 24;
+
+impl FaultStats {
+    /// Accounts for a hit of the fault at the given time, with the delay it injected, if any.
+    pub fn record_hit(&mut self, hit_count: u64, hit_at: DateTime<Utc>, delay_ms: Option<u64>) {
+        self.hit_count = hit_count;
+        self.first_hit_at.get_or_insert(hit_at);
+        self.last_hit_at = Some(hit_at);
+        self.total_delay_ms = self
+            .total_delay_ms
+            .saturating_add(delay_ms.unwrap_or_default());
+
+        // the minute of the hit is never out of range, hence the hit time is kept on failure
+        let minute = hit_at
+            .duration_trunc(Duration::minutes(1))
+            .unwrap_or(hit_at);
+        match self.hits_per_minute.back_mut() {
+            Some(last) if last.minute == minute => last.hits += 1,
+            _ => self
+                .hits_per_minute
+                .push_back(MinuteHits { minute, hits: 1 }),
+        }
+        let window_start = hit_at - Duration::hours(RECENT_HITS_WINDOW_HOURS);
+        while self
+            .hits_per_minute
+            .front()
+            .is_some_and(|oldest| oldest.minute <= window_start)
+        {
+            self.hits_per_minute.pop_front();
+        }
+    }
+
+    /// Returns the number of hits since the given time, to the minute, within the last
+    /// [RECENT_HITS_WINDOW_HOURS] hours. The hits of the minute of the given time are counted.
+    pub fn hits_since(&self, since: DateTime<Utc>) -> u64 {
+        self.hits_per_minute
+            .iter()
+            .filter(|minute_hits| minute_hits.minute + Duration::minutes(1) > since)
+            .map(|minute_hits| minute_hits.hits)
+            .sum()
     }
 }
 
@@ -125,3 +1205,163 @@ impl fmt::Display for StoreError {
         write!(f, "{}", self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_key() {
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        assert!(fault.matches_key(None));
+        assert!(fault.matches_key(Some("user:1")));
+
+        fault.set_key_pattern(Some("user:*".to_string()));
+        assert!(fault.matches_key(Some("user:1")));
+        assert!(!fault.matches_key(Some("order:1")));
+        assert!(!fault.matches_key(None));
+
+        // the compiled glob is kept by the clones and dropped when the key pattern is set again
+        let mut cloned_fault = fault.clone();
+        assert!(cloned_fault.key_glob.0.get().is_some());
+        cloned_fault.set_key_pattern(Some("order:*".to_string()));
+        assert!(cloned_fault.matches_key(Some("order:1")));
+        assert!(!cloned_fault.matches_key(Some("user:1")));
+
+        fault.set_key_pattern(Some("user:[".to_string()));
+        assert!(fault.key_glob().unwrap().is_err());
+        assert!(!fault.matches_key(Some("user:[")));
+    }
+
+    #[test]
+    fn test_select_weighted_fault() {
+        let faults: Vec<Fault> = [("delay", 0.5), ("error", 0.3), ("drop", 0.2)]
+            .iter()
+            .map(|&(name, probability)| {
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault.name = name.to_string();
+                fault.probability = Some(probability);
+                fault
+            })
+            .collect();
+
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..10000 {
+            let fault = select_weighted_fault(faults.clone()).unwrap();
+            *counts.entry(fault.name).or_insert(0) += 1;
+        }
+
+        // each fault is selected by its weight, give or take 5%
+        for fault in &faults {
+            let frequency = counts[&fault.name] as f64 / 10000.0;
+            assert!(
+                (frequency - fault.probability.unwrap()).abs() < 0.05,
+                "{}: {}",
+                fault.name,
+                frequency
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_by_redis_cmd_wildcard_tie_break() {
+        let now = Utc::now();
+        let mut get_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        get_fault.name = "get".to_string();
+        get_fault.command = "GET".to_string();
+        get_fault.last_modified = Some(now - Duration::minutes(1));
+
+        let mut wildcard_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        wildcard_fault.name = "wildcard".to_string();
+        wildcard_fault.command = WILDCARD_COMMAND.to_string();
+        wildcard_fault.last_modified = Some(now);
+
+        // the fault of the command wins over a newer wildcard fault of the same priority
+        let faults = vec![wildcard_fault.clone(), get_fault.clone()];
+        assert_eq!(
+            find_by_redis_cmd(
+                faults.clone(),
+                &FaultRequest::from("GET"),
+                FaultSelectionStrategy::Priority
+            ),
+            Some(get_fault)
+        );
+        assert_eq!(
+            find_by_redis_cmd(
+                faults,
+                &FaultRequest::from("SET"),
+                FaultSelectionStrategy::Priority
+            ),
+            Some(wildcard_fault)
+        );
+    }
+
+    #[test]
+    fn test_sample_by_probability() {
+        let mut delay_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        delay_fault.name = "delay".to_string();
+        delay_fault.probability = Some(0.3);
+        let mut error_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        error_fault.name = "error".to_string();
+        error_fault.probability = Some(0.1);
+        let faults = vec![delay_fault.clone(), error_fault.clone()];
+
+        for (sample, expected_fault) in [
+            (0.0, Some(&delay_fault)),
+            (0.29, Some(&delay_fault)),
+            (0.3, Some(&error_fault)),
+            (0.39, Some(&error_fault)),
+            (0.4, None),
+            (0.99, None),
+        ] {
+            assert_eq!(
+                sample_by_probability(faults.clone(), sample).as_ref(),
+                expected_fault,
+                "sample {}",
+                sample
+            );
+        }
+
+        // the probabilities that add up to more than 1 are scaled down, a fault without a
+        // probability being always applied on its own
+        error_fault.probability = None;
+        let faults = vec![delay_fault.clone(), error_fault.clone()];
+        assert_eq!(
+            sample_by_probability(faults.clone(), 0.2),
+            Some(delay_fault)
+        );
+        assert_eq!(
+            sample_by_probability(faults, 0.3),
+            Some(error_fault.clone())
+        );
+        assert_eq!(
+            sample_by_probability(vec![error_fault.clone()], 0.99),
+            Some(error_fault)
+        );
+        assert_eq!(sample_by_probability(vec![], 0.0), None);
+    }
+}
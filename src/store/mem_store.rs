@@ -1,24 +1,63 @@
-use crate::store::fault_store::{Fault, FaultStore, StoreError, DB};
+use crate::store::fault_store::{
+    find_by_redis_cmd, validate_batch, BatchStoreError, Fault, FaultPlaybook, FaultRequest,
+    FaultSelectionStrategy, FaultStats, FaultStore, PlaybookDB, PlaybookStep, PlaybookStore,
+    StoreError, DB,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use dashmap::DashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing::{debug, error};
 
+/// The number of previous versions kept per fault when `max_history_versions` is not set
+pub const DEFAULT_MAX_HISTORY_VERSIONS: usize = 10;
+
 /// MemStore is an in-memory store implementation of FaultStore
 #[derive(Debug, Clone)]
 pub struct MemStore {
-    store: chashmap::CHashMap<String, Fault>,
+    store: DashMap<String, Fault>,
+    hit_counts: DashMap<String, Arc<AtomicU64>>,
+    stats: DashMap<String, FaultStats>,
+    /// The previous versions of the faults, the most recent first
+    history: DashMap<String, VecDeque<Fault>>,
+    max_history_versions: usize,
 }
 
 impl MemStore {
     pub fn new_db() -> DB {
-        Arc::new(RwLock::new(Box::new(MemStore {
-            store: chashmap::CHashMap::new(),
-        })))
+        MemStore::new_db_with_history(DEFAULT_MAX_HISTORY_VERSIONS)
+    }
+
+    /// Creates the store keeping up to the given number of previous versions per fault, see
+    /// [FaultStore::get_fault_history].
+    pub fn new_db_with_history(max_history_versions: usize) -> DB {
+        Arc::new(MemStore {
+            store: DashMap::new(),
+            hit_counts: DashMap::new(),
+            stats: DashMap::new(),
+            history: DashMap::new(),
+            max_history_versions,
+        })
+    }
+
+    /// Pushes the replaced version of the fault to its history, dropping the oldest version
+    /// beyond `max_history_versions`.
+    fn push_history(&self, fault_name: &str, fault: Fault) {
+        if self.max_history_versions == 0 {
+            return;
+        }
+
+        let mut versions = self.history.entry(fault_name.to_string()).or_default();
+        versions.push_front(fault);
+        versions.truncate(self.max_history_versions);
     }
 }
 
+#[async_trait]
 impl FaultStore for MemStore {
-    fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
+    async fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
         match self.store.insert(fault_name.to_string(), fault.clone()) {
             None => {
                 debug!("Fault {} stored in memory", fault.name);
@@ -26,22 +65,49 @@ impl FaultStore for MemStore {
             }
             Some(val) => {
                 debug!("Fault {} is replaced by the latest config", val.name);
+                self.push_history(fault_name, val);
                 Ok(true)
             }
         }
     }
 
-    fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+    async fn store_batch_atomic(&self, faults: &[Fault]) -> Result<(), BatchStoreError> {
+        validate_batch(faults)?;
+
+        // inserting in memory can't fail once the batch is valid, so there is nothing to roll back
+        for fault in faults {
+            if let Some(val) = self.store.insert(fault.name.clone(), fault.clone()) {
+                self.push_history(&fault.name, val);
+            }
+        }
+        debug!("Batch of {} faults stored in memory", faults.len());
+
+        Ok(())
+    }
+
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
         match self.store.get(fault_name) {
-            Some(val) => Ok(val.clone()),
-            None => Err(StoreError::new(
+            Some(val) if !val.is_expired() => Ok(val.clone()),
+            _ => Err(StoreError::new(
                 format!("Fault {} not found", fault_name).as_str(),
             )),
         }
     }
 
-    fn get_by_redis_cmd(&self, redis_cmd: &str) -> Option<Fault> {
-        let faults = match self.get_all_faults() {
+    async fn get_fault_etag(&self, fault_name: &str) -> Option<String> {
+        // the fault is hashed in place rather than cloned
+        self.store
+            .get(fault_name)
+            .filter(|fault| !fault.is_expired())
+            .map(|fault| fault.etag())
+    }
+
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault> {
+        let faults = match self.get_all_faults().await {
             Ok(faults) => faults,
             Err(e) => {
                 error!("error fetching all faults: {:?}", e);
@@ -49,233 +115,1092 @@ impl FaultStore for MemStore {
             }
         };
 
-        let mut is_all_faulter: Option<Fault> = None;
-        let mut matched_fault: Option<Fault> = None;
+        find_by_redis_cmd(faults, request, strategy)
+    }
 
-        for fault in faults {
-            if fault.command == "*" {
-                is_all_faulter = Some(fault.clone());
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        Ok(self
+            .store
+            .iter()
+            .map(|fault| fault.value().clone())
+            .collect())
+    }
+
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        self.hit_counts.remove(fault_name);
+        self.stats.remove(fault_name);
+        self.history.remove(fault_name);
+
+        match self.store.remove(fault_name) {
+            None => Ok(false),
+            Some((_, fault)) => {
+                debug!("Delete fault {}", fault.name);
+                Ok(true)
             }
+        }
+    }
 
-            if redis_cmd.to_lowercase() == fault.command.to_lowercase() {
-                matched_fault = Some(fault);
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        match self.store.get_mut(fault_name) {
+            None => Ok(false),
+            Some(mut fault) => {
+                fault.enabled = false;
+                debug!("Disabled fault {}", fault.name);
+                Ok(true)
             }
         }
+    }
 
-        if matched_fault != None {
-            return matched_fault;
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        // the entry is locked while it is checked and disabled
+        match self.store.get_mut(fault_name) {
+            Some(mut fault) if fault.enabled => {
+                fault.enabled = false;
+                debug!("Claimed fault {}", fault.name);
+                Ok(true)
+            }
+            _ => Ok(false),
         }
+    }
 
-        is_all_faulter
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+        self.record_hit(fault_name, None).await
     }
 
-    fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
-        let mut faults = Vec::new();
-        for (_, value) in self.store.clone() {
-            faults.push(value);
+    async fn record_hit(&self, fault_name: &str, delay_ms: Option<u64>) -> Result<u64, StoreError> {
+        if !self.store.contains_key(fault_name) {
+            return Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            ));
         }
 
-        Ok(faults)
+        let hit_count = self
+            .hit_counts
+            .entry(fault_name.to_string())
+            .or_insert_with(|| Arc::new(AtomicU64::new(0)))
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+
+        self.stats
+            .entry(fault_name.to_string())
+            .or_default()
+            .record_hit(hit_count, Utc::now(), delay_ms);
+
+        Ok(hit_count)
     }
 
-    fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
-        match self.store.remove(fault_name) {
-            None => Ok(false),
-            Some(fault) => {
-                debug!("Delete fault {}", fault.name);
-                Ok(true)
-            }
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        if !self.store.contains_key(fault_name) {
+            return Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            ));
+        }
+
+        let reserved = self
+            .hit_counts
+            .entry(fault_name.to_string())
+            .or_insert_with(|| Arc::new(AtomicU64::new(0)))
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |hit_count| {
+                (hit_count < max_hits).then(|| hit_count + 1)
+            });
+        let hit_count = match reserved {
+            Ok(previous_hit_count) => previous_hit_count + 1,
+            Err(_) => return Ok(None),
+        };
+
+        self.stats
+            .entry(fault_name.to_string())
+            .or_default()
+            .record_hit(hit_count, Utc::now(), delay_ms);
+
+        Ok(Some(hit_count))
+    }
+
+    async fn get_fault_stats(&self, fault_name: &str) -> Result<FaultStats, StoreError> {
+        let hit_count = self.get_fault_hit_count(fault_name).await?;
+
+        let mut stats = self
+            .stats
+            .get(fault_name)
+            .map(|stats| stats.clone())
+            .unwrap_or_default();
+        stats.hit_count = hit_count;
+
+        Ok(stats)
+    }
+
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        if !self.store.contains_key(fault_name) {
+            return Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            ));
+        }
+
+        match self.hit_counts.get(fault_name) {
+            Some(counter) => Ok(counter.load(Ordering::SeqCst)),
+            None => Ok(0),
+        }
+    }
+
+    async fn get_fault_history(&self, fault_name: &str) -> Result<Vec<Fault>, StoreError> {
+        if !self.store.contains_key(fault_name) {
+            return Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            ));
+        }
+
+        match self.history.get(fault_name) {
+            Some(versions) => Ok(versions.iter().cloned().collect()),
+            None => Ok(Vec::new()),
         }
     }
 }
 
+/// MemPlaybookStore is an in-memory store implementation of PlaybookStore. Each playbook is held
+/// together with the number of requests it has been applied to.
+#[derive(Debug, Default)]
+pub struct MemPlaybookStore {
+    playbooks: DashMap<String, (FaultPlaybook, Arc<AtomicU64>)>,
+}
+
+impl MemPlaybookStore {
+    pub fn new_db() -> PlaybookDB {
+        Arc::new(MemPlaybookStore::default())
+    }
+}
+
+impl PlaybookStore for MemPlaybookStore {
+    fn store_playbook(&self, playbook: &FaultPlaybook) -> Result<bool, StoreError> {
+        self.playbooks.insert(
+            playbook.name.clone(),
+            (playbook.clone(), Arc::new(AtomicU64::new(0))),
+        );
+        debug!("Playbook {} stored in memory", playbook.name);
+        Ok(true)
+    }
+
+    fn get_all_playbooks(&self) -> Result<Vec<FaultPlaybook>, StoreError> {
+        Ok(self
+            .playbooks
+            .iter()
+            .map(|playbook| playbook.value().0.clone())
+            .collect())
+    }
+
+    fn delete_playbook(&self, playbook_name: &str) -> Result<bool, StoreError> {
+        Ok(self.playbooks.remove(playbook_name).is_some())
+    }
+
+    fn next_step(&self) -> Option<PlaybookStep> {
+        let mut playbooks: Vec<_> = self
+            .playbooks
+            .iter()
+            .map(|playbook| (playbook.key().clone(), playbook.value().clone()))
+            .collect();
+        playbooks.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        playbooks
+            .into_iter()
+            .find_map(|(_, (playbook, request_count))| {
+                let request_index = request_count.fetch_add(1, Ordering::SeqCst);
+                playbook.step_at(request_index).cloned()
+            })
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::store::fault_store::*;
     use crate::store::mem_store;
     use chrono::{Duration, Utc};
 
-    #[tokio::test]
-    async fn test_store() {
-        let mem_store = mem_store::MemStore::new_db();
+    /// Defines the tests that every FaultStore implementation must pass, given the expression
+    /// that creates an empty store and the attributes of the tests, if any
+    macro_rules! fault_store_tests {
+        ($new_db:expr $(, #[$attr:meta])*) => {
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_store() {
+                let fault_store = $new_db;
 
-        let fault = get_mock_fault();
-        match mem_store.write().await.store(fault.name.as_str(), &fault) {
-            Ok(val) => {
-                assert_eq!(true, val);
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                match fault_store.store(fault.name.as_str(), &fault).await {
+                    Ok(val) => {
+                        assert_eq!(true, val);
+                    }
+                    Err(e) => {
+                        panic!("store test failed {}", e);
+                    }
+                };
             }
-            Err(e) => {
-                panic!("store test failed {}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_duplicate_store() {
+                let fault_store = $new_db;
+
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                match fault_store.store(fault.name.as_str(), &fault).await {
+                    Ok(val) => {
+                        assert_eq!(true, val);
+                    }
+                    Err(e) => {
+                        panic!("store failed {}", e);
+                    }
+                };
+
+                fault.command = "GET".to_string();
+
+                match fault_store.store(fault.name.as_str(), &fault).await {
+                    Ok(val) => {
+                        assert_eq!(true, val);
+                    }
+                    Err(e) => {
+                        panic!("store fault test failed {}", e);
+                    }
+                };
+
+                match fault_store.get_by_fault_name(fault.name.as_str()).await {
+                    Ok(fault) => {
+                        assert_eq!(fault.command, "GET");
+                    }
+                    Err(e) => {
+                        panic!("{}", e);
+                    }
+                };
             }
-        };
-    }
 
-    #[tokio::test]
-    async fn test_duplicate_store() {
-        let mem_store = mem_store::MemStore::new_db();
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_fault_by_name() {
+                let fault_store = $new_db;
 
-        let mut fault = get_mock_fault();
-        match mem_store.write().await.store(fault.name.as_str(), &fault) {
-            Ok(val) => {
-                assert_eq!(true, val);
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                match fault_store.store(fault.name.as_str(), &fault).await {
+                    Ok(_) => {}
+                    Err(e) => {
+                        panic!("store fault test failed {}", e);
+                    }
+                }
+
+                match fault_store.get_by_fault_name(fault.name.as_str()).await {
+                    Ok(fault) => {
+                        assert_eq!(
+                            fault,
+                            Fault {
+                                description: Some("inject a delay of 10 milliseconds".to_string()),
+                                duration: Some(20),
+                                ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                            }
+                        );
+                    }
+                    Err(e) => {
+                        panic!("get_by_fault_name test failed {}", e);
+                    }
+                };
             }
-            Err(e) => {
-                panic!("store failed {}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_fault_etag() {
+                let fault_store = $new_db;
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store.store(&fault.name, &fault).await.unwrap();
+
+                let etag = fault_store.get_fault_etag(&fault.name).await.unwrap();
+                assert_eq!(etag, fault.etag());
+                assert_eq!(etag.len(), 64);
+
+                // the ETag changes with the fault
+                fault.enabled = !fault.enabled;
+                fault_store.store(&fault.name, &fault).await.unwrap();
+                let updated_etag = fault_store.get_fault_etag(&fault.name).await.unwrap();
+                assert_ne!(updated_etag, etag);
+
+                assert_eq!(fault_store.get_fault_etag("unknown").await, None);
             }
-        };
 
-        fault.command = "GET".to_string();
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_expired_fault() {
+                let fault_store = $new_db;
 
-        match mem_store.write().await.store(fault.name.as_str(), &fault) {
-            Ok(val) => {
-                assert_eq!(true, val);
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault.expires_at = Some(Utc::now() - Duration::milliseconds(1));
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                assert!(fault_store
+                    .get_by_fault_name(fault.name.as_str())
+                    .await
+                    .is_err());
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    None
+                );
             }
-            Err(e) => {
-                panic!("store fault test failed {}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_not_yet_expired_fault() {
+                let fault_store = $new_db;
+
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault.expires_at = Some(Utc::now() + Duration::minutes(1));
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    fault_store
+                        .get_by_fault_name(fault.name.as_str())
+                        .await
+                        .unwrap(),
+                    fault
+                );
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(fault)
+                );
             }
-        };
 
-        match mem_store
-            .read()
-            .await
-            .get_by_fault_name(fault.name.as_str())
-        {
-            Ok(fault) => {
-                assert_eq!(fault.command, "GET");
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_all_faults() {
+                let fault_store = $new_db;
+
+                let mock_faults = vec![
+                    Fault {
+                        description: Some("inject a delay of 10 milliseconds".to_string()),
+                        duration: Some(20),
+                        last_modified: Some(Utc::now()),
+                        ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                    },
+                    Fault {
+                        description: Some("inject set error".to_string()),
+                        error_msg: Some("SET ERROR".to_string()),
+                        last_modified: Some(Utc::now() + Duration::minutes(1)),
+                        ..Fault::fixture("SET Error", FaultVariants::Error, "SET")
+                    },
+                ];
+
+                for mock_fault in &mock_faults {
+                    match fault_store
+                        .store(mock_fault.name.as_str(), &mock_fault)
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(e) => {
+                            panic!("store fault test failed {}", e);
+                        }
+                    };
+                }
+
+                match fault_store.get_all_faults().await {
+                    Ok(faults) => {
+                        let n = mock_faults.len();
+                        assert_eq!(faults.len(), n);
+
+                        let mut faults = faults.clone();
+                        faults.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+
+                        for (i, fault) in faults.into_iter().enumerate() {
+                            assert_eq!(&fault.name, &mock_faults[n - i - 1].name);
+                        }
+                    }
+                    Err(e) => {
+                        panic!("get_all_faults test failed {}", e);
+                    }
+                };
             }
-            Err(e) => {
-                panic!("{}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_delete_fault() {
+                let fault_store = $new_db;
+
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                match fault_store.store(fault.name.as_str(), &fault).await {
+                    Ok(_) => {}
+                    Err(e) => {
+                        panic!("store fault test failed {}", e);
+                    }
+                }
+
+                match fault_store.delete_fault(fault.name.as_str()).await {
+                    Ok(is_deleted) => {
+                        assert_eq!(is_deleted, true);
+                    }
+                    Err(e) => {
+                        panic!("delete fault test failed: {}", e);
+                    }
+                };
             }
-        };
-    }
 
-    #[tokio::test]
-    async fn test_get_fault_by_name() {
-        let mem_store = mem_store::MemStore::new_db();
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_delete_invalid_fault() {
+                let fault_store = $new_db;
 
-        let fault = get_mock_fault();
-        match mem_store.write().await.store(fault.name.as_str(), &fault) {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("store fault test failed {}", e);
+                match fault_store.delete_fault("invalid_fault").await {
+                    Ok(is_deleted) => {
+                        assert_eq!(is_deleted, false);
+                    }
+                    Err(e) => {
+                        panic!("delete fault test failed: {}", e);
+                    }
+                };
             }
-        }
 
-        match mem_store
-            .read()
-            .await
-            .get_by_fault_name(fault.name.as_str())
-        {
-            Ok(fault) => {
-                assert_eq!(fault, get_mock_fault());
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_disable_fault() {
+                let fault_store = $new_db;
+
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                let is_disabled = fault_store
+                    .disable_fault(fault.name.as_str())
+                    .await
+                    .unwrap();
+                assert!(is_disabled);
+
+                assert!(
+                    !fault_store
+                        .get_by_fault_name(&fault.name)
+                        .await
+                        .unwrap()
+                        .enabled
+                );
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    None
+                );
+                assert!(!fault_store.disable_fault("invalid_fault").await.unwrap());
             }
-            Err(e) => {
-                panic!("get_by_fault_name test failed {}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_reserve_hit() {
+                let fault_store = $new_db;
+
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    fault_store.reserve_hit(&fault.name, 2, None).await.unwrap(),
+                    Some(1)
+                );
+                assert_eq!(
+                    fault_store.reserve_hit(&fault.name, 2, None).await.unwrap(),
+                    Some(2)
+                );
+                // the hit counter doesn't go beyond max_hits
+                assert_eq!(
+                    fault_store.reserve_hit(&fault.name, 2, None).await.unwrap(),
+                    None
+                );
+                assert_eq!(
+                    fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+                    2
+                );
+                assert!(fault_store
+                    .reserve_hit("invalid_fault", 2, None)
+                    .await
+                    .is_err());
             }
-        };
-    }
 
-    #[tokio::test]
-    async fn test_get_all_faults() {
-        let mem_store = mem_store::MemStore::new_db();
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_claim_fault() {
+                let fault_store = $new_db;
+
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                // only the first claim of the enabled fault wins
+                assert!(fault_store.claim_fault(&fault.name).await.unwrap());
+                assert!(!fault_store.claim_fault(&fault.name).await.unwrap());
+                assert!(
+                    !fault_store
+                        .get_by_fault_name(&fault.name)
+                        .await
+                        .unwrap()
+                        .enabled
+                );
+                assert!(!fault_store.claim_fault("invalid_fault").await.unwrap());
+            }
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_fault_hit_count() {
+                let fault_store = $new_db;
+
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+                    0
+                );
+                assert_eq!(fault_store.record_fault_hit(&fault.name).await.unwrap(), 1);
+                assert_eq!(fault_store.record_fault_hit(&fault.name).await.unwrap(), 2);
+                assert_eq!(
+                    fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+                    2
+                );
+
+                assert!(fault_store.record_fault_hit("invalid_fault").await.is_err());
+                assert!(fault_store
+                    .get_fault_hit_count("invalid_fault")
+                    .await
+                    .is_err());
+
+                fault_store.delete_fault(&fault.name).await.unwrap();
+                fault_store
+                    .store(fault.name.as_str(), &fault)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    fault_store.get_fault_hit_count(&fault.name).await.unwrap(),
+                    0
+                );
+            }
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_by_redis_cmd_wildcard() {
+                let fault_store = $new_db;
 
-        let mock_faults = vec![
-            Fault {
-                name: "delay 10 milliseconds".to_string(),
-                description: Some("inject a delay of 10 milliseconds".to_string()),
-                fault_type: FaultVariants::Delay,
-                duration: Some(20),
-                error_msg: None,
-                command: "SET".to_string(),
-                last_modified: Some(Utc::now()),
-            },
-            Fault {
-                name: "SET Error".to_string(),
-                description: Some("inject set error".to_string()),
-                fault_type: FaultVariants::Error,
-                duration: None,
-                error_msg: Some("SET ERROR".to_string()),
-                command: "SET".to_string(),
-                last_modified: Some(Utc::now() + Duration::minutes(1)),
-            },
-        ];
-
-        for mock_fault in &mock_faults {
-            match mem_store
-                .write()
-                .await
-                .store(mock_fault.name.as_str(), &mock_fault)
-            {
-                Ok(_) => {}
-                Err(e) => {
-                    panic!("store fault test failed {}", e);
+                let mut wildcard_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                wildcard_fault.name = "wildcard".to_string();
+                wildcard_fault.command = "*".to_string();
+                fault_store
+                    .store(&wildcard_fault.name, &wildcard_fault)
+                    .await
+                    .unwrap();
+
+                for redis_cmd in ["GET", "SET", "PING"] {
+                    assert_eq!(
+                        fault_store
+                            .get_by_redis_cmd(&FaultRequest::from(redis_cmd), FaultSelectionStrategy::Priority)
+                            .await,
+                        Some(wildcard_fault.clone())
+                    );
                 }
-            };
-        }
 
-        match mem_store.read().await.get_all_faults() {
-            Ok(faults) => {
-                let n = mock_faults.len();
-                assert_eq!(faults.len(), n);
+                let fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault_store.store(&fault.name, &fault).await.unwrap();
+
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("set"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(fault)
+                );
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(wildcard_fault)
+                );
+            }
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_by_redis_cmd_scheduled() {
+                let fault_store = $new_db;
+                let now = Utc::now();
+
+                let mut fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                fault.activate_at = Some(now + Duration::hours(1));
+                fault_store.store(&fault.name, &fault).await.unwrap();
+
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    None
+                );
+                assert!(!fault.is_activated_at(now));
+                assert!(fault.is_activated_at(now + Duration::hours(2)));
 
-                let mut faults = faults.clone();
-                faults.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+                fault.activate_at = Some(now - Duration::seconds(1));
+                fault_store.store(&fault.name, &fault).await.unwrap();
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(fault)
+                );
+            }
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_by_redis_cmd_priority() {
+                let fault_store = $new_db;
+                let now = Utc::now();
+
+                let mut wildcard_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                wildcard_fault.name = "wildcard".to_string();
+                wildcard_fault.command = "*".to_string();
+                wildcard_fault.priority = 10;
+                wildcard_fault.last_modified = Some(now);
+
+                let mut low_priority_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                low_priority_fault.name = "low priority".to_string();
+                low_priority_fault.priority = 200;
+                low_priority_fault.last_modified = Some(now);
 
-                for (i, fault) in faults.into_iter().enumerate() {
-                    assert_eq!(&fault.name, &mock_faults[n - i - 1].name);
+                let mut high_priority_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                high_priority_fault.name = "high priority".to_string();
+                high_priority_fault.priority = 1;
+                high_priority_fault.last_modified = Some(now - Duration::minutes(1));
+
+                for fault in [&wildcard_fault, &low_priority_fault, &high_priority_fault] {
+                    fault_store.store(&fault.name, fault).await.unwrap();
                 }
+
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(high_priority_fault.clone())
+                );
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(wildcard_fault.clone())
+                );
+
+                // the fault of the command wins over a more recently modified wildcard fault of
+                // the same priority
+                fault_store
+                    .delete_fault(&high_priority_fault.name)
+                    .await
+                    .unwrap();
+                wildcard_fault.priority = 200;
+                wildcard_fault.last_modified = Some(now + Duration::minutes(1));
+                fault_store
+                    .store(&wildcard_fault.name, &wildcard_fault)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(low_priority_fault.clone())
+                );
+
+                // the most recently modified fault wins among the faults of the same command
+                let mut newer_fault = low_priority_fault.clone();
+                newer_fault.name = "newer".to_string();
+                newer_fault.last_modified = Some(now + Duration::minutes(2));
+                fault_store
+                    .store(&newer_fault.name, &newer_fault)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await,
+                    Some(newer_fault)
+                );
             }
-            Err(e) => {
-                panic!("get_all_faults test failed {}", e);
+
+            #[tokio::test]
+            $(#[$attr])*
+            async fn test_get_by_redis_cmd_weighted() {
+                let fault_store = $new_db;
+                let now = Utc::now();
+
+                let mut delay_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                delay_fault.name = "delay".to_string();
+                delay_fault.fault_type = FaultVariants::Delay;
+                delay_fault.probability = Some(0.3);
+                delay_fault.last_modified = Some(now);
+
+                let mut error_fault = Fault {
+                    description: Some("inject a delay of 10 milliseconds".to_string()),
+                    duration: Some(20),
+                    ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+                };
+                error_fault.name = "error".to_string();
+                error_fault.probability = Some(0.1);
+                error_fault.last_modified = Some(now - Duration::minutes(1));
+
+                for fault in [&delay_fault, &error_fault] {
+                    fault_store.store(&fault.name, fault).await.unwrap();
+                }
+
+                let mut delay_count = 0;
+                let mut error_count = 0;
+                for _ in 0..10000 {
+                    match fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Weighted)
+                        .await
+                    {
+                        Some(fault) if fault.name == delay_fault.name => delay_count += 1,
+                        Some(fault) if fault.name == error_fault.name => error_count += 1,
+                        Some(fault) => panic!("unexpected fault {}", fault.name),
+                        None => {}
+                    }
+                }
+                // 30% and 10% of the requests, give or take
+                assert!((2500..3500).contains(&delay_count), "{}", delay_count);
+                assert!((500..1500).contains(&error_count), "{}", error_count);
+
+                // the priority strategy only applies the most recently modified fault
+                for _ in 0..100 {
+                    let fault = fault_store
+                        .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                        .await;
+                    assert!(fault.is_none() || fault == Some(delay_fault.clone()));
+                }
             }
         };
     }
 
+    pub(crate) use fault_store_tests;
+
+    fault_store_tests!(mem_store::MemStore::new_db());
+
     #[tokio::test]
-    async fn test_delete_fault() {
+    async fn test_fault_stats() {
         let mem_store = mem_store::MemStore::new_db();
 
-        let fault = get_mock_fault();
-        match mem_store.write().await.store(fault.name.as_str(), &fault) {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("store fault test failed {}", e);
-            }
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        mem_store.store(fault.name.as_str(), &fault).await.unwrap();
+
+        assert_eq!(
+            mem_store.get_fault_stats(&fault.name).await.unwrap(),
+            FaultStats::default()
+        );
+
+        assert_eq!(
+            mem_store.record_hit(&fault.name, Some(100)).await.unwrap(),
+            1
+        );
+        let first = mem_store.get_fault_stats(&fault.name).await.unwrap();
+        assert_eq!(first.hit_count, 1);
+        assert_eq!(first.first_hit_at, first.last_hit_at);
+        assert_eq!(first.total_delay_ms, 100);
+
+        assert_eq!(
+            mem_store.record_hit(&fault.name, Some(50)).await.unwrap(),
+            2
+        );
+        assert_eq!(mem_store.record_fault_hit(&fault.name).await.unwrap(), 3);
+        let stats = mem_store.get_fault_stats(&fault.name).await.unwrap();
+        assert_eq!(stats.hit_count, 3);
+        assert_eq!(stats.first_hit_at, first.first_hit_at);
+        assert!(stats.last_hit_at >= first.last_hit_at);
+        assert_eq!(stats.total_delay_ms, 150);
+
+        let all_stats = mem_store.get_all_fault_stats().await.unwrap();
+        assert_eq!(all_stats.len(), 1);
+        assert_eq!(all_stats[&fault.name], stats);
+
+        assert!(mem_store
+            .record_hit("invalid_fault", Some(10))
+            .await
+            .is_err());
+        assert!(mem_store.get_fault_stats("invalid_fault").await.is_err());
+
+        mem_store.delete_fault(&fault.name).await.unwrap();
+        mem_store.store(fault.name.as_str(), &fault).await.unwrap();
+        assert_eq!(
+            mem_store.get_fault_stats(&fault.name).await.unwrap(),
+            FaultStats::default()
+        );
+    }
+
+    #[test]
+    fn test_fault_stats_hits_since() {
+        let now = Utc::now();
+        let mut stats = FaultStats::default();
+        for minutes_ago in [25 * 60, 23 * 60, 90, 30, 30, 0] {
+            stats.record_hit(
+                stats.hit_count + 1,
+                now - Duration::minutes(minutes_ago),
+                None,
+            );
         }
 
-        match mem_store.write().await.delete_fault(fault.name.as_str()) {
-            Ok(is_deleted) => {
-                assert_eq!(is_deleted, true);
-            }
-            Err(e) => {
-                panic!("delete fault test failed: {}", e);
-            }
-        };
+        assert_eq!(stats.hit_count, 6);
+        assert_eq!(stats.hits_since(now - Duration::hours(1)), 3);
+        assert_eq!(stats.hits_since(now - Duration::hours(2)), 4);
+        assert_eq!(stats.hits_since(now - Duration::days(1)), 5);
+        // the hits older than a day are dropped once a later hit is recorded
+        assert_eq!(stats.hits_since(now - Duration::days(2)), 5);
+        assert_eq!(stats.hits_since(now + Duration::minutes(1)), 0);
     }
 
     #[tokio::test]
-    async fn test_delete_invalid_fault() {
-        let mem_store = mem_store::MemStore::new_db();
+    async fn test_fault_history() {
+        let mem_store = mem_store::MemStore::new_db_with_history(2);
 
-        match mem_store.write().await.delete_fault("invalid_fault") {
-            Ok(is_deleted) => {
-                assert_eq!(is_deleted, false);
-            }
-            Err(e) => {
-                panic!("delete fault test failed: {}", e);
-            }
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        mem_store.store(&fault.name, &fault).await.unwrap();
+        assert!(mem_store
+            .get_fault_history(&fault.name)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(mem_store.get_fault_history("invalid_fault").await.is_err());
+
+        let mut versions = vec![fault.clone()];
+        for duration in [200, 300, 400] {
+            let mut fault = fault.clone();
+            fault.duration = Some(duration);
+            mem_store.store(&fault.name, &fault).await.unwrap();
+            versions.insert(0, fault);
+        }
+
+        // the oldest version is dropped beyond the 2 versions
+        let history = mem_store.get_fault_history(&fault.name).await.unwrap();
+        assert_eq!(history, versions[1..3]);
+
+        mem_store.rollback_fault(&fault.name, 1).await.unwrap();
+        assert_eq!(
+            mem_store.get_by_fault_name(&fault.name).await.unwrap(),
+            versions[2]
+        );
+        assert_eq!(
+            mem_store.get_fault_history(&fault.name).await.unwrap(),
+            versions[0..2]
+        );
+        assert!(mem_store.rollback_fault(&fault.name, 2).await.is_err());
+
+        mem_store.delete_fault(&fault.name).await.unwrap();
+        mem_store.store(&fault.name, &fault).await.unwrap();
+        assert!(mem_store
+            .get_fault_history(&fault.name)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_playbook_store() {
+        let playbook_store = mem_store::MemPlaybookStore::new_db();
+        assert_eq!(playbook_store.next_step(), None);
+
+        let pass = PlaybookStep {
+            fault_name: None,
+            repeat_count: 2,
+        };
+        let fail = PlaybookStep {
+            fault_name: Some("SET Error".to_string()),
+            repeat_count: 1,
         };
+        let playbook = FaultPlaybook {
+            name: "b".to_string(),
+            steps: vec![pass.clone(), fail.clone()],
+            loop_playbook: false,
+        };
+        playbook_store.store_playbook(&playbook).unwrap();
+
+        let steps: Vec<_> = (0..4).map(|_| playbook_store.next_step()).collect();
+        assert_eq!(
+            steps,
+            vec![
+                Some(pass.clone()),
+                Some(pass.clone()),
+                Some(fail.clone()),
+                None
+            ]
+        );
+
+        // the playbooks are applied in the order of their names and a looping playbook never
+        // ends
+        let looping_playbook = FaultPlaybook {
+            name: "a".to_string(),
+            steps: vec![fail.clone(), pass.clone()],
+            loop_playbook: true,
+        };
+        playbook_store.store_playbook(&looping_playbook).unwrap();
+        playbook_store.store_playbook(&playbook).unwrap();
+
+        let steps: Vec<_> = (0..4).map(|_| playbook_store.next_step()).collect();
+        assert_eq!(
+            steps,
+            vec![
+                Some(fail.clone()),
+                Some(pass.clone()),
+                Some(pass.clone()),
+                Some(fail)
+            ]
+        );
+        assert_eq!(playbook_store.get_all_playbooks().unwrap().len(), 2);
+
+        assert!(playbook_store.delete_playbook("a").unwrap());
+        assert!(!playbook_store.delete_playbook("a").unwrap());
+        assert_eq!(playbook_store.next_step(), Some(pass));
     }
 
-    fn get_mock_fault() -> Fault {
-        Fault {
-            name: "delay 10 milliseconds".to_string(),
+    #[tokio::test]
+    async fn test_store_batch_atomic() {
+        let mem_store = mem_store::MemStore::new_db();
+
+        let fault = Fault {
             description: Some("inject a delay of 10 milliseconds".to_string()),
-            fault_type: FaultVariants::Delay,
             duration: Some(20),
-            error_msg: None,
-            command: "SET".to_string(),
-            last_modified: None,
-        }
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        let mut get_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        get_fault.name = "get_delay".to_string();
+        get_fault.command = "GET".to_string();
+        let mut invalid_fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        invalid_fault.name = "invalid_delay".to_string();
+        invalid_fault.duration = None;
+
+        // a repeated name and a delay fault without duration reject the whole batch
+        let err = mem_store
+            .store_batch_atomic(&[
+                fault.clone(),
+                get_fault.clone(),
+                fault.clone(),
+                invalid_fault,
+            ])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BatchStoreError::InvalidBatchError(_)));
+        let names: Vec<&str> = err.errors().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec![fault.name.as_str(), "invalid_delay"]);
+        assert!(mem_store.get_all_faults().await.unwrap().is_empty());
+
+        mem_store.store(&fault.name, &fault).await.unwrap();
+        let mut updated_fault = fault.clone();
+        updated_fault.duration = Some(200);
+        mem_store
+            .store_batch_atomic(&[updated_fault.clone(), get_fault.clone()])
+            .await
+            .unwrap();
+        assert_eq!(mem_store.get_all_faults().await.unwrap().len(), 2);
+        assert_eq!(
+            mem_store.get_by_fault_name(&fault.name).await.unwrap(),
+            updated_fault
+        );
+        assert_eq!(
+            mem_store.get_fault_history(&fault.name).await.unwrap(),
+            vec![fault]
+        );
     }
 }
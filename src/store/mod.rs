@@ -1,2 +1,8 @@
 pub mod fault_store;
 pub mod mem_store;
+pub mod postgres_store;
+pub mod redis_store;
+#[cfg(feature = "rocksdb")]
+pub mod rocks_store;
+pub mod sqlite_store;
+pub mod sweeper;
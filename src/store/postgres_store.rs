@@ -0,0 +1,529 @@
+use crate::store::fault_store::{
+    find_by_redis_cmd, ArgMatcher, Fault, FaultRequest, FaultSelectionStrategy, FaultStore,
+    StoreError, SubFault, DB,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::types::Json;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::future::Future;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// PostgresStore is a persistent store implementation of FaultStore backed by PostgreSQL, which
+/// can be shared by several red-monkey instances
+///
+/// The schema is created by the embedded migrations of the `migrations` directory when the store
+/// is opened. The hit count of a fault is kept in the `hit_count` column of its row, hence it is
+/// reset when the fault is deleted.
+///
+/// The queries are checked against the schema at compile time with the `sqlx::query!` macros. The
+/// query metadata is kept in the `.sqlx` directory, such that the build doesn't need a database;
+/// it is regenerated with `cargo sqlx prepare` once a query or the schema changes.
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Connects to the PostgreSQL database at the given URL with a pool of the given number of
+    /// connections, and applies the pending migrations.
+    pub async fn new_db(postgres_url: &str, pool_size: usize) -> Result<DB, StoreError> {
+        let pool = run(PgPoolOptions::new()
+            .max_connections(pool_size as u32)
+            .connect(postgres_url))
+        .await?;
+
+        sqlx::migrate!().run(&pool).await.map_err(|err| {
+            error!("PostgreSQL fault store migration failed: {}", err);
+            to_store_error(err)
+        })?;
+        debug!("PostgreSQL fault store migrations applied");
+
+        Ok(Arc::new(PostgresStore { pool }))
+    }
+}
+
+/// Runs the query, logging the failure of the query.
+async fn run<F, T>(query: F) -> Result<T, StoreError>
+where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    query.await.map_err(|err| {
+        error!("PostgreSQL fault store query failed: {}", err);
+        to_store_error(err)
+    })
+}
+
+fn to_store_error<E: Display>(err: E) -> StoreError {
+    StoreError::new(&err.to_string())
+}
+
+fn not_found_error(fault_name: &str) -> StoreError {
+    StoreError::new(format!("Fault {} not found", fault_name).as_str())
+}
+
+fn to_rfc3339(timestamp: Option<DateTime<Utc>>) -> Option<String> {
+    timestamp.map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true))
+}
+
+/// FaultRow is a row of the `faults` table, but for its `hit_count`
+struct FaultRow {
+    name: String,
+    description: Option<String>,
+    fault_type: String,
+    duration: Option<i64>,
+    error_msg: Option<String>,
+    command: String,
+    last_modified: Option<String>,
+    expires_at: Option<String>,
+    activate_at: Option<String>,
+    enabled: bool,
+    one_shot: bool,
+    max_hits: Option<i64>,
+    jitter_ms: Option<i64>,
+    adaptive_delay_step_ms: Option<i64>,
+    max_adaptive_duration_ms: Option<i64>,
+    burst_count: Option<i64>,
+    burst_interval_ms: Option<i64>,
+    truncate_after_bytes: Option<i64>,
+    bytes_per_ms: Option<i64>,
+    key_pattern: Option<String>,
+    tags: Json<Vec<String>>,
+    fault_group: Option<String>,
+    client_ip_filter: Option<Json<Vec<IpAddr>>>,
+    redis_database: Option<i64>,
+    proxy_port: Option<i64>,
+    priority: i64,
+    probability: Option<f64>,
+    sub_faults: Option<Json<Vec<SubFault>>>,
+    mutation_pattern: Option<String>,
+    mutation_replacement: Option<String>,
+    arg_matchers: Option<Json<Vec<ArgMatcher>>>,
+}
+
+impl TryFrom<FaultRow> for Fault {
+    type Error = sqlx::Error;
+
+    fn try_from(row: FaultRow) -> Result<Self, Self::Error> {
+        let to_u64 = |val: Option<i64>| val.map(|val| val as u64);
+        let to_timestamp = |timestamp: Option<String>| {
+            timestamp
+                .map(|timestamp| DateTime::parse_from_rfc3339(&timestamp).map(DateTime::from))
+                .transpose()
+                .map_err(|err| sqlx::Error::Decode(Box::new(err)))
+        };
+
+        Ok(Fault {
+            name: row.name,
+            description: row.description,
+            fault_type: FromStr::from_str(&row.fault_type)
+                .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+            duration: to_u64(row.duration),
+            error_msg: row.error_msg,
+            command: row.command,
+            last_modified: to_timestamp(row.last_modified)?,
+            expires_at: to_timestamp(row.expires_at)?,
+            activate_at: to_timestamp(row.activate_at)?,
+            enabled: row.enabled,
+            one_shot: row.one_shot,
+            max_hits: to_u64(row.max_hits),
+            jitter_ms: to_u64(row.jitter_ms),
+            adaptive_delay_step_ms: to_u64(row.adaptive_delay_step_ms),
+            max_adaptive_duration_ms: to_u64(row.max_adaptive_duration_ms),
+            burst_count: row.burst_count.map(|val| val as u32),
+            burst_interval_ms: to_u64(row.burst_interval_ms),
+            truncate_after_bytes: to_u64(row.truncate_after_bytes),
+            bytes_per_ms: to_u64(row.bytes_per_ms),
+            key_pattern: row.key_pattern,
+            key_glob: Default::default(),
+            tags: row.tags.0,
+            group: row.fault_group,
+            client_ip_filter: row.client_ip_filter.map(|json| json.0),
+            database: row.redis_database.map(|val| val as u8),
+            proxy_port: row.proxy_port.map(|val| val as u16),
+            priority: row.priority as u32,
+            probability: row.probability,
+            sub_faults: row.sub_faults.map(|json| json.0),
+            mutation_pattern: row.mutation_pattern,
+            mutation_replacement: row.mutation_replacement,
+            arg_matchers: row.arg_matchers.map(|json| json.0),
+        })
+    }
+}
+
+#[async_trait]
+impl FaultStore for PostgresStore {
+    async fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
+        // the config of the fault of the same name is replaced, while its hit count is kept
+        run(sqlx::query!(
+            r#"INSERT INTO faults (
+                name, description, fault_type, duration, error_msg, command, last_modified,
+                expires_at, activate_at, enabled, one_shot, max_hits, jitter_ms,
+                adaptive_delay_step_ms, max_adaptive_duration_ms, burst_count, burst_interval_ms,
+                truncate_after_bytes, bytes_per_ms, key_pattern, tags, fault_group,
+                client_ip_filter, redis_database, proxy_port, priority, probability, sub_faults,
+                mutation_pattern, mutation_replacement, arg_matchers
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31
+            ) ON CONFLICT (name) DO UPDATE SET
+                description = EXCLUDED.description,
+                fault_type = EXCLUDED.fault_type,
+                duration = EXCLUDED.duration,
+                error_msg = EXCLUDED.error_msg,
+                command = EXCLUDED.command,
+                last_modified = EXCLUDED.last_modified,
+                expires_at = EXCLUDED.expires_at,
+                activate_at = EXCLUDED.activate_at,
+                enabled = EXCLUDED.enabled,
+                one_shot = EXCLUDED.one_shot,
+                max_hits = EXCLUDED.max_hits,
+                jitter_ms = EXCLUDED.jitter_ms,
+                adaptive_delay_step_ms = EXCLUDED.adaptive_delay_step_ms,
+                max_adaptive_duration_ms = EXCLUDED.max_adaptive_duration_ms,
+                burst_count = EXCLUDED.burst_count,
+                burst_interval_ms = EXCLUDED.burst_interval_ms,
+                truncate_after_bytes = EXCLUDED.truncate_after_bytes,
+                bytes_per_ms = EXCLUDED.bytes_per_ms,
+                key_pattern = EXCLUDED.key_pattern,
+                tags = EXCLUDED.tags,
+                fault_group = EXCLUDED.fault_group,
+                client_ip_filter = EXCLUDED.client_ip_filter,
+                redis_database = EXCLUDED.redis_database,
+                proxy_port = EXCLUDED.proxy_port,
+                priority = EXCLUDED.priority,
+                probability = EXCLUDED.probability,
+                sub_faults = EXCLUDED.sub_faults,
+                mutation_pattern = EXCLUDED.mutation_pattern,
+                mutation_replacement = EXCLUDED.mutation_replacement,
+                arg_matchers = EXCLUDED.arg_matchers"#,
+            fault_name,
+            fault.description,
+            format!("{:?}", fault.fault_type),
+            fault.duration.map(|val| val as i64),
+            fault.error_msg,
+            fault.command,
+            to_rfc3339(fault.last_modified),
+            to_rfc3339(fault.expires_at),
+            to_rfc3339(fault.activate_at),
+            fault.enabled,
+            fault.one_shot,
+            fault.max_hits.map(|val| val as i64),
+            fault.jitter_ms.map(|val| val as i64),
+            fault.adaptive_delay_step_ms.map(|val| val as i64),
+            fault.max_adaptive_duration_ms.map(|val| val as i64),
+            fault.burst_count.map(|val| val as i64),
+            fault.burst_interval_ms.map(|val| val as i64),
+            fault.truncate_after_bytes.map(|val| val as i64),
+            fault.bytes_per_ms.map(|val| val as i64),
+            fault.key_pattern,
+            Json(&fault.tags) as _,
+            fault.group,
+            fault.client_ip_filter.as_ref().map(Json) as _,
+            fault.database.map(|val| val as i64),
+            fault.proxy_port.map(|val| val as i64),
+            fault.priority as i64,
+            fault.probability,
+            fault.sub_faults.as_ref().map(Json) as _,
+            fault.mutation_pattern,
+            fault.mutation_replacement,
+            fault.arg_matchers.as_ref().map(Json) as _,
+        )
+        .execute(&self.pool))
+        .await?;
+
+        debug!("Fault {} stored in PostgreSQL", fault_name);
+        Ok(true)
+    }
+
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+        let fault = run(async {
+            sqlx::query_as!(
+                FaultRow,
+                r#"SELECT
+                    name, description, fault_type, duration, error_msg, command, last_modified,
+                    expires_at, activate_at, enabled, one_shot, max_hits, jitter_ms,
+                    adaptive_delay_step_ms, max_adaptive_duration_ms, burst_count,
+                    burst_interval_ms, truncate_after_bytes, bytes_per_ms, key_pattern,
+                    tags AS "tags: Json<Vec<String>>",
+                    fault_group,
+                    client_ip_filter AS "client_ip_filter: Json<Vec<IpAddr>>",
+                    redis_database, proxy_port, priority, probability,
+                    sub_faults AS "sub_faults: Json<Vec<SubFault>>",
+                    mutation_pattern, mutation_replacement,
+                    arg_matchers AS "arg_matchers: Json<Vec<ArgMatcher>>"
+                FROM faults WHERE name = $1"#,
+                fault_name
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(Fault::try_from)
+            .transpose()
+        })
+        .await?;
+
+        match fault {
+            Some(fault) if !fault.is_expired() => Ok(fault),
+            _ => Err(not_found_error(fault_name)),
+        }
+    }
+
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        run(async {
+            sqlx::query_as!(
+                FaultRow,
+                r#"SELECT
+                    name, description, fault_type, duration, error_msg, command, last_modified,
+                    expires_at, activate_at, enabled, one_shot, max_hits, jitter_ms,
+                    adaptive_delay_step_ms, max_adaptive_duration_ms, burst_count,
+                    burst_interval_ms, truncate_after_bytes, bytes_per_ms, key_pattern,
+                    tags AS "tags: Json<Vec<String>>",
+                    fault_group,
+                    client_ip_filter AS "client_ip_filter: Json<Vec<IpAddr>>",
+                    redis_database, proxy_port, priority, probability,
+                    sub_faults AS "sub_faults: Json<Vec<SubFault>>",
+                    mutation_pattern, mutation_replacement,
+                    arg_matchers AS "arg_matchers: Json<Vec<ArgMatcher>>"
+                FROM faults"#
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(Fault::try_from)
+            .collect()
+        })
+        .await
+    }
+
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault> {
+        let faults = match self.get_all_faults().await {
+            Ok(faults) => faults,
+            Err(e) => {
+                error!("error fetching all faults: {:?}", e);
+                return None;
+            }
+        };
+
+        find_by_redis_cmd(faults, request, strategy)
+    }
+
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let result =
+            run(sqlx::query!("DELETE FROM faults WHERE name = $1", fault_name).execute(&self.pool))
+                .await?;
+
+        let is_deleted = result.rows_affected() > 0;
+        if is_deleted {
+            debug!("Delete fault {}", fault_name);
+        }
+        Ok(is_deleted)
+    }
+
+    async fn clear_all(&self) -> Result<usize, StoreError> {
+        let result = run(sqlx::query!("DELETE FROM faults").execute(&self.pool)).await?;
+
+        debug!("Cleared {} faults from PostgreSQL", result.rows_affected());
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let result = run(sqlx::query!(
+            "UPDATE faults SET enabled = FALSE WHERE name = $1",
+            fault_name
+        )
+        .execute(&self.pool))
+        .await?;
+
+        let is_disabled = result.rows_affected() > 0;
+        if is_disabled {
+            debug!("Disabled fault {}", fault_name);
+        }
+        Ok(is_disabled)
+    }
+
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let result = run(sqlx::query!(
+            "UPDATE faults SET enabled = FALSE WHERE name = $1 AND enabled",
+            fault_name
+        )
+        .execute(&self.pool))
+        .await?;
+
+        let is_claimed = result.rows_affected() > 0;
+        if is_claimed {
+            debug!("Claimed fault {}", fault_name);
+        }
+        Ok(is_claimed)
+    }
+
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let hit_count = run(sqlx::query_scalar!(
+            "UPDATE faults SET hit_count = hit_count + 1 WHERE name = $1 RETURNING hit_count",
+            fault_name
+        )
+        .fetch_optional(&self.pool))
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(hit_count as u64),
+            None => Err(not_found_error(fault_name)),
+        }
+    }
+
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        _delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        let hit_count = run(sqlx::query_scalar!(
+            "UPDATE faults SET hit_count = hit_count + 1 \
+             WHERE name = $1 AND hit_count < $2 RETURNING hit_count",
+            fault_name,
+            max_hits as i64
+        )
+        .fetch_optional(&self.pool))
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(Some(hit_count as u64)),
+            // the fault reached its max hits, unless it is not found
+            None => self.get_fault_hit_count(fault_name).await.map(|_| None),
+        }
+    }
+
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let hit_count = run(sqlx::query_scalar!(
+            "SELECT hit_count FROM faults WHERE name = $1",
+            fault_name
+        )
+        .fetch_optional(&self.pool))
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(hit_count as u64),
+            None => Err(not_found_error(fault_name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fault_store::*;
+    use crate::store::mem_store::tests::fault_store_tests;
+    use chrono::Duration;
+    use std::ops::Deref;
+    use testcontainers_modules::postgres::Postgres;
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+    use testcontainers_modules::testcontainers::ContainerAsync;
+
+    /// A PostgresStore of a PostgreSQL container, which is removed once dropped
+    struct TestDb {
+        db: DB,
+        _container: ContainerAsync<Postgres>,
+    }
+
+    impl Deref for TestDb {
+        type Target = DB;
+
+        fn deref(&self) -> &DB {
+            &self.db
+        }
+    }
+
+    /// Starts a PostgreSQL container and opens a PostgresStore on it
+    async fn new_test_db() -> TestDb {
+        let container = Postgres::default().start().await.unwrap();
+        let postgres_url = format!(
+            "postgres://postgres:postgres@{}:{}/postgres",
+            container.get_host().await.unwrap(),
+            container.get_host_port_ipv4(5432).await.unwrap()
+        );
+
+        TestDb {
+            db: PostgresStore::new_db(&postgres_url, 2).await.unwrap(),
+            _container: container,
+        }
+    }
+
+    fault_store_tests!(new_test_db().await, #[ignore = "requires Docker"]);
+
+    #[tokio::test]
+    #[ignore = "requires Docker"]
+    async fn test_store_and_get_fault_by_name() {
+        let postgres_store = new_test_db().await;
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.last_modified = Some(Utc::now());
+        fault.expires_at = Some(Utc::now() + Duration::minutes(1));
+        fault.max_hits = Some(3);
+        fault.key_pattern = Some("user:*".to_string());
+        fault.tags = vec!["production".to_string(), "auth-service".to_string()];
+        fault.client_ip_filter = Some(vec!["10.0.0.1".parse().unwrap(), "::1".parse().unwrap()]);
+        fault.database = Some(2);
+        fault.proxy_port = Some(6351);
+        fault.probability = Some(0.5);
+        assert!(postgres_store.store(&fault.name, &fault).await.unwrap());
+
+        assert_eq!(
+            postgres_store.get_by_fault_name(&fault.name).await.unwrap(),
+            fault
+        );
+
+        // the hit count is kept when the fault is replaced
+        postgres_store.record_fault_hit(&fault.name).await.unwrap();
+        fault.command = "GET".to_string();
+        postgres_store.store(&fault.name, &fault).await.unwrap();
+        assert_eq!(
+            postgres_store.get_all_faults().await.unwrap(),
+            vec![fault.clone()]
+        );
+        assert_eq!(
+            postgres_store
+                .get_fault_hit_count(&fault.name)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Docker"]
+    async fn test_clear_all() {
+        let postgres_store = new_test_db().await;
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        for name in ["set_delay", "get_error"] {
+            fault.name = name.to_string();
+            postgres_store.store(&fault.name, &fault).await.unwrap();
+        }
+
+        assert_eq!(postgres_store.clear_all().await.unwrap(), 2);
+        assert!(postgres_store.get_all_faults().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_postgres_url() {
+        assert!(PostgresStore::new_db("not a postgres url", 2)
+            .await
+            .is_err());
+    }
+}
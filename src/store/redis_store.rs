@@ -0,0 +1,430 @@
+use crate::store::fault_store::{
+    find_by_redis_cmd, Fault, FaultRequest, FaultSelectionStrategy, FaultStore, StoreError, DB,
+};
+use async_trait::async_trait;
+use r2d2::{Pool, PooledConnection};
+use redis::{Client, Commands};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// The prefix of the Redis keys under which the faults are stored
+const KEY_PREFIX: &str = "red_monkey:faults:";
+
+/// The hash field that holds the JSON serialized fault
+const FAULT_FIELD: &str = "fault";
+
+/// The hash field that holds the hit count of the fault
+const HIT_COUNT_FIELD: &str = "hit_count";
+
+/// The maximum number of connections to the Redis server
+const POOL_SIZE: u32 = 8;
+
+/// The time to wait for a connection to the Redis server before failing the store operation
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Increments the hit count of the fault only when the fault exists, such that a hit recorded
+/// concurrently with the delete of the fault doesn't leave a dangling hash behind.
+const RECORD_HIT_SCRIPT: &str = r"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 1 then
+    return redis.call('HINCRBY', KEYS[1], ARGV[2], 1)
+end
+return nil
+";
+
+/// Increments the hit count of the fault of the key only while it is below the max hits of
+/// `ARGV[3]`. Returns nil when the fault doesn't exist, and -1 when it reached its max hits.
+const RESERVE_HIT_SCRIPT: &str = r"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+    return nil
+end
+local hit_count = tonumber(redis.call('HGET', KEYS[1], ARGV[2]) or '0')
+if hit_count >= tonumber(ARGV[3]) then
+    return -1
+end
+return redis.call('HINCRBY', KEYS[1], ARGV[2], 1)
+";
+
+/// RedisStore is a persistent store implementation of FaultStore backed by Redis
+///
+/// Each fault is stored as a Redis hash under the key `red_monkey:faults:{name}`. The hash holds
+/// the JSON serialized fault in the `fault` field and its hit count in the `hit_count` field. Loss
+/// of the connection to Redis surfaces as a StoreError of the failed operation. The Redis calls are
+/// blocking, hence they run on the blocking thread pool of the async runtime.
+#[derive(Clone)]
+pub struct RedisStore {
+    pool: Pool<Client>,
+}
+
+impl RedisStore {
+    /// Creates a RedisStore for the given Redis URL, e.g. `redis://127.0.0.1:6379/0`.
+    ///
+    /// The connections are established lazily, hence an unreachable Redis server doesn't fail the
+    /// creation of the store.
+    pub fn new_db(redis_url: &str) -> Result<DB, StoreError> {
+        Self::connect(redis_url, CONNECTION_TIMEOUT)
+    }
+
+    fn connect(redis_url: &str, connection_timeout: Duration) -> Result<DB, StoreError> {
+        let client = Client::open(redis_url).map_err(to_store_error)?;
+        let pool = Pool::builder()
+            .max_size(POOL_SIZE)
+            .connection_timeout(connection_timeout)
+            .build_unchecked(client);
+
+        Ok(Arc::new(RedisStore { pool }))
+    }
+
+    /// Runs the given operation on the blocking thread pool, such that waiting for a connection
+    /// of the pool or for the reply of Redis doesn't block the threads of the async runtime.
+    async fn spawn_blocking<T, F>(&self, operation: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&RedisStore) -> Result<T, StoreError> + Send + 'static,
+    {
+        let redis_store = self.clone();
+        tokio::task::spawn_blocking(move || operation(&redis_store))
+            .await
+            .map_err(to_store_error)?
+    }
+
+    fn conn(&self) -> Result<PooledConnection<Client>, StoreError> {
+        self.pool.get().map_err(|err| {
+            error!(
+                "error getting a connection to the Redis fault store: {}",
+                err
+            );
+            to_store_error(err)
+        })
+    }
+
+    /// Disables the fault in a transaction that is retried when the fault is modified
+    /// concurrently. With `only_enabled`, a disabled fault is left as is. Returns true when the
+    /// fault is disabled by this call.
+    fn disable(&self, fault_name: &str, only_enabled: bool) -> Result<bool, StoreError> {
+        let key = fault_key(fault_name);
+
+        redis::transaction(&mut *self.conn()?, &[&key], |conn, pipe| {
+            let fault: Option<String> = conn.hget(&key, FAULT_FIELD)?;
+            let mut fault: Fault = match fault {
+                Some(fault) => serde_json::from_str(&fault).map_err(|err| {
+                    redis::RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Error parsing fault",
+                        err.to_string(),
+                    ))
+                })?,
+                None => return Ok(Some(false)),
+            };
+            if only_enabled && !fault.enabled {
+                return Ok(Some(false));
+            }
+            fault.enabled = false;
+
+            let fault_json = serde_json::to_string(&fault).map_err(|err| {
+                redis::RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error serializing fault",
+                    err.to_string(),
+                ))
+            })?;
+
+            pipe.hset(&key, FAULT_FIELD, fault_json)
+                .ignore()
+                .query::<Option<()>>(conn)
+                .map(|res| res.map(|_| true))
+        })
+        .map_err(to_store_error)
+    }
+}
+
+fn fault_key(fault_name: &str) -> String {
+    format!("{}{}", KEY_PREFIX, fault_name)
+}
+
+fn to_store_error<E: Display>(err: E) -> StoreError {
+    StoreError::new(&err.to_string())
+}
+
+fn not_found_error(fault_name: &str) -> StoreError {
+    StoreError::new(format!("Fault {} not found", fault_name).as_str())
+}
+
+fn parse_fault(fault: &str) -> Result<Fault, StoreError> {
+    serde_json::from_str(fault).map_err(to_store_error)
+}
+
+#[async_trait]
+impl FaultStore for RedisStore {
+    async fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+        let fault_json = serde_json::to_string(fault).map_err(to_store_error)?;
+
+        self.spawn_blocking(move |redis_store| {
+            redis::pipe()
+                .atomic()
+                .hset(fault_key(&fault_name), FAULT_FIELD, fault_json)
+                .ignore()
+                .query::<()>(&mut *redis_store.conn()?)
+                .map_err(to_store_error)?;
+
+            debug!("Fault {} stored in Redis", fault_name);
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let fault: Option<String> = redis_store
+                .conn()?
+                .hget(fault_key(&fault_name), FAULT_FIELD)
+                .map_err(to_store_error)?;
+
+            match fault {
+                Some(fault) => {
+                    let fault = parse_fault(&fault)?;
+                    if fault.is_expired() {
+                        return Err(not_found_error(&fault_name));
+                    }
+                    Ok(fault)
+                }
+                None => Err(not_found_error(&fault_name)),
+            }
+        })
+        .await
+    }
+
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        self.spawn_blocking(|redis_store| {
+            let mut conn = redis_store.conn()?;
+            let keys: Vec<String> = conn
+                .keys(format!("{}*", KEY_PREFIX))
+                .map_err(to_store_error)?;
+
+            // the hashes of all the faults are fetched in a single round trip
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.hgetall(key);
+            }
+            let hashes: Vec<HashMap<String, String>> =
+                pipe.query(&mut *conn).map_err(to_store_error)?;
+
+            // the fault might have been deleted after the keys are listed
+            hashes
+                .iter()
+                .filter_map(|hash| hash.get(FAULT_FIELD))
+                .map(|fault| parse_fault(fault))
+                .collect()
+        })
+        .await
+    }
+
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault> {
+        let faults = match self.get_all_faults().await {
+            Ok(faults) => faults,
+            Err(e) => {
+                error!("error fetching all faults: {:?}", e);
+                return None;
+            }
+        };
+
+        find_by_redis_cmd(faults, request, strategy)
+    }
+
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let (deleted,): (u64,) = redis::pipe()
+                .atomic()
+                .del(fault_key(&fault_name))
+                .query(&mut *redis_store.conn()?)
+                .map_err(to_store_error)?;
+
+            if deleted > 0 {
+                debug!("Delete fault {}", fault_name);
+            }
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let is_disabled = redis_store.disable(&fault_name, false)?;
+
+            if is_disabled {
+                debug!("Disabled fault {}", fault_name);
+            }
+            Ok(is_disabled)
+        })
+        .await
+    }
+
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let is_claimed = redis_store.disable(&fault_name, true)?;
+
+            if is_claimed {
+                debug!("Claimed fault {}", fault_name);
+            }
+            Ok(is_claimed)
+        })
+        .await
+    }
+
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let hit_count: Option<u64> = redis::Script::new(RECORD_HIT_SCRIPT)
+                .key(fault_key(&fault_name))
+                .arg(FAULT_FIELD)
+                .arg(HIT_COUNT_FIELD)
+                .invoke(&mut *redis_store.conn()?)
+                .map_err(to_store_error)?;
+
+            hit_count.ok_or_else(|| not_found_error(&fault_name))
+        })
+        .await
+    }
+
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        _delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let hit_count: Option<i64> = redis::Script::new(RESERVE_HIT_SCRIPT)
+                .key(fault_key(&fault_name))
+                .arg(FAULT_FIELD)
+                .arg(HIT_COUNT_FIELD)
+                .arg(max_hits)
+                .invoke(&mut *redis_store.conn()?)
+                .map_err(to_store_error)?;
+
+            match hit_count {
+                Some(hit_count) if hit_count >= 0 => Ok(Some(hit_count as u64)),
+                Some(_) => Ok(None),
+                None => Err(not_found_error(&fault_name)),
+            }
+        })
+        .await
+    }
+
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |redis_store| {
+            let (fault, hit_count): (Option<String>, Option<u64>) = redis_store
+                .conn()?
+                .hget(fault_key(&fault_name), &[FAULT_FIELD, HIT_COUNT_FIELD])
+                .map_err(to_store_error)?;
+
+            match fault {
+                Some(_) => Ok(hit_count.unwrap_or(0)),
+                None => Err(not_found_error(&fault_name)),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::connection::tests::next_test_ip4;
+    use crate::store::fault_store::*;
+    use crate::store::mem_store::tests::fault_store_tests;
+    use chrono::{Duration, Utc};
+    use std::ops::Deref;
+    use testcontainers_modules::redis::{Redis, REDIS_PORT};
+    use testcontainers_modules::testcontainers::runners::AsyncRunner;
+    use testcontainers_modules::testcontainers::ContainerAsync;
+
+    /// A RedisStore of a Redis container, which is removed once dropped
+    struct TestDb {
+        db: DB,
+        _container: ContainerAsync<Redis>,
+    }
+
+    impl Deref for TestDb {
+        type Target = DB;
+
+        fn deref(&self) -> &DB {
+            &self.db
+        }
+    }
+
+    /// Starts a Redis container and opens a RedisStore on it
+    async fn new_test_db() -> TestDb {
+        let container = Redis::default().start().await.unwrap();
+        let redis_url = format!(
+            "redis://{}:{}",
+            container.get_host().await.unwrap(),
+            container.get_host_port_ipv4(REDIS_PORT).await.unwrap()
+        );
+
+        TestDb {
+            db: RedisStore::new_db(&redis_url).unwrap(),
+            _container: container,
+        }
+    }
+
+    fault_store_tests!(new_test_db().await, #[ignore = "requires Docker"]);
+
+    #[tokio::test]
+    async fn test_connection_loss() {
+        // nothing listens on the address, hence every store operation fails to connect
+        let redis_url = format!("redis://{}", next_test_ip4());
+        let redis_store =
+            RedisStore::connect(&redis_url, std::time::Duration::from_millis(100)).unwrap();
+
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        assert!(redis_store.store(&fault.name, &fault).await.is_err());
+        assert!(redis_store.get_by_fault_name(&fault.name).await.is_err());
+        assert!(redis_store.get_all_faults().await.is_err());
+        assert_eq!(
+            redis_store
+                .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                .await,
+            None
+        );
+        assert!(redis_store.delete_fault(&fault.name).await.is_err());
+        assert!(redis_store.disable_fault(&fault.name).await.is_err());
+        assert!(redis_store.claim_fault(&fault.name).await.is_err());
+        assert!(redis_store.reserve_hit(&fault.name, 1, None).await.is_err());
+        assert!(redis_store.record_fault_hit(&fault.name).await.is_err());
+        assert!(redis_store.get_fault_hit_count(&fault.name).await.is_err());
+    }
+
+    #[test]
+    fn test_invalid_redis_url() {
+        assert!(RedisStore::new_db("not a redis url").is_err());
+    }
+
+    #[test]
+    fn test_fault_key() {
+        assert_eq!(fault_key("get_err"), "red_monkey:faults:get_err");
+    }
+}
@@ -0,0 +1,610 @@
+use crate::store::fault_store::{
+    find_by_redis_cmd, Fault, FaultRequest, FaultSelectionStrategy, FaultStore, StoreError, DB,
+    WILDCARD_COMMAND,
+};
+use async_trait::async_trait;
+use rocksdb::{DBWithThreadMode, Direction, IteratorMode, MultiThreaded, Options, WriteBatch};
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error};
+
+/// The column family that holds the JSON serialized faults keyed by fault name
+const FAULTS_CF: &str = "faults";
+
+/// The column family that holds the hit counts of the faults keyed by fault name
+const FAULT_HITS_CF: &str = "fault_hits";
+
+/// The column family that indexes the faults by command, see [command_index_key]
+const COMMAND_INDEX_CF: &str = "faults_by_command";
+
+/// RocksDB opened in the multi-threaded mode, which allows the column families to be dropped and
+/// created again through a shared reference
+type RocksDB = DBWithThreadMode<MultiThreaded>;
+
+/// RocksStore is a persistent store implementation of FaultStore backed by RocksDB, for the
+/// workloads that need both the durability and a high write throughput
+///
+/// Each fault is stored in the `faults` column family as the JSON serialized fault keyed by the
+/// fault name, and its hit count in the `fault_hits` column family as a big-endian u64. The
+/// `faults_by_command` column family indexes the faults by command, such that the lookup of the
+/// faults of a Redis command only deserializes the faults of the command and the wildcard faults.
+///
+/// RocksDB blocks on the disk I/O, hence its operations run on the blocking thread pool of tokio.
+#[derive(Clone)]
+pub struct RocksStore {
+    db: Arc<RocksDB>,
+    /// Serializes the writes of the faults and the read-modify-write of the hit counts and of the
+    /// disabled faults
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl RocksStore {
+    /// Opens the RocksDB database at the given path, creating it and its column families when
+    /// they don't exist. The command index is rebuilt from the stored faults.
+    pub fn new_db(rocksdb_path: &str) -> Result<DB, StoreError> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let db = RocksDB::open_cf(
+            &options,
+            rocksdb_path,
+            [FAULTS_CF, FAULT_HITS_CF, COMMAND_INDEX_CF],
+        )
+        .map_err(to_store_error)?;
+        debug!("RocksDB fault store opened at {}", rocksdb_path);
+
+        let rocks_store = RocksStore {
+            db: Arc::new(db),
+            write_lock: Arc::new(Mutex::new(())),
+        };
+        rocks_store.rebuild_command_index()?;
+
+        Ok(Arc::new(rocks_store))
+    }
+
+    /// Runs the given operation on the blocking thread pool, such that the RocksDB calls don't
+    /// block the threads of the async runtime.
+    async fn spawn_blocking<T, F>(&self, operation: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&RocksStore) -> Result<T, StoreError> + Send + 'static,
+    {
+        let rocks_store = self.clone();
+        tokio::task::spawn_blocking(move || operation(&rocks_store))
+            .await
+            .map_err(to_store_error)?
+    }
+
+    /// Runs the given operation with the handle of the given column family.
+    fn with_cf<T>(
+        &self,
+        name: &str,
+        operation: impl FnOnce(&Arc<rocksdb::BoundColumnFamily>) -> Result<T, rocksdb::Error>,
+    ) -> Result<T, StoreError> {
+        let cf = self
+            .db
+            .cf_handle(name)
+            .ok_or_else(|| StoreError::new(format!("Column family {} not found", name).as_str()))?;
+
+        operation(&cf).map_err(|err| {
+            error!("RocksDB fault store operation failed: {}", err);
+            to_store_error(err)
+        })
+    }
+
+    /// Fetches the fault by name, including the expired faults.
+    fn get_fault(&self, fault_name: &str) -> Result<Option<Fault>, StoreError> {
+        self.with_cf(FAULTS_CF, |faults| self.db.get_cf(faults, fault_name))?
+            .map(|fault| parse_fault(&fault))
+            .transpose()
+    }
+
+    /// Fetches all the faults, including the expired faults.
+    fn get_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        self.with_cf(FAULTS_CF, |faults| {
+            self.db
+                .iterator_cf(faults, IteratorMode::Start)
+                .map(|item| item.map(|(_, fault)| fault))
+                .collect::<Result<Vec<_>, _>>()
+        })?
+        .iter()
+        .map(|fault| parse_fault(fault))
+        .collect()
+    }
+
+    /// Fetches the faults of the given command through the command index, including the expired
+    /// faults.
+    fn get_faults_by_command(&self, command: &str) -> Result<Vec<Fault>, StoreError> {
+        let prefix = command_prefix(command);
+        let index_keys = self.with_cf(COMMAND_INDEX_CF, |command_index| {
+            self.db
+                .iterator_cf(
+                    command_index,
+                    IteratorMode::From(prefix.as_slice(), Direction::Forward),
+                )
+                .map(|item| item.map(|(index_key, _)| index_key))
+                .take_while(|index_key| match index_key {
+                    Ok(index_key) => index_key.starts_with(&prefix),
+                    Err(_) => true,
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let mut faults = Vec::with_capacity(index_keys.len());
+        for index_key in index_keys {
+            let fault_name = String::from_utf8_lossy(&index_key[prefix.len()..]);
+            if let Some(fault) = self.get_fault(&fault_name)? {
+                faults.push(fault);
+            }
+        }
+        Ok(faults)
+    }
+
+    /// Stores the fault and updates its entry of the command index in a single write.
+    fn put_fault_indexed(&self, fault_name: &str, fault: &Fault) -> Result<(), StoreError> {
+        let fault_json = serde_json::to_vec(fault).map_err(to_store_error)?;
+        let previous_fault = self.get_fault(fault_name)?;
+
+        let mut batch = WriteBatch::default();
+        self.with_cf(COMMAND_INDEX_CF, |command_index| {
+            if let Some(previous_fault) = previous_fault {
+                batch.delete_cf(
+                    command_index,
+                    command_index_key(&previous_fault.command, fault_name),
+                );
+            }
+            batch.put_cf(
+                command_index,
+                command_index_key(&fault.command, fault_name),
+                b"",
+            );
+            Ok(())
+        })?;
+        self.with_cf(FAULTS_CF, |faults| {
+            batch.put_cf(faults, fault_name, fault_json);
+            Ok(())
+        })?;
+        self.db.write(batch).map_err(to_store_error)
+    }
+
+    /// Stores the fault whose command is unchanged, hence without updating the command index.
+    fn put_fault(&self, fault_name: &str, fault: &Fault) -> Result<(), StoreError> {
+        let fault_json = serde_json::to_vec(fault).map_err(to_store_error)?;
+        self.with_cf(FAULTS_CF, |faults| {
+            self.db.put_cf(faults, fault_name, fault_json)
+        })
+    }
+
+    fn get_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let hit_count = self.with_cf(FAULT_HITS_CF, |fault_hits| {
+            self.db.get_cf(fault_hits, fault_name)
+        })?;
+
+        match hit_count {
+            Some(hit_count) => {
+                let hit_count: [u8; 8] = hit_count.as_slice().try_into().map_err(|_| {
+                    StoreError::new(format!("Invalid hit count of fault {}", fault_name).as_str())
+                })?;
+                Ok(u64::from_be_bytes(hit_count))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Rebuilds the command index from the stored faults, e.g. for a database written before the
+    /// index existed.
+    fn rebuild_command_index(&self) -> Result<(), StoreError> {
+        self.recreate_cf(COMMAND_INDEX_CF)?;
+
+        let mut batch = WriteBatch::default();
+        let faults = self.get_faults()?;
+        self.with_cf(COMMAND_INDEX_CF, |command_index| {
+            for fault in &faults {
+                batch.put_cf(
+                    command_index,
+                    command_index_key(&fault.command, &fault.name),
+                    b"",
+                );
+            }
+            Ok(())
+        })?;
+        self.db.write(batch).map_err(to_store_error)
+    }
+
+    /// Drops the column family and creates it again empty, which is cheaper than deleting its
+    /// keys one by one.
+    fn recreate_cf(&self, name: &str) -> Result<(), StoreError> {
+        self.db.drop_cf(name).map_err(to_store_error)?;
+        self.db
+            .create_cf(name, &Options::default())
+            .map_err(to_store_error)
+    }
+}
+
+fn to_store_error<E: Display>(err: E) -> StoreError {
+    StoreError::new(&err.to_string())
+}
+
+fn not_found_error(fault_name: &str) -> StoreError {
+    StoreError::new(format!("Fault {} not found", fault_name).as_str())
+}
+
+fn parse_fault(fault: &[u8]) -> Result<Fault, StoreError> {
+    serde_json::from_slice(fault).map_err(to_store_error)
+}
+
+/// Returns the prefix of the command index keys of the given command, i.e. the uppercase command
+/// followed by a NUL separator.
+fn command_prefix(command: &str) -> Vec<u8> {
+    let mut prefix = command.to_ascii_uppercase().into_bytes();
+    prefix.push(0);
+    prefix
+}
+
+/// Returns the key of the fault in the command index, i.e. the command prefix followed by the
+/// fault name. The commands are matched case-insensitively, hence they are uppercased.
+fn command_index_key(command: &str, fault_name: &str) -> Vec<u8> {
+    let mut index_key = command_prefix(command);
+    index_key.extend_from_slice(fault_name.as_bytes());
+    index_key
+}
+
+#[async_trait]
+impl FaultStore for RocksStore {
+    async fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+        let fault = fault.clone();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            rocks_store.put_fault_indexed(&fault_name, &fault)?;
+
+            debug!("Fault {} stored in RocksDB", fault_name);
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(
+            move |rocks_store| match rocks_store.get_fault(&fault_name)? {
+                Some(fault) if !fault.is_expired() => Ok(fault),
+                _ => Err(not_found_error(&fault_name)),
+            },
+        )
+        .await
+    }
+
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        self.spawn_blocking(|rocks_store| rocks_store.get_faults())
+            .await
+    }
+
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault> {
+        let command = request.command.to_string();
+        let faults = self
+            .spawn_blocking(move |rocks_store| {
+                let mut faults = rocks_store.get_faults_by_command(&command)?;
+                faults.extend(rocks_store.get_faults_by_command(WILDCARD_COMMAND)?);
+                Ok(faults)
+            })
+            .await;
+        let faults = match faults {
+            Ok(faults) => faults,
+            Err(e) => {
+                error!(
+                    "error fetching faults of command {}: {:?}",
+                    request.command, e
+                );
+                return None;
+            }
+        };
+
+        find_by_redis_cmd(faults, request, strategy)
+    }
+
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            let fault = match rocks_store.get_fault(&fault_name)? {
+                Some(fault) => fault,
+                None => return Ok(false),
+            };
+
+            let mut batch = WriteBatch::default();
+            rocks_store.with_cf(FAULTS_CF, |faults| {
+                batch.delete_cf(faults, &fault_name);
+                Ok(())
+            })?;
+            rocks_store.with_cf(FAULT_HITS_CF, |fault_hits| {
+                batch.delete_cf(fault_hits, &fault_name);
+                Ok(())
+            })?;
+            rocks_store.with_cf(COMMAND_INDEX_CF, |command_index| {
+                batch.delete_cf(
+                    command_index,
+                    command_index_key(&fault.command, &fault_name),
+                );
+                Ok(())
+            })?;
+            rocks_store.db.write(batch).map_err(to_store_error)?;
+
+            debug!("Delete fault {}", fault_name);
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            let mut fault = match rocks_store.get_fault(&fault_name)? {
+                Some(fault) => fault,
+                None => return Ok(false),
+            };
+
+            fault.enabled = false;
+            rocks_store.put_fault(&fault_name, &fault)?;
+
+            debug!("Disabled fault {}", fault_name);
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            let mut fault = match rocks_store.get_fault(&fault_name)? {
+                Some(fault) if fault.enabled => fault,
+                _ => return Ok(false),
+            };
+
+            fault.enabled = false;
+            rocks_store.put_fault(&fault_name, &fault)?;
+
+            debug!("Claimed fault {}", fault_name);
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            if rocks_store.get_fault(&fault_name)?.is_none() {
+                return Err(not_found_error(&fault_name));
+            }
+
+            let hit_count = rocks_store.get_hit_count(&fault_name)? + 1;
+            rocks_store.with_cf(FAULT_HITS_CF, |fault_hits| {
+                rocks_store
+                    .db
+                    .put_cf(fault_hits, &fault_name, hit_count.to_be_bytes())
+            })?;
+
+            Ok(hit_count)
+        })
+        .await
+    }
+
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        _delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            if rocks_store.get_fault(&fault_name)?.is_none() {
+                return Err(not_found_error(&fault_name));
+            }
+
+            let hit_count = rocks_store.get_hit_count(&fault_name)?;
+            if hit_count >= max_hits {
+                return Ok(None);
+            }
+            rocks_store.with_cf(FAULT_HITS_CF, |fault_hits| {
+                rocks_store
+                    .db
+                    .put_cf(fault_hits, &fault_name, (hit_count + 1).to_be_bytes())
+            })?;
+
+            Ok(Some(hit_count + 1))
+        })
+        .await
+    }
+
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let fault_name = fault_name.to_string();
+
+        self.spawn_blocking(move |rocks_store| {
+            if rocks_store.get_fault(&fault_name)?.is_none() {
+                return Err(not_found_error(&fault_name));
+            }
+
+            rocks_store.get_hit_count(&fault_name)
+        })
+        .await
+    }
+
+    /// Drops the column families of the faults, of their hit counts and of the command index, and
+    /// creates them again empty. The operations that run concurrently may fail while the column
+    /// families are missing.
+    async fn clear_all(&self) -> Result<usize, StoreError> {
+        self.spawn_blocking(|rocks_store| {
+            let _guard = rocks_store.write_lock.lock().map_err(to_store_error)?;
+            let deleted = rocks_store.get_faults()?.len();
+
+            rocks_store.recreate_cf(FAULTS_CF)?;
+            rocks_store.recreate_cf(FAULT_HITS_CF)?;
+            rocks_store.recreate_cf(COMMAND_INDEX_CF)?;
+
+            debug!("Cleared {} faults from RocksDB", deleted);
+            Ok(deleted)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fault_store::*;
+    use crate::store::mem_store::tests::fault_store_tests;
+    use chrono::{Duration, Utc};
+    use std::ops::Deref;
+    use tempfile::TempDir;
+
+    /// TestDb is a RocksStore opened in a temporary directory, which is removed on drop
+    struct TestDb {
+        db: DB,
+        _dir: TempDir,
+    }
+
+    impl Deref for TestDb {
+        type Target = DB;
+
+        fn deref(&self) -> &DB {
+            &self.db
+        }
+    }
+
+    /// Opens a RocksStore in a new temporary directory
+    fn new_test_db() -> TestDb {
+        let dir = TempDir::new().unwrap();
+
+        TestDb {
+            db: RocksStore::new_db(dir.path().to_str().unwrap()).unwrap(),
+            _dir: dir,
+        }
+    }
+
+    fault_store_tests!(new_test_db());
+
+    #[tokio::test]
+    async fn test_faults_persist_across_restarts() {
+        let dir = TempDir::new().unwrap();
+        let rocksdb_path = dir.path().to_str().unwrap();
+
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        {
+            let rocks_store = RocksStore::new_db(rocksdb_path).unwrap();
+            rocks_store.store(&fault.name, &fault).await.unwrap();
+            rocks_store.record_fault_hit(&fault.name).await.unwrap();
+        }
+
+        let rocks_store = RocksStore::new_db(rocksdb_path).unwrap();
+        assert_eq!(
+            rocks_store.get_by_fault_name(&fault.name).await.unwrap(),
+            fault
+        );
+        assert_eq!(
+            rocks_store.get_fault_hit_count(&fault.name).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_index() {
+        let dir = TempDir::new().unwrap();
+        let rocksdb_path = dir.path().to_str().unwrap();
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        {
+            let rocks_store = RocksStore::new_db(rocksdb_path).unwrap();
+            rocks_store.store(&fault.name, &fault).await.unwrap();
+            assert_eq!(
+                rocks_store
+                    .get_by_redis_cmd(&FaultRequest::from("set"), FaultSelectionStrategy::Priority)
+                    .await,
+                Some(fault.clone())
+            );
+
+            // the fault is moved to its new command in the index
+            fault.command = "GET".to_string();
+            rocks_store.store(&fault.name, &fault).await.unwrap();
+            assert_eq!(
+                rocks_store
+                    .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                    .await,
+                None
+            );
+        }
+
+        // the index is rebuilt from the faults when the database is opened again
+        let rocks_store = RocksStore::new_db(rocksdb_path).unwrap();
+        assert_eq!(
+            rocks_store
+                .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                .await,
+            Some(fault.clone())
+        );
+
+        rocks_store.delete_fault(&fault.name).await.unwrap();
+        assert_eq!(
+            rocks_store
+                .get_by_redis_cmd(&FaultRequest::from("GET"), FaultSelectionStrategy::Priority)
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_all() {
+        let rocks_store = new_test_db();
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        for name in ["set_delay", "get_error"] {
+            fault.name = name.to_string();
+            rocks_store.store(&fault.name, &fault).await.unwrap();
+            rocks_store.record_fault_hit(&fault.name).await.unwrap();
+        }
+
+        assert_eq!(rocks_store.clear_all().await.unwrap(), 2);
+        assert!(rocks_store.get_all_faults().await.unwrap().is_empty());
+
+        // the store is usable once the column families are created again
+        rocks_store.store(&fault.name, &fault).await.unwrap();
+        assert_eq!(
+            rocks_store.get_fault_hit_count(&fault.name).await.unwrap(),
+            0
+        );
+        assert_eq!(
+            rocks_store
+                .get_by_redis_cmd(&FaultRequest::from("SET"), FaultSelectionStrategy::Priority)
+                .await,
+            Some(fault)
+        );
+    }
+}
@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS faults (
+    name TEXT PRIMARY KEY NOT NULL,
+    description TEXT,
+    fault_type TEXT NOT NULL,
+    duration INTEGER,
+    error_msg TEXT,
+    command TEXT NOT NULL,
+    last_modified TEXT,
+    expires_at TEXT,
+    activate_at TEXT,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    one_shot INTEGER NOT NULL DEFAULT 0,
+    max_hits INTEGER,
+    jitter_ms INTEGER,
+    adaptive_delay_step_ms INTEGER,
+    max_adaptive_duration_ms INTEGER,
+    burst_count INTEGER,
+    burst_interval_ms INTEGER,
+    truncate_after_bytes INTEGER,
+    bytes_per_ms INTEGER,
+    key_pattern TEXT,
+    tags TEXT NOT NULL DEFAULT '[]',
+    fault_group TEXT,
+    client_ip_filter TEXT,
+    redis_database INTEGER,
+    proxy_port INTEGER,
+    priority INTEGER NOT NULL DEFAULT 100,
+    probability REAL,
+    sub_faults TEXT,
+    mutation_pattern TEXT,
+    mutation_replacement TEXT,
+    arg_matchers TEXT
+);
+
+CREATE TABLE IF NOT EXISTS fault_hits (
+    name TEXT PRIMARY KEY NOT NULL,
+    hit_count INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,470 @@
+use crate::store::fault_store::{
+    find_by_redis_cmd, Fault, FaultRequest, FaultSelectionStrategy, FaultStore, StoreError, DB,
+};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow};
+use sqlx::Row;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// The schema of the fault store, applied when the store is opened
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// SqliteStore is a persistent store implementation of FaultStore backed by SQLite
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens the SQLite database at the given path, creating it when it doesn't exist, and
+    /// applies the schema.
+    pub async fn new_db(sqlite_path: &str) -> Result<DB, StoreError> {
+        let options = SqliteConnectOptions::new()
+            .filename(sqlite_path)
+            .create_if_missing(true);
+
+        Self::connect(options).await
+    }
+
+    async fn connect(options: SqliteConnectOptions) -> Result<DB, StoreError> {
+        let store = SqliteStore {
+            pool: SqlitePool::connect_lazy_with(options),
+        };
+
+        let pool = store.pool.clone();
+        run(async move { sqlx::raw_sql(SCHEMA).execute(&pool).await }).await?;
+        debug!("SQLite fault store schema applied");
+
+        Ok(Arc::new(store))
+    }
+}
+
+/// Runs the query, logging the failure of the query.
+async fn run<F, T>(query: F) -> Result<T, StoreError>
+where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    query.await.map_err(|err| {
+        error!("SQLite fault store query failed: {}", err);
+        StoreError::new(&err.to_string())
+    })
+}
+
+/// Maps a row of the `faults` table to a Fault
+fn to_fault(row: &SqliteRow) -> Result<Fault, sqlx::Error> {
+    let to_u64 = |column: &str| -> Result<Option<u64>, sqlx::Error> {
+        Ok(row.try_get::<Option<i64>, _>(column)?.map(|val| val as u64))
+    };
+
+    Ok(Fault {
+        name: row.try_get("name")?,
+        description: row.try_get("description")?,
+        fault_type: FromStr::from_str(row.try_get("fault_type")?)
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        duration: to_u64("duration")?,
+        error_msg: row.try_get("error_msg")?,
+        command: row.try_get("command")?,
+        last_modified: row.try_get("last_modified")?,
+        expires_at: row.try_get("expires_at")?,
+        activate_at: row.try_get("activate_at")?,
+        enabled: row.try_get("enabled")?,
+        one_shot: row.try_get("one_shot")?,
+        max_hits: to_u64("max_hits")?,
+        jitter_ms: to_u64("jitter_ms")?,
+        adaptive_delay_step_ms: to_u64("adaptive_delay_step_ms")?,
+        max_adaptive_duration_ms: to_u64("max_adaptive_duration_ms")?,
+        burst_count: row
+            .try_get::<Option<i64>, _>("burst_count")?
+            .map(|val| val as u32),
+        burst_interval_ms: to_u64("burst_interval_ms")?,
+        truncate_after_bytes: to_u64("truncate_after_bytes")?,
+        bytes_per_ms: to_u64("bytes_per_ms")?,
+        key_pattern: row.try_get("key_pattern")?,
+        key_glob: Default::default(),
+        tags: serde_json::from_str(row.try_get("tags")?)
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        group: row.try_get("fault_group")?,
+        client_ip_filter: row
+            .try_get::<Option<&str>, _>("client_ip_filter")?
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        database: row
+            .try_get::<Option<i64>, _>("redis_database")?
+            .map(|val| val as u8),
+        proxy_port: row
+            .try_get::<Option<i64>, _>("proxy_port")?
+            .map(|val| val as u16),
+        priority: row.try_get::<i64, _>("priority")? as u32,
+        probability: row.try_get("probability")?,
+        sub_faults: row
+            .try_get::<Option<&str>, _>("sub_faults")?
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        mutation_pattern: row.try_get("mutation_pattern")?,
+        mutation_replacement: row.try_get("mutation_replacement")?,
+        arg_matchers: row
+            .try_get::<Option<&str>, _>("arg_matchers")?
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+    })
+}
+
+#[async_trait]
+impl FaultStore for SqliteStore {
+    async fn store(&self, fault_name: &str, fault: &Fault) -> Result<bool, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+        let fault = fault.clone();
+
+        run(async move {
+            sqlx::query(
+                "INSERT OR REPLACE INTO faults (name, description, fault_type, duration, \
+                 error_msg, command, last_modified, expires_at, activate_at, enabled, one_shot, \
+                 max_hits, jitter_ms, adaptive_delay_step_ms, max_adaptive_duration_ms, \
+                 burst_count, burst_interval_ms, truncate_after_bytes, bytes_per_ms, key_pattern, \
+                 tags, fault_group, client_ip_filter, redis_database, proxy_port, priority, \
+                 probability, sub_faults, mutation_pattern, mutation_replacement, arg_matchers) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, \
+                 ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(name)
+            .bind(&fault.description)
+            .bind(format!("{:?}", fault.fault_type))
+            .bind(fault.duration.map(|val| val as i64))
+            .bind(&fault.error_msg)
+            .bind(&fault.command)
+            .bind(fault.last_modified)
+            .bind(fault.expires_at)
+            .bind(fault.activate_at)
+            .bind(fault.enabled)
+            .bind(fault.one_shot)
+            .bind(fault.max_hits.map(|val| val as i64))
+            .bind(fault.jitter_ms.map(|val| val as i64))
+            .bind(fault.adaptive_delay_step_ms.map(|val| val as i64))
+            .bind(fault.max_adaptive_duration_ms.map(|val| val as i64))
+            .bind(fault.burst_count.map(|val| val as i64))
+            .bind(fault.burst_interval_ms.map(|val| val as i64))
+            .bind(fault.truncate_after_bytes.map(|val| val as i64))
+            .bind(fault.bytes_per_ms.map(|val| val as i64))
+            .bind(&fault.key_pattern)
+            .bind(serde_json::to_string(&fault.tags).unwrap_or_else(|_| "[]".to_string()))
+            .bind(&fault.group)
+            .bind(
+                fault
+                    .client_ip_filter
+                    .as_ref()
+                    .and_then(|client_ip_filter| serde_json::to_string(client_ip_filter).ok()),
+            )
+            .bind(fault.database.map(|val| val as i64))
+            .bind(fault.proxy_port.map(|val| val as i64))
+            .bind(fault.priority as i64)
+            .bind(fault.probability)
+            .bind(
+                fault
+                    .sub_faults
+                    .as_ref()
+                    .and_then(|sub_faults| serde_json::to_string(sub_faults).ok()),
+            )
+            .bind(&fault.mutation_pattern)
+            .bind(&fault.mutation_replacement)
+            .bind(
+                fault
+                    .arg_matchers
+                    .as_ref()
+                    .and_then(|arg_matchers| serde_json::to_string(arg_matchers).ok()),
+            )
+            .execute(&pool)
+            .await
+        }).await?;
+
+        debug!("Fault {} stored in SQLite", fault_name);
+        Ok(true)
+    }
+
+    async fn get_by_fault_name(&self, fault_name: &str) -> Result<Fault, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let fault = run(async move {
+            sqlx::query("SELECT * FROM faults WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&pool)
+                .await?
+                .map(|row| to_fault(&row))
+                .transpose()
+        })
+        .await?;
+
+        match fault {
+            Some(fault) if !fault.is_expired() => Ok(fault),
+            _ => Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            )),
+        }
+    }
+
+    async fn get_all_faults(&self) -> Result<Vec<Fault>, StoreError> {
+        let pool = self.pool.clone();
+
+        run(async move {
+            sqlx::query("SELECT * FROM faults")
+                .fetch_all(&pool)
+                .await?
+                .iter()
+                .map(to_fault)
+                .collect()
+        })
+        .await
+    }
+
+    async fn get_by_redis_cmd(
+        &self,
+        request: &FaultRequest<'_>,
+        strategy: FaultSelectionStrategy,
+    ) -> Option<Fault> {
+        let faults = match self.get_all_faults().await {
+            Ok(faults) => faults,
+            Err(e) => {
+                error!("error fetching all faults: {:?}", e);
+                return None;
+            }
+        };
+
+        find_by_redis_cmd(faults, request, strategy)
+    }
+
+    async fn delete_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let is_deleted = run(async move {
+            let mut tx = pool.begin().await?;
+            sqlx::query("DELETE FROM fault_hits WHERE name = ?")
+                .bind(&name)
+                .execute(&mut *tx)
+                .await?;
+            let result = sqlx::query("DELETE FROM faults WHERE name = ?")
+                .bind(&name)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await?;
+
+        if is_deleted {
+            debug!("Delete fault {}", fault_name);
+        }
+        Ok(is_deleted)
+    }
+
+    async fn disable_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let is_disabled = run(async move {
+            let result = sqlx::query("UPDATE faults SET enabled = 0 WHERE name = ?")
+                .bind(name)
+                .execute(&pool)
+                .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await?;
+
+        if is_disabled {
+            debug!("Disabled fault {}", fault_name);
+        }
+        Ok(is_disabled)
+    }
+
+    async fn claim_fault(&self, fault_name: &str) -> Result<bool, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let is_claimed = run(async move {
+            let result =
+                sqlx::query("UPDATE faults SET enabled = 0 WHERE name = ? AND enabled = 1")
+                    .bind(name)
+                    .execute(&pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await?;
+
+        if is_claimed {
+            debug!("Claimed fault {}", fault_name);
+        }
+        Ok(is_claimed)
+    }
+
+    async fn record_fault_hit(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let hit_count = run(async move {
+            let mut tx = pool.begin().await?;
+            let hit_count: Option<i64> = sqlx::query_scalar(
+                "INSERT INTO fault_hits (name, hit_count) \
+                 SELECT name, 1 FROM faults WHERE name = ? \
+                 ON CONFLICT (name) DO UPDATE SET hit_count = hit_count + 1 \
+                 RETURNING hit_count",
+            )
+            .bind(name)
+            .fetch_optional(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            Ok(hit_count)
+        })
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(hit_count as u64),
+            None => Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            )),
+        }
+    }
+
+    async fn reserve_hit(
+        &self,
+        fault_name: &str,
+        max_hits: u64,
+        _delay_ms: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+        let max_hits = max_hits as i64;
+
+        let hit_count = run(async move {
+            let mut tx = pool.begin().await?;
+            let hit_count: Option<i64> = sqlx::query_scalar(
+                "INSERT INTO fault_hits (name, hit_count) \
+                 SELECT name, 1 FROM faults WHERE name = ? AND ? > 0 \
+                 ON CONFLICT (name) DO UPDATE SET hit_count = hit_count + 1 \
+                 WHERE hit_count < ? \
+                 RETURNING hit_count",
+            )
+            .bind(name)
+            .bind(max_hits)
+            .bind(max_hits)
+            .fetch_optional(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            Ok(hit_count)
+        })
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(Some(hit_count as u64)),
+            // the fault reached its max hits, unless it is not found
+            None => self.get_fault_hit_count(fault_name).await.map(|_| None),
+        }
+    }
+
+    async fn get_fault_hit_count(&self, fault_name: &str) -> Result<u64, StoreError> {
+        let pool = self.pool.clone();
+        let name = fault_name.to_string();
+
+        let hit_count: Option<i64> = run(async move {
+            sqlx::query_scalar(
+                "SELECT COALESCE(fault_hits.hit_count, 0) FROM faults \
+                 LEFT JOIN fault_hits ON fault_hits.name = faults.name \
+                 WHERE faults.name = ?",
+            )
+            .bind(name)
+            .fetch_optional(&pool)
+            .await
+        })
+        .await?;
+
+        match hit_count {
+            Some(hit_count) => Ok(hit_count as u64),
+            None => Err(StoreError::new(
+                format!("Fault {} not found", fault_name).as_str(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fault_store::*;
+    use crate::store::mem_store::tests::fault_store_tests;
+    use chrono::{Duration, Utc};
+
+    /// Opens a SqliteStore of a new in-memory database
+    async fn new_test_db() -> DB {
+        SqliteStore::connect(SqliteConnectOptions::from_str("sqlite::memory:").unwrap())
+            .await
+            .unwrap()
+    }
+
+    fault_store_tests!(new_test_db().await);
+
+    #[tokio::test]
+    async fn test_store_and_get_fault_by_name() {
+        let sqlite_store = new_test_db().await;
+
+        let mut fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        fault.last_modified = Some(Utc::now());
+        fault.expires_at = Some(Utc::now() + Duration::minutes(1));
+        fault.max_hits = Some(3);
+        fault.jitter_ms = Some(5);
+        fault.adaptive_delay_step_ms = Some(10);
+        fault.max_adaptive_duration_ms = Some(100);
+        fault.key_pattern = Some("user:*".to_string());
+        fault.tags = vec!["production".to_string(), "auth-service".to_string()];
+        fault.client_ip_filter = Some(vec!["10.0.0.1".parse().unwrap(), "::1".parse().unwrap()]);
+        assert!(sqlite_store.store(&fault.name, &fault).await.unwrap());
+
+        assert_eq!(
+            sqlite_store.get_by_fault_name(&fault.name).await.unwrap(),
+            fault
+        );
+        assert!(sqlite_store
+            .get_by_fault_name("invalid_fault")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_faults_persist_across_restarts() {
+        let sqlite_path =
+            std::env::temp_dir().join(format!("red-monkey-{}.db", uuid::Uuid::new_v4()));
+        let sqlite_path = sqlite_path.to_str().unwrap();
+
+        let fault = Fault {
+            description: Some("inject a delay of 10 milliseconds".to_string()),
+            duration: Some(20),
+            ..Fault::fixture("delay 10 milliseconds", FaultVariants::Delay, "SET")
+        };
+        {
+            let sqlite_store = SqliteStore::new_db(sqlite_path).await.unwrap();
+            sqlite_store.store(&fault.name, &fault).await.unwrap();
+        }
+
+        let sqlite_store = SqliteStore::new_db(sqlite_path).await.unwrap();
+        assert_eq!(
+            sqlite_store.get_by_fault_name(&fault.name).await.unwrap(),
+            fault
+        );
+
+        std::fs::remove_file(sqlite_path).unwrap();
+    }
+}
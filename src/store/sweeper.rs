@@ -0,0 +1,141 @@
+use crate::store::fault_store::{StoreError, DB};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// The interval at which the sweeper looks for expired faults in the store.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Runs the sweeper forever, removing expired faults from the store every `interval`. It also logs
+/// the scheduled faults that got activated since the previous sweep.
+///
+/// Expired faults are already ignored by the store lookups; the sweeper only makes sure they don't
+/// pile up in the store.
+pub async fn run(fault_store: DB, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut last_sweep = Utc::now();
+
+    loop {
+        ticker.tick().await;
+
+        let now = Utc::now();
+        if let Err(err) = log_activated_faults(&fault_store, last_sweep, now).await {
+            error!("error looking for activated faults: {}", err);
+        }
+        last_sweep = now;
+
+        if let Err(err) = sweep_expired_faults(&fault_store).await {
+            error!("error sweeping expired faults: {}", err);
+        }
+    }
+}
+
+/// Deletes every fault whose `expires_at` is in the past.
+///
+/// Returns the number of deleted faults on success.
+pub async fn sweep_expired_faults(fault_store: &DB) -> Result<usize, StoreError> {
+    let faults = fault_store.get_all_faults().await?;
+
+    let mut deleted = 0;
+    for fault in faults.iter().filter(|fault| fault.is_expired()) {
+        if fault_store.delete_fault(&fault.name).await? {
+            info!("Deleted expired fault: {}", fault.name);
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Logs every fault whose `activate_at` is in `(since, now]`, i.e. the faults that became active
+/// for the first time since the previous sweep.
+///
+/// Returns the names of the activated faults on success.
+pub async fn log_activated_faults(
+    fault_store: &DB,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, StoreError> {
+    let faults = fault_store.get_all_faults().await?;
+
+    let activated_faults: Vec<String> = faults
+        .into_iter()
+        .filter(|fault| {
+            fault
+                .activate_at
+                .is_some_and(|activate_at| since < activate_at && activate_at <= now)
+        })
+        .map(|fault| fault.name)
+        .collect();
+
+    for fault_name in &activated_faults {
+        info!("Activated scheduled fault: {}", fault_name);
+    }
+
+    Ok(activated_faults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fault_store::{Fault, FaultVariants};
+    use crate::store::mem_store::MemStore;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_sweep_expired_faults() {
+        let fault_store = MemStore::new_db();
+
+        let expired_fault = Fault {
+            error_msg: Some("SET ERROR".to_string()),
+            expires_at: Some(Utc::now() - Duration::seconds(1)),
+            ..Fault::fixture("expired", FaultVariants::Error, "SET")
+        };
+
+        let mut active_fault = expired_fault.clone();
+        active_fault.name = "active".to_string();
+        active_fault.command = "GET".to_string();
+        active_fault.expires_at = Some(Utc::now() + Duration::minutes(10));
+
+        for fault in [&expired_fault, &active_fault] {
+            fault_store.store(&fault.name, fault).await.unwrap();
+        }
+
+        let deleted = sweep_expired_faults(&fault_store).await.unwrap();
+        assert_eq!(deleted, 1);
+
+        let faults = fault_store.get_all_faults().await.unwrap();
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].name, "active");
+    }
+
+    #[tokio::test]
+    async fn test_log_activated_faults() {
+        let fault_store = MemStore::new_db();
+        let now = Utc::now();
+
+        let fault = Fault {
+            error_msg: Some("SET ERROR".to_string()),
+            activate_at: Some(now + Duration::hours(1)),
+            ..Fault::fixture("scheduled", FaultVariants::Error, "SET")
+        };
+        fault_store.store(&fault.name, &fault).await.unwrap();
+
+        let activated = log_activated_faults(&fault_store, now - Duration::minutes(1), now)
+            .await
+            .unwrap();
+        assert!(activated.is_empty());
+
+        // a sweep in the future logs the fault once it is activated
+        let later = now + Duration::hours(2);
+        let activated = log_activated_faults(&fault_store, now, later)
+            .await
+            .unwrap();
+        assert_eq!(activated, vec!["scheduled".to_string()]);
+
+        let activated = log_activated_faults(&fault_store, later, later + Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(activated.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use opentelemetry::trace::{
+    SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::{debug, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+use uuid::Uuid;
+
+/// Builds the layer that exports the spans to the OpenTelemetry collector at the given OTLP gRPC
+/// endpoint, e.g. `http://localhost:4317`.
+///
+/// The returned tracer provider must be shut down on exit, such that the buffered spans are
+/// flushed. The exporter requires a Tokio runtime.
+pub fn otel_layer<S>(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<(OpenTelemetryLayer<S, SdkTracer>, SdkTracerProvider), anyhow::Error>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+    let tracer = tracer_provider.tracer(service_name.to_string());
+
+    Ok((
+        tracing_opentelemetry::layer().with_tracer(tracer),
+        tracer_provider,
+    ))
+}
+
+/// Makes the request id the trace id of the given span, such that the exported trace can be looked
+/// up by the `request_id` field of the logs.
+///
+/// The span is parented to a remote span context derived from the request id, hence it must be
+/// called before the span is entered. It is a no-op when the OpenTelemetry layer is not installed.
+pub fn set_trace_id(span: &Span, request_id: &Uuid) {
+    let bytes = request_id.as_bytes();
+    let mut span_id = [0; 8];
+    span_id.copy_from_slice(&bytes[8..]);
+
+    let parent_span_context = SpanContext::new(
+        TraceId::from_bytes(*bytes),
+        SpanId::from_bytes(span_id),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+
+    if let Err(err) =
+        span.set_parent(opentelemetry::Context::new().with_remote_span_context(parent_span_context))
+    {
+        debug!("trace id not set for request {}: {}", request_id, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_otel_layer() {
+        let (layer, tracer_provider) =
+            otel_layer("http://127.0.0.1:4317", "red-monkey-test").unwrap();
+        let subscriber = Registry::default().with(layer);
+
+        let request_id = Uuid::new_v4();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("Handling connection", request_id = %request_id);
+            set_trace_id(&span, &request_id);
+            let _entered = span.enter();
+
+            assert_eq!(
+                span.context().span().span_context().trace_id(),
+                TraceId::from_bytes(*request_id.as_bytes())
+            );
+        });
+
+        // nothing listens on the endpoint, hence flushing the span fails
+        let _ = tracer_provider.shutdown();
+    }
+}